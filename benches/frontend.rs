@@ -0,0 +1,60 @@
+//! Throughput of the lexer, parser and analyzer together on a large program
+
+use std::hint::black_box;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use tol2::compiler;
+
+/// Lines of the generated program
+const LINES: usize = 100_000;
+
+/// A `paraan` calling the one before it, the generated program being made of
+/// enough of them to reach [`LINES`]
+const PARAAN: &str = "\
+paraan hakbang_{i}(n na i64) -> i64:
+    ang kabuuan na i64 = n * 3 + {i}
+    ang i na i64 = 0
+    habang i < 4:
+        kabuuan += i ** 2 - (n >> 1)
+        i += 1
+    kung kabuuan % 2 == 0 && n > 0:
+        ibalik hakbang_{prev}(kabuuan / 2)
+    kungdi:
+        ibalik kabuuan - 0x1f
+
+";
+
+fn generate_program() -> String {
+    let paraan_count = LINES / PARAAN.lines().count();
+    let mut source = String::from("paraan hakbang_0(n na i64) -> i64:\n    ibalik n\n\n");
+    for i in 1..paraan_count {
+        source.push_str(
+            &PARAAN
+                .replace("{i}", &i.to_string())
+                .replace("{prev}", &(i - 1).to_string()),
+        );
+    }
+    source.push_str(&format!(
+        "paraan main() -> i32:\n    ang _n na i64 = hakbang_{}(7)\n    ibalik 0\n",
+        paraan_count - 1
+    ));
+
+    source
+}
+
+fn frontend(c: &mut Criterion) {
+    let source = generate_program();
+    let ctx = compiler::check(&source, "bench.tol");
+    assert!(ctx.errors.is_empty(), "{:?}", ctx.errors.first());
+
+    let mut group = c.benchmark_group("frontend");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.sample_size(10);
+    group.bench_function("100k_lines", |b| {
+        b.iter(|| compiler::check(black_box(&source), "bench.tol"))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, frontend);
+criterion_main!(benches);
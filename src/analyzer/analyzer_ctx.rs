@@ -1,25 +1,73 @@
-use crate::toltype::TolType;
+use std::ops::Range;
+
+use crate::{analyzer::SymbolId, toltype::TolType};
+
+/// Name of a loop and where it was written
+pub type LoopLabel = (String, Range<usize>);
 
 pub struct AnalyzerContext {
     fn_return_types: Vec<TolType>,
+    fn_symbols: Vec<SymbolId>,
+    /// Labels of the loops surrounding the current statement, one list per
+    /// `paraan` since loop control cannot cross into an enclosing function
+    loop_labels: Vec<Vec<Option<LoopLabel>>>,
 }
 
 impl AnalyzerContext {
     pub fn new() -> Self {
         Self {
             fn_return_types: vec![TolType::Unknown],
+            fn_symbols: Vec::new(),
+            loop_labels: vec![Vec::new()],
         }
     }
 
-    pub fn enter_fn(&mut self, new_fn_return_type: TolType) {
+    pub fn enter_fn(&mut self, new_fn_symbol: SymbolId, new_fn_return_type: TolType) {
+        self.fn_symbols.push(new_fn_symbol);
         self.fn_return_types.push(new_fn_return_type);
+        self.loop_labels.push(Vec::new());
     }
 
     pub fn exit_fn(&mut self) -> TolType {
+        self.fn_symbols.pop();
+        self.loop_labels.pop();
         self.fn_return_types.pop().unwrap()
     }
 
     pub fn cur_fn_return_type(&self) -> &TolType {
         self.fn_return_types.last().unwrap()
     }
+
+    /// Returns the symbol of the `paraan` being analyzed, if any
+    pub fn cur_fn_symbol(&self) -> Option<SymbolId> {
+        self.fn_symbols.last().copied()
+    }
+
+    pub fn enter_loop(&mut self, label: Option<LoopLabel>) {
+        self.loop_labels.last_mut().unwrap().push(label);
+    }
+
+    pub fn exit_loop(&mut self) {
+        self.loop_labels.last_mut().unwrap().pop();
+    }
+
+    pub fn in_loop(&self) -> bool {
+        !self.loop_labels.last().unwrap().is_empty()
+    }
+
+    /// Returns true if a loop named `label` surrounds the current statement
+    pub fn has_loop_label(&self, label: &str) -> bool {
+        self.loop_label_span(label).is_some()
+    }
+
+    /// Returns where the surrounding loop named `label` was named, if any
+    pub fn loop_label_span(&self, label: &str) -> Option<Range<usize>> {
+        self.loop_labels
+            .last()
+            .unwrap()
+            .iter()
+            .flatten()
+            .find(|(name, _)| name == label)
+            .map(|(_, span)| span.clone())
+    }
 }
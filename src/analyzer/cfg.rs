@@ -0,0 +1,365 @@
+use std::ops::Range;
+
+use crate::ast::{
+    typed_expr::{TypedExpr, TypedExprKind},
+    typed_stmt::{TypedStmt, TypedStmtKind},
+};
+
+pub type BlockId = usize;
+
+#[derive(Debug, Clone)]
+pub enum Terminator {
+    /// Not terminated yet, only seen while the graph is being built
+    Open,
+    Goto(BlockId),
+    Branch {
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return,
+    /// Falls off the end of the function without an `ibalik`
+    Exit,
+}
+
+#[derive(Debug)]
+pub struct BasicBlock {
+    pub terminator: Terminator,
+}
+
+/// A statement as seen from the list it was written in, used to find the
+/// first statement of code that can never run
+struct StmtEntry {
+    block: BlockId,
+    span: Range<usize>,
+    prev: Option<usize>,
+}
+
+/// A loop being lowered, where `tuloy` and `tigil` inside it jump to
+struct LoopTargets {
+    label: Option<String>,
+    continue_to: BlockId,
+    break_to: BlockId,
+}
+
+/// Control flow graph of the body of a single `paraan`
+pub struct Cfg {
+    blocks: Vec<BasicBlock>,
+    entries: Vec<StmtEntry>,
+    loops: Vec<LoopTargets>,
+}
+
+impl Cfg {
+    const ENTRY: BlockId = 0;
+
+    pub fn from_body(body: &TypedStmt) -> Self {
+        let mut cfg = Self {
+            blocks: Vec::new(),
+            entries: Vec::new(),
+            loops: Vec::new(),
+        };
+
+        let entry = cfg.new_block();
+        let end = cfg.lower_stmt(body, entry);
+        cfg.terminate(end, Terminator::Exit);
+
+        cfg
+    }
+
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut seen = vec![false; self.blocks.len()];
+        let mut stack = vec![Self::ENTRY];
+        while let Some(id) = stack.pop() {
+            if seen[id] {
+                continue;
+            }
+
+            seen[id] = true;
+            match self.blocks[id].terminator {
+                Terminator::Goto(to) => stack.push(to),
+                Terminator::Branch { then, otherwise } => {
+                    stack.push(then);
+                    stack.push(otherwise);
+                }
+                Terminator::Open | Terminator::Return | Terminator::Exit => {}
+            }
+        }
+
+        seen
+    }
+
+    /// Returns true if some path through the body ends without an `ibalik`
+    pub fn falls_through(&self) -> bool {
+        self.reachable()
+            .into_iter()
+            .zip(self.blocks.iter())
+            .any(|(reachable, block)| reachable && matches!(block.terminator, Terminator::Exit))
+    }
+
+    /// Returns the span of the first statement of every unreachable run of
+    /// statements, paired with the span of the statement that cut it off
+    pub fn unreachable_stmts(&self) -> Vec<(Range<usize>, Range<usize>)> {
+        let reachable = self.reachable();
+
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let prev = &self.entries[entry.prev?];
+                if !reachable[entry.block] && reachable[prev.block] {
+                    Some((entry.span.clone(), prev.span.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Lowers a statement starting at `current`, returning the block where
+    /// control continues after it
+    fn lower_stmt(&mut self, stmt: &TypedStmt, current: BlockId) -> BlockId {
+        match &stmt.kind {
+            TypedStmtKind::Block { stmts } => self.lower_stmts(stmts, current),
+            TypedStmtKind::Ibalik { rhs } => {
+                let current = match rhs {
+                    Some(rhs) => self.lower_expr(rhs, current),
+                    None => current,
+                };
+                self.terminate(current, Terminator::Return);
+
+                // Anything after this point has no predecessor
+                self.new_block()
+            }
+            TypedStmtKind::Kung { branches } => self.lower_kung(
+                branches
+                    .iter()
+                    .map(|branch| (branch.cond.as_ref(), &*branch.block)),
+                current,
+                Self::lower_stmt,
+            ),
+            TypedStmtKind::Habang { cond, block, label } => {
+                let header = self.new_block();
+                self.terminate(current, Terminator::Goto(header));
+                let header_end = self.lower_expr(cond, header);
+
+                let body = self.new_block();
+                let exit = self.new_block();
+                let body_end = self.lower_loop_body(block, label, body, header, exit);
+                self.terminate(body_end, Terminator::Goto(header));
+
+                if Self::is_always_true(cond) {
+                    self.terminate(header_end, Terminator::Goto(body));
+                } else {
+                    self.terminate(
+                        header_end,
+                        Terminator::Branch {
+                            then: body,
+                            otherwise: exit,
+                        },
+                    );
+                }
+
+                exit
+            }
+            TypedStmtKind::Bawat {
+                iter, block, label, ..
+            } => {
+                let current = self.lower_expr(iter, current);
+                let header = self.new_block();
+                self.terminate(current, Terminator::Goto(header));
+
+                let body = self.new_block();
+                let exit = self.new_block();
+                let body_end = self.lower_loop_body(block, label, body, header, exit);
+                self.terminate(body_end, Terminator::Goto(header));
+                self.terminate(
+                    header,
+                    Terminator::Branch {
+                        then: body,
+                        otherwise: exit,
+                    },
+                );
+
+                exit
+            }
+            TypedStmtKind::Babalik { block, cond, label } => {
+                let body = self.new_block();
+                self.terminate(current, Terminator::Goto(body));
+
+                // `tuloy` skips to the condition, not back to the top of the body
+                let latch = self.new_block();
+                let exit = self.new_block();
+                let body_end = self.lower_loop_body(block, label, body, latch, exit);
+                self.terminate(body_end, Terminator::Goto(latch));
+                let latch_end = self.lower_expr(cond, latch);
+
+                if Self::is_always_true(cond) {
+                    self.terminate(latch_end, Terminator::Goto(body));
+                } else {
+                    self.terminate(
+                        latch_end,
+                        Terminator::Branch {
+                            then: body,
+                            otherwise: exit,
+                        },
+                    );
+                }
+
+                exit
+            }
+            TypedStmtKind::Tigil { label } => {
+                let target = self.loop_targets(label).break_to;
+                self.terminate(current, Terminator::Goto(target));
+                self.new_block()
+            }
+            TypedStmtKind::Tuloy { label } => {
+                let target = self.loop_targets(label).continue_to;
+                self.terminate(current, Terminator::Goto(target));
+                self.new_block()
+            }
+            TypedStmtKind::Ang { rhs, .. } | TypedStmtKind::Dapat { rhs, .. } => {
+                self.lower_expr(rhs, current)
+            }
+            TypedStmtKind::Expr { expr } => self.lower_expr(expr, current),
+            // A nested `paraan` gets its own graph when it is analyzed
+            TypedStmtKind::Paraan { .. } => current,
+        }
+    }
+
+    fn lower_stmts(&mut self, stmts: &[TypedStmt], current: BlockId) -> BlockId {
+        let mut current = current;
+        let mut prev = None;
+        for stmt in stmts.iter() {
+            self.entries.push(StmtEntry {
+                block: current,
+                span: stmt.span(),
+                prev,
+            });
+            prev = Some(self.entries.len() - 1);
+
+            current = self.lower_stmt(stmt, current);
+        }
+
+        current
+    }
+
+    /// Lowers the `kung` and block expressions inside `expr`, whose
+    /// statements can leave through `ibalik`, `tigil` or `tuloy`
+    fn lower_expr(&mut self, expr: &TypedExpr, current: BlockId) -> BlockId {
+        match &expr.kind {
+            TypedExprKind::Block { stmts, tail } => {
+                let current = self.lower_stmts(stmts, current);
+                match tail {
+                    Some(tail) => self.lower_expr(tail, current),
+                    None => current,
+                }
+            }
+            TypedExprKind::Kung { branches } => self.lower_kung(
+                branches.iter().map(|arm| (arm.cond.as_ref(), &arm.value)),
+                current,
+                Self::lower_expr,
+            ),
+            TypedExprKind::Binary { left, right, .. } => {
+                let current = self.lower_expr(left, current);
+                self.lower_expr(right, current)
+            }
+            TypedExprKind::Unary { right, .. } => self.lower_expr(right, current),
+            TypedExprKind::FnCall { callee, args } => {
+                let current = self.lower_expr(callee, current);
+                args.iter()
+                    .fold(current, |current, arg| self.lower_expr(arg, current))
+            }
+            TypedExprKind::ArrayLiteral { elems } => elems
+                .iter()
+                .fold(current, |current, elem| self.lower_expr(elem, current)),
+            TypedExprKind::Integer { .. }
+            | TypedExprKind::Float { .. }
+            | TypedExprKind::Bool { .. }
+            | TypedExprKind::Identifier { .. } => current,
+        }
+    }
+
+    /// Lowers a `kung` chain, whether a statement or an expression, with
+    /// `lower` lowering the block of each branch
+    fn lower_kung<'a, T: 'a>(
+        &mut self,
+        branches: impl Iterator<Item = (Option<&'a TypedExpr>, &'a T)>,
+        current: BlockId,
+        lower: fn(&mut Self, &T, BlockId) -> BlockId,
+    ) -> BlockId {
+        let join = self.new_block();
+        let mut current = current;
+        for (cond, block) in branches {
+            let then = self.new_block();
+            let then_end = lower(self, block, then);
+            self.terminate(then_end, Terminator::Goto(join));
+
+            match cond {
+                Some(cond) => {
+                    current = self.lower_expr(cond, current);
+                    let otherwise = self.new_block();
+                    self.terminate(current, Terminator::Branch { then, otherwise });
+                    current = otherwise;
+                }
+                None => {
+                    self.terminate(current, Terminator::Goto(then));
+                    return join;
+                }
+            }
+        }
+
+        // No `kungdi` without a condition, so every condition can be false
+        self.terminate(current, Terminator::Goto(join));
+        join
+    }
+
+    fn lower_loop_body(
+        &mut self,
+        block: &TypedStmt,
+        label: &Option<String>,
+        body: BlockId,
+        continue_to: BlockId,
+        exit: BlockId,
+    ) -> BlockId {
+        self.loops.push(LoopTargets {
+            label: label.clone(),
+            continue_to,
+            break_to: exit,
+        });
+        let body_end = self.lower_stmt(block, body);
+        self.loops.pop();
+
+        body_end
+    }
+
+    /// Finds the loop a `tigil` or `tuloy` refers to, which the analyzer has
+    /// already checked exists
+    fn loop_targets(&self, label: &Option<String>) -> &LoopTargets {
+        match label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rev()
+                .find(|l| l.label.as_ref() == Some(label))
+                .unwrap(),
+            None => self.loops.last().unwrap(),
+        }
+    }
+
+    fn is_always_true(cond: &TypedExpr) -> bool {
+        matches!(cond.kind, TypedExprKind::Bool { value: true })
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock {
+            terminator: Terminator::Open,
+        });
+
+        self.blocks.len() - 1
+    }
+
+    fn terminate(&mut self, id: BlockId, terminator: Terminator) {
+        let block = &mut self.blocks[id];
+        if matches!(block.terminator, Terminator::Open) {
+            block.terminator = terminator;
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Evaluates expressions while compiling.
+//!
+//! `dapat` initializers made of literals, other `dapat`s and operators are
+//! folded into a single literal, so the generated C gets the value instead of
+//! the expression. Unlike the running program, which wraps like C, folding
+//! reports a result that doesn't fit its type, pointing at the part of the
+//! expression that doesn't. Operators on literals alone are folded the same
+//! way to check them against the type they are used as.
+
+use std::ops::Range;
+
+use crate::{
+    analyzer::symbol::{Symbol, SymbolKind},
+    ast::typed_expr::{TypedExpr, TypedExprKind},
+    compiler::CompilerCtx,
+    error::CompilerError,
+    lexer::token::TokenKind,
+    toltype::TolType,
+};
+
+/// The value of a `dapat` known while compiling
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+}
+
+/// Why an expression couldn't be folded
+#[derive(Debug)]
+pub enum ConstEvalError {
+    /// The expression reads something only known while running
+    NotConstant,
+    Overflow(TolType),
+    DivisionByZero,
+    NegativeExponent,
+    /// Shifting a `ttype` by `amount` bits, at least as many as it has
+    InvalidShift {
+        amount: i128,
+        ttype: TolType,
+    },
+}
+
+impl ConstEvalError {
+    pub fn into_compiler_error(self, span: Range<usize>) -> CompilerError {
+        let reason = match self {
+            ConstEvalError::NotConstant => {
+                return CompilerError::NotConstant { span: span.into() };
+            }
+            ConstEvalError::Overflow(ttype) => format!("Lumampas sa saklaw ng `{ttype}`"),
+            ConstEvalError::DivisionByZero => "Paghahati sa zero".to_string(),
+            ConstEvalError::NegativeExponent => "Negatibong exponent sa integer".to_string(),
+            ConstEvalError::InvalidShift { amount, ttype } => {
+                return CompilerError::InvalidShiftAmount {
+                    amount,
+                    max: ttype.bit_width().unwrap_or(64) - 1,
+                    ttype: ttype.to_string(),
+                    span: span.into(),
+                };
+            }
+        };
+
+        CompilerError::ConstEvalFailed {
+            reason,
+            span: span.into(),
+        }
+    }
+}
+
+pub struct ConstEvaluator<'a> {
+    symbols: &'a [Symbol],
+    /// The type integers without a size are folded as, once it is known
+    unsized_as: Option<TolType>,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(symbols: &'a [Symbol]) -> Self {
+        Self {
+            symbols,
+            unsized_as: None,
+        }
+    }
+
+    /// Folds integers without a size as `ttype`, the type they are used as
+    pub fn unsized_as(mut self, ttype: &TolType) -> Self {
+        self.unsized_as = Some(ttype.clone());
+        self
+    }
+
+    fn type_of<'e>(&'e self, expr: &'e TypedExpr) -> &'e TolType {
+        match &self.unsized_as {
+            Some(ttype) if expr.ttype == TolType::UnsizedInteger => ttype,
+            _ => &expr.ttype,
+        }
+    }
+
+    /// Folds `expr`, or tells why it can't along with the span of the part
+    /// that can't be folded
+    pub fn eval(&self, expr: &TypedExpr) -> Result<ConstValue, (ConstEvalError, Range<usize>)> {
+        let at_expr = |e| (e, expr.span());
+        let ttype = self.type_of(expr);
+
+        let value = match &expr.kind {
+            TypedExprKind::Integer { .. } => ConstValue::Int(
+                expr.int_literal_value()
+                    .ok_or_else(|| at_expr(ConstEvalError::NotConstant))?,
+            ),
+            TypedExprKind::Float { .. } => ConstValue::Float(
+                expr.float_literal_value()
+                    .ok_or_else(|| at_expr(ConstEvalError::NotConstant))?,
+            ),
+            TypedExprKind::Bool { value } => ConstValue::Bool(*value),
+            TypedExprKind::Identifier { symbol_id, .. } => {
+                match self.symbols.get(*symbol_id).map(Symbol::kind) {
+                    Some(SymbolKind::ConstVar {
+                        value: Some(value), ..
+                    }) => value.clone(),
+                    _ => return Err(at_expr(ConstEvalError::NotConstant)),
+                }
+            }
+            TypedExprKind::Unary { right, op } => {
+                let right = self.eval(right)?;
+                match (op, right) {
+                    (TokenKind::Minus, ConstValue::Int(value)) => ConstValue::Int(-value),
+                    (TokenKind::Minus, ConstValue::Float(value)) => ConstValue::Float(-value),
+                    (TokenKind::Bang, ConstValue::Bool(value)) => ConstValue::Bool(!value),
+                    // Flipping the bits can't overflow, only wrap
+                    (TokenKind::Tilde, ConstValue::Int(value)) => {
+                        ConstValue::Int(wrap(!value, ttype))
+                    }
+                    _ => return Err(at_expr(ConstEvalError::NotConstant)),
+                }
+            }
+            TypedExprKind::Binary { left, right, op } => {
+                let left = self.eval(left)?;
+                let right_value = self.eval(right)?;
+                Self::binary(left, right_value, op, ttype).map_err(|e| match e {
+                    ConstEvalError::InvalidShift { .. } => (e, right.span()),
+                    e => at_expr(e),
+                })?
+            }
+            _ => return Err(at_expr(ConstEvalError::NotConstant)),
+        };
+
+        value.convert(ttype).map_err(at_expr)
+    }
+
+    fn binary(
+        left: ConstValue,
+        right: ConstValue,
+        op: &TokenKind,
+        ttype: &TolType,
+    ) -> Result<ConstValue, ConstEvalError> {
+        use ConstValue::*;
+
+        let value = match (left, right) {
+            (Bool(l), Bool(r)) => Bool(match op {
+                TokenKind::AmperAmper => l && r,
+                TokenKind::PipePipe => l || r,
+                TokenKind::EqualEqual => l == r,
+                TokenKind::BangEqual => l != r,
+                _ => return Err(ConstEvalError::NotConstant),
+            }),
+            (Int(l), Int(r)) => match op {
+                TokenKind::EqualEqual => Bool(l == r),
+                TokenKind::BangEqual => Bool(l != r),
+                TokenKind::Less => Bool(l < r),
+                TokenKind::LessEqual => Bool(l <= r),
+                TokenKind::Greater => Bool(l > r),
+                TokenKind::GreaterEqual => Bool(l >= r),
+                _ => Int(Self::int_arith(l, r, op, ttype)?),
+            },
+            (l, r) => {
+                let (l, r) = (l.as_float()?, r.as_float()?);
+                match op {
+                    TokenKind::EqualEqual => Bool(l == r),
+                    TokenKind::BangEqual => Bool(l != r),
+                    TokenKind::Less => Bool(l < r),
+                    TokenKind::LessEqual => Bool(l <= r),
+                    TokenKind::Greater => Bool(l > r),
+                    TokenKind::GreaterEqual => Bool(l >= r),
+                    TokenKind::Plus => Float(l + r),
+                    TokenKind::Minus => Float(l - r),
+                    TokenKind::Star => Float(l * r),
+                    TokenKind::Slash => Float(l / r),
+                    TokenKind::StarStar => Float(l.powf(r)),
+                    _ => return Err(ConstEvalError::NotConstant),
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Integer arithmetic, checked against the range of `ttype` by the caller.
+    /// Only `*` and `**` can overflow the `i128` holding 64-bit operands.
+    fn int_arith(
+        l: i128,
+        r: i128,
+        op: &TokenKind,
+        ttype: &TolType,
+    ) -> Result<i128, ConstEvalError> {
+        let overflow = || ConstEvalError::Overflow(ttype.clone());
+
+        let value = match op {
+            TokenKind::Plus => l + r,
+            TokenKind::Minus => l - r,
+            TokenKind::Star => l.checked_mul(r).ok_or_else(overflow)?,
+            TokenKind::Slash | TokenKind::Percent if r == 0 => {
+                return Err(ConstEvalError::DivisionByZero);
+            }
+            TokenKind::Slash => l / r,
+            TokenKind::Percent => l % r,
+            TokenKind::StarStar => {
+                let exp = u32::try_from(r).map_err(|_| ConstEvalError::NegativeExponent)?;
+                l.checked_pow(exp).ok_or_else(overflow)?
+            }
+            TokenKind::Amper => l & r,
+            TokenKind::Pipe => l | r,
+            TokenKind::Caret => l ^ r,
+            // Bits shifted out are dropped like in C, only the amount is checked
+            TokenKind::LessLess | TokenKind::GreaterGreater => {
+                let width = ttype.bit_width().unwrap_or(64);
+                let amount = u32::try_from(r)
+                    .ok()
+                    .filter(|amount| *amount < width)
+                    .ok_or_else(|| ConstEvalError::InvalidShift {
+                        amount: r,
+                        ttype: ttype.clone(),
+                    })?;
+                if *op == TokenKind::LessLess {
+                    wrap(l.wrapping_shl(amount), ttype)
+                } else {
+                    l >> amount
+                }
+            }
+            _ => return Err(ConstEvalError::NotConstant),
+        };
+
+        Ok(value)
+    }
+}
+
+impl ConstValue {
+    fn as_float(&self) -> Result<f64, ConstEvalError> {
+        match self {
+            ConstValue::Float(value) => Ok(*value),
+            // An integer exponent of a float base
+            ConstValue::Int(value) => Ok(*value as f64),
+            ConstValue::Bool(_) => Err(ConstEvalError::NotConstant),
+        }
+    }
+
+    /// Converts the value to `ttype`, failing if an integer doesn't fit
+    pub fn convert(self, ttype: &TolType) -> Result<ConstValue, ConstEvalError> {
+        match self {
+            ConstValue::Int(value) => match int_range(ttype) {
+                Some(range) if !range.contains(&value) => {
+                    Err(ConstEvalError::Overflow(ttype.clone()))
+                }
+                _ => Ok(ConstValue::Int(value)),
+            },
+            ConstValue::Float(value) if *ttype == TolType::F32 => {
+                Ok(ConstValue::Float(value as f32 as f64))
+            }
+            value => Ok(value),
+        }
+    }
+
+    /// Converts the value to `ttype` like the running program does, wrapping
+    /// integers that don't fit
+    pub fn cast(self, ttype: &TolType) -> ConstValue {
+        match self {
+            ConstValue::Int(value) if ttype.is_float() => {
+                ConstValue::Float(value as f64).cast(ttype)
+            }
+            ConstValue::Int(value) => ConstValue::Int(wrap(value, ttype)),
+            ConstValue::Float(value) if ttype.is_integer() => {
+                ConstValue::Int(wrap(value as i128, ttype))
+            }
+            ConstValue::Float(value) if *ttype == TolType::F32 => {
+                ConstValue::Float(value as f32 as f64)
+            }
+            value => value,
+        }
+    }
+
+    /// Builds the literal holding the value in place of the expression at
+    /// `span`, or `None` for floats that no literal can spell, like infinity
+    pub fn to_expr(
+        &self,
+        ttype: &TolType,
+        span: Range<usize>,
+        ctx: &mut CompilerCtx,
+    ) -> Option<TypedExpr> {
+        let (expr_kind, negative) = match self {
+            // `i128::MIN` has no positive literal
+            ConstValue::Int(i128::MIN) => return None,
+            ConstValue::Int(value) => (TypedExprKind::Integer { value: value.abs() }, *value < 0),
+            ConstValue::Float(value) if value.is_finite() => (
+                TypedExprKind::Float { value: value.abs() },
+                value.is_sign_negative(),
+            ),
+            ConstValue::Float(_) => return None,
+            ConstValue::Bool(value) => (TypedExprKind::Bool { value: *value }, false),
+        };
+
+        let expr = TypedExpr::new(expr_kind, ttype.clone(), span.clone(), ctx.new_node_id());
+        if !negative {
+            return Some(expr);
+        }
+
+        // Spelled the way the parser reads a negative literal
+        Some(TypedExpr::new(
+            TypedExprKind::Unary {
+                right: Box::new(expr),
+                op: TokenKind::Minus,
+            },
+            ttype.clone(),
+            span,
+            ctx.new_node_id(),
+        ))
+    }
+}
+
+/// Values an integer type can hold. Integers without a size only have to fit
+/// some 64-bit type until they are given one.
+pub fn int_range(ttype: &TolType) -> Option<Range<i128>> {
+    let range = match ttype {
+        TolType::I8 => i8::MIN as i128..i8::MAX as i128 + 1,
+        TolType::I16 => i16::MIN as i128..i16::MAX as i128 + 1,
+        TolType::I32 => i32::MIN as i128..i32::MAX as i128 + 1,
+        TolType::I64 | TolType::ISize => i64::MIN as i128..i64::MAX as i128 + 1,
+        TolType::UnsizedInteger => i64::MIN as i128..u64::MAX as i128 + 1,
+        TolType::U8 => 0..u8::MAX as i128 + 1,
+        TolType::U16 => 0..u16::MAX as i128 + 1,
+        TolType::U32 => 0..u32::MAX as i128 + 1,
+        TolType::U64 | TolType::USize => 0..u64::MAX as i128 + 1,
+        _ => return None,
+    };
+
+    Some(range)
+}
+
+/// Wraps an integer into the range of `ttype`, for operations that drop bits
+fn wrap(value: i128, ttype: &TolType) -> i128 {
+    match int_range(ttype) {
+        Some(range) => {
+            let len = range.end - range.start;
+            (value - range.start).rem_euclid(len) + range.start
+        }
+        None => value,
+    }
+}
@@ -1,5 +1,9 @@
 pub mod analyzer_ctx;
+pub mod cfg;
+pub mod const_eval;
 pub mod symbol;
+#[cfg(test)]
+mod tests;
 pub mod type_resolver;
 
 use std::{
@@ -11,57 +15,226 @@ use miette::LabeledSpan;
 
 use crate::{
     analyzer::{
-        analyzer_ctx::AnalyzerContext,
+        analyzer_ctx::{AnalyzerContext, LoopLabel},
+        cfg::Cfg,
+        const_eval::{ConstEvalError, ConstEvaluator, ConstValue},
         symbol::{Symbol, SymbolKind},
         type_resolver::TypeResolver,
     },
     ast::{
         Ast, TypedAst,
-        expr::{Expr, ExprKind},
-        stmt::{Stmt, StmtKind},
-        typed_expr::{TypedExpr, TypedExprKind},
+        expr::{Expr, ExprId, ExprKind, Exprs},
+        stmt::{ParamInfo, Stmt, StmtId, StmtKind, Stmts},
+        typed_expr::{TypedExpr, TypedExprKind, TypedKungArm},
         typed_stmt::{TypedKungBranches, TypedStmt, TypedStmtKind},
     },
     compiler::CompilerCtx,
-    error::CompilerError,
-    lexer::token::{Token, TokenKind},
+    error::{CompilerError, Fix, WarningKind},
+    intern::Name,
+    lexer::{
+        literal,
+        token::{Token, TokenKind},
+    },
     toltype::TolType,
 };
 
 pub type SymbolId = usize;
 
-pub struct SemanticAnalyzer<'ctx> {
+/// Names declared at the top level, kept by the REPL between inputs
+pub type TopLevelScope = HashMap<Name, SymbolId>;
+
+/// Name of the `paraan` where the program starts
+pub const ENTRY_POINT: &str = "main";
+
+pub struct SemanticAnalyzer<'ctx, 'src> {
     compiler_ctx: &'ctx mut CompilerCtx,
     analyzer_ctx: AnalyzerContext,
-    symbol_ids: Vec<HashMap<String, SymbolId>>,
+    /// Statements and expressions of the AST being analyzed, each taken out
+    /// as it is analyzed
+    stmts: Stmts<'src>,
+    exprs: Exprs<'src>,
+    symbol_ids: Vec<HashMap<Name, SymbolId>>,
+    /// Symbols of top-level declarations registered ahead of analysis, keyed by
+    /// the start of their name's span
+    predeclared: HashMap<usize, SymbolId>,
+    /// Top-level `dapat`s yet to be analyzed, keyed by name, so that using
+    /// one too early isn't reported as using an undeclared name
+    pending_dapats: HashMap<Name, Range<usize>>,
 }
 
-impl<'ctx> SemanticAnalyzer<'ctx> {
+impl<'ctx, 'src> SemanticAnalyzer<'ctx, 'src> {
     pub fn new(compiler_ctx: &'ctx mut CompilerCtx) -> Self {
         Self {
             compiler_ctx,
             analyzer_ctx: AnalyzerContext::new(),
+            stmts: Stmts::default(),
+            exprs: Exprs::default(),
             symbol_ids: vec![HashMap::new()],
+            predeclared: HashMap::new(),
+            pending_dapats: HashMap::new(),
         }
     }
 
-    pub fn analyze(mut self, ast: Ast) -> TypedAst {
-        // TODO: Declare symbols first then analyze
-        let mut typed_ast = Vec::new();
-        for mut stmt in ast {
-            if let Err(e) = TypeResolver::resolve_stmt(&mut stmt) {
+    pub fn analyze(mut self, ast: Ast<'src>) -> TypedAst {
+        let typed_ast = self.analyze_ast(ast);
+        self.check_unused();
+
+        typed_ast
+    }
+
+    /// Continues analyzing at the top level of an earlier input of the REPL
+    pub fn with_scope(compiler_ctx: &'ctx mut CompilerCtx, scope: TopLevelScope) -> Self {
+        Self {
+            symbol_ids: vec![scope],
+            ..Self::new(compiler_ctx)
+        }
+    }
+
+    /// Analyzes one input of the REPL, returning the top-level names it leaves
+    /// behind. Unused symbols aren't reported since later input may use them.
+    pub fn analyze_input(mut self, ast: Ast<'src>) -> (TypedAst, TopLevelScope) {
+        let typed_ast = self.analyze_ast(ast);
+        let scope = self.symbol_ids.swap_remove(0);
+
+        (typed_ast, scope)
+    }
+
+    fn analyze_ast(&mut self, ast: Ast<'src>) -> TypedAst {
+        let Ast {
+            top_level,
+            stmts,
+            exprs,
+        } = ast;
+        self.stmts = stmts;
+        self.exprs = exprs;
+        for id in top_level.iter() {
+            if let Err(e) = TypeResolver::resolve_stmt(&mut self.stmts[*id]) {
                 self.compiler_ctx.add_error(e);
             }
+        }
+
+        let (dapats, rest): (Vec<_>, Vec<_>) = top_level
+            .into_iter()
+            .map(|id| self.stmts.take(id))
+            .enumerate()
+            .filter(|(_, stmt)| !matches!(stmt.kind, StmtKind::Gagawin | StmtKind::Null))
+            .partition(|(_, stmt)| matches!(stmt.kind, StmtKind::Dapat { .. }));
+
+        // Top-level `dapat`s are evaluated before everything else, since
+        // `paraan` signatures can use them as array sizes. The signatures
+        // that don't are declared first so that a `dapat` calling a `paraan`
+        // is told it isn't constant rather than undeclared.
+        self.declare_globals(rest.iter().map(|(_, stmt)| stmt));
+        self.pending_dapats = dapats
+            .iter()
+            .filter_map(|(_, stmt)| match &stmt.kind {
+                StmtKind::Dapat { id, .. } => Some((id.name(), id.span())),
+                _ => None,
+            })
+            .collect();
+        let mut used_early = Vec::new();
+        let mut typed_stmts = Vec::new();
+        for (index, stmt) in dapats {
+            if let StmtKind::Dapat { id, .. } = &stmt.kind {
+                self.pending_dapats.remove(&id.name());
+            }
+            match self.analyze_statement(stmt) {
+                Ok(ts) => typed_stmts.push((index, ts)),
+                Err(e) => {
+                    if let CompilerError::UsedBeforeDeclaration { name, .. } = &e {
+                        used_early.push(Name::new(name));
+                    }
+                    self.compiler_ctx.add_error(e);
+                }
+            };
+        }
+        // The error already tells about the early use, which shouldn't also
+        // make the `dapat` look unused
+        for name in used_early {
+            if let Some(&id) = self.symbol_ids[0].get(&name) {
+                self.compiler_ctx.symbol_table[id].add_use();
+            }
+        }
+        self.declare_globals(rest.iter().map(|(_, stmt)| stmt));
+
+        for (index, stmt) in rest {
             match self.analyze_statement(stmt) {
-                Ok(ts) => typed_ast.push(ts),
+                Ok(ts) => typed_stmts.push((index, ts)),
                 Err(e) => self.compiler_ctx.add_error(e),
             };
         }
 
-        typed_ast
+        typed_stmts.sort_by_key(|(index, _)| *index);
+        typed_stmts.into_iter().map(|(_, ts)| ts).collect()
     }
 
-    pub fn analyze_statement(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
+    /// Warns about every variable and `paraan` that was never read. Names
+    /// starting with `_` and the entry point are left alone.
+    fn check_unused(&mut self) {
+        let mut unused = Vec::new();
+        for sym in self.compiler_ctx.symbol_table.iter() {
+            if sym.uses() > 0 || sym.name().starts_with('_') {
+                continue;
+            }
+
+            let span = sym.span();
+            let warning = match sym.kind() {
+                SymbolKind::Var { .. } | SymbolKind::ConstVar { .. } => {
+                    WarningKind::UnusedVariable {
+                        name: sym.name().to_string(),
+                        span: span.clone().into(),
+                        fix: Fix {
+                            span,
+                            replacement: format!("_{}", sym.name()),
+                        },
+                    }
+                }
+                SymbolKind::Func { .. } if sym.name() == ENTRY_POINT => continue,
+                SymbolKind::Func { .. } => WarningKind::UnusedFunction {
+                    name: sym.name().to_string(),
+                    span: span.into(),
+                },
+            };
+            unused.push(warning);
+        }
+
+        for warning in unused {
+            self.compiler_ctx.add_warning(warning);
+        }
+    }
+
+    /// Registers the signatures of every top-level `paraan` before any body is
+    /// analyzed, so that a `paraan` can be called before its definition.
+    fn declare_globals<'s>(&mut self, stmts: impl Iterator<Item = &'s Stmt<'src>>)
+    where
+        'src: 's,
+    {
+        for stmt in stmts {
+            let StmtKind::Paraan {
+                id,
+                return_type,
+                params,
+                ..
+            } = &stmt.kind
+            else {
+                continue;
+            };
+            if self.predeclared.contains_key(&id.span.start) {
+                continue;
+            }
+
+            // Redeclarations and bad array sizes are reported once the
+            // `paraan` itself is analyzed
+            let Ok(signature) = self.paraan_signature(params, return_type) else {
+                continue;
+            };
+            if let Ok(symbol_id) = self.declare_symbol(id, signature) {
+                self.predeclared.insert(id.span.start, symbol_id);
+            }
+        }
+    }
+
+    pub fn analyze_statement(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
         match &stmt.kind {
             StmtKind::Paraan { .. } => self.analyze_paraan(stmt),
             StmtKind::Ang { .. } => self.analyze_decl(stmt),
@@ -69,20 +242,24 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
             StmtKind::Ibalik { .. } => self.analyze_ibalik(stmt),
             StmtKind::Bawat { .. } => self.analyze_bawat(stmt),
             StmtKind::Habang { .. } => self.analyze_habang(stmt),
+            StmtKind::Babalik { .. } => self.analyze_babalik(stmt),
+            StmtKind::Expr { .. } => self.analyze_expr_stmt(stmt),
+            StmtKind::Tigil { .. } | StmtKind::Tuloy { .. } => self.analyze_loop_control(stmt),
             StmtKind::Kung { .. } => self.analyze_kung(stmt),
             StmtKind::Block { indent_span, .. } => Err(CompilerError::InvalidIndent {
                 span: indent_span.clone().into(),
             }),
-            StmtKind::Gagawin => todo!(),
-            StmtKind::Null => todo!(),
+            // Placeholders produce no code, callers skip them
+            StmtKind::Gagawin | StmtKind::Null => unreachable!(),
         }
     }
 
-    fn analyze_paraan(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
+    fn analyze_paraan(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
         let StmtKind::Paraan {
             id,
             return_type,
-            params,
+            mut params,
             block,
             ..
         } = stmt.kind
@@ -90,37 +267,119 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
             unreachable!()
         };
 
-        let symbol_id = self.declare_symbol(
-            &id,
-            SymbolKind::Func {
-                param_types: params.iter().map(|pi| pi.ttype.clone()).collect(),
-                return_type: return_type.clone(),
-            },
-        )?;
+        for param in params.iter_mut() {
+            param.ttype = self.resolve_array_sizes(&param.ttype)?;
+        }
+        let return_type = self.resolve_array_sizes(&return_type)?;
+
+        let symbol_id = match self.predeclared.remove(&id.span.start) {
+            Some(symbol_id) => symbol_id,
+            None => {
+                let signature = self.paraan_signature(&params, &return_type)?;
+                self.declare_symbol(&id, signature)?
+            }
+        };
 
         self.enter_scope();
+        let mut param_ids = Vec::with_capacity(params.len());
         for param in params.iter() {
-            self.declare_symbol(
+            param_ids.push(self.declare_symbol(
                 &param.id,
                 SymbolKind::Var {
                     ttype: param.ttype.clone(),
                 },
-            )?;
+            )?);
         }
 
-        self.analyzer_ctx.enter_fn(return_type.clone());
-        let block = self.analyze_block(*block)?;
+        let errors_before = self.compiler_ctx.errors.len();
+        self.analyzer_ctx.enter_fn(symbol_id, return_type.clone());
+        let block = self.analyze_block(block)?;
         self.analyzer_ctx.exit_fn();
         self.exit_scope();
 
-        Ok(TypedStmt::new(TypedStmtKind::Paraan {
-            params,
-            symbol_id,
-            block: Box::new(block),
-        }))
+        // Statements with errors are left out of the typed body, so its flow
+        // would be checked without them, e.g. an `ibalik` of the wrong type
+        if self.compiler_ctx.errors.len() == errors_before {
+            self.check_flow(&block, &return_type, id.span());
+        }
+
+        Ok(self.typed_stmt(
+            TypedStmtKind::Paraan {
+                param_ids,
+                symbol_id,
+                block: Box::new(block),
+            },
+            stmt_span,
+        ))
     }
 
-    pub fn analyze_decl(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
+    fn check_flow(&mut self, body: &TypedStmt, return_type: &TolType, name_span: Range<usize>) {
+        let cfg = Cfg::from_body(body);
+
+        for (span, cause_span) in cfg.unreachable_stmts() {
+            self.compiler_ctx.add_warning(WarningKind::UnreachableCode {
+                span: span.into(),
+                cause_span: cause_span.into(),
+            });
+        }
+
+        if *return_type != TolType::Void && cfg.falls_through() {
+            self.compiler_ctx.add_error(CompilerError::MissingReturn {
+                expected: return_type.to_string(),
+                span: name_span.into(),
+            });
+        }
+    }
+
+    fn paraan_signature(
+        &mut self,
+        params: &[ParamInfo<'src>],
+        return_type: &TolType,
+    ) -> Result<SymbolKind, CompilerError> {
+        Ok(SymbolKind::Func {
+            param_types: params
+                .iter()
+                .map(|pi| self.resolve_array_sizes(&pi.ttype))
+                .collect::<Result<_, _>>()?,
+            return_type: self.resolve_array_sizes(return_type)?,
+        })
+    }
+
+    /// Replaces the `dapat` names used as array sizes with their values
+    fn resolve_array_sizes(&mut self, ttype: &TolType) -> Result<TolType, CompilerError> {
+        match ttype {
+            TolType::Array { inner, size } => Ok(TolType::Array {
+                inner: Box::new(self.resolve_array_sizes(inner)?),
+                size: *size,
+            }),
+            TolType::UnresolvedArray {
+                inner,
+                size,
+                size_span,
+            } => {
+                let symbol_id = self.lookup_symbol(Name::new(size), size_span.clone())?;
+                let size = match self.compiler_ctx.symbol_table[symbol_id].kind() {
+                    SymbolKind::ConstVar {
+                        value: Some(ConstValue::Int(value)),
+                        ..
+                    } => usize::try_from(*value).ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| CompilerError::InvalidArraySize {
+                    span: size_span.clone().into(),
+                })?;
+
+                Ok(TolType::Array {
+                    inner: Box::new(self.resolve_array_sizes(inner)?),
+                    size: Some(size),
+                })
+            }
+            _ => Ok(ttype.clone()),
+        }
+    }
+
+    pub fn analyze_decl(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
         let (is_ang, id, ttype, rhs) = {
             match stmt.kind {
                 StmtKind::Ang { id, ttype, rhs } => (true, id, ttype, rhs),
@@ -129,41 +388,74 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
             }
         };
 
-        let rhs_span = rhs.span();
-        let rhs_str = rhs.to_string();
-        let rhs_typex = self.analyze_expression(rhs)?;
+        let rhs_span = self.exprs[rhs].span();
+        let rhs_str = self.exprs.display(rhs).to_string();
+        let mut rhs_typex = self.analyze_expression(rhs)?;
 
+        let ttype = ttype
+            .map(|ttype| self.resolve_array_sizes(&ttype))
+            .transpose()?;
         let ttype = self.infer_type(
             ttype.as_ref(),
             &rhs_typex.ttype,
             id.span(),
-            rhs_span,
+            rhs_span.clone(),
             id.lexeme(),
             &rhs_str,
         )?;
+        Self::fit_literals(&mut rhs_typex, &ttype)?;
 
-        let symbol_id = self.declare_symbol(
-            &id,
-            if is_ang {
-                SymbolKind::Var { ttype }
-            } else {
-                SymbolKind::ConstVar { ttype }
-            },
-        )?;
+        let kind = if is_ang {
+            SymbolKind::Var { ttype }
+        } else {
+            let value = self.eval_dapat(&mut rhs_typex, &ttype)?;
+            SymbolKind::ConstVar { ttype, value }
+        };
+        let symbol_id = self.declare_symbol(&id, kind)?;
         if is_ang {
-            Ok(TypedStmt::new(TypedStmtKind::Ang {
-                symbol_id,
-                rhs: rhs_typex,
-            }))
+            Ok(self.typed_stmt(
+                TypedStmtKind::Ang {
+                    symbol_id,
+                    rhs: rhs_typex,
+                },
+                stmt_span,
+            ))
         } else {
-            Ok(TypedStmt::new(TypedStmtKind::Dapat {
-                symbol_id,
-                rhs: rhs_typex,
-            }))
+            Ok(self.typed_stmt(
+                TypedStmtKind::Dapat {
+                    symbol_id,
+                    rhs: rhs_typex,
+                },
+                stmt_span,
+            ))
+        }
+    }
+
+    /// Folds the initializer of a `dapat` into a literal when it can be
+    /// computed while compiling, which it has to be outside of a `paraan`
+    fn eval_dapat(
+        &mut self,
+        rhs_typex: &mut TypedExpr,
+        ttype: &TolType,
+    ) -> Result<Option<ConstValue>, CompilerError> {
+        let is_top_level = self.symbol_ids.len() == 1;
+        let value = match ConstEvaluator::new(&self.compiler_ctx.symbol_table)
+            .eval(rhs_typex)
+            .and_then(|value| value.convert(ttype).map_err(|e| (e, rhs_typex.span())))
+        {
+            Ok(value) => value,
+            Err((ConstEvalError::NotConstant, _)) if !is_top_level => return Ok(None),
+            Err((e, span)) => return Err(e.into_compiler_error(span)),
+        };
+
+        if let Some(literal) = value.to_expr(ttype, rhs_typex.span(), self.compiler_ctx) {
+            *rhs_typex = literal;
         }
+
+        Ok(Some(value))
     }
 
-    fn analyze_ibalik(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
+    fn analyze_ibalik(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
         let stmt_span = stmt.span();
         let StmtKind::Ibalik { rhs } = stmt.kind else {
             unreachable!()
@@ -179,17 +471,18 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
         }
 
         if rhs.is_none() && cur_fn_return_type == &TolType::Void {
-            return Ok(TypedStmt::new(TypedStmtKind::Ibalik { rhs: None }));
+            return Ok(self.typed_stmt(TypedStmtKind::Ibalik { rhs: None }, stmt_span));
         }
 
-        let rhs_span = rhs.as_ref().unwrap().span();
-        let rhs_typex = self.analyze_expression(rhs.unwrap())?;
+        let rhs = rhs.unwrap();
+        let rhs_span = self.exprs[rhs].span();
+        let mut rhs_typex = self.analyze_expression(rhs)?;
         match self
             .analyzer_ctx
             .cur_fn_return_type()
             .coerce(&rhs_typex.ttype)
         {
-            Some(_) => {}
+            Some(_) => Self::fit_literals(&mut rhs_typex, self.analyzer_ctx.cur_fn_return_type())?,
             None => {
                 return Err(CompilerError::UnexpectedType2 {
                     expected: self.analyzer_ctx.cur_fn_return_type().to_string(),
@@ -199,20 +492,31 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
             }
         }
 
-        Ok(TypedStmt::new(TypedStmtKind::Ibalik {
-            rhs: Some(rhs_typex),
-        }))
+        Ok(self.typed_stmt(
+            TypedStmtKind::Ibalik {
+                rhs: Some(rhs_typex),
+            },
+            stmt_span,
+        ))
     }
 
-    fn analyze_bawat(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
-        let StmtKind::Bawat { bind, iter, block } = stmt.kind else {
+    fn analyze_bawat(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
+        let StmtKind::Bawat {
+            bind,
+            iter,
+            block,
+            label,
+        } = stmt.kind
+        else {
             unreachable!()
         };
+        let label = self.loop_label(label)?;
 
         self.enter_scope();
         let iter_typex = self.analyze_expression(iter)?;
         let bind_type = iter_typex.ttype.clone();
-        self.declare_symbol(
+        let bind_id = self.declare_symbol(
             &bind,
             SymbolKind::Var {
                 ttype: bind_type.clone(),
@@ -220,23 +524,33 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
         )?;
 
         self.enter_scope();
-        let block = self.analyze_block(*block)?;
+        self.analyzer_ctx.enter_loop(label.clone());
+        let block = self.analyze_block(block);
+        self.analyzer_ctx.exit_loop();
+        let block = block?;
         self.exit_scope();
 
         self.exit_scope();
 
-        Ok(TypedStmt::new(TypedStmtKind::Bawat {
-            iter: iter_typex,
-            bind_type,
-            block: Box::new(block),
-        }))
+        Ok(self.typed_stmt(
+            TypedStmtKind::Bawat {
+                iter: iter_typex,
+                bind_id,
+                bind_type,
+                block: Box::new(block),
+                label: label.map(|(name, _)| name),
+            },
+            stmt_span,
+        ))
     }
 
-    fn analyze_habang(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
-        let StmtKind::Habang { cond, block } = stmt.kind else {
+    fn analyze_habang(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
+        let StmtKind::Habang { cond, block, label } = stmt.kind else {
             unreachable!()
         };
-        let cond_span = cond.span();
+        let label = self.loop_label(label)?;
+        let cond_span = self.exprs[cond].span();
         let cond_typex = self.analyze_expression(cond)?;
 
         if cond_typex.ttype != TolType::Bool {
@@ -248,16 +562,110 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
         }
 
         self.enter_scope();
-        let block = self.analyze_block(*block)?;
+        self.analyzer_ctx.enter_loop(label.clone());
+        let block = self.analyze_block(block);
+        self.analyzer_ctx.exit_loop();
+        let block = block?;
         self.exit_scope();
 
-        Ok(TypedStmt::new(TypedStmtKind::Habang {
-            cond: cond_typex,
-            block: Box::new(block),
-        }))
+        Ok(self.typed_stmt(
+            TypedStmtKind::Habang {
+                cond: cond_typex,
+                block: Box::new(block),
+                label: label.map(|(name, _)| name),
+            },
+            stmt_span,
+        ))
     }
 
-    fn analyze_kung(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
+    fn analyze_babalik(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
+        let StmtKind::Babalik { block, cond, label } = stmt.kind else {
+            unreachable!()
+        };
+        let label = self.loop_label(label)?;
+
+        self.enter_scope();
+        self.analyzer_ctx.enter_loop(label.clone());
+        let block = self.analyze_block(block);
+        self.analyzer_ctx.exit_loop();
+        let block = block?;
+        self.exit_scope();
+
+        // Like C's `do { } while`, the condition cannot see the body's variables
+        let cond_span = self.exprs[cond].span();
+        let cond_typex = self.analyze_expression(cond)?;
+        if cond_typex.ttype != TolType::Bool {
+            return Err(CompilerError::UnexpectedType2 {
+                expected: TolType::Bool.to_string(),
+                found: cond_typex.ttype.to_string(),
+                span: cond_span.into(),
+            });
+        }
+
+        Ok(self.typed_stmt(
+            TypedStmtKind::Babalik {
+                block: Box::new(block),
+                cond: cond_typex,
+                label: label.map(|(name, _)| name),
+            },
+            stmt_span,
+        ))
+    }
+
+    /// Returns the label of a loop, rejecting a name already given to a loop
+    /// surrounding it
+    fn loop_label(&self, label: Option<Token<'src>>) -> Result<Option<LoopLabel>, CompilerError> {
+        let Some(label) = label else {
+            return Ok(None);
+        };
+
+        if let Some(declared_span) = self.analyzer_ctx.loop_label_span(label.lexeme()) {
+            return Err(CompilerError::DuplicateLabel {
+                declared_span: declared_span.into(),
+                label: label.lexeme().to_string(),
+                redeclared_span: label.span().into(),
+            });
+        }
+
+        Ok(Some((label.lexeme().to_string(), label.span())))
+    }
+
+    fn analyze_loop_control(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
+        let (keyword, label) = match stmt.kind {
+            StmtKind::Tigil { label } => ("tigil", label),
+            StmtKind::Tuloy { label } => ("tuloy", label),
+            _ => unreachable!(),
+        };
+
+        if !self.analyzer_ctx.in_loop() {
+            return Err(CompilerError::LoopControlOutsideLoop {
+                keyword: keyword.to_string(),
+                span: stmt_span.into(),
+            });
+        }
+
+        if let Some(label) = &label
+            && !self.analyzer_ctx.has_loop_label(label.lexeme())
+        {
+            return Err(CompilerError::UndeclaredLabel {
+                label: label.lexeme().to_string(),
+                span: label.span().into(),
+            });
+        }
+
+        let label = label.map(|tok| tok.lexeme().to_string());
+        let kind = match keyword {
+            "tigil" => TypedStmtKind::Tigil { label },
+            _ => TypedStmtKind::Tuloy { label },
+        };
+
+        Ok(self.typed_stmt(kind, stmt_span))
+    }
+
+    fn analyze_kung(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
         let StmtKind::Kung { branches } = stmt.kind else {
             unreachable!()
         };
@@ -265,30 +673,8 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
         let mut typed_kung_branches = Vec::new();
         let branches_len = branches.len();
         for (i, branch) in branches.into_iter().enumerate() {
-            let cond_typex = match branch.cond {
-                Some(e) => {
-                    let cond_span = e.span();
-                    let cond_typex = self.analyze_expression(e)?;
-                    if cond_typex.ttype != TolType::Bool {
-                        return Err(CompilerError::UnexpectedType2 {
-                            expected: TolType::Bool.to_string(),
-                            found: cond_typex.ttype.to_string(),
-                            span: cond_span.into(),
-                        });
-                    }
-
-                    Some(cond_typex)
-                }
-                None => {
-                    if branches_len - 1 != i {
-                        return Err(CompilerError::InvalidKungdiBranch {
-                            span: branch.span.into(),
-                        });
-                    }
-
-                    None
-                }
-            };
+            let cond_typex =
+                self.analyze_kung_cond(branch.cond, branches_len - 1 == i, branch.span)?;
             self.enter_scope();
             let block = self.analyze_block(branch.block)?;
             self.exit_scope();
@@ -298,118 +684,330 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
             })
         }
 
-        Ok(TypedStmt::new(TypedStmtKind::Kung {
-            branches: typed_kung_branches,
-        }))
+        Ok(self.typed_stmt(
+            TypedStmtKind::Kung {
+                branches: typed_kung_branches,
+            },
+            stmt_span,
+        ))
     }
 
-    fn analyze_block(&mut self, stmt: Stmt) -> Result<TypedStmt, CompilerError> {
+    /// Checks the condition of a `kung` or `kungdi` branch. Only the last
+    /// branch may leave it out.
+    fn analyze_kung_cond(
+        &mut self,
+        cond: Option<ExprId>,
+        is_last: bool,
+        branch_span: Range<usize>,
+    ) -> Result<Option<TypedExpr>, CompilerError> {
+        let Some(cond) = cond else {
+            if !is_last {
+                return Err(CompilerError::InvalidKungdiBranch {
+                    span: branch_span.into(),
+                });
+            }
+
+            return Ok(None);
+        };
+
+        let cond_span = self.exprs[cond].span();
+        let cond_typex = self.analyze_expression(cond)?;
+        if cond_typex.ttype != TolType::Bool {
+            return Err(CompilerError::UnexpectedType2 {
+                expected: TolType::Bool.to_string(),
+                found: cond_typex.ttype.to_string(),
+                span: cond_span.into(),
+            });
+        }
+
+        Ok(Some(cond_typex))
+    }
+
+    fn analyze_expr_stmt(&mut self, stmt: Stmt<'src>) -> Result<TypedStmt, CompilerError> {
+        let stmt_span = stmt.span();
+        let StmtKind::Expr { expr } = stmt.kind else {
+            unreachable!()
+        };
+
+        let expr = self.analyze_expression(expr)?;
+        Ok(self.typed_stmt(TypedStmtKind::Expr { expr }, stmt_span))
+    }
+
+    fn analyze_block(&mut self, id: StmtId) -> Result<TypedStmt, CompilerError> {
+        let stmt = self.stmts.take(id);
+        let stmt_span = stmt.span();
         let StmtKind::Block { stmts, .. } = stmt.kind else {
             unreachable!()
         };
 
+        let stmts = self.analyze_stmts(stmts);
+        Ok(self.typed_stmt(TypedStmtKind::Block { stmts }, stmt_span))
+    }
+
+    /// Analyzes the statements of a block, recording errors so one bad
+    /// statement doesn't hide the rest
+    fn analyze_stmts(&mut self, stmts: Vec<StmtId>) -> Vec<TypedStmt> {
+        let stmts_len = stmts.len();
         let mut typed_stmts = Vec::new();
-        for stmt in stmts {
+        for id in stmts {
+            let stmt = self.stmts.take(id);
+            match stmt.kind {
+                StmtKind::Gagawin => {
+                    if stmts_len > 1 {
+                        self.compiler_ctx.add_warning(WarningKind::NeedlessGagawin {
+                            span: stmt.span.into(),
+                        });
+                    }
+                    continue;
+                }
+                StmtKind::Null => continue,
+                _ => {}
+            }
+
             match self.analyze_statement(stmt) {
                 Ok(ts) => typed_stmts.push(ts),
                 Err(e) => self.compiler_ctx.add_error(e),
             };
         }
 
-        Ok(TypedStmt::new(TypedStmtKind::Block { stmts: typed_stmts }))
+        typed_stmts
     }
 
-    pub fn analyze_expression(&mut self, expr: Expr) -> Result<TypedExpr, CompilerError> {
+    pub fn analyze_expression(&mut self, id: ExprId) -> Result<TypedExpr, CompilerError> {
+        let expr = self.exprs.take(id);
         let expr_span = expr.span();
         match expr.kind {
-            ExprKind::Integer { mut lexeme, suffix } => {
-                let ttype = match suffix {
-                    Some(s) => {
-                        // Remove suffix as the compiler doesn't need it anymore
-                        lexeme.lexeme = lexeme.lexeme[0..lexeme.lexeme.len() - s.len()].to_string();
-                        TolType::UnsizedInteger.coerce_or_mismatch(
-                            &s.as_str().into(),
-                            expr_span.start..expr_span.end - s.len(),
-                            expr_span.end - s.len()..expr_span.end,
-                        )?
-                    }
-                    None => TolType::UnsizedInteger,
-                };
-                Ok(TypedExpr::new(TypedExprKind::Integer { lexeme }, ttype))
-            }
-            ExprKind::Float { mut lexeme, suffix } => {
+            ExprKind::Integer { .. } => self.analyze_integer(expr, false),
+            ExprKind::Float { lexeme, suffix } => {
+                // Remove suffix as the compiler doesn't need it anymore
+                let digits = &lexeme.lexeme()[..lexeme.lexeme().len() - suffix.map_or(0, str::len)];
                 let ttype = match suffix {
-                    Some(s) => {
-                        // Remove suffix as the compiler doesn't need it anymore
-                        lexeme.lexeme = lexeme.lexeme[0..lexeme.lexeme.len() - s.len()].to_string();
-                        TolType::UnsizedFloat.coerce_or_mismatch(
-                            &s.as_str().into(),
-                            expr_span.start..expr_span.end - s.len(),
-                            expr_span.end - s.len()..expr_span.end,
-                        )?
-                    }
+                    Some(s) => TolType::UnsizedFloat.coerce_or_mismatch(
+                        &s.into(),
+                        expr_span.start..expr_span.end - s.len(),
+                        expr_span.end - s.len()..expr_span.end,
+                    )?,
                     None => TolType::UnsizedFloat,
                 };
-                Ok(TypedExpr::new(TypedExprKind::Float { lexeme }, ttype))
+                let value = literal::float_value(digits).ok_or_else(|| {
+                    CompilerError::InvalidFloatLiteral {
+                        span: expr_span.clone().into(),
+                    }
+                })?;
+                Ok(self.typed_expr(TypedExprKind::Float { value }, ttype, expr_span))
+            }
+            ExprKind::Boolean { lexeme } => {
+                let value = lexeme.lexeme() == "tama";
+                Ok(self.typed_expr(TypedExprKind::Bool { value }, TolType::Bool, expr_span))
             }
-            ExprKind::Boolean { lexeme } => Ok(TypedExpr::new(
-                TypedExprKind::Bool { lexeme },
-                TolType::Bool,
-            )),
             ExprKind::Identifier { .. } => self.analyze_identifier(expr),
             ExprKind::Binary { .. } => self.analyze_binary(expr),
             ExprKind::Unary { .. } => self.analyze_unary(expr),
             ExprKind::FnCall { .. } => self.analyze_fncall(expr),
             ExprKind::ArrayLiteral { .. } => self.analyze_array_literal(expr),
+            ExprKind::Block { stmts, tail } => self.analyze_block_expr(stmts, tail, expr_span),
+            ExprKind::Kung { .. } => self.analyze_kung_expr(expr),
             ExprKind::StructLiteral { .. } => todo!(),
             ExprKind::Dummy => todo!(),
         }
     }
 
-    fn analyze_identifier(&mut self, expr: Expr) -> Result<TypedExpr, CompilerError> {
+    /// Analyzes an integer literal, `negated` when it is the operand of a
+    /// prefix `-`, so that `-128i8` is checked as the `i8` it is
+    fn analyze_integer(
+        &mut self,
+        expr: Expr<'src>,
+        negated: bool,
+    ) -> Result<TypedExpr, CompilerError> {
+        let expr_span = expr.span();
+        let ExprKind::Integer { lexeme, suffix } = expr.kind else {
+            unreachable!()
+        };
+
+        // Remove suffix as the compiler doesn't need it anymore
+        let digits = &lexeme.lexeme()[..lexeme.lexeme().len() - suffix.map_or(0, str::len)];
+        let ttype = match suffix {
+            Some(s) => TolType::UnsizedInteger.coerce_or_mismatch(
+                &s.into(),
+                expr_span.start..expr_span.end - s.len(),
+                expr_span.end - s.len()..expr_span.end,
+            )?,
+            None => TolType::UnsizedInteger,
+        };
+        let value = literal::int_value(digits)
+            .filter(|value| {
+                const_eval::int_range(&TolType::UnsizedInteger)
+                    .is_some_and(|range| range.contains(value))
+            })
+            .ok_or_else(|| CompilerError::LiteralTooLarge {
+                span: expr_span.clone().into(),
+            })?;
+        // Literals without a suffix are checked once they are given a type
+        if suffix.is_some() {
+            let signed = if negated { -value } else { value };
+            Self::check_int_fits(signed, &ttype, expr_span.clone())?;
+        }
+
+        Ok(self.typed_expr(TypedExprKind::Integer { value }, ttype, expr_span))
+    }
+
+    fn analyze_identifier(&mut self, expr: Expr<'src>) -> Result<TypedExpr, CompilerError> {
+        let expr_span = expr.span();
         let ExprKind::Identifier { lexeme } = expr.kind else {
             unreachable!()
         };
 
-        let id = self.lookup_symbol(&lexeme)?;
-        let ttype = self.compiler_ctx.symbol_table[id].get_type();
-        Ok(TypedExpr::new(TypedExprKind::Identifier { lexeme }, ttype))
+        let symbol_id = self.lookup_symbol(lexeme.name(), lexeme.span())?;
+        Ok(self.typed_identifier(lexeme.name(), symbol_id, expr_span))
     }
 
-    fn analyze_binary(&mut self, expr: Expr) -> Result<TypedExpr, CompilerError> {
-        let ExprKind::Binary { left, right, op } = expr.kind else {
+    /// Resolves the variable an assignment writes to. Writing to a variable
+    /// doesn't read it, so unlike other identifiers it doesn't count as a use.
+    fn analyze_assign_target(&mut self, target: ExprId) -> Result<TypedExpr, CompilerError> {
+        self.check_assignable(target)?;
+
+        let expr = self.exprs.take(target);
+        let expr_span = expr.span();
+        let ExprKind::Identifier { lexeme } = expr.kind else {
             unreachable!()
         };
 
-        let left_span = left.span();
-        let right_span = right.span();
+        let symbol_id = self.find_symbol(lexeme.name(), lexeme.span())?;
+        Ok(self.typed_identifier(lexeme.name(), symbol_id, expr_span))
+    }
 
-        let left_typex = self.analyze_expression(*left)?;
-        let right_typex = self.analyze_expression(*right)?;
+    fn typed_identifier(
+        &mut self,
+        lexeme: Name,
+        symbol_id: SymbolId,
+        span: Range<usize>,
+    ) -> TypedExpr {
+        let ttype = self.compiler_ctx.symbol_table[symbol_id].get_type();
+        self.typed_expr(TypedExprKind::Identifier { lexeme, symbol_id }, ttype, span)
+    }
+
+    fn analyze_binary(&mut self, expr: Expr<'src>) -> Result<TypedExpr, CompilerError> {
+        let expr_span = expr.span();
+        let ExprKind::Binary { left, right, op } = expr.kind else {
+            unreachable!()
+        };
+
+        let left_span = self.exprs[left].span();
+        let right_span = self.exprs[right].span();
+
+        let mut left_typex = if matches!(
+            op,
+            TokenKind::Equal
+                | TokenKind::PlusEqual
+                | TokenKind::MinusEqual
+                | TokenKind::StarEqual
+                | TokenKind::SlashEqual
+        ) {
+            self.analyze_assign_target(left)?
+        } else {
+            self.analyze_expression(left)?
+        };
+        let mut right_typex = self.analyze_expression(right)?;
 
         match &op {
             TokenKind::Plus
             | TokenKind::Minus
             | TokenKind::Star
-            | TokenKind::Slash
             | TokenKind::Equal
             | TokenKind::PlusEqual
             | TokenKind::MinusEqual
-            | TokenKind::StarEqual
-            | TokenKind::SlashEqual => {
+            | TokenKind::StarEqual => {
                 let coerced = left_typex.ttype.coerce_or_mismatch(
                     &right_typex.ttype,
                     left_span,
                     right_span,
                 )?;
+                Self::fit_literals(&mut left_typex, &coerced)?;
+                Self::fit_literals(&mut right_typex, &coerced)?;
 
-                Ok(TypedExpr::new(
+                Ok(self.typed_expr(
                     TypedExprKind::Binary {
                         left: Box::new(left_typex),
                         right: Box::new(right_typex),
                         op,
                     },
                     coerced,
+                    expr_span,
+                ))
+            }
+            TokenKind::Slash | TokenKind::SlashEqual | TokenKind::Percent => {
+                if op == TokenKind::Percent {
+                    Self::check_integer_operands(
+                        &op,
+                        &left_typex,
+                        &right_typex,
+                        left_span.clone(),
+                        right_span.clone(),
+                    )?;
+                }
+
+                let coerced = left_typex.ttype.coerce_or_mismatch(
+                    &right_typex.ttype,
+                    left_span,
+                    right_span.clone(),
+                )?;
+                Self::fit_literals(&mut left_typex, &coerced)?;
+                Self::fit_literals(&mut right_typex, &coerced)?;
+
+                if coerced.is_integer() && right_typex.int_literal_value() == Some(0) {
+                    return Err(CompilerError::DivisionByZero {
+                        span: right_span.into(),
+                    });
+                }
+
+                Ok(self.typed_expr(
+                    TypedExprKind::Binary {
+                        left: Box::new(left_typex),
+                        right: Box::new(right_typex),
+                        op,
+                    },
+                    coerced,
+                    expr_span,
+                ))
+            }
+            TokenKind::StarStar => {
+                // A float can be raised to an integer, but not the other way around
+                let ttype = if left_typex.ttype.is_float() && right_typex.ttype.is_integer() {
+                    left_typex.ttype.clone()
+                } else {
+                    left_typex.ttype.coerce_or_mismatch(
+                        &right_typex.ttype,
+                        left_span.clone(),
+                        right_span.clone(),
+                    )?
+                };
+
+                if !ttype.is_numeric() {
+                    return Err(CompilerError::InvalidExpression {
+                        spans: vec![LabeledSpan::new(
+                            Some(format!(
+                                "Umaasa ng numerikong tipo, pero ang nakita ay `{}`",
+                                left_typex.ttype
+                            )),
+                            left_span.start,
+                            left_span.end - left_span.start,
+                        )],
+                        help: Some("Numerikong tipo lamang ang tinatanggap ng `**`".to_string()),
+                    });
+                }
+                Self::fit_literals(&mut left_typex, &ttype)?;
+                if ttype.is_integer() {
+                    Self::fit_literals(&mut right_typex, &ttype)?;
+                }
+
+                Ok(self.typed_expr(
+                    TypedExprKind::Binary {
+                        left: Box::new(left_typex),
+                        right: Box::new(right_typex),
+                        op,
+                    },
+                    ttype,
+                    expr_span,
                 ))
             }
             TokenKind::Less
@@ -417,13 +1015,15 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
             | TokenKind::Greater
             | TokenKind::GreaterEqual => {
                 if left_typex.ttype.is_numeric() && right_typex.ttype.is_numeric() {
-                    return Ok(TypedExpr::new(
+                    Self::fit_comparison_literals(&mut left_typex, &mut right_typex)?;
+                    return Ok(self.typed_expr(
                         TypedExprKind::Binary {
                             left: Box::new(left_typex),
                             right: Box::new(right_typex),
                             op,
                         },
                         TolType::Bool,
+                        expr_span,
                     ));
                 }
 
@@ -456,13 +1056,15 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
                 if (left_typex.ttype.is_numeric() && right_typex.ttype.is_numeric())
                     || (left_typex.ttype == TolType::Bool && right_typex.ttype == TolType::Bool)
                 {
-                    return Ok(TypedExpr::new(
+                    Self::fit_comparison_literals(&mut left_typex, &mut right_typex)?;
+                    return Ok(self.typed_expr(
                         TypedExprKind::Binary {
                             left: Box::new(left_typex),
                             right: Box::new(right_typex),
                             op,
                         },
                         TolType::Bool,
+                        expr_span,
                     ));
                 }
                 Err(CompilerError::InvalidExpression { spans: vec![
@@ -488,28 +1090,260 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
                     });
                 }
 
-                Ok(TypedExpr::new(
+                Ok(self.typed_expr(
                     TypedExprKind::Binary {
                         left: Box::new(left_typex),
                         right: Box::new(right_typex),
                         op,
                     },
                     TolType::Bool,
+                    expr_span,
+                ))
+            }
+            TokenKind::Pipe | TokenKind::Amper | TokenKind::Caret => {
+                Self::check_integer_operands(
+                    &op,
+                    &left_typex,
+                    &right_typex,
+                    left_span.clone(),
+                    right_span.clone(),
+                )?;
+                let coerced = left_typex.ttype.coerce_or_mismatch(
+                    &right_typex.ttype,
+                    left_span,
+                    right_span,
+                )?;
+                Self::fit_literals(&mut left_typex, &coerced)?;
+                Self::fit_literals(&mut right_typex, &coerced)?;
+
+                Ok(self.typed_expr(
+                    TypedExprKind::Binary {
+                        left: Box::new(left_typex),
+                        right: Box::new(right_typex),
+                        op,
+                    },
+                    coerced,
+                    expr_span,
+                ))
+            }
+            TokenKind::LessLess | TokenKind::GreaterGreater => {
+                Self::check_integer_operands(
+                    &op,
+                    &left_typex,
+                    &right_typex,
+                    left_span,
+                    right_span.clone(),
+                )?;
+
+                // A shift as wide as the value is undefined behavior in C. Literals
+                // without a suffix may still become 64 bits wide.
+                if let Some(amount) = right_typex.int_literal_value() {
+                    let max = left_typex.ttype.bit_width().unwrap_or(64) - 1;
+                    if amount < 0 || amount > max as i128 {
+                        return Err(CompilerError::InvalidShiftAmount {
+                            amount,
+                            ttype: left_typex.ttype.to_string(),
+                            max,
+                            span: right_span.into(),
+                        });
+                    }
+                }
+
+                // The shifted value decides the type, the amount can be any integer
+                let ttype = left_typex.ttype.clone();
+                Ok(self.typed_expr(
+                    TypedExprKind::Binary {
+                        left: Box::new(left_typex),
+                        right: Box::new(right_typex),
+                        op,
+                    },
+                    ttype,
+                    expr_span,
                 ))
             }
-            TokenKind::Pipe => todo!(),
-            TokenKind::Amper => todo!(),
             _ => todo!(),
         }
     }
 
-    fn analyze_unary(&mut self, expr: Expr) -> Result<TypedExpr, CompilerError> {
+    /// Only variables declared with `ang` can be assigned to
+    fn check_assignable(&self, target: ExprId) -> Result<(), CompilerError> {
+        let is_var = match &self.exprs[target].kind {
+            ExprKind::Identifier { .. } => {
+                let id = self.lookup_symbol_from_expr(target)?;
+                matches!(
+                    self.compiler_ctx.symbol_table[id].kind(),
+                    SymbolKind::Var { .. }
+                )
+            }
+            _ => false,
+        };
+
+        if is_var {
+            Ok(())
+        } else {
+            Err(CompilerError::InvalidAssignmentTarget {
+                span: self.exprs[target].span().into(),
+            })
+        }
+    }
+
+    fn check_integer_operands(
+        op: &TokenKind,
+        left_typex: &TypedExpr,
+        right_typex: &TypedExpr,
+        left_span: Range<usize>,
+        right_span: Range<usize>,
+    ) -> Result<(), CompilerError> {
+        let spans = [(left_typex, left_span), (right_typex, right_span)]
+            .into_iter()
+            .filter(|(typex, _)| !typex.ttype.is_integer())
+            .map(|(typex, span)| {
+                LabeledSpan::new(
+                    Some(format!(
+                        "Umaasa ng integer na tipo, pero ang nakita ay `{}`",
+                        typex.ttype
+                    )),
+                    span.start,
+                    span.end - span.start,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if spans.is_empty() {
+            return Ok(());
+        }
+
+        Err(CompilerError::InvalidExpression {
+            spans,
+            help: Some(format!(
+                "Integer na tipo lamang ang tinatanggap ng `{}`",
+                op.op_to_string().unwrap()
+            )),
+        })
+    }
+
+    /// Checks that the integer literals of `typex` fit in `ttype`, the type
+    /// `typex` is used as. Literals keep their unsized type in the typed AST,
+    /// so one that doesn't fit would otherwise be wrapped once lowered. A
+    /// `kung` or block evaluating to literals only is given `ttype` instead,
+    /// so its value is held in a `ttype` rather than the widest integer.
+    fn fit_literals(typex: &mut TypedExpr, ttype: &TolType) -> Result<(), CompilerError> {
+        if let (TypedExprKind::ArrayLiteral { elems }, TolType::Array { inner, .. }) =
+            (&mut typex.kind, ttype)
+        {
+            return elems
+                .iter_mut()
+                .try_for_each(|elem| Self::fit_literals(elem, inner));
+        }
+        if typex.ttype != TolType::UnsizedInteger {
+            return Ok(());
+        }
+        // Negated as a whole, as `-128` fits in an `i8` but `128` doesn't
+        if let Some(value) = typex.int_literal_value() {
+            return Self::check_int_fits(value, ttype, typex.span());
+        }
+
+        match &mut typex.kind {
+            TypedExprKind::Unary { right, .. } => {
+                Self::fit_literals(right, ttype)?;
+                Self::check_folded_fits(typex, ttype)
+            }
+            // The shift amount isn't converted to the type of the result
+            TypedExprKind::Binary {
+                left,
+                op: TokenKind::LessLess | TokenKind::GreaterGreater,
+                ..
+            } => {
+                Self::fit_literals(left, ttype)?;
+                Self::check_folded_fits(typex, ttype)
+            }
+            TypedExprKind::Binary { left, right, .. } => {
+                Self::fit_literals(left, ttype)?;
+                Self::fit_literals(right, ttype)?;
+                Self::check_folded_fits(typex, ttype)
+            }
+            TypedExprKind::Block {
+                tail: Some(tail), ..
+            } => {
+                Self::fit_literals(tail, ttype)?;
+                Self::settle_type(typex, ttype);
+                Ok(())
+            }
+            TypedExprKind::Kung { branches } => {
+                for arm in branches.iter_mut() {
+                    Self::fit_literals(&mut arm.value, ttype)?;
+                }
+                Self::settle_type(typex, ttype);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn settle_type(typex: &mut TypedExpr, ttype: &TolType) {
+        if ttype.is_integer() && *ttype != TolType::UnsizedInteger {
+            typex.ttype = ttype.clone();
+        }
+    }
+
+    /// Folds an operator on literals only in `ttype`, so that `1 << 40` is
+    /// held to the same range as the literal it amounts to
+    fn check_folded_fits(typex: &TypedExpr, ttype: &TolType) -> Result<(), CompilerError> {
+        if !ttype.is_integer() {
+            return Ok(());
+        }
+
+        match ConstEvaluator::new(&[]).unsized_as(ttype).eval(typex) {
+            Err((ConstEvalError::NotConstant, _)) | Ok(_) => Ok(()),
+            Err((e, span)) => Err(e.into_compiler_error(span)),
+        }
+    }
+
+    /// Both sides of a comparison are converted to their common type
+    fn fit_comparison_literals(
+        left_typex: &mut TypedExpr,
+        right_typex: &mut TypedExpr,
+    ) -> Result<(), CompilerError> {
+        match left_typex.ttype.coerce(&right_typex.ttype) {
+            Some(common) => {
+                Self::fit_literals(left_typex, &common)?;
+                Self::fit_literals(right_typex, &common)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn check_int_fits(
+        value: i128,
+        ttype: &TolType,
+        span: Range<usize>,
+    ) -> Result<(), CompilerError> {
+        match const_eval::int_range(ttype) {
+            Some(range) if !range.contains(&value) => Err(CompilerError::LiteralOutOfRange {
+                value,
+                ttype: ttype.to_string(),
+                min: range.start,
+                max: range.end - 1,
+                span: span.into(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn analyze_unary(&mut self, expr: Expr<'src>) -> Result<TypedExpr, CompilerError> {
+        let expr_span = expr.span();
         let ExprKind::Unary { op, right } = expr.kind else {
             unreachable!()
         };
 
-        let right_span = right.span();
-        let right_typex = self.analyze_expression(*right)?;
+        let right_span = self.exprs[right].span();
+        let right_typex = match self.exprs[right].kind {
+            ExprKind::Integer { .. } if op == TokenKind::Minus => {
+                let right = self.exprs.take(right);
+                self.analyze_integer(right, true)?
+            }
+            _ => self.analyze_expression(right)?,
+        };
 
         match &op {
             TokenKind::Bang => {
@@ -521,30 +1355,58 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
                     });
                 }
 
-                Ok(TypedExpr::new(
+                Ok(self.typed_expr(
                     TypedExprKind::Unary {
                         right: Box::new(right_typex),
                         op,
                     },
                     TolType::Bool,
+                    expr_span,
+                ))
+            }
+            TokenKind::Tilde => {
+                if !right_typex.ttype.is_integer() {
+                    return Err(CompilerError::InvalidExpression {
+                        spans: vec![LabeledSpan::new(
+                            Some(format!(
+                                "Umaasa ng integer na tipo, pero ang nakita ay `{}`",
+                                right_typex.ttype
+                            )),
+                            right_span.start,
+                            right_span.end - right_span.start,
+                        )],
+                        help: Some("Integer na tipo lamang ang tinatanggap ng `~`".to_string()),
+                    });
+                }
+
+                let ttype = right_typex.ttype.clone();
+                Ok(self.typed_expr(
+                    TypedExprKind::Unary {
+                        right: Box::new(right_typex),
+                        op,
+                    },
+                    ttype,
+                    expr_span,
                 ))
             }
             TokenKind::Minus => {
                 if let Some(t) = TolType::UnsizedInteger.coerce(&right_typex.ttype) {
-                    Ok(TypedExpr::new(
+                    Ok(self.typed_expr(
                         TypedExprKind::Unary {
                             right: Box::new(right_typex),
                             op,
                         },
                         t,
+                        expr_span,
                     ))
                 } else if let Some(t) = TolType::UnsizedFloat.coerce(&right_typex.ttype) {
-                    Ok(TypedExpr::new(
+                    Ok(self.typed_expr(
                         TypedExprKind::Unary {
                             right: Box::new(right_typex),
                             op,
                         },
                         t,
+                        expr_span,
                     ))
                 } else {
                     Err(CompilerError::InvalidExpression {
@@ -563,41 +1425,178 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
         }
     }
 
-    fn analyze_fncall(&mut self, expr: Expr) -> Result<TypedExpr, CompilerError> {
-        let ExprKind::FnCall { callee, args, .. } = &expr.kind else {
+    fn analyze_fncall(&mut self, expr: Expr<'src>) -> Result<TypedExpr, CompilerError> {
+        let expr_span = expr.span();
+        let ExprKind::FnCall { callee, args, .. } = expr.kind else {
             unreachable!()
         };
+        if !matches!(self.exprs[callee].kind, ExprKind::Identifier { .. }) {
+            return Err(CompilerError::InvalidCallExpression {
+                span: self.exprs[callee].span().into(),
+            });
+        }
         let id = self.lookup_symbol_from_expr(callee)?;
-        let sym = self.compiler_ctx.symbol_table[id].clone();
-        let callee_typex = self.analyze_expression(callee.as_ref().clone())?;
-
-        match sym.kind() {
-            SymbolKind::Func { param_types, .. } => {
-                let arg_types = args
-                    .clone()
-                    .into_iter()
-                    .map(|arg| self.analyze_expression(arg))
-                    .collect::<Result<Vec<TypedExpr>, _>>()?;
-
-                self.check_call(param_types, &arg_types, sym.span(), expr.span(), args)?;
-
-                Ok(TypedExpr::new(
-                    TypedExprKind::FnCall {
-                        callee: Box::new(callee_typex),
-                        args: arg_types,
-                    },
-                    sym.get_type(),
-                ))
+        // Analyzed as a name, as calling a `paraan` isn't using it as a value
+        let callee = self.exprs.take(callee);
+        let callee_typex = self.analyze_identifier(callee)?;
+        if !matches!(
+            self.compiler_ctx.symbol_table[id].kind(),
+            SymbolKind::Func { .. }
+        ) {
+            return Err(CompilerError::InvalidCallExpression {
+                span: callee_typex.span().into(),
+            });
+        }
+
+        let mut args = args
+            .into_iter()
+            .map(|arg| self.analyze_expression(arg))
+            .collect::<Result<Vec<TypedExpr>, _>>()?;
+
+        let sym = &self.compiler_ctx.symbol_table[id];
+        let SymbolKind::Func { param_types, .. } = sym.kind() else {
+            unreachable!()
+        };
+        self.check_call(param_types, &mut args, sym.span(), expr_span.clone())?;
+
+        let ttype = sym.get_type();
+        Ok(self.typed_expr(
+            TypedExprKind::FnCall {
+                callee: Box::new(callee_typex),
+                args,
+            },
+            ttype,
+            expr_span,
+        ))
+    }
+
+    fn analyze_block_expr(
+        &mut self,
+        stmts: Vec<StmtId>,
+        tail: Option<ExprId>,
+        expr_span: Range<usize>,
+    ) -> Result<TypedExpr, CompilerError> {
+        self.enter_scope();
+        let stmts = self.analyze_stmts(stmts);
+        let tail = tail.map(|tail| self.analyze_expression(tail));
+        self.exit_scope();
+
+        let tail = tail.transpose()?;
+        let ttype = tail
+            .as_ref()
+            .map_or(TolType::Void, |tail| tail.ttype.clone());
+
+        Ok(self.typed_expr(
+            TypedExprKind::Block {
+                stmts,
+                tail: tail.map(Box::new),
+            },
+            ttype,
+            expr_span,
+        ))
+    }
+
+    fn analyze_kung_expr(&mut self, expr: Expr<'src>) -> Result<TypedExpr, CompilerError> {
+        let expr_span = expr.span();
+        let ExprKind::Kung { branches } = expr.kind else {
+            unreachable!()
+        };
+
+        let has_kungdi = branches.last().is_some_and(|branch| branch.cond.is_none());
+        let branches_len = branches.len();
+        let mut arms = Vec::new();
+        let mut ttype: Option<(TolType, Range<usize>)> = None;
+        for (i, branch) in branches.into_iter().enumerate() {
+            let cond = self.analyze_kung_cond(branch.cond, branches_len - 1 == i, branch.span)?;
+
+            let block_span = self.stmts[branch.block].span();
+            let (stmts, tail) = self.split_tail(branch.block);
+            let tail_span = tail.map_or(block_span.clone(), |tail| self.exprs[tail].span());
+            let value = self.analyze_block_expr(stmts, tail, block_span)?;
+            if Self::diverges(&value) {
+                arms.push(TypedKungArm { cond, value });
+                continue;
             }
-            SymbolKind::Var { .. } | SymbolKind::ConstVar { .. } => {
-                Err(CompilerError::InvalidCallExpression {
-                    span: callee.span().into(),
-                })
+
+            ttype = Some(match ttype {
+                None => (value.ttype.clone(), tail_span),
+                Some((t, first_span)) => (
+                    t.coerce_or_mismatch(&value.ttype, first_span.clone(), tail_span)?,
+                    first_span,
+                ),
+            });
+            arms.push(TypedKungArm { cond, value });
+        }
+
+        let ttype = ttype.map_or(TolType::Void, |(t, _)| t);
+        if !has_kungdi && ttype != TolType::Void {
+            return Err(CompilerError::KungExprWithoutKungdi {
+                ttype: ttype.to_string(),
+                span: expr_span.into(),
+            });
+        }
+        // A branch that is only a literal takes the type of the others
+        for arm in arms.iter_mut() {
+            Self::fit_literals(&mut arm.value, &ttype)?;
+        }
+
+        Ok(self.typed_expr(TypedExprKind::Kung { branches: arms }, ttype, expr_span))
+    }
+
+    /// A branch ending in `ibalik`, `tigil` or `tuloy` never gives a value,
+    /// so it goes with whatever type the other branches have
+    fn diverges(value: &TypedExpr) -> bool {
+        let TypedExprKind::Block { stmts, tail: None } = &value.kind else {
+            return false;
+        };
+
+        stmts.last().is_some_and(|stmt| {
+            matches!(
+                stmt.kind,
+                TypedStmtKind::Ibalik { .. }
+                    | TypedStmtKind::Tigil { .. }
+                    | TypedStmtKind::Tuloy { .. }
+            )
+        })
+    }
+
+    /// Splits the block of a `kung` expression branch into its statements
+    /// and the expression it evaluates to. A trailing `kung` is itself used
+    /// as an expression so chains can be nested.
+    fn split_tail(&mut self, block: StmtId) -> (Vec<StmtId>, Option<ExprId>) {
+        let StmtKind::Block { mut stmts, .. } = self.stmts.take(block).kind else {
+            unreachable!()
+        };
+
+        let Some(&last) = stmts.last() else {
+            return (stmts, None);
+        };
+        let tail = match &self.stmts[last].kind {
+            StmtKind::Expr { expr } => Some(*expr),
+            StmtKind::Kung { .. } => {
+                let Stmt {
+                    kind: StmtKind::Kung { branches },
+                    span,
+                } = self.stmts.take(last)
+                else {
+                    unreachable!()
+                };
+                Some(self.exprs.push(Expr {
+                    kind: ExprKind::Kung { branches },
+                    span,
+                }))
             }
+            _ => None,
+        };
+        if tail.is_some() {
+            stmts.pop();
         }
+
+        (stmts, tail)
     }
 
-    fn analyze_array_literal(&mut self, array: Expr) -> Result<TypedExpr, CompilerError> {
+    fn analyze_array_literal(&mut self, array: Expr<'src>) -> Result<TypedExpr, CompilerError> {
+        let expr_span = array.span();
         let ExprKind::ArrayLiteral { mut elems } = array.kind else {
             unreachable!()
         };
@@ -607,29 +1606,30 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
             // return Ok(TypedExpr::new(TypedExprKind::ArrayLiteral { elems: Vec::new() }, TolType::Array { inner: Box::new(TolType::Unknown), size: None }));
         }
 
-        let first_span = elems[0].span();
+        let first_span = self.exprs[elems[0]].span();
         let elems_len = elems.len();
 
         let mut typed_elems = Vec::new();
         typed_elems.push(self.analyze_expression(elems.remove(0))?);
 
-        let elems_tail = elems.split_off(1);
         let current = typed_elems[0].ttype.clone();
         let ttype = TolType::Array {
             inner: Box::new(current.clone()),
             size: Some(elems_len),
         };
 
-        for (i, elem) in elems_tail.into_iter().enumerate() {
-            let elem_span = elem.span();
-            typed_elems.push(self.analyze_expression(elem)?);
-
-            current.coerce_or_mismatch(&typed_elems[i].ttype, first_span.clone(), elem_span)?;
+        for elem in elems {
+            let elem_span = self.exprs[elem].span();
+            let mut typed_elem = self.analyze_expression(elem)?;
+            current.coerce_or_mismatch(&typed_elem.ttype, first_span.clone(), elem_span)?;
+            Self::fit_literals(&mut typed_elem, &current)?;
+            typed_elems.push(typed_elem);
         }
 
-        Ok(TypedExpr::new(
+        Ok(self.typed_expr(
             TypedExprKind::ArrayLiteral { elems: typed_elems },
             ttype,
+            expr_span,
         ))
     }
 
@@ -667,23 +1667,22 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
     pub fn check_call(
         &self,
         param_types: &[TolType],
-        arg_typex: &[TypedExpr],
+        arg_typex: &mut [TypedExpr],
         func_sym_span: Range<usize>,
         call_span: Range<usize>,
-        args: &[Expr],
     ) -> Result<(), CompilerError> {
-        if args.len() != param_types.len() {
+        if arg_typex.len() != param_types.len() {
             return Err(CompilerError::InvalidNumberOfArguments {
-                arg_len: args.len(),
+                arg_len: arg_typex.len(),
                 expected_len: param_types.len(),
                 args_span: call_span.into(),
             });
         }
 
-        for (i, (arg, param)) in param_types.iter().zip(arg_typex).enumerate() {
-            param
-                .ttype
-                .coerce_or_mismatch(arg, func_sym_span.clone(), args[i].span())?;
+        for (param_type, arg) in param_types.iter().zip(arg_typex) {
+            arg.ttype
+                .coerce_or_mismatch(param_type, func_sym_span.clone(), arg.span())?;
+            Self::fit_literals(arg, param_type)?;
         }
 
         Ok(())
@@ -694,17 +1693,17 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
         name_tok: &Token,
         kind: SymbolKind,
     ) -> Result<usize, CompilerError> {
+        let name = name_tok.name();
         let last_scope = self.symbol_ids.last_mut().unwrap();
         let current_id = self.compiler_ctx.symbol_table.len();
 
-        match last_scope.entry(name_tok.lexeme().to_string()) {
+        match last_scope.entry(name) {
             Entry::Vacant(ent) => {
                 ent.insert(current_id);
-                self.compiler_ctx.symbol_table.push(Symbol::new(
-                    name_tok.lexeme(),
-                    kind,
-                    name_tok.span(),
-                ));
+                self.compiler_ctx
+                    .symbol_table
+                    .push(Symbol::new(name, kind, name_tok.span()));
+                self.check_shadowing(name, name_tok.span());
                 Ok(current_id)
             }
             Entry::Occupied(ent) => {
@@ -717,25 +1716,65 @@ impl<'ctx> SemanticAnalyzer<'ctx> {
         }
     }
 
-    fn lookup_symbol(&self, name_tok: &Token) -> Result<usize, CompilerError> {
+    fn check_shadowing(&mut self, name: Name, span: Range<usize>) {
+        let outer_scopes = &self.symbol_ids[..self.symbol_ids.len() - 1];
+        let Some(&shadowed_id) = outer_scopes.iter().rev().find_map(|scope| scope.get(&name))
+        else {
+            return;
+        };
+
+        let shadowed_span = self.compiler_ctx.symbol_table[shadowed_id].span();
+        self.compiler_ctx.add_warning(WarningKind::Shadowing {
+            name: name.to_string(),
+            span: span.into(),
+            shadowed_span: shadowed_span.into(),
+        });
+    }
+
+    /// Resolves an identifier that is being read, counting it as a use of the symbol
+    fn lookup_symbol(&mut self, name: Name, span: Range<usize>) -> Result<usize, CompilerError> {
+        let id = self.find_symbol(name, span)?;
+        if self.analyzer_ctx.cur_fn_symbol() != Some(id) {
+            self.compiler_ctx.symbol_table[id].add_use();
+        }
+
+        Ok(id)
+    }
+
+    fn find_symbol(&self, name: Name, span: Range<usize>) -> Result<usize, CompilerError> {
         for scope in self.symbol_ids.iter().rev() {
-            if let Some(id) = scope.get(name_tok.lexeme()) {
+            if let Some(id) = scope.get(&name) {
                 return Ok(*id);
             }
         }
 
-        Err(CompilerError::UndeclaredSymbol {
-            span: name_tok.span().into(),
-        })
+        if let Some(declared_span) = self.pending_dapats.get(&name) {
+            return Err(CompilerError::UsedBeforeDeclaration {
+                name: name.as_str().to_string(),
+                span: span.into(),
+                declared_span: declared_span.clone().into(),
+            });
+        }
+
+        Err(CompilerError::UndeclaredSymbol { span: span.into() })
     }
 
-    fn lookup_symbol_from_expr(&self, expr: &Expr) -> Result<usize, CompilerError> {
+    fn lookup_symbol_from_expr(&self, id: ExprId) -> Result<usize, CompilerError> {
+        let expr = &self.exprs[id];
         match &expr.kind {
-            ExprKind::Identifier { lexeme } => self.lookup_symbol(lexeme),
+            ExprKind::Identifier { lexeme } => self.find_symbol(lexeme.name(), lexeme.span()),
             _ => panic!("Can't lookup from expression `{:?}`", expr.kind),
         }
     }
 
+    fn typed_expr(&mut self, kind: TypedExprKind, ttype: TolType, span: Range<usize>) -> TypedExpr {
+        TypedExpr::new(kind, ttype, span, self.compiler_ctx.new_node_id())
+    }
+
+    fn typed_stmt(&mut self, kind: TypedStmtKind, span: Range<usize>) -> TypedStmt {
+        TypedStmt::new(kind, span, self.compiler_ctx.new_node_id())
+    }
+
     fn enter_scope(&mut self) {
         self.symbol_ids.push(HashMap::new());
     }
@@ -1,6 +1,6 @@
 use std::ops::Range;
 
-use crate::toltype::TolType;
+use crate::{analyzer::const_eval::ConstValue, intern::Name, toltype::TolType};
 
 #[derive(Debug, Clone)]
 pub enum SymbolKind {
@@ -9,6 +9,8 @@ pub enum SymbolKind {
     },
     ConstVar {
         ttype: TolType,
+        /// Known while compiling when the initializer could be folded
+        value: Option<ConstValue>,
     },
     Func {
         param_types: Vec<TolType>,
@@ -18,22 +20,24 @@ pub enum SymbolKind {
 
 #[derive(Debug, Clone)]
 pub struct Symbol {
-    name: String,
+    name: Name,
     kind: SymbolKind,
     span: Range<usize>,
+    uses: usize,
 }
 
 impl Symbol {
-    pub fn new(name: &str, kind: SymbolKind, span: Range<usize>) -> Self {
+    pub fn new(name: Name, kind: SymbolKind, span: Range<usize>) -> Self {
         Self {
-            name: name.to_string(),
+            name,
             kind,
             span,
+            uses: 0,
         }
     }
 
-    pub fn name(&self) -> &str {
-        &self.name
+    pub fn name(&self) -> &'static str {
+        self.name.as_str()
     }
 
     pub fn kind(&self) -> &SymbolKind {
@@ -44,7 +48,16 @@ impl Symbol {
         self.span.clone()
     }
 
-    /// ```
+    /// How many times the symbol was read, not counting a `paraan` calling itself
+    pub fn uses(&self) -> usize {
+        self.uses
+    }
+
+    pub fn add_use(&mut self) {
+        self.uses += 1;
+    }
+
+    /// ```text
     /// SymbolKind::Var => ttype.to_owned(),
     /// SymbolKind::Const => ttype.to_owned(),
     /// SymbolKind::Func => return_type.to_owned(),
@@ -52,7 +65,7 @@ impl Symbol {
     pub fn get_type(&self) -> TolType {
         match self.kind() {
             SymbolKind::Var { ttype } => ttype.to_owned(),
-            SymbolKind::ConstVar { ttype } => ttype.to_owned(),
+            SymbolKind::ConstVar { ttype, .. } => ttype.to_owned(),
             SymbolKind::Func { return_type, .. } => return_type.to_owned(),
         }
     }
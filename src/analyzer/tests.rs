@@ -0,0 +1,446 @@
+use std::collections::HashSet;
+
+use crate::{
+    analyzer::SemanticAnalyzer,
+    ast::{
+        TypedAst,
+        typed_expr::{TypedExpr, TypedExprKind},
+        typed_stmt::{TypedStmt, TypedStmtKind},
+    },
+    compiler::CompilerCtx,
+    error::{CompilerError, WarningKind},
+    lexer::Lexer,
+    parser::Parser,
+};
+
+const PROGRAM: &str = "\
+dapat HANGGAN na i32 = -(2 + 3)
+
+paraan dagdag(a na i32, b na i32) -> i32:
+    ibalik a + b
+
+paraan main() -> i32:
+    ang x na i32 = dagdag(HANGGAN, 2) * 3
+    ang y na i32 = kung x > 0:
+        x
+    kungdi:
+        0
+    ibalik x + y
+";
+
+fn analyze(source: &str, ctx: &mut CompilerCtx) -> TypedAst {
+    let tokens = Lexer::new(source, "test").lex(ctx);
+    let ast = Parser::new(&tokens).parse(ctx);
+    SemanticAnalyzer::new(ctx).analyze(ast)
+}
+
+/// Every statement and expression of the typed AST, parents first
+#[derive(Default)]
+struct Nodes<'a> {
+    stmts: Vec<&'a TypedStmt>,
+    exprs: Vec<&'a TypedExpr>,
+}
+
+impl<'a> Nodes<'a> {
+    fn of(ast: &'a TypedAst) -> Self {
+        let mut nodes = Self::default();
+        for stmt in ast {
+            nodes.stmt(stmt);
+        }
+
+        nodes
+    }
+
+    fn stmt(&mut self, stmt: &'a TypedStmt) {
+        self.stmts.push(stmt);
+        match &stmt.kind {
+            TypedStmtKind::Ang { rhs, .. } | TypedStmtKind::Dapat { rhs, .. } => self.expr(rhs),
+            TypedStmtKind::Ibalik { rhs } => rhs.iter().for_each(|rhs| self.expr(rhs)),
+            TypedStmtKind::Expr { expr } => self.expr(expr),
+            TypedStmtKind::Paraan { block, .. } => self.stmt(block),
+            TypedStmtKind::Block { stmts } => stmts.iter().for_each(|stmt| self.stmt(stmt)),
+            TypedStmtKind::Bawat {
+                iter: cond, block, ..
+            }
+            | TypedStmtKind::Habang { cond, block, .. }
+            | TypedStmtKind::Babalik { block, cond, .. } => {
+                self.expr(cond);
+                self.stmt(block);
+            }
+            TypedStmtKind::Kung { branches } => {
+                for branch in branches {
+                    branch.cond.iter().for_each(|cond| self.expr(cond));
+                    self.stmt(&branch.block);
+                }
+            }
+            TypedStmtKind::Tigil { .. } | TypedStmtKind::Tuloy { .. } => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &'a TypedExpr) {
+        self.exprs.push(expr);
+        match &expr.kind {
+            TypedExprKind::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            TypedExprKind::FnCall { callee, args } => {
+                self.expr(callee);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            TypedExprKind::Unary { right, .. } => self.expr(right),
+            TypedExprKind::ArrayLiteral { elems } => elems.iter().for_each(|elem| self.expr(elem)),
+            TypedExprKind::Block { stmts, tail } => {
+                stmts.iter().for_each(|stmt| self.stmt(stmt));
+                tail.iter().for_each(|tail| self.expr(tail));
+            }
+            TypedExprKind::Kung { branches } => {
+                for arm in branches {
+                    arm.cond.iter().for_each(|cond| self.expr(cond));
+                    self.expr(&arm.value);
+                }
+            }
+            TypedExprKind::Integer { .. }
+            | TypedExprKind::Float { .. }
+            | TypedExprKind::Bool { .. }
+            | TypedExprKind::Identifier { .. } => {}
+        }
+    }
+}
+
+#[test]
+fn keeps_spans_of_typed_expressions() {
+    let mut ctx = CompilerCtx::new();
+    let typed_ast = analyze(PROGRAM, &mut ctx);
+    assert!(ctx.errors.is_empty(), "{:?}", ctx.errors);
+
+    let nodes = Nodes::of(&typed_ast);
+    let sources = nodes
+        .exprs
+        .iter()
+        .map(|expr| &PROGRAM[expr.span()])
+        .collect::<Vec<_>>();
+    for expected in [
+        "dagdag(HANGGAN, 2) * 3",
+        "dagdag(HANGGAN, 2)",
+        "HANGGAN",
+        "a + b",
+        "x > 0",
+        "x + y",
+    ] {
+        assert!(
+            sources.contains(&expected),
+            "no `{expected}` in {sources:?}"
+        );
+    }
+
+    // A folded `dapat` keeps the span of the expression it replaced
+    let TypedStmtKind::Dapat { rhs, .. } = &typed_ast[0].kind else {
+        panic!("{:?}", typed_ast[0]);
+    };
+    assert_eq!(rhs.int_literal_value(), Some(-5));
+    assert_eq!(&PROGRAM[rhs.span()], "-(2 + 3)");
+}
+
+#[test]
+fn gives_every_node_its_own_id() {
+    let mut ctx = CompilerCtx::new();
+    let typed_ast = analyze(PROGRAM, &mut ctx);
+
+    let nodes = Nodes::of(&typed_ast);
+    let ids = nodes
+        .stmts
+        .iter()
+        .map(|stmt| stmt.id)
+        .chain(nodes.exprs.iter().map(|expr| expr.id))
+        .collect::<Vec<_>>();
+    let unique = ids.iter().collect::<HashSet<_>>();
+    assert_eq!(unique.len(), ids.len(), "{ids:?}");
+    assert!(ids.iter().all(|id| *id < ctx.node_count), "{ids:?}");
+}
+
+#[test]
+fn points_const_eval_errors_at_the_failing_part() {
+    let source = "\
+paraan tatlo() -> i32:
+    ibalik 3
+
+dapat ANIM na i32 = 3 + tatlo()
+";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [CompilerError::NotConstant { span }] = ctx.errors.as_slice() else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!(
+        &source[span.offset()..span.offset() + span.len()],
+        "tatlo()"
+    );
+}
+
+#[test]
+fn skips_flow_check_of_bodies_with_errors() {
+    let source = "\
+paraan f() -> i32:
+    ibalik tama
+";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    // Only the bad `ibalik`, not a missing return for leaving it out
+    let [CompilerError::UnexpectedType2 { .. }] = ctx.errors.as_slice() else {
+        panic!("{:?}", ctx.errors);
+    };
+}
+
+#[test]
+fn suggests_a_fix_for_unused_variables() {
+    let source = "\
+paraan main() -> i32:
+    ang bilang na i32 = 1
+    ibalik 0
+";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [warning] = ctx.warnings.as_slice() else {
+        panic!("{:?}", ctx.warnings);
+    };
+    let fix = warning.kind.fix().expect("unused variables have a fix");
+    let mut fixed = source.to_string();
+    fixed.replace_range(fix.span.clone(), &fix.replacement);
+    assert!(fixed.contains("ang _bilang na i32 = 1"), "{fixed}");
+
+    // The fixed source has nothing left to warn about
+    let mut ctx = CompilerCtx::new();
+    analyze(&fixed, &mut ctx);
+    assert!(ctx.warnings.is_empty(), "{:?}", ctx.warnings);
+}
+
+#[test]
+fn warns_about_variables_that_are_only_written() {
+    let source = "\
+paraan main() -> i32:
+    ang bilang na i32 = 1
+    bilang = 2
+    bilang += 3
+    ibalik 0
+";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    assert!(ctx.errors.is_empty(), "{:?}", ctx.errors);
+    let [warning] = ctx.warnings.as_slice() else {
+        panic!("{:?}", ctx.warnings);
+    };
+    let WarningKind::UnusedVariable { name, .. } = &warning.kind else {
+        panic!("{warning:?}");
+    };
+    assert_eq!(name, "bilang");
+}
+
+#[test]
+fn reports_integer_literals_too_large_to_hold() {
+    let source = "ang x na i64 = 99999999999999999999\n";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [CompilerError::LiteralTooLarge { span }] = ctx.errors.as_slice() else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!(span.offset(), source.find('9').unwrap());
+}
+
+#[test]
+fn rejects_floats_with_more_than_one_point() {
+    let source = "ang x na f64 = 1.5.0\n";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    // The parser reports the missing value after it too
+    let Some(CompilerError::Lexer { span, .. }) = ctx.errors.first() else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!(&source[span.offset()..span.offset() + span.len()], "1.5.0");
+}
+
+#[test]
+fn rejects_literals_that_do_not_fit_their_type() {
+    for (decl, value) in [
+        ("ang x na u8 = 300u8", 300),
+        ("ang x na u8 = 300", 300),
+        ("ang x na u8 = -1", -1),
+        ("ang x na i8 = -129", -129),
+        ("ang x na i32 = 4294967296", 4294967296),
+        ("ang x na i32 = 2 * 4294967296", 4294967296),
+        ("ang x na i32 = 1\n    ang y = x + 2147483648", 2147483648),
+        (
+            "ang x na i32 = 1\n    ang y = x == 10000000000",
+            10000000000,
+        ),
+    ] {
+        let source = format!("paraan main() -> i32:\n    {decl}\n    ibalik 0\n");
+        let mut ctx = CompilerCtx::new();
+        analyze(&source, &mut ctx);
+
+        let [CompilerError::LiteralOutOfRange { value: found, .. }] = ctx.errors.as_slice() else {
+            panic!("`{decl}`: {:?}", ctx.errors);
+        };
+        assert_eq!(*found, value, "`{decl}`");
+    }
+
+    for decl in [
+        "ang x na i8 = -128",
+        "ang x na i8 = -128i8",
+        "ang x na u8 = 255",
+        "ang x na u64 = 18446744073709551615",
+        "ang x na i64 = -9223372036854775808",
+    ] {
+        let source = format!("paraan main() -> i32:\n    {decl}\n    ibalik 0\n");
+        let mut ctx = CompilerCtx::new();
+        analyze(&source, &mut ctx);
+        assert!(ctx.errors.is_empty(), "`{decl}`: {:?}", ctx.errors);
+    }
+}
+
+#[test]
+fn folds_literal_shifts_and_powers_in_the_type_they_are_used_as() {
+    let source = "paraan main() -> i32:\n    ang x na i32 = 1 << 40\n    ibalik 0\n";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [
+        CompilerError::InvalidShiftAmount {
+            amount, max, span, ..
+        },
+    ] = ctx.errors.as_slice()
+    else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!((*amount, *max), (40, 31));
+    assert_eq!(span.offset(), source.find("40").unwrap());
+
+    let source = "paraan main() -> i32:\n    ang x na i32 = 2 ** 40\n    ibalik 0\n";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [CompilerError::ConstEvalFailed { span, .. }] = ctx.errors.as_slice() else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!(
+        &source[span.offset()..span.offset() + span.len()],
+        "2 ** 40"
+    );
+
+    for decl in [
+        "ang x na i32 = 1 << 31",
+        "ang x na i32 = 2 ** 30",
+        "ang x na i64 = 1 << 40",
+        "ang x na u8 = ~0",
+    ] {
+        let source = format!("paraan main() -> i32:\n    {decl}\n    ibalik 0\n");
+        let mut ctx = CompilerCtx::new();
+        analyze(&source, &mut ctx);
+        assert!(ctx.errors.is_empty(), "`{decl}`: {:?}", ctx.errors);
+    }
+}
+
+#[test]
+fn checks_kung_branch_literals_against_the_other_branches() {
+    let source = "\
+paraan main() -> i32:
+    ang a na i32 = 2
+    ang b na i64 = kung a > 1:
+        a
+    kungdi:
+        10000000000
+    ibalik 0
+";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [CompilerError::LiteralOutOfRange { ttype, span, .. }] = ctx.errors.as_slice() else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!(ttype, "i32");
+    assert_eq!(span.offset(), source.find("10000000000").unwrap());
+}
+
+#[test]
+fn lets_kung_branches_leave_instead_of_giving_a_value() {
+    let source = "\
+paraan main() -> i32:
+    ang i na i32 = 0
+    habang i < 10:
+        ang susunod na i32 = kung i < 5:
+            i + 1
+        kungdi:
+            tigil
+        i = susunod
+    ang bawas = kung i > 2:
+        i - 2
+    kungdi:
+        ibalik 0
+    ibalik bawas
+";
+    let mut ctx = CompilerCtx::new();
+    let typed_ast = analyze(source, &mut ctx);
+
+    assert!(ctx.errors.is_empty(), "{:?}", ctx.errors);
+    assert!(ctx.warnings.is_empty(), "{:?}", ctx.warnings);
+    let kungs = Nodes::of(&typed_ast)
+        .exprs
+        .into_iter()
+        .filter(|expr| matches!(expr.kind, TypedExprKind::Kung { .. }))
+        .map(|expr| expr.ttype.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(kungs, ["i32", "i32"]);
+}
+
+#[test]
+fn rejects_a_loop_named_like_a_loop_around_it() {
+    let source = "paraan main() -> i32:\n    labas: habang tama:\n        labas: habang tama:\n            tigil labas\n    ibalik 0\n";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [
+        CompilerError::DuplicateLabel {
+            declared_span,
+            redeclared_span,
+            ..
+        },
+    ] = ctx.errors.as_slice()
+    else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!(declared_span.offset(), source.find("labas").unwrap());
+    assert_eq!(redeclared_span.offset(), source.rfind("labas:").unwrap());
+
+    // Loops after one another may share a name
+    let source = "paraan main() -> i32:\n    labas: habang tama:\n        tigil labas\n    labas: habang tama:\n        tigil labas\n    ibalik 0\n";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+    assert!(ctx.errors.is_empty(), "{:?}", ctx.errors);
+}
+
+#[test]
+fn reports_a_dapat_used_before_its_declaration_once() {
+    let source = "dapat A na i32 = B + 1\ndapat B na i32 = 2\n";
+    let mut ctx = CompilerCtx::new();
+    analyze(source, &mut ctx);
+
+    let [
+        CompilerError::UsedBeforeDeclaration {
+            span,
+            declared_span,
+            ..
+        },
+    ] = ctx.errors.as_slice()
+    else {
+        panic!("{:?}", ctx.errors);
+    };
+    assert_eq!(span.offset(), source.find("B +").unwrap());
+    assert_eq!(declared_span.offset(), source.find("B na").unwrap());
+    assert!(ctx.warnings.is_empty(), "{:?}", ctx.warnings);
+}
@@ -19,7 +19,6 @@ impl TypeResolver {
         let StmtKind::Paraan {
             return_type,
             params,
-            block,
             ..
         } = &mut stmt.kind
         else {
@@ -32,8 +31,6 @@ impl TypeResolver {
 
         *return_type = Self::resolve_type(return_type);
 
-        Self::resolve_stmt(block)?;
-
         Ok(())
     }
 
@@ -1,58 +1,76 @@
-use std::{fmt, ops::Range};
+use std::{
+    fmt,
+    ops::{Index, IndexMut, Range},
+};
 
-use crate::lexer::token::{Token, TokenKind};
+use crate::{
+    ast::stmt::{KungBranch, StmtId},
+    lexer::token::{Token, TokenKind},
+};
+
+/// Index of an expression in the [`Exprs`] of its AST
+pub type ExprId = usize;
 
 #[derive(Debug, Clone)]
-pub struct Expr {
-    pub kind: ExprKind,
+pub struct Expr<'src> {
+    pub kind: ExprKind<'src>,
     pub span: Range<usize>,
 }
 
 #[derive(Debug, Clone)]
-pub enum ExprKind {
+pub enum ExprKind<'src> {
     Integer {
-        lexeme: Token,
-        suffix: Option<String>,
+        lexeme: Token<'src>,
+        suffix: Option<&'static str>,
     },
     Float {
-        lexeme: Token,
-        suffix: Option<String>,
+        lexeme: Token<'src>,
+        suffix: Option<&'static str>,
     },
     Boolean {
-        lexeme: Token,
+        lexeme: Token<'src>,
     },
     Identifier {
-        lexeme: Token,
+        lexeme: Token<'src>,
     },
     Binary {
-        left: Box<Expr>,
-        right: Box<Expr>,
+        left: ExprId,
+        right: ExprId,
         op: TokenKind,
     },
     FnCall {
-        callee: Box<Expr>,
-        args: Vec<Expr>,
+        callee: ExprId,
+        args: Vec<ExprId>,
         args_span: Range<usize>,
     },
     Unary {
         op: TokenKind,
-        right: Box<Expr>,
+        right: ExprId,
     },
     StructLiteral {
-        left: Box<Expr>,
+        left: ExprId,
         fields: Vec<StructLiteralField>,
     },
     ArrayLiteral {
-        elems: Vec<Expr>,
+        elems: Vec<ExprId>,
+    },
+    /// `{ stmt; stmt; tail }`, evaluating to `tail` if there is one
+    Block {
+        stmts: Vec<StmtId>,
+        tail: Option<ExprId>,
+    },
+    /// A `kung` chain whose branches evaluate to the value of their last expression
+    Kung {
+        branches: Vec<KungBranch>,
     },
 
     // Special
     Dummy,
 }
 
-impl Expr {
+impl<'src> Expr<'src> {
     #[deprecated]
-    pub fn new(kind: ExprKind, span: Range<usize>) -> Self {
+    pub fn new(kind: ExprKind<'src>, span: Range<usize>) -> Self {
         Self { kind, span }
     }
 
@@ -72,24 +90,96 @@ impl Expr {
     }
 }
 
-impl fmt::Display for Expr {
+/// Every expression of an AST. Expressions refer to their operands by
+/// [`ExprId`] instead of owning them, so a whole program's expressions live
+/// in one allocation.
+#[derive(Debug, Default, Clone)]
+pub struct Exprs<'src> {
+    exprs: Vec<Expr<'src>>,
+}
+
+impl<'src> Exprs<'src> {
+    pub fn push(&mut self, expr: Expr<'src>) -> ExprId {
+        self.exprs.push(expr);
+        self.exprs.len() - 1
+    }
+
+    /// Moves an expression out for passes consuming the AST, leaving a
+    /// `Dummy` with the same span in its place
+    pub fn take(&mut self, id: ExprId) -> Expr<'src> {
+        let dummy = Expr {
+            kind: ExprKind::Dummy,
+            span: self.exprs[id].span(),
+        };
+        std::mem::replace(&mut self.exprs[id], dummy)
+    }
+
+    /// Prints the expression the way it was written, give or take parentheses
+    pub fn display(&self, id: ExprId) -> ExprDisplay<'_, 'src> {
+        ExprDisplay { exprs: self, id }
+    }
+}
+
+impl<'src> Index<ExprId> for Exprs<'src> {
+    type Output = Expr<'src>;
+
+    fn index(&self, id: ExprId) -> &Self::Output {
+        &self.exprs[id]
+    }
+}
+
+impl IndexMut<ExprId> for Exprs<'_> {
+    fn index_mut(&mut self, id: ExprId) -> &mut Self::Output {
+        &mut self.exprs[id]
+    }
+}
+
+pub struct ExprDisplay<'a, 'src> {
+    exprs: &'a Exprs<'src>,
+    id: ExprId,
+}
+
+impl fmt::Display for ExprDisplay<'_, '_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.kind {
+        let display = |id| self.exprs.display(id);
+        let list = |ids: &[ExprId]| {
+            ids.iter()
+                .map(|id| display(*id).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match &self.exprs[self.id].kind {
             ExprKind::Integer { lexeme, .. }
             | ExprKind::Float { lexeme, .. }
             | ExprKind::Boolean { lexeme }
             | ExprKind::Identifier { lexeme } => write!(f, "{}", lexeme.lexeme()),
             ExprKind::Dummy => write!(f, "<dummy>"),
-            ExprKind::StructLiteral { left, fields } => write!(f, "{} {{ {:#?} }}", left, fields),
-            ExprKind::Binary { left, right, op } => {
-                write!(f, "{} {} {}", left, op.op_to_string().unwrap(), right)
+            ExprKind::StructLiteral { left, fields } => {
+                write!(f, "{} {{ {:#?} }}", display(*left), fields)
+            }
+            ExprKind::Binary { left, right, op } => write!(
+                f,
+                "{} {} {}",
+                display(*left),
+                op.op_to_string().unwrap(),
+                display(*right)
+            ),
+            ExprKind::FnCall { callee, args, .. } => {
+                write!(f, "{}({})", display(*callee), list(args))
+            }
+            ExprKind::Unary { op, right } => {
+                write!(f, "{}{}", op.op_to_string().unwrap(), display(*right))
             }
-            ExprKind::FnCall { callee, args, .. } => write!(f, "{}({:#?})", callee, args),
-            ExprKind::Unary { op, right } => write!(f, "{}{}", op.op_to_string().unwrap(), right),
-            ExprKind::ArrayLiteral { elems } => write!(f, "{:#?}", elems),
+            ExprKind::ArrayLiteral { elems } => write!(f, "[{}]", list(elems)),
+            ExprKind::Block { tail, .. } => match tail {
+                Some(tail) => write!(f, "{{ ... {} }}", display(*tail)),
+                None => write!(f, "{{ ... }}"),
+            },
+            ExprKind::Kung { .. } => write!(f, "kung ..."),
         }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct StructLiteralField(pub String, pub Option<Expr>);
+pub struct StructLiteralField(pub String, pub Option<ExprId>);
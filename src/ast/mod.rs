@@ -1,8 +1,26 @@
-use crate::ast::{stmt::Stmt, typed_stmt::TypedStmt};
+use crate::ast::{
+    expr::Exprs,
+    stmt::{StmtId, Stmts},
+    typed_stmt::TypedStmt,
+};
+
+/// A parsed program, borrowing its tokens' lexemes from the source. Its
+/// statements and expressions are kept in arenas, with `top_level` listing
+/// the statements of the program itself in order.
+#[derive(Debug, Default)]
+pub struct Ast<'src> {
+    pub top_level: Vec<StmtId>,
+    pub stmts: Stmts<'src>,
+    pub exprs: Exprs<'src>,
+}
 
-pub type Ast = Vec<Stmt>;
 pub type TypedAst = Vec<TypedStmt>;
 
+/// Identifies a node of the typed AST, for keying side tables like the types
+/// or symbols of nodes. Every node of a compilation gets its own, handed out
+/// in order from 0, so a side table can also be a `Vec` indexed by them.
+pub type NodeId = usize;
+
 pub mod expr;
 pub mod stmt;
 pub mod typed_expr;
@@ -1,14 +1,17 @@
-use std::ops::Range;
+use std::ops::{Index, IndexMut, Range};
 
-use crate::{ast::expr::Expr, lexer::token::Token, toltype::TolType};
+use crate::{ast::expr::ExprId, lexer::token::Token, toltype::TolType};
 
-#[derive(Debug)]
-pub struct Stmt {
-    pub kind: StmtKind,
+/// Index of a statement in the [`Stmts`] of its AST
+pub type StmtId = usize;
+
+#[derive(Debug, Clone)]
+pub struct Stmt<'src> {
+    pub kind: StmtKind<'src>,
     pub span: Range<usize>,
 }
 
-impl Stmt {
+impl Stmt<'_> {
     pub fn new_null() -> Self {
         Self {
             kind: StmtKind::Null,
@@ -28,43 +31,100 @@ impl Stmt {
     }
 }
 
-#[derive(Debug)]
-pub enum StmtKind {
+/// Every statement of an AST. Like [`Exprs`](crate::ast::expr::Exprs),
+/// statements refer to the statements of their blocks by [`StmtId`] instead
+/// of owning them.
+#[derive(Debug, Default, Clone)]
+pub struct Stmts<'src> {
+    stmts: Vec<Stmt<'src>>,
+}
+
+impl<'src> Stmts<'src> {
+    pub fn push(&mut self, stmt: Stmt<'src>) -> StmtId {
+        self.stmts.push(stmt);
+        self.stmts.len() - 1
+    }
+
+    /// Moves a statement out for passes consuming the AST, leaving a `Null`
+    /// with the same span in its place
+    pub fn take(&mut self, id: StmtId) -> Stmt<'src> {
+        let null = Stmt {
+            kind: StmtKind::Null,
+            span: self.stmts[id].span(),
+        };
+        std::mem::replace(&mut self.stmts[id], null)
+    }
+}
+
+impl<'src> Index<StmtId> for Stmts<'src> {
+    type Output = Stmt<'src>;
+
+    fn index(&self, id: StmtId) -> &Self::Output {
+        &self.stmts[id]
+    }
+}
+
+impl IndexMut<StmtId> for Stmts<'_> {
+    fn index_mut(&mut self, id: StmtId) -> &mut Self::Output {
+        &mut self.stmts[id]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind<'src> {
     Paraan {
-        id: Token,
+        id: Token<'src>,
         return_type: TolType,
-        params: Vec<ParamInfo>,
+        params: Vec<ParamInfo<'src>>,
         params_span: Range<usize>,
-        block: Box<Stmt>,
+        block: StmtId,
     },
     Ang {
-        id: Token,
+        id: Token<'src>,
         ttype: Option<TolType>,
-        rhs: Expr,
+        rhs: ExprId,
     },
     Dapat {
-        id: Token,
+        id: Token<'src>,
         ttype: Option<TolType>,
-        rhs: Expr,
+        rhs: ExprId,
     },
     Ibalik {
-        rhs: Option<Expr>,
+        rhs: Option<ExprId>,
     },
     Bawat {
-        bind: Token,
-        iter: Expr,
-        block: Box<Stmt>,
+        bind: Token<'src>,
+        iter: ExprId,
+        block: StmtId,
+        label: Option<Token<'src>>,
     },
     Habang {
-        cond: Expr,
-        block: Box<Stmt>,
+        cond: ExprId,
+        block: StmtId,
+        label: Option<Token<'src>>,
+    },
+    Babalik {
+        block: StmtId,
+        cond: ExprId,
+        label: Option<Token<'src>>,
+    },
+    Tigil {
+        label: Option<Token<'src>>,
+    },
+    Tuloy {
+        label: Option<Token<'src>>,
+    },
+    /// An expression evaluated for its side effects, or the value of a block
+    /// when it is the last statement of a `kung` used as an expression
+    Expr {
+        expr: ExprId,
     },
     Kung {
         branches: Vec<KungBranch>,
     },
     Block {
         indent_span: Range<usize>,
-        stmts: Vec<Stmt>,
+        stmts: Vec<StmtId>,
     },
     Gagawin,
 
@@ -73,15 +133,15 @@ pub enum StmtKind {
 }
 
 #[derive(Debug, Clone)]
-pub struct ParamInfo {
-    pub id: Token,
+pub struct ParamInfo<'src> {
+    pub id: Token<'src>,
     pub ttype: TolType,
     pub span: Range<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct KungBranch {
-    pub cond: Option<Expr>,
-    pub block: Stmt,
+    pub cond: Option<ExprId>,
+    pub block: StmtId,
     pub span: Range<usize>,
 }
@@ -1,6 +1,10 @@
+use std::ops::Range;
+
 use crate::{
-    ast::expr::StructLiteralField,
-    lexer::token::{Token, TokenKind},
+    analyzer::SymbolId,
+    ast::{NodeId, typed_stmt::TypedStmt},
+    intern::Name,
+    lexer::token::TokenKind,
     toltype::TolType,
 };
 
@@ -8,27 +12,59 @@ use crate::{
 pub struct TypedExpr {
     pub kind: TypedExprKind,
     pub ttype: TolType,
+    pub span: Range<usize>,
+    pub id: NodeId,
 }
 
 impl TypedExpr {
-    pub fn new(kind: TypedExprKind, ttype: TolType) -> Self {
-        Self { kind, ttype }
+    pub fn new(kind: TypedExprKind, ttype: TolType, span: Range<usize>, id: NodeId) -> Self {
+        Self {
+            kind,
+            ttype,
+            span,
+            id,
+        }
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Returns the value of an integer literal, possibly negated
+    pub fn int_literal_value(&self) -> Option<i128> {
+        match &self.kind {
+            TypedExprKind::Integer { value } => Some(*value),
+            TypedExprKind::Unary {
+                right,
+                op: TokenKind::Minus,
+            } => right.int_literal_value().map(|value| -value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a float literal
+    pub fn float_literal_value(&self) -> Option<f64> {
+        match &self.kind {
+            TypedExprKind::Float { value } => Some(*value),
+            _ => None,
+        }
     }
 }
 
 #[derive(Debug)]
 pub enum TypedExprKind {
     Integer {
-        lexeme: Token,
+        value: i128,
     },
     Float {
-        lexeme: Token,
+        value: f64,
     },
     Bool {
-        lexeme: Token,
+        value: bool,
     },
     Identifier {
-        lexeme: Token,
+        lexeme: Name,
+        symbol_id: SymbolId,
     },
     Binary {
         left: Box<TypedExpr>,
@@ -39,10 +75,6 @@ pub enum TypedExprKind {
         callee: Box<TypedExpr>,
         args: Vec<TypedExpr>,
     },
-    StructLiteral {
-        left: Box<TypedExpr>,
-        fields: Vec<StructLiteralField>,
-    },
     ArrayLiteral {
         elems: Vec<TypedExpr>,
     },
@@ -50,4 +82,18 @@ pub enum TypedExprKind {
         right: Box<TypedExpr>,
         op: TokenKind,
     },
+    Block {
+        stmts: Vec<TypedStmt>,
+        tail: Option<Box<TypedExpr>>,
+    },
+    Kung {
+        branches: Vec<TypedKungArm>,
+    },
+}
+
+/// A branch of a `kung` expression, `value` being the `Block` it evaluates
+#[derive(Debug)]
+pub struct TypedKungArm {
+    pub cond: Option<TypedExpr>,
+    pub value: TypedExpr,
 }
@@ -1,12 +1,16 @@
+use std::ops::Range;
+
 use crate::{
     analyzer::SymbolId,
-    ast::{stmt::ParamInfo, typed_expr::TypedExpr},
+    ast::{NodeId, typed_expr::TypedExpr},
     toltype::TolType,
 };
 
 #[derive(Debug)]
 pub struct TypedStmt {
     pub kind: TypedStmtKind,
+    pub span: Range<usize>,
+    pub id: NodeId,
 }
 
 #[derive(Debug)]
@@ -20,7 +24,7 @@ pub enum TypedStmtKind {
         rhs: TypedExpr,
     },
     Paraan {
-        params: Vec<ParamInfo>,
+        param_ids: Vec<SymbolId>,
         symbol_id: SymbolId,
         block: Box<TypedStmt>,
     },
@@ -32,12 +36,29 @@ pub enum TypedStmtKind {
     },
     Bawat {
         iter: TypedExpr,
+        bind_id: SymbolId,
         bind_type: TolType,
         block: Box<TypedStmt>,
+        label: Option<String>,
     },
     Habang {
         cond: TypedExpr,
         block: Box<TypedStmt>,
+        label: Option<String>,
+    },
+    Babalik {
+        block: Box<TypedStmt>,
+        cond: TypedExpr,
+        label: Option<String>,
+    },
+    Tigil {
+        label: Option<String>,
+    },
+    Tuloy {
+        label: Option<String>,
+    },
+    Expr {
+        expr: TypedExpr,
     },
     Kung {
         branches: Vec<TypedKungBranches>,
@@ -45,8 +66,12 @@ pub enum TypedStmtKind {
 }
 
 impl TypedStmt {
-    pub fn new(kind: TypedStmtKind) -> Self {
-        Self { kind }
+    pub fn new(kind: TypedStmtKind, span: Range<usize>, id: NodeId) -> Self {
+        Self { kind, span, id }
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
     }
 }
 
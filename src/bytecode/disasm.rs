@@ -0,0 +1,114 @@
+//! Prints a [`Program`] for `tol emit --stage bytecode`.
+//!
+//! Variables are printed as `name@id`, since shadowing reuses names. Every
+//! instruction is prefixed by its index, which is what jumps target.
+
+use std::fmt;
+
+use crate::{
+    analyzer::SymbolId,
+    bytecode::{Function, Op, Program, Ty},
+};
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Ty::I8 => "i8",
+            Ty::I16 => "i16",
+            Ty::I32 => "i32",
+            Ty::I64 => "i64",
+            Ty::U8 => "u8",
+            Ty::U16 => "u16",
+            Ty::U32 => "u32",
+            Ty::U64 => "u64",
+            Ty::F32 => "f32",
+            Ty::F64 => "f64",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "constants:")?;
+        for (index, value) in self.constants.iter().enumerate() {
+            writeln!(f, "  #{index} = {value}")?;
+        }
+
+        self.fmt_function(f, &self.init)?;
+        for function in self.functions.iter() {
+            self.fmt_function(f, function)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Program {
+    fn fmt_function(&self, f: &mut fmt::Formatter<'_>, function: &Function) -> fmt::Result {
+        let params = function
+            .params
+            .iter()
+            .map(|id| self.var(*id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(f, "\n{}({params}):", function.name)?;
+
+        for (index, op) in function.code.iter().enumerate() {
+            write!(f, "  {index:04}  ")?;
+            self.fmt_op(f, op)?;
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+
+    fn fmt_op(&self, f: &mut fmt::Formatter<'_>, op: &Op) -> fmt::Result {
+        match op {
+            Op::Const(index) => write!(f, "const #{index}  ; {}", self.constants[*index as usize]),
+            Op::PushVoid => write!(f, "void"),
+            Op::Pop => write!(f, "pop"),
+            Op::Dup => write!(f, "dup"),
+            Op::Truncate(len) => write!(f, "truncate {len}"),
+            Op::Load(id) => write!(f, "load {}", self.var(*id)),
+            Op::Store(id) => write!(f, "store {}", self.var(*id)),
+            Op::LoadGlobal(id) => write!(f, "load.global {}", self.var(*id)),
+            Op::StoreGlobal(id) => write!(f, "store.global {}", self.var(*id)),
+            Op::Cast(ty) => write!(f, "cast.{ty}"),
+            Op::Add(ty) => write!(f, "add.{ty}"),
+            Op::Sub(ty) => write!(f, "sub.{ty}"),
+            Op::Mul(ty) => write!(f, "mul.{ty}"),
+            Op::Div(ty) => write!(f, "div.{ty}"),
+            Op::Rem(ty) => write!(f, "rem.{ty}"),
+            Op::Pow(ty) => write!(f, "pow.{ty}"),
+            Op::BitAnd(ty) => write!(f, "and.{ty}"),
+            Op::BitOr(ty) => write!(f, "or.{ty}"),
+            Op::BitXor(ty) => write!(f, "xor.{ty}"),
+            Op::Shl(ty) => write!(f, "shl.{ty}"),
+            Op::Shr(ty) => write!(f, "shr.{ty}"),
+            Op::Neg(ty) => write!(f, "neg.{ty}"),
+            Op::BitNot(ty) => write!(f, "bitnot.{ty}"),
+            Op::Not => write!(f, "not"),
+            Op::Eq => write!(f, "eq"),
+            Op::Ne => write!(f, "ne"),
+            Op::Lt => write!(f, "lt"),
+            Op::Le => write!(f, "le"),
+            Op::Gt => write!(f, "gt"),
+            Op::Ge => write!(f, "ge"),
+            Op::Jump(target) => write!(f, "jump {target:04}"),
+            Op::JumpIfFalse(target) => write!(f, "jump.false {target:04}"),
+            Op::Call { function, argc } => {
+                write!(f, "call {} {argc}", self.functions[*function as usize].name)
+            }
+            Op::Return => write!(f, "return"),
+            Op::MakeArray(len) => write!(f, "array {len}"),
+            Op::IterStart => write!(f, "iter.start"),
+            Op::IterNext(exit) => write!(f, "iter.next {exit:04}"),
+        }
+    }
+
+    fn var(&self, symbol_id: SymbolId) -> String {
+        let name = self.names.get(&symbol_id).map_or("?", String::as_str);
+        format!("{name}@{symbol_id}")
+    }
+}
@@ -0,0 +1,630 @@
+//! Lowers the typed AST into a [`Program`].
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analyzer::{
+        ENTRY_POINT, SymbolId,
+        symbol::{Symbol, SymbolKind},
+    },
+    ast::{
+        TypedAst,
+        typed_expr::{TypedExpr, TypedExprKind},
+        typed_stmt::{TypedStmt, TypedStmtKind},
+    },
+    bytecode::{Function, INIT_NAME, Op, Program, Ty},
+    error::CompilerError,
+    interpreter::value::Value,
+    lexer::token::TokenKind,
+    toltype::TolType,
+};
+
+/// Jumps out of a loop that are patched once the loop is emitted
+struct Loop {
+    label: Option<String>,
+    /// Stack depth outside of the loop
+    exit_depth: usize,
+    /// Stack depth at the start of an iteration
+    body_depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+pub struct Emitter<'a> {
+    symbols: &'a [Symbol],
+    constants: Vec<Value>,
+    /// Index of every `paraan` in `Program::functions`
+    function_ids: HashMap<SymbolId, u32>,
+    names: HashMap<SymbolId, String>,
+
+    /// Function being emitted
+    function: Function,
+    /// What the function being emitted returns, `None` for `Program::init`
+    return_type: Option<TolType>,
+    /// Variables declared by the function being emitted
+    locals: HashSet<SymbolId>,
+    /// Stack depth at the current instruction
+    depth: usize,
+    loops: Vec<Loop>,
+}
+
+impl<'a> Emitter<'a> {
+    pub fn new(symbols: &'a [Symbol]) -> Self {
+        Self {
+            symbols,
+            constants: Vec::new(),
+            function_ids: HashMap::new(),
+            names: HashMap::new(),
+            function: Function::new(INIT_NAME, Vec::new()),
+            return_type: None,
+            locals: HashSet::new(),
+            depth: 0,
+            loops: Vec::new(),
+        }
+    }
+
+    pub fn emit(mut self, ast: &TypedAst) -> Result<Program, CompilerError> {
+        let paraans: Vec<_> = ast
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                TypedStmtKind::Paraan {
+                    param_ids,
+                    symbol_id,
+                    block,
+                    ..
+                } => Some((*symbol_id, param_ids, block)),
+                _ => None,
+            })
+            .collect();
+        for (index, (symbol_id, ..)) in paraans.iter().enumerate() {
+            self.function_ids.insert(*symbol_id, index as u32);
+        }
+
+        for stmt in ast.iter() {
+            self.emit_stmt(stmt)?;
+        }
+        self.push(Op::PushVoid);
+        self.push(Op::Return);
+        let init = self.function.clone();
+
+        let mut functions = Vec::new();
+        let mut entry = None;
+        for (symbol_id, param_ids, block) in paraans {
+            let name = self.symbols[symbol_id].name().to_string();
+            if name == ENTRY_POINT {
+                entry = Some(functions.len() as u32);
+            }
+            functions.push(self.emit_function(symbol_id, &name, param_ids, block)?);
+        }
+
+        Ok(Program {
+            constants: self.constants,
+            functions,
+            init,
+            entry,
+            names: self.names.into_iter().collect(),
+        })
+    }
+
+    fn emit_function(
+        &mut self,
+        symbol_id: SymbolId,
+        name: &str,
+        param_ids: &[SymbolId],
+        block: &TypedStmt,
+    ) -> Result<Function, CompilerError> {
+        for id in param_ids {
+            self.name(*id);
+        }
+        self.function = Function::new(name, param_ids.to_vec());
+        self.return_type = Some(self.symbols[symbol_id].get_type());
+        self.locals = param_ids.iter().copied().collect();
+        self.depth = 0;
+
+        self.emit_stmt(block)?;
+        self.push(Op::PushVoid);
+        self.push(Op::Return);
+
+        Ok(self.function.clone())
+    }
+
+    fn emit_stmt(&mut self, stmt: &TypedStmt) -> Result<(), CompilerError> {
+        match &stmt.kind {
+            // Emitted as functions of their own
+            TypedStmtKind::Paraan { .. } => {}
+            TypedStmtKind::Ang { symbol_id, rhs } | TypedStmtKind::Dapat { symbol_id, rhs } => {
+                self.emit_expr(rhs)?;
+                self.convert(&rhs.ttype, &self.symbols[*symbol_id].get_type());
+                self.declare(*symbol_id);
+            }
+            TypedStmtKind::Block { stmts } => {
+                for stmt in stmts {
+                    self.emit_stmt(stmt)?;
+                }
+            }
+            TypedStmtKind::Ibalik { rhs } => {
+                match rhs {
+                    Some(rhs) => {
+                        self.emit_expr(rhs)?;
+                        let return_type = self
+                            .return_type
+                            .clone()
+                            .expect("the analyzer rejects `ibalik` outside of a `paraan`");
+                        self.convert(&rhs.ttype, &return_type);
+                    }
+                    None => self.push(Op::PushVoid),
+                }
+                self.push(Op::Return);
+            }
+            TypedStmtKind::Bawat {
+                iter,
+                bind_id,
+                block,
+                label,
+                ..
+            } => {
+                if !matches!(iter.ttype, TolType::Array { .. }) {
+                    return Err(CompilerError::Unsupported {
+                        what: format!("`bawat` sa `{}`", iter.ttype),
+                    });
+                }
+
+                let exit_depth = self.depth;
+                self.emit_expr(iter)?;
+                self.push(Op::IterStart);
+
+                let start = self.here();
+                self.push(Op::IterNext(0));
+                self.convert(
+                    Self::inner_type(&iter.ttype),
+                    &self.symbols[*bind_id].get_type(),
+                );
+                self.declare(*bind_id);
+
+                self.emit_loop_body(block, label, exit_depth, |emitter| {
+                    emitter.push(Op::Jump(start));
+                    Ok(start)
+                })?;
+                self.patch(start as usize, self.here());
+            }
+            TypedStmtKind::Habang { cond, block, label } => {
+                let exit_depth = self.depth;
+                let start = self.here();
+                self.emit_expr(cond)?;
+                let exit = self.push_jump(Op::JumpIfFalse(0));
+
+                self.emit_loop_body(block, label, exit_depth, |emitter| {
+                    emitter.push(Op::Jump(start));
+                    Ok(start)
+                })?;
+                self.patch(exit, self.here());
+            }
+            TypedStmtKind::Babalik { block, cond, label } => {
+                let exit_depth = self.depth;
+                let start = self.here();
+
+                self.emit_loop_body(block, label, exit_depth, |emitter| {
+                    let next = emitter.here();
+                    emitter.emit_expr(cond)?;
+                    let exit = emitter.push_jump(Op::JumpIfFalse(0));
+                    emitter.push(Op::Jump(start));
+                    let end = emitter.here();
+                    emitter.patch(exit, end);
+                    Ok(next)
+                })?;
+            }
+            TypedStmtKind::Tigil { label } => {
+                let target = self.target_loop(label);
+                let exit_depth = self.loops[target].exit_depth;
+                self.push(Op::Truncate(exit_depth as u32));
+                let jump = self.push_jump(Op::Jump(0));
+                self.loops[target].breaks.push(jump);
+            }
+            TypedStmtKind::Tuloy { label } => {
+                let target = self.target_loop(label);
+                let body_depth = self.loops[target].body_depth;
+                self.push(Op::Truncate(body_depth as u32));
+                let jump = self.push_jump(Op::Jump(0));
+                self.loops[target].continues.push(jump);
+            }
+            TypedStmtKind::Expr { expr } => {
+                self.emit_expr(expr)?;
+                self.push(Op::Pop);
+            }
+            TypedStmtKind::Kung { branches } => {
+                let mut ends = Vec::new();
+                for branch in branches {
+                    let next = match &branch.cond {
+                        Some(cond) => {
+                            self.emit_expr(cond)?;
+                            Some(self.push_jump(Op::JumpIfFalse(0)))
+                        }
+                        None => None,
+                    };
+
+                    self.emit_stmt(&branch.block)?;
+
+                    match next {
+                        Some(next) => {
+                            ends.push(self.push_jump(Op::Jump(0)));
+                            self.patch(next, self.here());
+                        }
+                        // Nothing after `kundiman` is reachable
+                        None => break,
+                    }
+                }
+
+                let end = self.here();
+                for jump in ends {
+                    self.patch(jump, end);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Emits the body of a loop whose iteration starts at `body_depth`, then
+    /// what `next` emits to start the next iteration. `next` returns where
+    /// `tuloy` jumps to. `tigil` jumps past everything emitted.
+    fn emit_loop_body(
+        &mut self,
+        block: &TypedStmt,
+        label: &Option<String>,
+        exit_depth: usize,
+        next: impl FnOnce(&mut Self) -> Result<u32, CompilerError>,
+    ) -> Result<(), CompilerError> {
+        let body_depth = self.depth;
+        self.loops.push(Loop {
+            label: label.clone(),
+            exit_depth,
+            body_depth,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+
+        self.emit_stmt(block)?;
+        let continue_target = next(self)?;
+
+        let lp = self.loops.pop().unwrap();
+        for jump in lp.continues {
+            self.patch(jump, continue_target);
+        }
+        let end = self.here();
+        for jump in lp.breaks {
+            self.patch(jump, end);
+        }
+
+        self.depth = exit_depth;
+        Ok(())
+    }
+
+    /// The innermost loop, or the one with the label
+    fn target_loop(&self, label: &Option<String>) -> usize {
+        self.loops
+            .iter()
+            .rposition(|lp| label.is_none() || lp.label == *label)
+            .expect("the analyzer rejects `tigil` and `tuloy` outside of a loop")
+    }
+
+    fn emit_expr(&mut self, expr: &TypedExpr) -> Result<(), CompilerError> {
+        match &expr.kind {
+            TypedExprKind::Integer { .. } => {
+                let value = Value::Int(expr.int_literal_value().unwrap()).convert(&expr.ttype);
+                self.push_const(value);
+            }
+            TypedExprKind::Float { .. } => {
+                let value = Value::Float(expr.float_literal_value().unwrap()).convert(&expr.ttype);
+                self.push_const(value);
+            }
+            TypedExprKind::Bool { value } => {
+                self.push_const(Value::Bool(*value));
+            }
+            TypedExprKind::Identifier { symbol_id, .. } => {
+                if self.function_ids.contains_key(symbol_id) {
+                    return Err(CompilerError::Unsupported {
+                        what: "paraan bilang value".to_string(),
+                    });
+                }
+                self.load(*symbol_id);
+            }
+            TypedExprKind::Binary { left, right, op } => {
+                self.emit_binary(left, right, op, &expr.ttype)?;
+            }
+            TypedExprKind::Unary { right, op } => {
+                self.emit_expr(right)?;
+                let ty = Ty::of(&expr.ttype);
+                match op {
+                    TokenKind::Minus => self.push(Op::Neg(ty.unwrap())),
+                    TokenKind::Tilde => self.push(Op::BitNot(ty.unwrap())),
+                    TokenKind::Bang => self.push(Op::Not),
+                    _ => unreachable!("Unary `{op:?}`"),
+                }
+            }
+            TypedExprKind::FnCall { callee, args } => {
+                let function = match &callee.kind {
+                    TypedExprKind::Identifier { symbol_id, .. } => {
+                        self.function_ids.get(symbol_id).copied()
+                    }
+                    _ => None,
+                };
+                let Some(function) = function else {
+                    return Err(CompilerError::Unsupported {
+                        what: "pagtawag sa hindi pangalan ng paraan".to_string(),
+                    });
+                };
+
+                let TypedExprKind::Identifier { symbol_id, .. } = &callee.kind else {
+                    unreachable!()
+                };
+                let SymbolKind::Func { param_types, .. } = self.symbols[*symbol_id].kind() else {
+                    unreachable!()
+                };
+                for (arg, param) in args.iter().zip(param_types) {
+                    self.emit_expr(arg)?;
+                    self.convert(&arg.ttype, param);
+                }
+                self.push(Op::Call {
+                    function,
+                    argc: args.len() as u32,
+                });
+            }
+            TypedExprKind::ArrayLiteral { elems } => {
+                let inner = Self::inner_type(&expr.ttype);
+                for elem in elems {
+                    self.emit_expr(elem)?;
+                    self.convert(&elem.ttype, inner);
+                }
+                self.push(Op::MakeArray(elems.len() as u32));
+            }
+            TypedExprKind::Block { stmts, tail } => {
+                for stmt in stmts {
+                    self.emit_stmt(stmt)?;
+                }
+                match tail {
+                    Some(tail) => {
+                        self.emit_expr(tail)?;
+                        self.convert(&tail.ttype, &expr.ttype);
+                    }
+                    None => self.push(Op::PushVoid),
+                }
+            }
+            TypedExprKind::Kung { branches } => {
+                let depth = self.depth;
+                let mut ends = Vec::new();
+                let mut has_else = false;
+                for arm in branches {
+                    let next = match &arm.cond {
+                        Some(cond) => {
+                            self.emit_expr(cond)?;
+                            Some(self.push_jump(Op::JumpIfFalse(0)))
+                        }
+                        None => None,
+                    };
+
+                    self.emit_expr(&arm.value)?;
+                    self.convert(&arm.value.ttype, &expr.ttype);
+                    self.depth = depth;
+
+                    match next {
+                        Some(next) => {
+                            ends.push(self.push_jump(Op::Jump(0)));
+                            self.patch(next, self.here());
+                        }
+                        None => {
+                            has_else = true;
+                            break;
+                        }
+                    }
+                }
+                if !has_else {
+                    self.push(Op::PushVoid);
+                }
+
+                self.depth = depth + 1;
+                let end = self.here();
+                for jump in ends {
+                    self.patch(jump, end);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_binary(
+        &mut self,
+        left: &TypedExpr,
+        right: &TypedExpr,
+        op: &TokenKind,
+        ttype: &TolType,
+    ) -> Result<(), CompilerError> {
+        if let Some(arith_op) = Self::assignment_op(op) {
+            let TypedExprKind::Identifier { symbol_id, .. } = &left.kind else {
+                unreachable!()
+            };
+            if let Some(arith_op) = arith_op {
+                self.load(*symbol_id);
+                self.emit_expr(right)?;
+                self.push(Self::arith_op(&arith_op, Ty::of(&left.ttype).unwrap()));
+            } else {
+                self.emit_expr(right)?;
+                self.convert(&right.ttype, &left.ttype);
+            }
+
+            self.push(Op::Dup);
+            self.store(*symbol_id);
+            return Ok(());
+        }
+
+        self.emit_expr(left)?;
+        match op {
+            // Short-circuits like C: the left operand is the result unless the
+            // right one has to be evaluated
+            TokenKind::AmperAmper | TokenKind::PipePipe => {
+                self.push(Op::Dup);
+                if *op == TokenKind::PipePipe {
+                    self.push(Op::Not);
+                }
+                let end = self.push_jump(Op::JumpIfFalse(0));
+                self.push(Op::Pop);
+                self.emit_expr(right)?;
+                self.patch(end, self.here());
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // Compared operands are converted to a common type like in C, as an
+        // unsized literal may not fit in the type of the other operand
+        let common = match op {
+            TokenKind::EqualEqual
+            | TokenKind::BangEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual
+            | TokenKind::Greater
+            | TokenKind::GreaterEqual => left.ttype.coerce(&right.ttype),
+            _ => None,
+        };
+        if let Some(common) = &common {
+            self.convert(&left.ttype, common);
+        }
+        self.emit_expr(right)?;
+        if let Some(common) = &common {
+            self.convert(&right.ttype, common);
+        }
+        let instruction = match op {
+            TokenKind::EqualEqual => Op::Eq,
+            TokenKind::BangEqual => Op::Ne,
+            TokenKind::Less => Op::Lt,
+            TokenKind::LessEqual => Op::Le,
+            TokenKind::Greater => Op::Gt,
+            TokenKind::GreaterEqual => Op::Ge,
+            _ => Self::arith_op(op, Ty::of(ttype).unwrap()),
+        };
+        self.push(instruction);
+
+        Ok(())
+    }
+
+    fn arith_op(op: &TokenKind, ty: Ty) -> Op {
+        match op {
+            TokenKind::Plus => Op::Add(ty),
+            TokenKind::Minus => Op::Sub(ty),
+            TokenKind::Star => Op::Mul(ty),
+            TokenKind::Slash => Op::Div(ty),
+            TokenKind::Percent => Op::Rem(ty),
+            TokenKind::StarStar => Op::Pow(ty),
+            TokenKind::Amper => Op::BitAnd(ty),
+            TokenKind::Pipe => Op::BitOr(ty),
+            TokenKind::Caret => Op::BitXor(ty),
+            TokenKind::LessLess => Op::Shl(ty),
+            TokenKind::GreaterGreater => Op::Shr(ty),
+            _ => unreachable!("Arithmetic operator `{op:?}`"),
+        }
+    }
+
+    /// The operator applied by an assignment, `Some(None)` for plain `=`
+    fn assignment_op(op: &TokenKind) -> Option<Option<TokenKind>> {
+        match op {
+            TokenKind::Equal => Some(None),
+            TokenKind::PlusEqual => Some(Some(TokenKind::Plus)),
+            TokenKind::MinusEqual => Some(Some(TokenKind::Minus)),
+            TokenKind::StarEqual => Some(Some(TokenKind::Star)),
+            TokenKind::SlashEqual => Some(Some(TokenKind::Slash)),
+            _ => None,
+        }
+    }
+
+    /// Emits a `Cast` if a value of type `from` has to be converted to `to`
+    fn convert(&mut self, from: &TolType, to: &TolType) {
+        let (from, to) = match (from, to) {
+            (TolType::Array { inner: from, .. }, TolType::Array { inner: to, .. }) => {
+                (from.as_ref(), to.as_ref())
+            }
+            _ => (from, to),
+        };
+
+        if let Some(to) = Ty::of(to)
+            && Ty::of(from) != Some(to)
+        {
+            self.push(Op::Cast(to));
+        }
+    }
+
+    fn inner_type(ttype: &TolType) -> &TolType {
+        match ttype {
+            TolType::Array { inner, .. } => inner,
+            _ => unreachable!("Expected an array, found `{ttype}`"),
+        }
+    }
+
+    /// Stores the value on top into a new variable. Variables declared outside
+    /// of any `paraan` are globals.
+    fn declare(&mut self, symbol_id: SymbolId) {
+        self.name(symbol_id);
+        if self.return_type.is_none() {
+            self.push(Op::StoreGlobal(symbol_id));
+        } else {
+            self.locals.insert(symbol_id);
+            self.push(Op::Store(symbol_id));
+        }
+    }
+
+    fn load(&mut self, symbol_id: SymbolId) {
+        self.name(symbol_id);
+        if self.locals.contains(&symbol_id) {
+            self.push(Op::Load(symbol_id));
+        } else {
+            self.push(Op::LoadGlobal(symbol_id));
+        }
+    }
+
+    fn store(&mut self, symbol_id: SymbolId) {
+        self.name(symbol_id);
+        if self.locals.contains(&symbol_id) {
+            self.push(Op::Store(symbol_id));
+        } else {
+            self.push(Op::StoreGlobal(symbol_id));
+        }
+    }
+
+    fn name(&mut self, symbol_id: SymbolId) {
+        self.names
+            .entry(symbol_id)
+            .or_insert_with(|| self.symbols[symbol_id].name().to_string());
+    }
+
+    fn push_const(&mut self, value: Value) {
+        let index = match self.constants.iter().position(|c| *c == value) {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        };
+        self.push(Op::Const(index as u32));
+    }
+
+    fn push(&mut self, op: Op) {
+        self.depth = self.depth.checked_add_signed(op.stack_effect()).unwrap();
+        self.function.code.push(op);
+    }
+
+    /// Pushes a jump to be patched later, returning where it is
+    fn push_jump(&mut self, op: Op) -> usize {
+        self.push(op);
+        self.function.code.len() - 1
+    }
+
+    fn patch(&mut self, at: usize, target: u32) {
+        match &mut self.function.code[at] {
+            Op::Jump(t) | Op::JumpIfFalse(t) | Op::IterNext(t) => *t = target,
+            op => unreachable!("Patching `{op:?}`"),
+        }
+    }
+
+    fn here(&self) -> u32 {
+        self.function.code.len() as u32
+    }
+}
@@ -0,0 +1,434 @@
+//! The `.tolc` file format.
+//!
+//! A file starts with the magic bytes `TOLC` and a `u16` format version,
+//! followed by the constant pool, the symbol names, the entry point, `init`
+//! and the functions. Numbers are little-endian, counts and indices are
+//! `u32`s, and strings are a `u32` length followed by UTF-8. Every
+//! instruction is an opcode byte followed by its operands.
+//!
+//! Decoding checks that every index in the code points at something that
+//! exists and that the code never takes more values than the stack holds, so
+//! a corrupted file is reported instead of crashing the VM. Values of the
+//! wrong kind can only be found while running, so the VM reports those.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    analyzer::SymbolId,
+    bytecode::{Function, Op, Program, Ty},
+    error::CompilerError,
+    interpreter::value::Value,
+};
+
+const MAGIC: &[u8; 4] = b"TOLC";
+
+/// Bumped whenever the layout or the opcodes change
+pub const VERSION: u16 = 1;
+
+/// `Program::entry` of a program without `main`
+const NO_ENTRY: u32 = u32::MAX;
+
+pub fn encode(program: &Program) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+
+    write_u32(&mut out, program.constants.len());
+    for value in program.constants.iter() {
+        match value {
+            Value::Int(value) => {
+                out.push(0);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::Float(value) => {
+                out.push(1);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::Bool(value) => {
+                out.push(2);
+                out.push(*value as u8);
+            }
+            _ => unreachable!("Only numbers and bools are constants, found `{value:?}`"),
+        }
+    }
+
+    write_u32(&mut out, program.names.len());
+    for (id, name) in program.names.iter() {
+        write_u32(&mut out, *id);
+        write_str(&mut out, name);
+    }
+
+    out.extend_from_slice(&program.entry.unwrap_or(NO_ENTRY).to_le_bytes());
+    write_function(&mut out, &program.init);
+    write_u32(&mut out, program.functions.len());
+    for function in program.functions.iter() {
+        write_function(&mut out, function);
+    }
+
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Program, CompilerError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(invalid("hindi ito `.tolc` na file"));
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version != VERSION {
+        return Err(invalid(format!(
+            "bersyon {version} ng format, {VERSION} ang kaya ng compiler na ito"
+        )));
+    }
+
+    let mut constants = Vec::new();
+    for _ in 0..reader.u32()? {
+        constants.push(match reader.u8()? {
+            0 => Value::Int(i128::from_le_bytes(reader.array()?)),
+            1 => Value::Float(f64::from_le_bytes(reader.array()?)),
+            2 => Value::Bool(reader.u8()? != 0),
+            tag => return Err(invalid(format!("hindi kilalang constant na {tag}"))),
+        });
+    }
+
+    let mut names = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let id = reader.u32()? as usize;
+        names.insert(id, reader.string()?);
+    }
+
+    let entry = match reader.u32()? {
+        NO_ENTRY => None,
+        entry => Some(entry),
+    };
+    let init = reader.function()?;
+    let mut functions = Vec::new();
+    for _ in 0..reader.u32()? {
+        functions.push(reader.function()?);
+    }
+
+    if reader.pos != bytes.len() {
+        return Err(invalid("may sobrang bytes sa dulo"));
+    }
+
+    let program = Program {
+        constants,
+        functions,
+        init,
+        entry,
+        names,
+    };
+    validate(&program)?;
+
+    Ok(program)
+}
+
+/// Checks that every index in the code points at something that exists, and
+/// that no path through the code takes more values than the stack holds
+fn validate(program: &Program) -> Result<(), CompilerError> {
+    if program
+        .entry
+        .is_some_and(|entry| entry as usize >= program.functions.len())
+    {
+        return Err(invalid("walang ganoong paraan ang entry point"));
+    }
+
+    let globals: HashSet<SymbolId> = program
+        .init
+        .code
+        .iter()
+        .filter_map(|op| match op {
+            Op::StoreGlobal(id) => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for function in std::iter::once(&program.init).chain(program.functions.iter()) {
+        let locals: HashSet<SymbolId> = function
+            .params
+            .iter()
+            .copied()
+            .chain(function.code.iter().filter_map(|op| match op {
+                Op::Store(id) => Some(*id),
+                _ => None,
+            }))
+            .collect();
+
+        for op in function.code.iter() {
+            let valid = match op {
+                Op::Const(index) => (*index as usize) < program.constants.len(),
+                Op::Load(id) => locals.contains(id),
+                Op::LoadGlobal(id) => globals.contains(id),
+                Op::Jump(target) | Op::JumpIfFalse(target) | Op::IterNext(target) => {
+                    (*target as usize) < function.code.len()
+                }
+                Op::Call { function, argc } => program
+                    .functions
+                    .get(*function as usize)
+                    .is_some_and(|callee| callee.params.len() == *argc as usize),
+                // Only `+`, `-`, `*`, `/` and `**` apply to floats
+                Op::Rem(ty)
+                | Op::BitAnd(ty)
+                | Op::BitOr(ty)
+                | Op::BitXor(ty)
+                | Op::Shl(ty)
+                | Op::Shr(ty)
+                | Op::BitNot(ty) => !matches!(ty, Ty::F32 | Ty::F64),
+                _ => true,
+            };
+            if !valid {
+                return Err(invalid(format!("maling `{op:?}` sa `{}`", function.name)));
+            }
+        }
+
+        if !matches!(function.code.last(), Some(Op::Return)) {
+            return Err(invalid(format!(
+                "hindi nagtatapos sa `return` ang `{}`",
+                function.name
+            )));
+        }
+
+        check_stack(function)?;
+    }
+
+    Ok(())
+}
+
+/// Follows every path through the code of a function, checking that each
+/// instruction has the values it takes and that paths meet at the same depth
+fn check_stack(function: &Function) -> Result<(), CompilerError> {
+    let mut depths: Vec<Option<usize>> = vec![None; function.code.len()];
+    let mut pending = vec![(0, 0)];
+    while let Some((pc, depth)) = pending.pop() {
+        let Some(op) = function.code.get(pc) else {
+            return Err(invalid(format!("lumampas sa dulo ng `{}`", function.name)));
+        };
+        match depths[pc] {
+            Some(seen) if seen == depth => continue,
+            Some(_) => {
+                return Err(invalid(format!(
+                    "hindi pare-pareho ang lalim ng stack sa `{}`",
+                    function.name
+                )));
+            }
+            None => depths[pc] = Some(depth),
+        }
+
+        let underflow = || {
+            invalid(format!(
+                "kulang ang laman ng stack para sa `{op:?}` sa `{}`",
+                function.name
+            ))
+        };
+        if op.takes() > depth {
+            return Err(underflow());
+        }
+
+        let next = depth.checked_add_signed(op.stack_effect()).unwrap();
+        match *op {
+            Op::Return => {}
+            Op::Jump(target) => pending.push((target as usize, depth)),
+            Op::JumpIfFalse(target) => {
+                pending.push((pc + 1, next));
+                pending.push((target as usize, next));
+            }
+            // Either pushes the next index and element or pops the array and
+            // index and leaves the loop
+            Op::IterNext(exit) => {
+                pending.push((pc + 1, next));
+                pending.push((exit as usize, depth - 2));
+            }
+            Op::Truncate(len) if len as usize > depth => return Err(underflow()),
+            Op::Truncate(len) => pending.push((pc + 1, len as usize)),
+            _ => pending.push((pc + 1, next)),
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid(reason: impl Into<String>) -> CompilerError {
+    CompilerError::InvalidBytecode {
+        reason: reason.into(),
+    }
+}
+
+fn write_u32(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    write_u32(out, value.len());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_function(out: &mut Vec<u8>, function: &Function) {
+    write_str(out, &function.name);
+    write_u32(out, function.params.len());
+    for id in function.params.iter() {
+        write_u32(out, *id);
+    }
+
+    write_u32(out, function.code.len());
+    for op in function.code.iter() {
+        write_op(out, op);
+    }
+}
+
+fn write_op(out: &mut Vec<u8>, op: &Op) {
+    let (opcode, operand, ty) = match *op {
+        Op::Const(index) => (0, Some(index as usize), None),
+        Op::PushVoid => (1, None, None),
+        Op::Pop => (2, None, None),
+        Op::Dup => (3, None, None),
+        Op::Truncate(len) => (4, Some(len as usize), None),
+        Op::Load(id) => (5, Some(id), None),
+        Op::Store(id) => (6, Some(id), None),
+        Op::LoadGlobal(id) => (7, Some(id), None),
+        Op::StoreGlobal(id) => (8, Some(id), None),
+        Op::Cast(ty) => (9, None, Some(ty)),
+        Op::Add(ty) => (10, None, Some(ty)),
+        Op::Sub(ty) => (11, None, Some(ty)),
+        Op::Mul(ty) => (12, None, Some(ty)),
+        Op::Div(ty) => (13, None, Some(ty)),
+        Op::Rem(ty) => (14, None, Some(ty)),
+        Op::Pow(ty) => (15, None, Some(ty)),
+        Op::BitAnd(ty) => (16, None, Some(ty)),
+        Op::BitOr(ty) => (17, None, Some(ty)),
+        Op::BitXor(ty) => (18, None, Some(ty)),
+        Op::Shl(ty) => (19, None, Some(ty)),
+        Op::Shr(ty) => (20, None, Some(ty)),
+        Op::Neg(ty) => (21, None, Some(ty)),
+        Op::BitNot(ty) => (22, None, Some(ty)),
+        Op::Not => (23, None, None),
+        Op::Eq => (24, None, None),
+        Op::Ne => (25, None, None),
+        Op::Lt => (26, None, None),
+        Op::Le => (27, None, None),
+        Op::Gt => (28, None, None),
+        Op::Ge => (29, None, None),
+        Op::Jump(target) => (30, Some(target as usize), None),
+        Op::JumpIfFalse(target) => (31, Some(target as usize), None),
+        Op::Call { function, argc } => {
+            out.push(32);
+            write_u32(out, function as usize);
+            write_u32(out, argc as usize);
+            return;
+        }
+        Op::Return => (33, None, None),
+        Op::MakeArray(len) => (34, Some(len as usize), None),
+        Op::IterStart => (35, None, None),
+        Op::IterNext(exit) => (36, Some(exit as usize), None),
+    };
+
+    out.push(opcode);
+    if let Some(operand) = operand {
+        write_u32(out, operand);
+    }
+    if let Some(ty) = ty {
+        out.push(Ty::ALL.iter().position(|t| *t == ty).unwrap() as u8);
+    }
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], CompilerError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("biglang natapos ang file"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CompilerError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, CompilerError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, CompilerError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String, CompilerError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("hindi UTF-8 ang pangalan"))
+    }
+
+    fn ty(&mut self) -> Result<Ty, CompilerError> {
+        let index = self.u8()?;
+        Ty::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| invalid(format!("hindi kilalang tipo na {index}")))
+    }
+
+    fn function(&mut self) -> Result<Function, CompilerError> {
+        let name = self.string()?;
+        let mut params = Vec::new();
+        for _ in 0..self.u32()? {
+            params.push(self.u32()? as usize);
+        }
+
+        let mut code = Vec::new();
+        for _ in 0..self.u32()? {
+            code.push(self.op()?);
+        }
+
+        Ok(Function { name, params, code })
+    }
+
+    fn op(&mut self) -> Result<Op, CompilerError> {
+        let op = match self.u8()? {
+            0 => Op::Const(self.u32()?),
+            1 => Op::PushVoid,
+            2 => Op::Pop,
+            3 => Op::Dup,
+            4 => Op::Truncate(self.u32()?),
+            5 => Op::Load(self.u32()? as usize),
+            6 => Op::Store(self.u32()? as usize),
+            7 => Op::LoadGlobal(self.u32()? as usize),
+            8 => Op::StoreGlobal(self.u32()? as usize),
+            9 => Op::Cast(self.ty()?),
+            10 => Op::Add(self.ty()?),
+            11 => Op::Sub(self.ty()?),
+            12 => Op::Mul(self.ty()?),
+            13 => Op::Div(self.ty()?),
+            14 => Op::Rem(self.ty()?),
+            15 => Op::Pow(self.ty()?),
+            16 => Op::BitAnd(self.ty()?),
+            17 => Op::BitOr(self.ty()?),
+            18 => Op::BitXor(self.ty()?),
+            19 => Op::Shl(self.ty()?),
+            20 => Op::Shr(self.ty()?),
+            21 => Op::Neg(self.ty()?),
+            22 => Op::BitNot(self.ty()?),
+            23 => Op::Not,
+            24 => Op::Eq,
+            25 => Op::Ne,
+            26 => Op::Lt,
+            27 => Op::Le,
+            28 => Op::Gt,
+            29 => Op::Ge,
+            30 => Op::Jump(self.u32()?),
+            31 => Op::JumpIfFalse(self.u32()?),
+            32 => Op::Call {
+                function: self.u32()?,
+                argc: self.u32()?,
+            },
+            33 => Op::Return,
+            34 => Op::MakeArray(self.u32()?),
+            35 => Op::IterStart,
+            36 => Op::IterNext(self.u32()?),
+            opcode => return Err(invalid(format!("hindi kilalang opcode na {opcode}"))),
+        };
+
+        Ok(op)
+    }
+}
@@ -0,0 +1,262 @@
+//! A compact bytecode for running programs without a C compiler.
+//!
+//! [`emit`] lowers the typed AST into a [`Program`]: a pool of constants and
+//! a list of functions whose code runs on the operand stack of the [`vm`].
+//! Variables are addressed by their `SymbolId`, so running a program needs
+//! nothing from the analyzer. [`format`] writes and reads programs as `.tolc`
+//! files, and [`disasm`] prints them.
+
+pub mod disasm;
+pub mod emit;
+pub mod format;
+pub mod vm;
+
+use std::collections::BTreeMap;
+
+use crate::{analyzer::SymbolId, interpreter::value::Value, toltype::TolType};
+
+/// Name of `Program::init` in the disassembly
+pub const INIT_NAME: &str = "<init>";
+
+/// Extension of the files written by `tol emit --stage bytecode -o`
+pub const FILE_EXTENSION: &str = "tolc";
+
+/// Numeric type an instruction works on. Integers and floats without a size
+/// are `i64`s and `f64`s, as in the generated C.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ty {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl Ty {
+    pub const ALL: [Ty; 10] = [
+        Ty::I8,
+        Ty::I16,
+        Ty::I32,
+        Ty::I64,
+        Ty::U8,
+        Ty::U16,
+        Ty::U32,
+        Ty::U64,
+        Ty::F32,
+        Ty::F64,
+    ];
+
+    /// Returns `None` for types that aren't numbers
+    pub fn of(ttype: &TolType) -> Option<Ty> {
+        match ttype {
+            TolType::I8 => Some(Ty::I8),
+            TolType::I16 => Some(Ty::I16),
+            TolType::I32 => Some(Ty::I32),
+            TolType::I64 | TolType::ISize | TolType::UnsizedInteger => Some(Ty::I64),
+            TolType::U8 => Some(Ty::U8),
+            TolType::U16 => Some(Ty::U16),
+            TolType::U32 => Some(Ty::U32),
+            TolType::U64 | TolType::USize => Some(Ty::U64),
+            TolType::F32 => Some(Ty::F32),
+            TolType::F64 | TolType::UnsizedFloat => Some(Ty::F64),
+            _ => None,
+        }
+    }
+
+    pub fn to_toltype(self) -> TolType {
+        match self {
+            Ty::I8 => TolType::I8,
+            Ty::I16 => TolType::I16,
+            Ty::I32 => TolType::I32,
+            Ty::I64 => TolType::I64,
+            Ty::U8 => TolType::U8,
+            Ty::U16 => TolType::U16,
+            Ty::U32 => TolType::U32,
+            Ty::U64 => TolType::U64,
+            Ty::F32 => TolType::F32,
+            Ty::F64 => TolType::F64,
+        }
+    }
+}
+
+/// An instruction. Jump targets are indices into the code of the function
+/// the instruction is in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// Pushes a value from the constant pool
+    Const(u32),
+    PushVoid,
+    Pop,
+    Dup,
+    /// Drops values until the stack of the current call holds this many
+    Truncate(u32),
+
+    Load(SymbolId),
+    Store(SymbolId),
+    LoadGlobal(SymbolId),
+    StoreGlobal(SymbolId),
+    /// Converts the value on top to a type, wrapping integers
+    Cast(Ty),
+
+    Add(Ty),
+    Sub(Ty),
+    Mul(Ty),
+    Div(Ty),
+    Rem(Ty),
+    Pow(Ty),
+    BitAnd(Ty),
+    BitOr(Ty),
+    BitXor(Ty),
+    Shl(Ty),
+    Shr(Ty),
+    Neg(Ty),
+    BitNot(Ty),
+    Not,
+
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+
+    Jump(u32),
+    /// Pops a bool and jumps if it is `mali`
+    JumpIfFalse(u32),
+
+    /// Calls a function of the program with the arguments on top of the stack
+    Call {
+        function: u32,
+        argc: u32,
+    },
+    /// Pops the result and returns it to the caller
+    Return,
+
+    /// Pops this many values into an array
+    MakeArray(u32),
+    /// Starts iterating over the array on top, pushing the index 0 after it
+    IterStart,
+    /// Pushes the next element of the array being iterated over, or pops the
+    /// array and index and jumps when there are no more elements
+    IterNext(u32),
+}
+
+impl Op {
+    /// How many values the instruction leaves on the stack minus how many it
+    /// takes. `IterNext` is counted as finding an element, `Truncate` as
+    /// leaving the stack as is.
+    pub fn stack_effect(&self) -> isize {
+        match self {
+            Op::Const(_)
+            | Op::PushVoid
+            | Op::Dup
+            | Op::Load(_)
+            | Op::LoadGlobal(_)
+            | Op::IterStart
+            | Op::IterNext(_) => 1,
+            Op::Pop
+            | Op::Store(_)
+            | Op::StoreGlobal(_)
+            | Op::Add(_)
+            | Op::Sub(_)
+            | Op::Mul(_)
+            | Op::Div(_)
+            | Op::Rem(_)
+            | Op::Pow(_)
+            | Op::BitAnd(_)
+            | Op::BitOr(_)
+            | Op::BitXor(_)
+            | Op::Shl(_)
+            | Op::Shr(_)
+            | Op::Eq
+            | Op::Ne
+            | Op::Lt
+            | Op::Le
+            | Op::Gt
+            | Op::Ge
+            | Op::JumpIfFalse(_)
+            | Op::Return => -1,
+            Op::Truncate(_) | Op::Cast(_) | Op::Neg(_) | Op::BitNot(_) | Op::Not | Op::Jump(_) => 0,
+            Op::Call { argc, .. } => 1 - *argc as isize,
+            Op::MakeArray(len) => 1 - *len as isize,
+        }
+    }
+
+    /// How many values the instruction needs on the stack
+    pub fn takes(&self) -> usize {
+        match self {
+            Op::Const(_)
+            | Op::PushVoid
+            | Op::Truncate(_)
+            | Op::Load(_)
+            | Op::LoadGlobal(_)
+            | Op::Jump(_)
+            | Op::IterStart => 0,
+            Op::Pop
+            | Op::Dup
+            | Op::Store(_)
+            | Op::StoreGlobal(_)
+            | Op::Cast(_)
+            | Op::Neg(_)
+            | Op::BitNot(_)
+            | Op::Not
+            | Op::JumpIfFalse(_)
+            | Op::Return => 1,
+            Op::Add(_)
+            | Op::Sub(_)
+            | Op::Mul(_)
+            | Op::Div(_)
+            | Op::Rem(_)
+            | Op::Pow(_)
+            | Op::BitAnd(_)
+            | Op::BitOr(_)
+            | Op::BitXor(_)
+            | Op::Shl(_)
+            | Op::Shr(_)
+            | Op::Eq
+            | Op::Ne
+            | Op::Lt
+            | Op::Le
+            | Op::Gt
+            | Op::Ge
+            | Op::IterNext(_) => 2,
+            Op::Call { argc, .. } => *argc as usize,
+            Op::MakeArray(len) => *len as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<SymbolId>,
+    pub code: Vec<Op>,
+}
+
+impl Function {
+    pub fn new(name: &str, params: Vec<SymbolId>) -> Self {
+        Self {
+            name: name.to_string(),
+            params,
+            code: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// Integers, floats and bools used by the code
+    pub constants: Vec<Value>,
+    pub functions: Vec<Function>,
+    /// Declares the top-level variables before `entry` is called
+    pub init: Function,
+    /// Index of `main` in `functions`, if the program has one
+    pub entry: Option<u32>,
+    /// Names of the symbols used by the code, for the disassembler
+    pub names: BTreeMap<SymbolId, String>,
+}
@@ -0,0 +1,304 @@
+//! Runs a [`Program`] on an operand stack.
+//!
+//! Arithmetic goes through the same helpers as the interpreter, so both wrap
+//! and fail the way the generated C does. The VM keeps its own call stack, so
+//! unlike the interpreter it doesn't need a large thread stack for deep
+//! recursion.
+
+use std::collections::HashMap;
+
+use crate::{
+    analyzer::SymbolId,
+    bytecode::{Op, Program, Ty},
+    error::RuntimeError,
+    interpreter::{
+        MAX_CALL_DEPTH,
+        value::{Value, arith, compare, unary},
+    },
+    lexer::token::TokenKind,
+};
+
+/// A call being run
+struct Frame {
+    /// Index into `Program::functions`, `None` for `Program::init`
+    function: Option<u32>,
+    pc: usize,
+    /// Where the values of this call start on the stack
+    base: usize,
+    locals: HashMap<SymbolId, Value>,
+}
+
+pub struct Vm<'p> {
+    program: &'p Program,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    globals: HashMap<SymbolId, Value>,
+}
+
+impl<'p> Vm<'p> {
+    pub fn new(program: &'p Program) -> Self {
+        Self {
+            program,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: HashMap::new(),
+        }
+    }
+
+    /// Declares the top-level variables, then calls `main`, which the caller
+    /// made sure exists. Returns the exit code of the program, which is what
+    /// `main` returns.
+    pub fn run(&mut self) -> Result<i32, RuntimeError> {
+        self.frames.push(Frame {
+            function: None,
+            pc: 0,
+            base: 0,
+            locals: HashMap::new(),
+        });
+        self.execute()?;
+
+        let entry = self
+            .program
+            .entry
+            .expect("`main` should have been checked before running");
+        self.call(entry, 0)?;
+
+        match self.execute()? {
+            Value::Int(code) => Ok(code as i32),
+            _ => Ok(0),
+        }
+    }
+
+    /// Pushes a frame for a call whose arguments are on top of the stack
+    fn call(&mut self, function: u32, argc: usize) -> Result<(), RuntimeError> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow);
+        }
+
+        let base = self.stack.len() - argc;
+        let params = &self.program.functions[function as usize].params;
+        let locals = params
+            .iter()
+            .copied()
+            .zip(self.stack.drain(base..))
+            .collect();
+        self.frames.push(Frame {
+            function: Some(function),
+            pc: 0,
+            base,
+            locals,
+        });
+
+        Ok(())
+    }
+
+    /// Runs until the frame that was on top when called returns, giving back
+    /// what it returned
+    fn execute(&mut self) -> Result<Value, RuntimeError> {
+        let depth = self.frames.len();
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let code = match frame.function {
+                Some(function) => &self.program.functions[function as usize].code,
+                None => &self.program.init.code,
+            };
+            let op = code[frame.pc];
+            frame.pc += 1;
+
+            match op {
+                Op::Const(index) => self
+                    .stack
+                    .push(self.program.constants[index as usize].clone()),
+                Op::PushVoid => self.stack.push(Value::Void),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Dup => {
+                    let top = self.stack.last().unwrap().clone();
+                    self.stack.push(top);
+                }
+                Op::Truncate(len) => {
+                    let base = self.frames.last().unwrap().base;
+                    self.stack.truncate(base + len as usize);
+                }
+                Op::Load(symbol_id) => {
+                    let value = self.frames.last().unwrap().locals.get(&symbol_id);
+                    let value = value.cloned().ok_or_else(|| invalid(op))?;
+                    self.stack.push(value);
+                }
+                Op::Store(symbol_id) => {
+                    let value = self.pop();
+                    self.frames
+                        .last_mut()
+                        .unwrap()
+                        .locals
+                        .insert(symbol_id, value);
+                }
+                Op::LoadGlobal(symbol_id) => {
+                    let value = self.globals.get(&symbol_id);
+                    let value = value.cloned().ok_or_else(|| invalid(op))?;
+                    self.stack.push(value);
+                }
+                Op::StoreGlobal(symbol_id) => {
+                    let value = self.pop();
+                    self.globals.insert(symbol_id, value);
+                }
+                Op::Cast(ty) => {
+                    let value = self.pop();
+                    self.stack.push(cast(value, ty));
+                }
+                Op::Add(ty) => self.arith(op, TokenKind::Plus, ty)?,
+                Op::Sub(ty) => self.arith(op, TokenKind::Minus, ty)?,
+                Op::Mul(ty) => self.arith(op, TokenKind::Star, ty)?,
+                Op::Div(ty) => self.arith(op, TokenKind::Slash, ty)?,
+                Op::Rem(ty) => self.arith(op, TokenKind::Percent, ty)?,
+                Op::Pow(ty) => self.arith(op, TokenKind::StarStar, ty)?,
+                Op::BitAnd(ty) => self.arith(op, TokenKind::Amper, ty)?,
+                Op::BitOr(ty) => self.arith(op, TokenKind::Pipe, ty)?,
+                Op::BitXor(ty) => self.arith(op, TokenKind::Caret, ty)?,
+                Op::Shl(ty) => self.arith(op, TokenKind::LessLess, ty)?,
+                Op::Shr(ty) => self.arith(op, TokenKind::GreaterGreater, ty)?,
+                Op::Neg(ty) => self.unary(op, TokenKind::Minus, Some(ty))?,
+                Op::BitNot(ty) => self.unary(op, TokenKind::Tilde, Some(ty))?,
+                Op::Not => self.unary(op, TokenKind::Bang, None)?,
+                Op::Eq => self.compare(op, TokenKind::EqualEqual)?,
+                Op::Ne => self.compare(op, TokenKind::BangEqual)?,
+                Op::Lt => self.compare(op, TokenKind::Less)?,
+                Op::Le => self.compare(op, TokenKind::LessEqual)?,
+                Op::Gt => self.compare(op, TokenKind::Greater)?,
+                Op::Ge => self.compare(op, TokenKind::GreaterEqual)?,
+                Op::Jump(target) => self.jump(target),
+                Op::JumpIfFalse(target) => {
+                    let Value::Bool(cond) = self.pop() else {
+                        return Err(invalid(op));
+                    };
+                    if !cond {
+                        self.jump(target);
+                    }
+                }
+                Op::Call { function, argc } => self.call(function, argc as usize)?,
+                Op::Return => {
+                    let value = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    self.stack.truncate(frame.base);
+                    if self.frames.len() < depth {
+                        return Ok(value);
+                    }
+                    self.stack.push(value);
+                }
+                Op::MakeArray(len) => {
+                    let start = self.stack.len() - len as usize;
+                    let elems = self.stack.split_off(start);
+                    self.stack.push(Value::Array(elems));
+                }
+                Op::IterStart => self.stack.push(Value::Int(0)),
+                Op::IterNext(exit) => {
+                    let (Value::Int(index), Some(Value::Array(elems))) =
+                        (self.pop(), self.stack.last())
+                    else {
+                        return Err(invalid(op));
+                    };
+
+                    match usize::try_from(index)
+                        .ok()
+                        .and_then(|i| elems.get(i))
+                        .cloned()
+                    {
+                        Some(elem) => {
+                            self.stack.push(Value::Int(index + 1));
+                            self.stack.push(elem);
+                        }
+                        None => {
+                            self.pop();
+                            self.jump(exit);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn arith(&mut self, instruction: Op, op: TokenKind, ty: Ty) -> Result<(), RuntimeError> {
+        let right = self.pop();
+        let left = self.pop();
+        // An integer exponent of a float base is the only mix
+        let fits = |value: &Value| match ty {
+            Ty::F32 | Ty::F64 => is_number(value),
+            _ => matches!(value, Value::Int(_)),
+        };
+        if !fits(&left) || !fits(&right) {
+            return Err(invalid(instruction));
+        }
+
+        self.stack.push(arith(left, right, &op, &ty.to_toltype())?);
+        Ok(())
+    }
+
+    fn unary(
+        &mut self,
+        instruction: Op,
+        op: TokenKind,
+        ty: Option<Ty>,
+    ) -> Result<(), RuntimeError> {
+        let right = self.pop();
+        let fits = match op {
+            TokenKind::Minus => is_number(&right),
+            TokenKind::Tilde => matches!(right, Value::Int(_)),
+            _ => matches!(right, Value::Bool(_)),
+        };
+        if !fits {
+            return Err(invalid(instruction));
+        }
+
+        let value = unary(&op, right);
+        self.stack.push(match ty {
+            Some(ty) => cast(value, ty),
+            None => value,
+        });
+        Ok(())
+    }
+
+    fn compare(&mut self, instruction: Op, op: TokenKind) -> Result<(), RuntimeError> {
+        let right = self.pop();
+        let left = self.pop();
+        // Floats are only compared with numbers
+        let has_float = matches!(left, Value::Float(_)) || matches!(right, Value::Float(_));
+        if has_float && !(is_number(&left) && is_number(&right)) {
+            return Err(invalid(instruction));
+        }
+
+        self.stack.push(compare(&left, &right, &op));
+        Ok(())
+    }
+
+    fn jump(&mut self, target: u32) {
+        self.frames.last_mut().unwrap().pc = target as usize;
+    }
+
+    /// Decoding made sure no instruction takes more values than there are
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the operand stack is empty")
+    }
+}
+
+fn is_number(value: &Value) -> bool {
+    matches!(value, Value::Int(_) | Value::Float(_))
+}
+
+fn invalid(op: Op) -> RuntimeError {
+    RuntimeError::InvalidBytecode(format!("{op:?}"))
+}
+
+/// Converts a value, or every element of an array, to `ty`
+fn cast(value: Value, ty: Ty) -> Value {
+    match value {
+        Value::Array(elems) => Value::Array(elems.into_iter().map(|e| cast(e, ty)).collect()),
+        Value::Int(value) if matches!(ty, Ty::F32 | Ty::F64) => {
+            Value::Float(value as f64).convert(&ty.to_toltype())
+        }
+        Value::Float(value) if !matches!(ty, Ty::F32 | Ty::F64) => {
+            Value::Int(value as i128).convert(&ty.to_toltype())
+        }
+        value => value.convert(&ty.to_toltype()),
+    }
+}
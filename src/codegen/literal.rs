@@ -0,0 +1,68 @@
+use gen_c::product::expr::{CBinaryOp, CExpr, CUnaryOp};
+
+use crate::{analyzer::const_eval::ConstValue, toltype::TolType};
+
+/// Writes a constant as a C expression of the C type of `ttype`.
+///
+/// C gives an unsuffixed integer literal the first of `int`, `long` and
+/// `long long` it fits in, and computes with it in that type. So an operand
+/// is only written bare when its type is no wider than a signed `int`. Other
+/// operands go through the `<stdint.h>` macros, which add the suffix the
+/// platform needs for the exact width and signedness, so that
+/// `100000 * 100000` is computed in 64 bits for an `i64` and `70000 * 60000`
+/// wraps as unsigned for a `u32`. `top` is set for the whole value of an
+/// assignment, argument or return, which C converts to the right type by
+/// itself, so there any value fitting in an `int` is written bare.
+pub fn c_literal(value: &ConstValue, ttype: &TolType, top: bool) -> CExpr {
+    match value {
+        ConstValue::Int(value) => int_literal(*value, ttype, top),
+        ConstValue::Float(value) => float_literal(*value, ttype),
+        ConstValue::Bool(value) => CExpr::literal(if *value { "true" } else { "false" }),
+    }
+}
+
+fn int_literal(value: i128, ttype: &TolType, top: bool) -> CExpr {
+    let negate = |expr| CExpr::unary(CUnaryOp::Neg, expr);
+    if value == i64::MIN as i128 {
+        return CExpr::ident("INT64_MIN");
+    }
+
+    let magnitude = value.unsigned_abs();
+    let name = match ttype {
+        _ if top && magnitude <= i32::MAX as u128 => None,
+        TolType::I8 | TolType::I16 | TolType::I32 | TolType::U8 | TolType::U16 => None,
+        TolType::U32 => Some("UINT32_C"),
+        TolType::U64 | TolType::USize => Some("UINT64_C"),
+        TolType::I64 | TolType::ISize => Some("INT64_C"),
+        _ if magnitude <= i32::MAX as u128 => None,
+        _ if value > i64::MAX as i128 => Some("UINT64_C"),
+        _ => Some("INT64_C"),
+    };
+    let literal = match name {
+        Some(name) => CExpr::call(name, vec![CExpr::literal(magnitude)]),
+        None => CExpr::literal(magnitude),
+    };
+
+    if value < 0 { negate(literal) } else { literal }
+}
+
+fn float_literal(value: f64, ttype: &TolType) -> CExpr {
+    let negate = |expr| CExpr::unary(CUnaryOp::Neg, expr);
+    let suffix = if *ttype == TolType::F32 { "f" } else { "" };
+    let zero = CExpr::literal(format!("0.0{suffix}"));
+
+    let literal = if value.is_nan() {
+        return CExpr::binary(CBinaryOp::Div, zero.clone(), zero);
+    } else if value.is_infinite() {
+        CExpr::binary(CBinaryOp::Div, CExpr::literal(format!("1.0{suffix}")), zero)
+    } else {
+        // `{:?}` always writes a `.` or an exponent, so C reads it as a float
+        CExpr::literal(format!("{:?}{suffix}", value.abs()))
+    };
+
+    if value.is_sign_negative() {
+        negate(literal)
+    } else {
+        literal
+    }
+}
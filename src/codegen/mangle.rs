@@ -0,0 +1,220 @@
+//! C names of the symbols of a program.
+//!
+//! A name is kept as written when C can use it. A name C can't use, being a
+//! keyword, something the included headers declare, a name reserved by the C
+//! standard or one looking like a temporary or a label, becomes
+//! `tol_<name>_<symbol id>`. Names starting with `tol_` are escaped too, which
+//! leaves the whole `tol_` prefix to generated code.
+
+use std::ops::Range;
+
+use crate::{
+    analyzer::{
+        ENTRY_POINT, SymbolId,
+        symbol::{Symbol, SymbolKind},
+    },
+    toltype::TolType,
+};
+
+/// Prefix of every name made up by the code generator
+pub const GENERATED_PREFIX: &str = "tol_";
+
+const C_KEYWORDS: &[&str] = &[
+    "alignas",
+    "alignof",
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "const",
+    "constexpr",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "nullptr",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "struct",
+    "switch",
+    "thread_local",
+    "true",
+    "typedef",
+    "typeof",
+    "typeof_unqual",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+];
+
+/// Names declared by the headers the generated C may include, or that the
+/// program's `main` is linked against
+const C_LIBRARY_NAMES: &[&str] = &[
+    // <stddef.h>, <stdint.h>
+    "NULL",
+    "int8_t",
+    "int16_t",
+    "int32_t",
+    "int64_t",
+    "intmax_t",
+    "intptr_t",
+    "max_align_t",
+    "offsetof",
+    "ptrdiff_t",
+    "size_t",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+    "uintmax_t",
+    "uintptr_t",
+    "wchar_t",
+    // <stdio.h>
+    "EOF",
+    "FILE",
+    "fclose",
+    "fgets",
+    "fopen",
+    "fprintf",
+    "fputs",
+    "getchar",
+    "printf",
+    "putchar",
+    "puts",
+    "scanf",
+    "snprintf",
+    "sprintf",
+    "stderr",
+    "stdin",
+    "stdout",
+    // <stdlib.h>
+    "abort",
+    "abs",
+    "atoi",
+    "calloc",
+    "exit",
+    "free",
+    "getenv",
+    "labs",
+    "malloc",
+    "qsort",
+    "rand",
+    "realloc",
+    "srand",
+    "system",
+    // <math.h>
+    "HUGE_VAL",
+    "INFINITY",
+    "NAN",
+    "ceil",
+    "cos",
+    "exp",
+    "fabs",
+    "floor",
+    "fmod",
+    "fmodf",
+    "log",
+    "pow",
+    "powf",
+    "round",
+    "sin",
+    "sqrt",
+    "tan",
+    // Others
+    "errno",
+    "main",
+];
+
+/// Whether C can't use `name` as written for something of the program
+pub fn is_reserved(name: &str) -> bool {
+    let reserved_by_standard = name.starts_with("__")
+        || name
+            .strip_prefix('_')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()));
+    // `INT32_MAX`, `UINT64_C` and the like from <stdint.h>
+    let limit_macro = (name.starts_with("INT")
+        || name.starts_with("UINT")
+        || name.starts_with("SIZE")
+        || name.starts_with("PTRDIFF"))
+        && ["_MIN", "_MAX", "_C"].iter().any(|end| name.ends_with(end));
+
+    reserved_by_standard
+        || limit_macro
+        || name.starts_with(GENERATED_PREFIX)
+        || is_generated_name(name)
+        || C_KEYWORDS.contains(&name)
+        || C_LIBRARY_NAMES.contains(&name)
+}
+
+/// The C name of a symbol. The entry point keeps its name, as C looks for it.
+pub fn symbol_c_name(symbol_id: SymbolId, symbol: &Symbol) -> String {
+    let is_entry_point =
+        symbol.name() == ENTRY_POINT && matches!(symbol.kind(), SymbolKind::Func { .. });
+    if is_entry_point || !is_reserved(symbol.name()) {
+        return symbol.name().to_string();
+    }
+
+    escape(symbol_id, symbol.name())
+}
+
+/// The name a symbol called `name` gets when C can't use it
+pub fn escape(symbol_id: SymbolId, name: &str) -> String {
+    format!("{GENERATED_PREFIX}{name}_{symbol_id}")
+}
+
+/// How a type is spelled in generated names, an array being `arr<size>_`
+/// followed by its element type, like `arr3_i32`
+pub fn type_name(ttype: &TolType) -> String {
+    match ttype {
+        TolType::Array {
+            inner,
+            size: Some(size),
+        } => format!("arr{size}_{}", type_name(inner)),
+        ttype => ttype.to_string(),
+    }
+}
+
+/// The name of the struct an array is wrapped in, since C can't pass,
+/// return or assign arrays themselves
+pub fn array_struct_name(ttype: &TolType) -> String {
+    format!("{GENERATED_PREFIX}{}", type_name(ttype))
+}
+
+/// Whether a name could clash with a temporary or a label
+pub fn is_generated_name(name: &str) -> bool {
+    ["t", "bb"].iter().any(|prefix| {
+        name.strip_prefix(prefix)
+            .is_some_and(|rest| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()))
+    })
+}
+
+/// Where a name in the generated C comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameMapping {
+    pub c_name: String,
+    /// C name of the function the name is local to, `None` for the names of
+    /// the whole file
+    pub function: Option<String>,
+    pub tol_name: String,
+    pub span: Range<usize>,
+}
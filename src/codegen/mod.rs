@@ -1,219 +1,337 @@
+//! Generates C from the IR.
+//!
+//! Every block becomes a run of statements, labeled when something jumps to
+//! it, and control flow becomes `if` and `goto`. A temporary used once, by the
+//! instruction right after the one assigning it, is folded into that
+//! instruction's expression, so most of the C reads like the source. The
+//! others are declared where they are assigned.
+//!
+//! Integer `/`, `%` and `**` go through the helpers in [`runtime`], which stop
+//! the program on division by zero, overflowing division and negative
+//! exponents. Signed `+`, `-` and `*` are emitted as plain C operators, so the
+//! generated code must be compiled with `-fwrapv` to make overflow wrap
+//! instead of being undefined behavior, as the comment at its top says.
+//!
+//! An array is wrapped in a struct holding its `items`, which C can pass,
+//! return and assign as a whole.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+};
+
 use gen_c::{
     CCodeGen,
     builder::{
-        block_builder::BlockBuilder,
         decl_builder::{ConstKind, DeclBuilder},
         function_builder::FunctionBuilder,
         if_builder::IfBuilder,
         return_builder::ReturnBuilder,
-        while_builder::WhileBuilder,
+        struct_builder::StructBuilder,
     },
     ctype::CType,
-    product::statement::CStatement,
+    product::{
+        expr::{CBinaryOp, CExpr, CUnaryOp},
+        include::CInclude,
+        statement::CStatement,
+    },
 };
 
 use crate::{
-    analyzer::symbol::Symbol,
-    ast::{
-        TypedAst,
-        typed_expr::{TypedExpr, TypedExprKind},
-        typed_stmt::{TypedStmt, TypedStmtKind},
+    analyzer::{ENTRY_POINT, const_eval::ConstValue, symbol::Symbol},
+    ir::{
+        BinOp, BlockId, Function, Global, Inst, Module, Operand, Rvalue, TempId, Terminator, UnOp,
+        shift_amount_ok,
     },
     toltype::TolType,
 };
 
+mod literal;
+pub mod mangle;
+mod runtime;
+pub mod source_map;
+
+use literal::c_literal;
+use mangle::NameMapping;
+use runtime::RuntimeFn;
+use source_map::SourceMap;
+
+/// Name of the C function running `Module::init`
+const INIT_FN_NAME: &str = "tol_init";
+
+/// Top of every generated file, for whoever compiles it by hand
+const WRAPV_NOTE: &str = "Binuo ng tol. I-compile gamit ang -fwrapv: umaasa ang code na ito na\n\
+                          magwa-wrap ang overflow ng signed na +, - at *.";
+
 pub struct Codegen<'a> {
-    ast: &'a TypedAst,
+    module: &'a Module,
     symbols: &'a [Symbol],
+    runtime_fns: RefCell<Vec<RuntimeFn>>,
+    /// Array types whose structs are defined, each after its element type
+    array_types: RefCell<Vec<TolType>>,
+    uses_math: Cell<bool>,
+    /// Where `#line` directives point, for a module lowered with locations
+    source_map: Option<SourceMap<'a>>,
 }
 
 impl<'a> Codegen<'a> {
-    pub fn new(ast: &'a TypedAst, symbols: &'a [Symbol]) -> Self {
-        Self { ast, symbols }
+    pub fn new(module: &'a Module, symbols: &'a [Symbol]) -> Self {
+        Self {
+            module,
+            symbols,
+            runtime_fns: RefCell::new(Vec::new()),
+            array_types: RefCell::new(Vec::new()),
+            uses_math: Cell::new(false),
+            source_map: None,
+        }
     }
 
-    pub fn generate_c(&self, mut generator: CCodeGen) -> String {
-        for stmt in self.ast.iter() {
-            generator = generator.add_statement(self.gen_stmt(stmt));
-        }
+    /// Turns the `Inst::Location`s of the module into `#line` directives
+    /// pointing at the source, and puts one before every `paraan` too
+    pub fn with_source_map(mut self, source_map: SourceMap<'a>) -> Self {
+        self.source_map = Some(source_map);
 
-        generator.produce_c()
+        self
     }
 
-    fn gen_stmt(&self, stmt: &TypedStmt) -> CStatement {
-        match &stmt.kind {
-            TypedStmtKind::Ang { .. } | TypedStmtKind::Dapat { .. } => self.gen_decl(stmt),
-            TypedStmtKind::Paraan { .. } => self.gen_paraan(stmt),
-            TypedStmtKind::Block { .. } => self.gen_block(stmt),
-            TypedStmtKind::Ibalik { .. } => self.gen_ibalik(stmt),
-            TypedStmtKind::Bawat { .. } => self.gen_bawat(stmt),
-            TypedStmtKind::Habang { .. } => self.gen_habang(stmt),
-            TypedStmtKind::Kung { .. } => self.gen_kung(stmt),
+    pub fn generate_c(&self, mut generator: CCodeGen) -> String {
+        // Generated first to find out which headers and helpers are needed
+        let has_init = !self.module.init.is_empty();
+        let mut definitions = Vec::new();
+        if has_init {
+            definitions.push(self.gen_function(&self.module.init, false));
+        }
+        for function in self.module.functions.iter() {
+            let runs_init = has_init && function.name == ENTRY_POINT;
+            let symbol_id = function.symbol_id.expect("only `init` has no symbol");
+            definitions.extend(self.line_directive(self.symbols[symbol_id].span().start));
+            definitions.push(self.gen_function(function, runs_init));
         }
-    }
-
-    fn gen_decl(&self, stmt: &TypedStmt) -> CStatement {
-        let (is_ang, symbol_id, rhs) = {
-            match &stmt.kind {
-                TypedStmtKind::Ang { symbol_id, rhs } => (true, symbol_id, rhs),
-                TypedStmtKind::Dapat { symbol_id, rhs } => (false, symbol_id, rhs),
-                _ => unreachable!(),
-            }
-        };
 
-        let sym = self.get_symbol(*symbol_id);
-        let rhs_c = self.gen_expr(rhs);
-        let mut decl = DeclBuilder::new(self.as_c(&sym.get_type()), sym.name()).with_rhs(rhs_c);
+        generator = generator.add_header_comment(WRAPV_NOTE);
 
-        if !is_ang {
-            decl = decl.as_const(ConstKind::Const);
+        let mut headers = vec!["stdbool.h", "stddef.h", "stdint.h"];
+        if self.runtime_fns.borrow().contains(&RuntimeFn::Panic) {
+            headers.extend(["stdio.h", "stdlib.h"]);
+        }
+        if self.uses_math.get() {
+            headers.push("math.h");
+        }
+        for header in headers {
+            generator = generator.add_include(CInclude::System(header.to_string()));
         }
 
-        decl.build()
-    }
+        // Forward declare every `paraan` so definition order doesn't matter in C
+        for function in self.module.functions.iter() {
+            generator = generator.add_prototype(self.function_builder(function).build_prototype());
+        }
 
-    fn gen_paraan(&self, stmt: &TypedStmt) -> CStatement {
-        let TypedStmtKind::Paraan {
-            params,
-            symbol_id,
-            block,
-        } = &stmt.kind
-        else {
-            unreachable!()
-        };
+        // Globals get no `#line`: a declaration has no code to step through,
+        // and the runtime helpers after them would be mapped to the Tol source
+        for global in self.module.globals.iter() {
+            generator = generator.add_global(self.gen_global(global));
+        }
 
-        let sym = self.get_symbol(*symbol_id);
-        let mut paraan = FunctionBuilder::new(self.as_c(&sym.get_type()), sym.name());
-        for param in params.iter() {
-            paraan = paraan.add_param(self.as_c(&param.ttype), param.id.lexeme());
+        for runtime_fn in self.runtime_fns.borrow().iter() {
+            generator = generator.add_statement(runtime_fn.build(|ttype| self.as_c(ttype)));
+        }
+        for definition in definitions {
+            generator = generator.add_statement(definition);
         }
 
-        paraan = self.build_fn_block(block, paraan);
+        // Last, as every use of an array type above adds it
+        let array_types = self.array_types.borrow().clone();
+        for ttype in array_types.iter() {
+            let TolType::Array {
+                inner,
+                size: Some(size),
+            } = ttype
+            else {
+                unreachable!("Array type `{ttype}`")
+            };
+            let items = CType::Array(Box::new(self.as_c(inner)), *size);
+            generator = generator.add_type(
+                StructBuilder::new(&mangle::array_struct_name(ttype))
+                    .add_field(items, "items")
+                    .build(),
+            );
+        }
 
-        paraan.build()
+        generator.produce_c()
     }
 
-    fn build_fn_block(&self, block: &TypedStmt, mut paraan: FunctionBuilder) -> FunctionBuilder {
-        let TypedStmtKind::Block { stmts } = &block.kind else {
-            unreachable!()
-        };
+    /// A `#line` directive for the line at `offset`, when there is a source
+    /// map
+    fn line_directive(&self, offset: usize) -> Option<CStatement> {
+        self.source_map.as_ref().map(|map| CStatement::Line {
+            line: map.line(offset),
+            file: Some(map.file().to_string()),
+        })
+    }
 
-        for stmt in stmts {
-            paraan = paraan.add_statement(self.gen_stmt(stmt));
+    /// Generates the C of the program alone, without headers, runtime
+    /// helpers or prototypes, for showing what a piece of code turns into.
+    /// Code outside of a `paraan` is shown as is instead of in `tol_init`,
+    /// keeping the value of an expression on its own.
+    pub fn generate_fragment(&self) -> String {
+        let mut generator = CCodeGen::new();
+        for global in self.module.globals.iter() {
+            generator = generator.add_statement(self.gen_global(global));
+        }
+        let init = FunctionCodegen::new(self, &self.module.init).keeping_values();
+        for statement in init.gen_body() {
+            generator = generator.add_statement(statement);
+        }
+        for function in self.module.functions.iter() {
+            generator = generator.add_statement(self.gen_function(function, false));
         }
 
-        paraan
+        generator.produce_c()
     }
 
-    fn gen_block(&self, stmt: &TypedStmt) -> CStatement {
-        let TypedStmtKind::Block { stmts } = &stmt.kind else {
-            unreachable!()
-        };
-
-        let mut block = BlockBuilder::new();
-        for stmt in stmts.iter() {
-            block = block.add_statement(self.gen_stmt(stmt));
+    fn gen_global(&self, global: &Global) -> CStatement {
+        let mut decl = DeclBuilder::new(
+            self.as_c(&global.ttype),
+            &self.symbol_name(global.symbol_id),
+        );
+        if let Some(value) = &global.init {
+            decl = decl.with_rhs(c_literal(value, &global.ttype, true));
+            if global.is_const {
+                decl = decl.as_const(ConstKind::Const);
+            }
         }
 
-        block.build()
+        decl.build()
     }
 
-    fn gen_ibalik(&self, stmt: &TypedStmt) -> CStatement {
-        let TypedStmtKind::Ibalik { rhs } = &stmt.kind else {
-            unreachable!()
-        };
-
-        let mut builder = ReturnBuilder::new();
-        if let Some(tex) = rhs {
-            builder = builder.with_rhs(self.gen_expr(tex));
+    /// Generates a function, calling `tol_init` first if `runs_init`
+    fn gen_function(&self, function: &Function, runs_init: bool) -> CStatement {
+        let mut builder = self.function_builder(function);
+        if runs_init {
+            builder = builder.add_statement(CStatement::Expression {
+                expr: CExpr::call(INIT_FN_NAME, Vec::new()),
+            });
+        }
+        for statement in FunctionCodegen::new(self, function).gen_body() {
+            builder = builder.add_statement(statement);
         }
 
         builder.build()
     }
 
-    fn gen_bawat(&self, stmt: &TypedStmt) -> CStatement {
-        let TypedStmtKind::Bawat {
-            iter,
-            bind_type,
-            block,
-        } = &stmt.kind
-        else {
-            unreachable!()
-        };
+    /// Creates a `FunctionBuilder` with the signature of the function but
+    /// without its body
+    fn function_builder(&self, function: &Function) -> FunctionBuilder {
+        let names = self.local_names(function);
+
+        let mut builder = FunctionBuilder::new(
+            self.as_c(&function.return_type),
+            &self.function_name(function),
+        );
+        for param in function.params.iter() {
+            builder = builder.add_param(self.as_c(&function.locals[*param].ttype), &names[*param]);
+        }
 
-        todo!("`bawat` statement does not work for now")
+        builder
     }
 
-    fn gen_habang(&self, stmt: &TypedStmt) -> CStatement {
-        let TypedStmtKind::Habang { cond, block } = &stmt.kind else {
-            unreachable!()
-        };
+    /// C names of the locals of a function. Locals are declared at the start
+    /// of the function in C, so a local sharing its name with another local,
+    /// a global or a function called in the function gets a number appended.
+    fn local_names(&self, function: &Function) -> Vec<String> {
+        let mut taken = self
+            .module
+            .globals
+            .iter()
+            .map(|g| g.symbol_id)
+            .chain(self.module.names.keys().copied())
+            .chain(self.module.functions.iter().filter_map(|f| f.symbol_id))
+            .map(|id| self.symbol_name(id))
+            .chain([INIT_FN_NAME.to_string()])
+            .collect::<HashSet<_>>();
+
+        let mut names = Vec::new();
+        for local in function.locals.iter() {
+            let base = match local.symbol_id {
+                Some(id) if mangle::is_reserved(&local.name) => mangle::escape(id, &local.name),
+                _ => local.name.clone(),
+            };
+            let mut name = base.clone();
+            let mut n = 0;
+            while taken.contains(&name) {
+                n += 1;
+                name = format!("{base}_{n}");
+            }
+
+            taken.insert(name.clone());
+            names.push(name);
+        }
 
-        WhileBuilder::new(self.gen_expr(cond), self.gen_block(block)).build()
+        names
     }
 
-    fn gen_kung(&self, stmt: &TypedStmt) -> CStatement {
-        let TypedStmtKind::Kung { branches } = &stmt.kind else {
-            unreachable!()
+    /// Where each name declared by the generated C comes from in the Tol
+    /// source, for finding a variable of the program while debugging the C
+    pub fn name_map(&self) -> Vec<NameMapping> {
+        let mut mappings = Vec::new();
+        let mut add = |c_name: String, function: Option<String>, symbol_id: usize| {
+            let symbol = &self.symbols[symbol_id];
+            mappings.push(NameMapping {
+                c_name,
+                function,
+                tol_name: symbol.name().to_string(),
+                span: symbol.span(),
+            });
         };
 
-        let initial_branch = &branches[0];
-        let initial_cond = self.gen_expr(initial_branch.cond.as_ref().unwrap());
-        let initial_body = self.gen_stmt(&initial_branch.block);
-        let mut builder = IfBuilder::new(initial_cond, initial_body);
-
-        let mut had_else = false;
-        for branch in branches[1..].iter() {
-            if branch.cond.is_none() {
-                had_else = true;
-                break;
+        for global in self.module.globals.iter() {
+            add(self.symbol_name(global.symbol_id), None, global.symbol_id);
+        }
+        for function in self.module.all_functions() {
+            let function_name = self.function_name(function);
+            if let Some(id) = function.symbol_id {
+                add(function_name.clone(), None, id);
             }
 
-            let cond = self.gen_expr(branch.cond.as_ref().unwrap());
-            let body = self.gen_stmt(&branch.block);
-            builder = builder.add_elseif_branch(cond, body);
+            let locals = function.locals.iter().zip(self.local_names(function));
+            for (local, c_name) in locals {
+                if let Some(id) = local.symbol_id {
+                    add(c_name, Some(function_name.clone()), id);
+                }
+            }
         }
 
-        if had_else {
-            let len = branches.len();
-            let body = self.gen_stmt(&branches[len - 1].block);
+        mappings
+    }
 
-            builder = builder.with_else_branch(body);
+    /// Marks a runtime helper as used, returning its name. The helpers it
+    /// calls are added before it, as they have no prototypes.
+    fn use_runtime(&self, runtime_fn: RuntimeFn) -> String {
+        if let Some(inner) = runtime_fn.inner_cast() {
+            self.use_runtime(inner);
         }
 
-        builder.build()
+        let mut runtime_fns = self.runtime_fns.borrow_mut();
+        if runtime_fn.panics() && !runtime_fns.contains(&RuntimeFn::Panic) {
+            runtime_fns.insert(0, RuntimeFn::Panic);
+        }
+        if !runtime_fns.contains(&runtime_fn) {
+            runtime_fns.push(runtime_fn.clone());
+        }
+
+        runtime_fn.name()
     }
 
-    fn gen_expr(&self, expr: &TypedExpr) -> String {
-        match &expr.kind {
-            TypedExprKind::Integer { lexeme }
-            | TypedExprKind::Float { lexeme }
-            | TypedExprKind::Identifier { lexeme } => lexeme.lexeme.clone(),
-            TypedExprKind::Bool { lexeme } => match lexeme.lexeme() {
-                "tama" => "true".to_string(),
-                "mali" => "mali".to_string(),
-                _ => unreachable!(),
-            },
-            TypedExprKind::Binary { left, right, op } => format!(
-                "({} {} {})",
-                self.gen_expr(left),
-                op.op_to_string().unwrap(),
-                self.gen_expr(right)
-            ),
-            TypedExprKind::FnCall { callee, args } => {
-                format!(
-                    "({}({}))",
-                    self.gen_expr(callee),
-                    args.iter()
-                        .map(|tex| self.gen_expr(tex))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            }
-            TypedExprKind::StructLiteral { .. } => todo!(),
-            TypedExprKind::Unary { right, op } => {
-                format!("({}{})", op.op_to_string().unwrap(), self.gen_expr(right))
-            }
+    /// C name of a global or a function from the symbols, as the REPL
+    /// generates code calling functions declared by earlier inputs
+    fn symbol_name(&self, symbol_id: usize) -> String {
+        mangle::symbol_c_name(symbol_id, &self.symbols[symbol_id])
+    }
+
+    fn function_name(&self, function: &Function) -> String {
+        match function.symbol_id {
+            Some(id) => self.symbol_name(id),
+            None => INIT_FN_NAME.to_string(),
         }
     }
 
@@ -236,14 +354,525 @@ impl<'a> Codegen<'a> {
             TolType::Bool => CType::Bool,
             TolType::UnknownIdentifier(s) => CType::Custom(s.to_owned()),
             TolType::Void => CType::Void,
+            TolType::Array {
+                inner,
+                size: Some(_),
+            } => {
+                // The element type is added first, so it is defined first
+                self.as_c(inner);
+                let mut array_types = self.array_types.borrow_mut();
+                if !array_types.contains(ttype) {
+                    array_types.push(ttype.clone());
+                }
+
+                CType::Custom(mangle::array_struct_name(ttype))
+            }
             _ => unreachable!(
                 "{} is unreachable as it is already checked by the analyzer",
                 ttype
             ),
         }
     }
+}
 
-    fn get_symbol(&self, id: usize) -> &Symbol {
-        &self.symbols[id]
+/// What evaluating an expression may do besides computing a value. C doesn't
+/// fix the order sibling operands are evaluated in, so an expression is only
+/// folded into another when its order against the other operands can't be
+/// observed.
+#[derive(Debug, Clone, Copy, Default)]
+struct Effects {
+    /// Calls a `paraan`, which may store to globals
+    calls: bool,
+    /// Calls a `paraan` or a runtime helper, which may print or stop the
+    /// program
+    effects: bool,
+    reads_globals: bool,
+}
+
+impl Effects {
+    fn conflicts(&self, other: &Effects) -> bool {
+        (self.effects && other.effects)
+            || (self.calls && other.reads_globals)
+            || (other.calls && self.reads_globals)
+    }
+
+    fn union(self, other: Effects) -> Effects {
+        Effects {
+            calls: self.calls || other.calls,
+            effects: self.effects || other.effects,
+            reads_globals: self.reads_globals || other.reads_globals,
+        }
+    }
+}
+
+/// The temporaries folded into an expression, with what they are assigned
+type Folded<'f> = HashMap<TempId, &'f Rvalue>;
+
+struct FunctionCodegen<'c, 'a> {
+    codegen: &'c Codegen<'a>,
+    function: &'a Function,
+    names: Vec<String>,
+    /// How many times each temporary is used
+    uses: Vec<usize>,
+    /// Blocks that are jumped to
+    labeled: HashSet<BlockId>,
+    /// Whether a value nothing uses is still generated, even if computing it
+    /// does nothing else
+    keeps_values: bool,
+}
+
+impl<'c, 'a> FunctionCodegen<'c, 'a> {
+    fn new(codegen: &'c Codegen<'a>, function: &'a Function) -> Self {
+        let mut uses = vec![0; function.temps.len()];
+        let mut labeled = HashSet::new();
+        for (id, block) in function.blocks.iter().enumerate() {
+            let operands = block
+                .insts
+                .iter()
+                .flat_map(|inst| inst.operands())
+                .chain(block.terminator.operand());
+            for operand in operands {
+                if let Operand::Temp(temp) = operand {
+                    uses[*temp] += 1;
+                }
+            }
+
+            labeled.extend(Self::gotos(id, &block.terminator));
+        }
+
+        Self {
+            codegen,
+            function,
+            names: codegen.local_names(function),
+            uses,
+            labeled,
+            keeps_values: false,
+        }
     }
+
+    fn keeping_values(mut self) -> Self {
+        self.keeps_values = true;
+
+        self
+    }
+
+    /// The statements of the function, without its signature
+    fn gen_body(&self) -> Vec<CStatement> {
+        let function = self.function;
+        let mut statements = Vec::new();
+        for id in function.params.len()..function.locals.len() {
+            let ttype = self.codegen.as_c(&function.locals[id].ttype);
+            statements.push(DeclBuilder::new(ttype, &self.names[id]).build());
+        }
+
+        // A `#line` numbers the lines after it one by one, so the one of the
+        // statement being generated is repeated before each C statement, and
+        // inside the block of an `if`, to keep every line pointing at it
+        let mut location = None;
+        for id in 0..function.blocks.len() {
+            if self.labeled.contains(&id) {
+                statements.push(CStatement::Label {
+                    name: block_label(id),
+                });
+            }
+            for mut statement in self.gen_block(id) {
+                if let CStatement::Line { line, file } = statement {
+                    location = Some((line, file));
+                    continue;
+                }
+
+                if let Some((line, file)) = &location {
+                    let directive = || CStatement::Line {
+                        line: *line,
+                        file: file.clone(),
+                    };
+                    if let CStatement::If { initial_block, .. } = &mut statement
+                        && let CStatement::Block { statements } = initial_block.as_mut()
+                    {
+                        statements.insert(0, directive());
+                    }
+                    statements.push(directive());
+                }
+                statements.push(statement);
+            }
+        }
+
+        statements
+    }
+
+    /// Generates a block from its end, each instruction that isn't folded
+    /// into a later one becoming a statement
+    fn gen_block(&self, id: BlockId) -> Vec<CStatement> {
+        let block = &self.function.blocks[id];
+        let insts = &block.insts;
+
+        let mut cursor = insts.len();
+        let operands = block.terminator.operand().into_iter().collect();
+        let (folded, effects) = self.fold(insts, &mut cursor, operands);
+        let mut reversed = self.gen_terminator(id, &block.terminator, &folded, effects);
+        reversed.reverse();
+
+        while cursor > 0 {
+            cursor -= 1;
+            let inst = &insts[cursor];
+            let (folded, effects) = self.fold(insts, &mut cursor, inst.operands());
+            let effects = match inst {
+                Inst::Let { value, .. } => effects.union(self.rvalue_effects(value)),
+                _ => effects,
+            };
+
+            if let Some(statement) = self.gen_inst(inst, &folded, effects) {
+                reversed.push(statement);
+            }
+        }
+
+        reversed.reverse();
+        reversed
+    }
+
+    /// Folds the temporaries assigned right before `cursor` into the
+    /// operands, moving `cursor` to the first instruction left to generate
+    fn fold(
+        &self,
+        insts: &'a [Inst],
+        cursor: &mut usize,
+        operands: Vec<&Operand>,
+    ) -> (Folded<'a>, Effects) {
+        let end = *cursor;
+        let effects = self.fold_operands(insts, cursor, operands);
+        let folded = insts[*cursor..end]
+            .iter()
+            .filter_map(|inst| match inst {
+                Inst::Let { dest, value } => Some((*dest, value)),
+                _ => None,
+            })
+            .collect();
+
+        (folded, effects)
+    }
+
+    /// Folds operands from the last, since the instruction right before the
+    /// one using them assigns the last operand evaluated. Stops at the first
+    /// operand that can't be folded, as folding the ones before it would move
+    /// them past the instruction assigning it.
+    fn fold_operands(
+        &self,
+        insts: &'a [Inst],
+        cursor: &mut usize,
+        operands: Vec<&Operand>,
+    ) -> Effects {
+        let mut effects = Effects {
+            reads_globals: operands.iter().any(|op| matches!(op, Operand::Global(_))),
+            ..Effects::default()
+        };
+
+        for operand in operands.into_iter().rev() {
+            let Operand::Temp(temp) = operand else {
+                continue;
+            };
+            let Some(Inst::Let { dest, value }) = cursor.checked_sub(1).map(|i| &insts[i]) else {
+                break;
+            };
+            if dest != temp || self.uses[*temp] != 1 {
+                break;
+            }
+
+            let before = *cursor;
+            *cursor -= 1;
+            let folded = self
+                .fold_operands(insts, cursor, value.operands())
+                .union(self.rvalue_effects(value));
+            if effects.conflicts(&folded) {
+                *cursor = before;
+                break;
+            }
+            effects = effects.union(folded);
+        }
+
+        effects
+    }
+
+    fn rvalue_effects(&self, value: &Rvalue) -> Effects {
+        match value {
+            Rvalue::Use(Operand::Global(_)) => Effects {
+                reads_globals: true,
+                ..Effects::default()
+            },
+            Rvalue::Call { .. } => Effects {
+                calls: true,
+                effects: true,
+                reads_globals: false,
+            },
+            Rvalue::Binary { op, left, right } => Effects {
+                effects: self
+                    .runtime_fn(*op, self.operand_type(left), right)
+                    .is_some(),
+                ..Effects::default()
+            },
+            _ => Effects::default(),
+        }
+    }
+
+    fn gen_inst(&self, inst: &Inst, folded: &Folded, effects: Effects) -> Option<CStatement> {
+        let statement = match inst {
+            Inst::Let { dest, value } => {
+                let ttype = &self.function.temps[*dest];
+                let value = self.gen_rvalue(value, ttype, folded, true);
+                if self.uses[*dest] == 0 {
+                    // Only kept for what computing it does
+                    return (effects.effects || self.keeps_values)
+                        .then_some(CStatement::Expression { expr: value });
+                }
+
+                DeclBuilder::new(self.codegen.as_c(ttype), &temp_name(*dest))
+                    .with_rhs(value)
+                    .build()
+            }
+            Inst::Store { place, value } => CStatement::Expression {
+                expr: CExpr::assign(
+                    self.gen_operand(&(*place).into(), folded, false),
+                    self.gen_operand(value, folded, true),
+                ),
+            },
+            Inst::Call { callee, args } => CStatement::Expression {
+                expr: self.gen_call(*callee, args, folded),
+            },
+            Inst::Location(span) => return self.codegen.line_directive(span.start),
+        };
+
+        Some(statement)
+    }
+
+    fn gen_terminator(
+        &self,
+        id: BlockId,
+        terminator: &Terminator,
+        folded: &Folded,
+        effects: Effects,
+    ) -> Vec<CStatement> {
+        let next = id + 1;
+        let goto = |target: BlockId| CStatement::Goto {
+            label: block_label(target),
+        };
+        let goto_if = |cond: CExpr, target: BlockId| {
+            IfBuilder::new(
+                cond,
+                CStatement::Block {
+                    statements: vec![goto(target)],
+                },
+            )
+            .build()
+        };
+
+        match terminator {
+            Terminator::Jump(target) if *target == next => Vec::new(),
+            Terminator::Jump(target) => vec![goto(*target)],
+            Terminator::Branch {
+                cond,
+                then_block,
+                else_block,
+            } => {
+                let cond = self.gen_operand(cond, folded, true);
+                match (*then_block == next, *else_block == next) {
+                    (true, true) if effects.effects => {
+                        vec![CStatement::Expression { expr: cond }]
+                    }
+                    (true, true) => Vec::new(),
+                    (true, false) => {
+                        vec![goto_if(CExpr::unary(CUnaryOp::Not, cond), *else_block)]
+                    }
+                    (false, true) => vec![goto_if(cond, *then_block)],
+                    (false, false) => vec![goto_if(cond, *then_block), goto(*else_block)],
+                }
+            }
+            // Falling off the end of a `void` function returns
+            Terminator::Return(None) if next == self.function.blocks.len() => Vec::new(),
+            Terminator::Return(value) => {
+                let mut builder = ReturnBuilder::new();
+                if let Some(value) = value {
+                    builder = builder.with_rhs(self.gen_operand(value, folded, true));
+                }
+                vec![builder.build()]
+            }
+            Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    /// Generates an operand, or the expression folded into it. `top` is set
+    /// for the whole value of an assignment, argument or return, where C
+    /// converts it to the right type by itself.
+    fn gen_operand(&self, operand: &Operand, folded: &Folded, top: bool) -> CExpr {
+        match operand {
+            Operand::Const(value, ttype) => c_literal(value, ttype, top),
+            Operand::Temp(temp) => match folded.get(temp) {
+                Some(value) => self.gen_rvalue(value, &self.function.temps[*temp], folded, top),
+                None => CExpr::Ident(temp_name(*temp)),
+            },
+            Operand::Local(id) => CExpr::ident(&self.names[*id]),
+            Operand::Global(id) => CExpr::Ident(self.codegen.symbol_name(*id)),
+        }
+    }
+
+    fn gen_rvalue(&self, value: &Rvalue, ttype: &TolType, folded: &Folded, top: bool) -> CExpr {
+        match value {
+            Rvalue::Use(operand) => self.gen_operand(operand, folded, top),
+            Rvalue::Binary { op, left, right } => {
+                let left_c = self.gen_operand(left, folded, false);
+                let right_c = self.gen_operand(right, folded, false);
+                let operand_type = self.operand_type(left);
+
+                if let Some(runtime_fn) = self.runtime_fn(*op, operand_type, right) {
+                    let name = self.codegen.use_runtime(runtime_fn);
+                    return CExpr::call(&name, vec![left_c, right_c]);
+                }
+                if operand_type.is_float() && matches!(op, BinOp::Pow | BinOp::Rem) {
+                    self.codegen.uses_math.set(true);
+                    let name = if *op == BinOp::Pow { "pow" } else { "fmod" };
+                    let suffix = if *operand_type == TolType::F32 {
+                        "f"
+                    } else {
+                        ""
+                    };
+                    return CExpr::call(&format!("{name}{suffix}"), vec![left_c, right_c]);
+                }
+
+                let c_expr = CExpr::binary(c_binary_op(*op), left_c, right_c);
+                match op {
+                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Shl if !top => {
+                        self.truncate_promoted(c_expr, ttype)
+                    }
+                    _ => c_expr,
+                }
+            }
+            Rvalue::Unary { op, operand } => {
+                let operand_c = self.gen_operand(operand, folded, false);
+                let c_op = match op {
+                    UnOp::Neg => CUnaryOp::Neg,
+                    UnOp::Not => CUnaryOp::Not,
+                    UnOp::BitNot => CUnaryOp::BitNot,
+                };
+                let c_expr = CExpr::unary(c_op, operand_c);
+                match op {
+                    UnOp::Neg | UnOp::BitNot if !top => self.truncate_promoted(c_expr, ttype),
+                    _ => c_expr,
+                }
+            }
+            Rvalue::Cast(operand) if matches!(ttype, TolType::Array { .. }) => {
+                let name = self.codegen.use_runtime(RuntimeFn::CastArray {
+                    from: self.operand_type(operand).clone(),
+                    to: ttype.clone(),
+                });
+                CExpr::call(&name, vec![self.gen_operand(operand, folded, true)])
+            }
+            Rvalue::Cast(operand) => {
+                let operand_c = self.gen_operand(operand, folded, false);
+                if top {
+                    operand_c
+                } else {
+                    CExpr::cast(self.codegen.as_c(ttype), operand_c)
+                }
+            }
+            Rvalue::Call { callee, args } => self.gen_call(*callee, args, folded),
+            Rvalue::Array(elems) => CExpr::CompoundLiteral {
+                ttype: self.codegen.as_c(ttype),
+                elems: vec![CExpr::InitList(
+                    elems
+                        .iter()
+                        .map(|elem| self.gen_operand(elem, folded, true))
+                        .collect(),
+                )],
+            },
+        }
+    }
+
+    fn gen_call(&self, callee: usize, args: &[Operand], folded: &Folded) -> CExpr {
+        CExpr::call(
+            &self.codegen.symbol_name(callee),
+            args.iter()
+                .map(|arg| self.gen_operand(arg, folded, true))
+                .collect(),
+        )
+    }
+
+    /// The helper computing an integer `/`, `%`, `**` or shift. Only zero and
+    /// -1 make division fail, and only amounts outside the width of the type
+    /// make a shift fail, so other constants don't need one.
+    fn runtime_fn(&self, op: BinOp, ttype: &TolType, right: &Operand) -> Option<RuntimeFn> {
+        if !ttype.is_integer() {
+            return None;
+        }
+
+        let safe_divisor = matches!(
+            right,
+            Operand::Const(ConstValue::Int(value), _) if *value != 0 && *value != -1
+        );
+        match op {
+            BinOp::Div if !safe_divisor => Some(RuntimeFn::Div(ttype.clone())),
+            BinOp::Rem if !safe_divisor => Some(RuntimeFn::Rem(ttype.clone())),
+            BinOp::Pow => Some(RuntimeFn::Pow(ttype.clone())),
+            BinOp::Shl if !shift_amount_ok(right, ttype) => Some(RuntimeFn::Shl(ttype.clone())),
+            BinOp::Shr if !shift_amount_ok(right, ttype) => Some(RuntimeFn::Shr(ttype.clone())),
+            _ => None,
+        }
+    }
+
+    /// C promotes integers narrower than `int` before operating on them, so
+    /// the result is cast back to keep the bits of the Tol type only
+    fn truncate_promoted(&self, c_expr: CExpr, ttype: &TolType) -> CExpr {
+        match ttype.bit_width() {
+            Some(width) if width < 32 => CExpr::cast(self.codegen.as_c(ttype), c_expr),
+            _ => c_expr,
+        }
+    }
+
+    fn operand_type<'o>(&'o self, operand: &'o Operand) -> &'o TolType {
+        match operand {
+            // Globals of earlier REPL inputs aren't part of the module
+            Operand::Global(id) => match self.codegen.module.global(*id) {
+                Some(global) => &global.ttype,
+                None => &TolType::Void,
+            },
+            operand => self.function.operand_type(operand, &[]),
+        }
+    }
+
+    /// Blocks the terminator of block `id` jumps to with a `goto`, rather
+    /// than by falling through to the next block
+    fn gotos(id: BlockId, terminator: &Terminator) -> Vec<BlockId> {
+        terminator
+            .successors()
+            .into_iter()
+            .filter(|target| *target != id + 1)
+            .collect()
+    }
+}
+
+fn c_binary_op(op: BinOp) -> CBinaryOp {
+    match op {
+        BinOp::Add => CBinaryOp::Add,
+        BinOp::Sub => CBinaryOp::Sub,
+        BinOp::Mul => CBinaryOp::Mul,
+        BinOp::Div => CBinaryOp::Div,
+        BinOp::Rem => CBinaryOp::Rem,
+        BinOp::Pow => unreachable!("`**` has no C operator"),
+        BinOp::BitAnd => CBinaryOp::BitAnd,
+        BinOp::BitOr => CBinaryOp::BitOr,
+        BinOp::BitXor => CBinaryOp::BitXor,
+        BinOp::Shl => CBinaryOp::Shl,
+        BinOp::Shr => CBinaryOp::Shr,
+        BinOp::Eq => CBinaryOp::Eq,
+        BinOp::Ne => CBinaryOp::Ne,
+        BinOp::Lt => CBinaryOp::Lt,
+        BinOp::Le => CBinaryOp::Le,
+        BinOp::Gt => CBinaryOp::Gt,
+        BinOp::Ge => CBinaryOp::Ge,
+    }
+}
+
+fn temp_name(temp: TempId) -> String {
+    format!("t{temp}")
+}
+
+fn block_label(block: BlockId) -> String {
+    format!("bb{block}")
 }
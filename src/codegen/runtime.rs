@@ -0,0 +1,313 @@
+use gen_c::{
+    builder::{
+        decl_builder::DeclBuilder, for_builder::ForBuilder, function_builder::FunctionBuilder,
+        if_builder::IfBuilder, return_builder::ReturnBuilder, while_builder::WhileBuilder,
+    },
+    ctype::CType,
+    product::{
+        expr::{CAssignOp, CBinaryOp, CExpr},
+        statement::CStatement,
+    },
+};
+
+use crate::{codegen::mangle, toltype::TolType};
+
+/// Functions emitted into the generated C for operations whose C behavior
+/// differs from Tol's. Integer division, remainder, exponentiation and shifts
+/// stop the program instead of hitting undefined behavior. Arrays, which C
+/// can't convert, are converted one element at a time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeFn {
+    Panic,
+    Div(TolType),
+    Rem(TolType),
+    Pow(TolType),
+    Shl(TolType),
+    Shr(TolType),
+    /// Converts an array to one of the same size with another element type
+    CastArray {
+        from: TolType,
+        to: TolType,
+    },
+}
+
+impl RuntimeFn {
+    pub const PANIC_NAME: &str = "tol_panic";
+
+    pub fn name(&self) -> String {
+        match self {
+            RuntimeFn::Panic => Self::PANIC_NAME.to_string(),
+            RuntimeFn::Div(ttype) => format!("tol_div_{ttype}"),
+            RuntimeFn::Rem(ttype) => format!("tol_rem_{ttype}"),
+            RuntimeFn::Pow(ttype) => format!("tol_pow_{ttype}"),
+            RuntimeFn::Shl(ttype) => format!("tol_shl_{ttype}"),
+            RuntimeFn::Shr(ttype) => format!("tol_shr_{ttype}"),
+            RuntimeFn::CastArray { from, to } => format!(
+                "tol_cast_{}_{}",
+                mangle::type_name(from),
+                mangle::type_name(to)
+            ),
+        }
+    }
+
+    /// Whether the function may stop the program with `tol_panic`
+    pub fn panics(&self) -> bool {
+        !matches!(self, RuntimeFn::Panic | RuntimeFn::CastArray { .. })
+    }
+
+    /// The conversion of the elements of an array conversion, when they are
+    /// arrays too
+    pub fn inner_cast(&self) -> Option<RuntimeFn> {
+        let RuntimeFn::CastArray {
+            from: TolType::Array { inner: from, .. },
+            to: TolType::Array { inner: to, .. },
+        } = self
+        else {
+            return None;
+        };
+
+        matches!(**to, TolType::Array { .. }).then(|| RuntimeFn::CastArray {
+            from: (**from).clone(),
+            to: (**to).clone(),
+        })
+    }
+
+    /// Builds the definition of the function, `c_type` being the C type of
+    /// the integer it works on
+    pub fn build(&self, c_type: impl Fn(&TolType) -> CType) -> CStatement {
+        match self {
+            RuntimeFn::Panic => FunctionBuilder::new(CType::Void, &self.name())
+                .add_param(
+                    CType::Pointer(Box::new(CType::Custom("const char".to_string()))),
+                    "msg",
+                )
+                .add_statement(CStatement::Expression {
+                    expr: CExpr::call(
+                        "fprintf",
+                        vec![
+                            CExpr::ident("stderr"),
+                            CExpr::Str("tol: %s\n".to_string()),
+                            CExpr::ident("msg"),
+                        ],
+                    ),
+                })
+                .add_statement(CStatement::Expression {
+                    expr: CExpr::call("abort", Vec::new()),
+                })
+                .build(),
+            RuntimeFn::Div(ttype) | RuntimeFn::Rem(ttype) => {
+                let op = if matches!(self, RuntimeFn::Div(_)) {
+                    CBinaryOp::Div
+                } else {
+                    CBinaryOp::Rem
+                };
+                let (a, b) = (CExpr::ident("a"), CExpr::ident("b"));
+                let mut builder = FunctionBuilder::new(c_type(ttype), &self.name())
+                    .add_param(c_type(ttype), "a")
+                    .add_param(c_type(ttype), "b")
+                    .add_statement(Self::panic_if(
+                        CExpr::binary(CBinaryOp::Eq, b.clone(), CExpr::literal(0)),
+                        "paghahati sa zero",
+                    ));
+
+                // The quotient of the smallest value and -1 doesn't fit
+                if let Some(min) = Self::signed_min(ttype) {
+                    let cond = CExpr::binary(
+                        CBinaryOp::And,
+                        CExpr::binary(CBinaryOp::Eq, a.clone(), CExpr::ident(min)),
+                        CExpr::binary(CBinaryOp::Eq, b.clone(), CExpr::literal(-1)),
+                    );
+                    builder = builder
+                        .add_statement(Self::panic_if(cond, "lumampas ang resulta ng paghahati"));
+                }
+
+                builder
+                    .add_statement(
+                        ReturnBuilder::new()
+                            .with_rhs(CExpr::binary(op, a, b))
+                            .build(),
+                    )
+                    .build()
+            }
+            RuntimeFn::Shl(ttype) | RuntimeFn::Shr(ttype) => self.build_shift(ttype, c_type),
+            RuntimeFn::CastArray { from, to } => self.build_cast_array(from, to, c_type),
+            RuntimeFn::Pow(ttype) => {
+                // Multiplying as unsigned wraps on overflow like `-fwrapv` does
+                let wrapping = Self::wrapping_type_of(ttype);
+                let (exp, b, result) = (
+                    CExpr::ident("exp"),
+                    CExpr::ident("b"),
+                    CExpr::ident("result"),
+                );
+                let mut builder = FunctionBuilder::new(c_type(ttype), &self.name())
+                    .add_param(c_type(ttype), "base")
+                    .add_param(c_type(ttype), "exp");
+                if Self::signed_min(ttype).is_some() {
+                    let cond = CExpr::binary(CBinaryOp::Lt, exp.clone(), CExpr::literal(0));
+                    builder = builder
+                        .add_statement(Self::panic_if(cond, "negatibong exponent sa integer"));
+                }
+
+                let assign_op = |op, place: &CExpr, value| CStatement::Expression {
+                    expr: CExpr::binary(CBinaryOp::AssignOp(op), place.clone(), value),
+                };
+                let loop_body = CStatement::Block {
+                    statements: vec![
+                        IfBuilder::new(
+                            CExpr::binary(CBinaryOp::BitAnd, exp.clone(), CExpr::literal(1)),
+                            CStatement::Block {
+                                statements: vec![assign_op(CAssignOp::Mul, &result, b.clone())],
+                            },
+                        )
+                        .build(),
+                        assign_op(CAssignOp::Mul, &b, b.clone()),
+                        assign_op(CAssignOp::Shr, &exp, CExpr::literal(1)),
+                    ],
+                };
+
+                builder
+                    .add_statement(
+                        DeclBuilder::new(c_type(&wrapping), "result")
+                            .with_rhs(CExpr::literal(1))
+                            .build(),
+                    )
+                    .add_statement(
+                        DeclBuilder::new(c_type(&wrapping), "b")
+                            .with_rhs(CExpr::cast(c_type(&wrapping), CExpr::ident("base")))
+                            .build(),
+                    )
+                    .add_statement(
+                        WhileBuilder::new(
+                            CExpr::binary(CBinaryOp::Gt, exp, CExpr::literal(0)),
+                            loop_body,
+                        )
+                        .build(),
+                    )
+                    .add_statement(
+                        ReturnBuilder::new()
+                            .with_rhs(CExpr::cast(c_type(ttype), result))
+                            .build(),
+                    )
+                    .build()
+            }
+        }
+    }
+
+    fn build_shift(&self, ttype: &TolType, c_type: impl Fn(&TolType) -> CType) -> CStatement {
+        let (a, b) = (CExpr::ident("a"), CExpr::ident("b"));
+        let width = ttype.bit_width().unwrap_or(64);
+        let out_of_range = CExpr::binary(
+            CBinaryOp::Or,
+            CExpr::binary(CBinaryOp::Lt, b.clone(), CExpr::literal(0)),
+            CExpr::binary(CBinaryOp::Ge, b.clone(), CExpr::literal(width)),
+        );
+        let shifted = match self {
+            // Shifting as unsigned drops the bits shifted out of a negative value
+            // instead of being undefined
+            RuntimeFn::Shl(_) => CExpr::cast(
+                c_type(ttype),
+                CExpr::binary(
+                    CBinaryOp::Shl,
+                    CExpr::cast(c_type(&Self::wrapping_type_of(ttype)), a),
+                    b,
+                ),
+            ),
+            _ => CExpr::binary(CBinaryOp::Shr, a, b),
+        };
+
+        // The amount may have any integer type. A `uint64_t` too large for
+        // `int64_t` turns negative, which is out of range all the same.
+        FunctionBuilder::new(c_type(ttype), &self.name())
+            .add_param(c_type(ttype), "a")
+            .add_param(c_type(&TolType::I64), "b")
+            .add_statement(Self::panic_if(out_of_range, "labis ang dami ng shift"))
+            .add_statement(ReturnBuilder::new().with_rhs(shifted).build())
+            .build()
+    }
+
+    fn build_cast_array(
+        &self,
+        from: &TolType,
+        to: &TolType,
+        c_type: impl Fn(&TolType) -> CType,
+    ) -> CStatement {
+        let TolType::Array {
+            size: Some(size), ..
+        } = to
+        else {
+            unreachable!("Conversion to `{to}`")
+        };
+        let (i, result) = (CExpr::ident("i"), CExpr::ident("result"));
+        let item = |array: CExpr| CExpr::index(CExpr::member(array, "items"), i.clone());
+
+        // C converts other elements by itself when assigning them
+        let elem = item(CExpr::ident("a"));
+        let elem = match self.inner_cast() {
+            Some(inner) => CExpr::call(&inner.name(), vec![elem]),
+            None => elem,
+        };
+        let loop_body = CStatement::Block {
+            statements: vec![CStatement::Expression {
+                expr: CExpr::assign(item(result.clone()), elem),
+            }],
+        };
+
+        FunctionBuilder::new(c_type(to), &self.name())
+            .add_param(c_type(from), "a")
+            .add_statement(DeclBuilder::new(c_type(to), "result").build())
+            .add_statement(
+                ForBuilder::new(loop_body)
+                    .with_init(
+                        DeclBuilder::new(CType::Size, "i")
+                            .with_rhs(CExpr::literal(0))
+                            .build(),
+                    )
+                    .with_cond(CExpr::binary(
+                        CBinaryOp::Lt,
+                        i.clone(),
+                        CExpr::literal(size),
+                    ))
+                    .with_step(CExpr::binary(
+                        CBinaryOp::AssignOp(CAssignOp::Add),
+                        i,
+                        CExpr::literal(1),
+                    ))
+                    .build(),
+            )
+            .add_statement(ReturnBuilder::new().with_rhs(result).build())
+            .build()
+    }
+
+    fn panic_if(cond: CExpr, msg: &str) -> CStatement {
+        IfBuilder::new(
+            cond,
+            CStatement::Block {
+                statements: vec![CStatement::Expression {
+                    expr: CExpr::call(Self::PANIC_NAME, vec![CExpr::Str(msg.to_string())]),
+                }],
+            },
+        )
+        .build()
+    }
+
+    fn signed_min(ttype: &TolType) -> Option<&'static str> {
+        match ttype {
+            TolType::I8 => Some("INT8_MIN"),
+            TolType::I16 => Some("INT16_MIN"),
+            TolType::I32 => Some("INT32_MIN"),
+            TolType::I64 => Some("INT64_MIN"),
+            TolType::ISize => Some("PTRDIFF_MIN"),
+            _ => None,
+        }
+    }
+
+    /// Unsigned type at least as wide as `int`, so multiplication never
+    /// promotes to a signed type that could overflow
+    fn wrapping_type_of(ttype: &TolType) -> TolType {
+        match ttype {
+            TolType::I64 | TolType::U64 => TolType::U64,
+            TolType::ISize | TolType::USize => TolType::USize,
+            _ => TolType::U32,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+/// Finds the line and column of offsets in a source file, for pointing the
+/// generated C back at it
+pub struct SourceMap<'a> {
+    /// The file as the C compiler and debuggers should look it up
+    file: String,
+    source: &'a str,
+    /// Offset each line starts at
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(file: &str, source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            file: file.to_string(),
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// The line of an offset, counted from 1
+    pub fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= offset)
+    }
+
+    /// The line and column of an offset, both counted from 1
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line(offset);
+        let line_start = self.line_starts[line - 1];
+
+        (line, self.source[line_start..offset].chars().count() + 1)
+    }
+}
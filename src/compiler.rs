@@ -1,55 +1,154 @@
 use gen_c::CCodeGen;
 
 use crate::{
-    analyzer::{SemanticAnalyzer, symbol::Symbol},
-    codegen::Codegen,
-    driver::CompilerOptions,
-    error::CompilerError,
+    analyzer::{
+        ENTRY_POINT, SemanticAnalyzer,
+        symbol::{Symbol, SymbolKind},
+    },
+    ast::{NodeId, TypedAst},
+    bytecode::{Program, emit::Emitter, format, vm::Vm},
+    codegen::{Codegen, source_map::SourceMap},
+    driver::{Action, CompilerOptions, Stage},
+    error::{CompilerError, CompilerWarning, WarningKind},
+    interpreter::{self, Interpreter},
+    ir::{self, Module},
     lexer::Lexer,
-    module::module_registry::ModuleRegistry,
+    lint::{LintLevel, LintLevels, LintSuppression},
     parser::Parser,
 };
-use std::path::Path;
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    process, thread,
+};
+
+/// Exit code of a program stopped by a runtime error. Programs killed by a
+/// signal, like the `abort()` of the C runtime helpers, exit with it too.
+pub const RUNTIME_ERROR_EXIT_CODE: i32 = 101;
 
 #[derive(Default)]
 pub struct CompilerCtx {
     pub continue_compiling: bool,
+    /// Print what each stage produced, for debugging the compiler itself
+    pub dev_debug: bool,
     pub errors: Vec<CompilerError>,
+    pub warnings: Vec<CompilerWarning>,
     pub symbol_table: Vec<Symbol>,
+    /// Nodes of the typed AST made so far, the next one getting this as its id
+    pub node_count: usize,
+    pub lint_levels: LintLevels,
+    pub lint_suppressions: Vec<LintSuppression>,
+    /// What the program returned, when it was run
+    pub exit_code: Option<i32>,
 }
 
 impl CompilerCtx {
     pub fn new() -> Self {
         Self {
             continue_compiling: true,
+            dev_debug: false,
             errors: Vec::new(),
+            warnings: Vec::new(),
             symbol_table: Vec::new(),
+            node_count: 0,
+            lint_levels: LintLevels::default(),
+            lint_suppressions: Vec::new(),
+            exit_code: None,
         }
     }
 
+    pub fn new_node_id(&mut self) -> NodeId {
+        self.node_count += 1;
+        self.node_count - 1
+    }
+
     pub fn add_error(&mut self, error: CompilerError) {
         self.errors.push(error);
     }
 
+    /// Reports a warning according to the level of its lint, unless a
+    /// `-- tol:allow(...)` comment silences it
+    pub fn add_warning(&mut self, kind: WarningKind) {
+        let mut warning = CompilerWarning::new(kind);
+        let offset = warning.kind.offset();
+        let suppressed = self
+            .lint_suppressions
+            .iter()
+            .any(|sup| sup.lint == warning.lint && sup.span.contains(&offset));
+        if suppressed {
+            return;
+        }
+
+        match self.lint_levels.level(warning.lint) {
+            LintLevel::Allow => {}
+            LintLevel::Warn => self.warnings.push(warning),
+            LintLevel::Deny => {
+                warning.denied = true;
+                self.errors.push(CompilerError::DeniedLint(warning));
+            }
+        }
+    }
+
     pub fn extend_errors(&mut self, iter: impl IntoIterator<Item = CompilerError>) {
         self.errors.extend(iter);
     }
 }
 
-pub struct Compiler<'com> {
+/// Lexes, parses and analyzes a program without going any further, returning
+/// what was reported about it
+pub fn check(source_code: &str, source_file_name: &str) -> CompilerCtx {
+    let mut ctx = CompilerCtx::new();
+    analyze(&mut ctx, source_code, source_file_name);
+
+    ctx
+}
+
+/// Runs the stages every action starts with, printing what each produced
+/// with `-D`. `None` if the source couldn't be lexed.
+fn analyze(ctx: &mut CompilerCtx, source_code: &str, source_file_name: &str) -> Option<TypedAst> {
+    let lexer = Lexer::new(source_code, source_file_name);
+    let tokens = lexer.lex(ctx);
+
+    if ctx.dev_debug {
+        for tok in tokens.iter() {
+            println!("{} <=> {:?}", tok.lexeme(), tok.kind());
+        }
+    }
+
+    if !ctx.continue_compiling {
+        return None;
+    }
+
+    let parser = Parser::new(&tokens);
+    let ast = parser.parse(ctx);
+
+    if ctx.dev_debug {
+        for stmt in ast.top_level.iter().map(|id| &ast.stmts[*id]) {
+            println!("{:#?}", stmt);
+        }
+    }
+
+    let analyzer = SemanticAnalyzer::new(ctx);
+    let typed_ast = analyzer.analyze(ast);
+    if ctx.dev_debug {
+        println!("{:#?}", typed_ast);
+        println!("{:#?}", ctx.symbol_table);
+    }
+
+    Some(typed_ast)
+}
+
+pub struct Compiler {
     opts: CompilerOptions,
-    module_registry: ModuleRegistry<'com>,
 }
 
-impl<'com> Compiler<'com> {
+impl Compiler {
     pub fn new(opts: CompilerOptions) -> Self {
-        Self {
-            opts,
-            module_registry: ModuleRegistry::new(),
-        }
+        Self { opts }
     }
 
-    pub fn run(&mut self, source_code: &'com str) -> CompilerCtx {
+    pub fn run(&mut self, source_code: &str) -> CompilerCtx {
         // WARN: Have better handling for this
         let source_file_name = self
             .opts
@@ -60,34 +159,250 @@ impl<'com> Compiler<'com> {
             .unwrap();
 
         let mut ctx = CompilerCtx::new();
+        ctx.lint_levels = self.opts.lint_levels();
+        ctx.dev_debug = self.opts.dev_debug();
+        let action = self.opts.action();
 
-        let lexer = Lexer::new(source_code, source_file_name);
-        let tokens = lexer.lex(&mut ctx);
+        let Some(typed_ast) = analyze(&mut ctx, source_code, source_file_name) else {
+            return ctx;
+        };
 
-        for tok in tokens.iter() {
-            println!("{} <=> {:?}", tok.lexeme(), tok.kind());
+        if !matches!(action, Action::Emit(_)) {
+            let has_entry_point = ctx.symbol_table.iter().any(|sym| {
+                sym.name() == ENTRY_POINT && matches!(sym.kind(), SymbolKind::Func { .. })
+            });
+            if !has_entry_point {
+                ctx.add_error(CompilerError::MissingEntryPoint {
+                    name: ENTRY_POINT.to_string(),
+                });
+            }
         }
 
-        if !ctx.continue_compiling {
+        if !ctx.errors.is_empty() {
             return ctx;
         }
 
-        let parser = Parser::new(&tokens);
-        let ast = parser.parse(&mut ctx);
+        match action {
+            Action::Emit(Stage::C) => {
+                let Some(module) = self.lower(&typed_ast, &mut ctx) else {
+                    return ctx;
+                };
+                let codegen = self.codegen(&module, &ctx.symbol_table, source_code);
+                let c_source = codegen.generate_c(CCodeGen::new());
+                if let Err(e) = self.emit(format!("{c_source}\n").into_bytes()) {
+                    ctx.add_error(e);
+                }
+            }
+            Action::Emit(Stage::Names) => {
+                let Some(module) = self.lower(&typed_ast, &mut ctx) else {
+                    return ctx;
+                };
+                let codegen = Codegen::new(&module, &ctx.symbol_table);
+                let source_map = SourceMap::new(source_file_name, source_code);
+                let mut names = String::new();
+                for mapping in codegen.name_map() {
+                    let (line, column) = source_map.line_column(mapping.span.start);
+                    if let Some(function) = &mapping.function {
+                        names.push_str(&format!("{function}::"));
+                    }
+                    names.push_str(&format!(
+                        "{} <- `{}` {source_file_name}:{line}:{column}\n",
+                        mapping.c_name, mapping.tol_name
+                    ));
+                }
+                if let Err(e) = self.emit(names.into_bytes()) {
+                    ctx.add_error(e);
+                }
+            }
+            Action::Emit(Stage::Ir) => {
+                let Some(module) = self.lower(&typed_ast, &mut ctx) else {
+                    return ctx;
+                };
+                if let Err(e) = self.emit(module.to_string().into_bytes()) {
+                    ctx.add_error(e);
+                }
+            }
+            Action::Emit(Stage::Bytecode) => {
+                let result = Emitter::new(&ctx.symbol_table)
+                    .emit(&typed_ast)
+                    .and_then(|program| match self.opts.output_path() {
+                        Some(_) => self.emit(format::encode(&program)),
+                        None => self.emit(program.to_string().into_bytes()),
+                    });
+                if let Err(e) = result {
+                    ctx.add_error(e);
+                }
+            }
+            Action::RunVm => match Emitter::new(&ctx.symbol_table).emit(&typed_ast) {
+                Ok(program) => ctx.exit_code = Some(Self::run_bytecode(&program)),
+                Err(e) => ctx.add_error(e),
+            },
+            Action::RunNative => {
+                let Some(module) = self.lower(&typed_ast, &mut ctx) else {
+                    return ctx;
+                };
+                let codegen = self.codegen(&module, &ctx.symbol_table, source_code);
+                let c_source = codegen.generate_c(CCodeGen::new());
+                let debug_name = self.opts.debug_info().then(|| {
+                    self.opts
+                        .source_path()
+                        .file_stem()
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                });
+                match Self::run_native(&c_source, debug_name) {
+                    Ok(code) => ctx.exit_code = Some(code),
+                    Err(e) => ctx.add_error(e),
+                }
+            }
+            Action::Interpret => {
+                // Deep recursion in the program recurses in the interpreter too
+                let result = thread::scope(|scope| {
+                    thread::Builder::new()
+                        .stack_size(interpreter::STACK_SIZE)
+                        .spawn_scoped(scope, || {
+                            Interpreter::new(&ctx.symbol_table).run(&typed_ast)
+                        })
+                        .expect("failed to spawn the interpreter thread")
+                        .join()
+                        .unwrap()
+                });
 
-        for stmt in ast.iter() {
-            println!("{:#?}", stmt);
+                ctx.exit_code = Some(match result {
+                    Ok(code) => code,
+                    Err(e) => {
+                        eprintln!("tol: {e}");
+                        RUNTIME_ERROR_EXIT_CODE
+                    }
+                });
+            }
         }
 
-        let analyzer = SemanticAnalyzer::new(&mut ctx);
-        let typed_ast = analyzer.analyze(ast);
-        println!("{:#?}", typed_ast);
-        println!("{:#?}", ctx.symbol_table);
+        ctx
+    }
 
-        let codegen = Codegen::new(&typed_ast, &ctx.symbol_table);
-        println!("{}", codegen.generate_c(CCodeGen::new()));
+    /// Lowers an analyzed program to the IR and runs the selected
+    /// optimization passes, checking the module after each. What C can't
+    /// hold yet is reported to `ctx` instead.
+    fn lower(&self, typed_ast: &TypedAst, ctx: &mut CompilerCtx) -> Option<Module> {
+        let lowered = if self.opts.debug_info() {
+            ir::lower::lower_with_locations(typed_ast, &ctx.symbol_table)
+        } else {
+            ir::lower::lower(typed_ast, &ctx.symbol_table)
+        };
+        let mut module = match lowered {
+            Ok(module) => module,
+            Err(errors) => {
+                for e in errors {
+                    ctx.add_error(e);
+                }
+                return None;
+            }
+        };
+        if let Err(e) = ir::verify::verify(&module) {
+            panic!("internal error: {e}\n{module}");
+        }
 
-        ctx
+        for pass in self.opts.passes() {
+            pass.run(&mut module);
+            if let Err(e) = ir::verify::verify(&module) {
+                panic!("internal error after `{}`: {e}\n{module}", pass.name());
+            }
+        }
+
+        Some(module)
+    }
+
+    /// A code generator for the module, pointing the C back at the source
+    /// file with `#line` directives when asked for debug info
+    fn codegen<'m>(
+        &self,
+        module: &'m Module,
+        symbols: &'m [Symbol],
+        source_code: &'m str,
+    ) -> Codegen<'m> {
+        let codegen = Codegen::new(module, symbols);
+        if !self.opts.debug_info() {
+            return codegen;
+        }
+
+        let file = self.opts.source_path().display().to_string();
+        codegen.with_source_map(SourceMap::new(&file, source_code))
+    }
+
+    /// Writes the output of `tol emit` to the `-o` file, or to stdout
+    fn emit(&self, output: Vec<u8>) -> Result<(), CompilerError> {
+        match self.opts.output_path() {
+            Some(path) => fs::write(path, output)?,
+            None => io::stdout().write_all(&output)?,
+        }
+
+        Ok(())
+    }
+
+    /// Runs a program on the bytecode VM, returning its exit code
+    pub fn run_bytecode(program: &Program) -> i32 {
+        match Vm::new(program).run() {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("tol: {e}");
+                RUNTIME_ERROR_EXIT_CODE
+            }
+        }
+    }
+
+    /// Compiles the generated C with `$CC` (or `cc`) and runs the executable.
+    /// With a `debug_name`, it is compiled with debug info and the C and the
+    /// executable are kept under that name in `tol-debug` in the temporary
+    /// directory for a debugger to load.
+    fn run_native(c_source: &str, debug_name: Option<&str>) -> Result<i32, CompilerError> {
+        let debug_info = debug_name.is_some();
+        let (dir, name) = match debug_name {
+            Some(name) => (env::temp_dir().join("tol-debug"), name),
+            None => (
+                env::temp_dir().join(format!("tol-{}", process::id())),
+                "main",
+            ),
+        };
+        fs::create_dir_all(&dir)?;
+        let c_path = dir.join(format!("{name}.c"));
+        let exe_path = dir.join(name);
+        fs::write(&c_path, c_source)?;
+
+        let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let debug_flag = if debug_info { " -g" } else { "" };
+        let mut command = process::Command::new(&cc);
+        command.arg("-fwrapv");
+        if debug_info {
+            command.arg("-g");
+        }
+        command.arg("-o").arg(&exe_path).arg(&c_path).arg("-lm");
+        let command_line = format!(
+            "{cc} -fwrapv{debug_flag} -o {} {} -lm",
+            exe_path.display(),
+            c_path.display()
+        );
+
+        let result = match command.status() {
+            Ok(status) if status.success() => {
+                if debug_info {
+                    eprintln!("tol: nasa {} ang programa", exe_path.display());
+                }
+                process::Command::new(&exe_path)
+                    .status()
+                    .map(|status| status.code().unwrap_or(RUNTIME_ERROR_EXIT_CODE))
+                    .map_err(CompilerError::from)
+            }
+            Ok(_) | Err(_) => Err(CompilerError::CCompilerFailed {
+                command: command_line,
+            }),
+        };
+
+        if !debug_info {
+            let _ = fs::remove_dir_all(&dir);
+        }
+        result
     }
 
     pub fn load_stdlib(&mut self, _stdlib_path: &Path) {
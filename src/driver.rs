@@ -1,15 +1,30 @@
-use crate::{ABOUT, AUTHOR, VERSION, compiler::Compiler, error::CompilerError};
-use clap::Parser;
-use miette::NamedSource;
+use crate::{
+    ABOUT, AUTHOR, VERSION,
+    analyzer::ENTRY_POINT,
+    bytecode,
+    compiler::Compiler,
+    error::{CompilerError, CompilerWarning},
+    interpreter,
+    ir::opt::{self, Pass, PassToggle},
+    lint::{LintLevels, LintSelector},
+    repl::Repl,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use miette::{NamedSource, Report};
 use std::{
     fs,
     path::{Path, PathBuf},
     sync::Arc,
+    thread,
 };
 
-pub struct ErrorsWithOptSource {
+pub struct Diagnostics {
     pub source_code: Option<NamedSource<Arc<str>>>,
     pub errors: Vec<CompilerError>,
+    pub warnings: Vec<CompilerWarning>,
+    /// What the program returned, when it was run
+    pub exit_code: Option<i32>,
 }
 
 #[derive(Debug, Parser)]
@@ -17,13 +32,109 @@ pub struct ErrorsWithOptSource {
     author = AUTHOR,
     version = VERSION,
     about = ABOUT,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
 )]
 pub struct CompilerOptions {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Compile the file and print the generated C
     #[arg(required = true, value_name = "SOURCE_FILE")]
-    source_path: PathBuf,
+    source_path: Option<PathBuf>,
 
-    #[arg(short = 'D', long = "dev-debug", default_value_t = false)]
+    /// Print what every stage of the compiler produced
+    #[arg(
+        short = 'D',
+        long = "dev-debug",
+        default_value_t = false,
+        global = true
+    )]
     dev_debug: bool,
+
+    /// Silence a lint (`warnings` selects every lint)
+    #[arg(long, value_name = "LINT", value_parser = LintSelector::parse, global = true)]
+    allow: Vec<LintSelector>,
+
+    /// Report a lint as a warning (`warnings` selects every lint)
+    #[arg(long, value_name = "LINT", value_parser = LintSelector::parse, global = true)]
+    warn: Vec<LintSelector>,
+
+    /// Report a lint as an error (`warnings` selects every lint)
+    #[arg(long, value_name = "LINT", value_parser = LintSelector::parse, global = true)]
+    deny: Vec<LintSelector>,
+
+    /// Optimize the IR before generating C
+    #[arg(short = 'O', long = "optimize", default_value_t = false, global = true)]
+    optimize: bool,
+
+    /// Point the generated C back at the Tol source with `#line` directives,
+    /// and compile it with `-g` when running it, keeping the executable for a
+    /// debugger
+    #[arg(
+        short = 'g',
+        long = "debug-info",
+        default_value_t = false,
+        global = true
+    )]
+    debug_info: bool,
+
+    /// Turn an optimization pass on with `pass=NAME`, or off with `pass=-NAME`
+    #[arg(short = 'Z', value_name = "OPTION", value_parser = PassToggle::parse, global = true)]
+    passes: Vec<PassToggle>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the program, exiting with what `main` returns. A `.tolc` file is
+    /// run on the bytecode VM.
+    Run {
+        #[arg(value_name = "SOURCE_FILE")]
+        source_path: PathBuf,
+
+        /// Evaluate the program directly instead of compiling it with a C compiler
+        #[arg(long, default_value_t = false, conflicts_with = "vm")]
+        interp: bool,
+
+        /// Compile the program to bytecode and run it on the VM
+        #[arg(long, default_value_t = false)]
+        vm: bool,
+    },
+
+    /// Print what a stage of the compiler produced
+    Emit {
+        #[arg(value_name = "SOURCE_FILE")]
+        source_path: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = Stage::C)]
+        stage: Stage,
+
+        /// Write to a file instead, the binary `.tolc` format for bytecode
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Run code as it is typed
+    Repl,
+}
+
+/// Output of the compiler that `tol emit` can print
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Stage {
+    C,
+    Ir,
+    Bytecode,
+    /// Where each name in the generated C comes from
+    Names,
+}
+
+/// What to do with the program once it is analyzed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Emit(Stage),
+    RunNative,
+    Interpret,
+    RunVm,
 }
 
 impl CompilerOptions {
@@ -32,15 +143,76 @@ impl CompilerOptions {
     }
 
     pub fn source_path(&self) -> &Path {
-        &self.source_path
+        match &self.command {
+            Some(Command::Run { source_path, .. } | Command::Emit { source_path, .. }) => {
+                source_path
+            }
+            Some(Command::Repl) => unreachable!("The REPL doesn't read a source file"),
+            None => self.source_path.as_ref().unwrap(),
+        }
+    }
+
+    pub fn is_repl(&self) -> bool {
+        matches!(self.command, Some(Command::Repl))
+    }
+
+    /// Where `tol emit -o` writes to
+    pub fn output_path(&self) -> Option<&Path> {
+        match &self.command {
+            Some(Command::Emit { output, .. }) => output.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn action(&self) -> Action {
+        match &self.command {
+            Some(Command::Run { interp: true, .. }) => Action::Interpret,
+            Some(Command::Run { vm: true, .. }) => Action::RunVm,
+            Some(Command::Run { .. }) => Action::RunNative,
+            Some(Command::Emit { stage, .. }) => Action::Emit(*stage),
+            Some(Command::Repl) | None => Action::Emit(Stage::C),
+        }
+    }
+
+    /// Whether the source file is bytecode written by `tol emit -o`
+    fn is_bytecode_file(&self) -> bool {
+        matches!(self.command, Some(Command::Run { .. }))
+            && self
+                .source_path()
+                .extension()
+                .is_some_and(|ext| ext == bytecode::FILE_EXTENSION)
+    }
+
+    pub fn lint_levels(&self) -> LintLevels {
+        LintLevels::from_selectors(&self.allow, &self.warn, &self.deny)
+    }
+
+    pub fn debug_info(&self) -> bool {
+        self.debug_info
+    }
+
+    /// The optimization passes to run over the IR, in order
+    pub fn passes(&self) -> Vec<Pass> {
+        opt::select_passes(self.optimize, &self.passes)
     }
 }
 
-pub fn compile(opts: CompilerOptions) -> Result<(), ErrorsWithOptSource> {
-    let source_code = fs::read_to_string(opts.source_path()).map_err(|e| ErrorsWithOptSource {
-        source_code: None,
-        errors: vec![e.into()],
-    })?;
+pub fn compile(opts: CompilerOptions) -> Diagnostics {
+    if opts.is_bytecode_file() {
+        return run_bytecode_file(opts.source_path());
+    }
+
+    let source_code = match fs::read_to_string(opts.source_path()) {
+        Ok(src) => src,
+        Err(e) => {
+            return Diagnostics {
+                source_code: None,
+                errors: vec![e.into()],
+                warnings: Vec::new(),
+                exit_code: None,
+            };
+        }
+    };
     let file_name = opts
         .source_path()
         .file_name()
@@ -51,18 +223,59 @@ pub fn compile(opts: CompilerOptions) -> Result<(), ErrorsWithOptSource> {
 
     let mut compiler = Compiler::new(opts);
     let compiler_ctx = compiler.run(&source_code);
-    let errors = compiler_ctx.errors;
-    if errors.is_empty() {
-        Ok(())
-    } else {
-        Err(ErrorsWithOptSource {
-            source_code: Some(NamedSource::new(file_name, Arc::from(source_code))),
-            errors,
-        })
+
+    Diagnostics {
+        source_code: Some(NamedSource::new(file_name, Arc::from(source_code))),
+        errors: compiler_ctx.errors,
+        warnings: compiler_ctx.warnings,
+        exit_code: compiler_ctx.exit_code,
     }
 }
 
-// NOTE: stdlib is searched in the current directory for now
-fn resolve_stdlib_path() -> PathBuf {
-    todo!()
+fn run_bytecode_file(path: &Path) -> Diagnostics {
+    let mut diagnostics = Diagnostics {
+        source_code: None,
+        errors: Vec::new(),
+        warnings: Vec::new(),
+        exit_code: None,
+    };
+
+    let program = fs::read(path)
+        .map_err(CompilerError::from)
+        .and_then(|bytes| bytecode::format::decode(&bytes));
+    match program {
+        Ok(program) if program.entry.is_none() => {
+            diagnostics.errors.push(CompilerError::MissingEntryPoint {
+                name: ENTRY_POINT.to_string(),
+            });
+        }
+        Ok(program) => diagnostics.exit_code = Some(Compiler::run_bytecode(&program)),
+        Err(e) => diagnostics.errors.push(e),
+    }
+
+    diagnostics
+}
+
+pub fn start_repl(opts: CompilerOptions) {
+    // Deep recursion in the program recurses in the interpreter too
+    thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(move || Repl::new(&opts).start())
+        .expect("failed to spawn the REPL thread")
+        .join()
+        .unwrap();
+}
+
+pub fn print_report(report: Report, source_code: Option<&NamedSource<Arc<str>>>) {
+    match source_code {
+        Some(src) => {
+            eprintln!(
+                "{}",
+                "========================================================================"
+                    .bright_cyan()
+            );
+            eprintln!("{:?}", report.with_source_code(src.clone()));
+        }
+        None => eprintln!("{:?}", report),
+    }
 }
@@ -1,9 +1,11 @@
-#![allow(unused)]
+use std::{fmt, ops::Range};
 
 use colored::Colorize;
-use miette::{Diagnostic, LabeledSpan, SourceSpan};
+use miette::{Diagnostic, LabeledSpan, Severity, SourceSpan};
 use thiserror::Error;
 
+use crate::lint::Lint;
+
 #[derive(Error, Debug, Diagnostic)]
 pub enum CompilerError {
     #[error("{}: {}", "Mali sa lexer".bright_red(), message)]
@@ -78,6 +80,18 @@ pub enum CompilerError {
         span: SourceSpan,
     },
 
+    #[error("{}", "Ginamit bago naideklara".bright_red())]
+    #[diagnostic(help("Ilagay ang `dapat` na `{name}` bago ang gumagamit nito"))]
+    UsedBeforeDeclaration {
+        name: String,
+
+        #[label("Ginamit dito")]
+        span: SourceSpan,
+
+        #[label("Naideklara lamang dito")]
+        declared_span: SourceSpan,
+    },
+
     #[error("{}", "Pagdeklara ulit ng kaparehong pangalan sa kaparehong sakop".bright_red())]
     Redeclaration {
         #[label("Naideklara na dito")]
@@ -147,6 +161,14 @@ pub enum CompilerError {
         args_span: SourceSpan,
     },
 
+    #[error("{} ang {what}", "Hindi pa kaya ng C backend".bright_red())]
+    #[diagnostic(help("Gamitin ang `tol run --interp` o `tol run --vm` para sa programang ito"))]
+    UnsupportedInC {
+        what: String,
+        #[label("Hindi pa ito maisasalin sa C")]
+        span: SourceSpan,
+    },
+
     #[error("{}", "Tinawag ang hindi natatawag")]
     InvalidCallExpression {
         #[label("Baka hindi ito idineklara bilang isang `paraan`?")]
@@ -160,17 +182,50 @@ pub enum CompilerError {
     },
 
     #[error("{}", "Umasa expresyon pagkatapos ng `kungdi`".bright_red())]
-    #[help(
-        "Ang `kungdi` na walang kasunod na expresyon ay maaari lamang gamitin kung ito na ang pinakahuling `kungdi`"
-    )]
-    #[help(
-        "Subukan mong lagyan ng expresyon pagkatapos ng `kungdi` kung ito ay hindi pa ang pinakahuling `kungdi`"
-    )]
+    #[diagnostic(help(
+        "Ang `kungdi` na walang kasunod na expresyon ay maaari lamang gamitin kung ito na ang pinakahuling `kungdi`. Subukan mong lagyan ng expresyon pagkatapos ng `kungdi` kung ito ay hindi pa ang pinakahuling `kungdi`"
+    ))]
     InvalidKungdiBranch {
         #[label("Dapat na may expresyon ang `kungdi` na ito")]
         span: SourceSpan,
     },
 
+    #[error("{}", "Hindi maaaring palitan ang halaga".bright_red())]
+    #[diagnostic(help("Variable na idineklara gamit ang `ang` lamang ang maaaring palitan"))]
+    InvalidAssignmentTarget {
+        #[label("Hindi ito variable na maaaring palitan")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Paghahati sa zero".bright_red())]
+    #[diagnostic(help("Ang paghahati ng integer sa zero ay hihinto sa programa"))]
+    DivisionByZero {
+        #[label("Laging zero ang halagang ito")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Labis ang dami ng shift".bright_red())]
+    #[diagnostic(help("Ang dami ng shift ay dapat mula 0 hanggang {max} para sa `{ttype}`"))]
+    InvalidShiftAmount {
+        amount: i128,
+        ttype: String,
+        max: u32,
+
+        #[label("Hindi maaaring i-shift ang `{ttype}` nang {amount}")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Kulang ng `kungdi` ang `kung` na expresyon".bright_red())]
+    #[diagnostic(help("Lagyan ng `kungdi:` na nagbibigay rin ng `{ttype}`"))]
+    KungExprWithoutKungdi {
+        ttype: String,
+
+        #[label(
+            "Nagbibigay ito ng `{ttype}` pero walang halaga kapag mali ang lahat ng kondisyon"
+        )]
+        span: SourceSpan,
+    },
+
     #[error("{}", "Kailangan ng konkretong tipo".bright_red())]
     UninferrableType {
         #[label(collection)]
@@ -180,10 +235,298 @@ pub enum CompilerError {
         help: Option<String>,
     },
 
+    #[error("{}", "Hindi lahat ng daan ay nagbabalik ng halaga".bright_red())]
+    #[diagnostic(help("Lagyan ng `ibalik` ang dulo ng bawat daan ng paraan"))]
+    MissingReturn {
+        expected: String,
+
+        #[label("Umaasa ang paraang ito na magbalik ng `{expected}`")]
+        span: SourceSpan,
+    },
+
+    #[error("{} `{keyword}` sa labas ng loop", "Ginamit ang".bright_red())]
+    LoopControlOutsideLoop {
+        keyword: String,
+
+        #[label("Magagamit lamang ang `{keyword}` sa loob ng `habang`, `bawat` o `babalik`")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Hindi kilalang pangalan ng loop".bright_red())]
+    #[diagnostic(help("Pangalanan ang loop gamit ang `{label}: habang ...`"))]
+    UndeclaredLabel {
+        label: String,
+
+        #[label("Walang nakapaligid na loop na `{label}`")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Kaparehong pangalan ng loop na nasa loob ng isa pa".bright_red())]
+    #[diagnostic(help("Bigyan ng ibang pangalan ang loop na nasa loob"))]
+    DuplicateLabel {
+        #[label("Pinangalanang `{label}` dito")]
+        declared_span: SourceSpan,
+
+        label: String,
+
+        #[label("Ginamit ulit dito")]
+        redeclared_span: SourceSpan,
+    },
+
+    #[error("{}", "Hindi kilalang lint".bright_red())]
+    UnknownLint {
+        name: String,
+
+        #[label("Walang lint na `{name}`")]
+        span: SourceSpan,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    DeniedLint(CompilerWarning),
+
     #[error("{}", "Hindi pwedeng suffix sa literal".bright_red())]
-    #[help("Halimbawa ng suffix: (u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64)")]
+    #[diagnostic(help(
+        "Halimbawa ng suffix: (u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64)"
+    ))]
     InvalidSuffix {
         #[label("Ito")]
         span: SourceSpan,
     },
+
+    #[error("{}", "Hindi alam ang halaga habang nagko-compile".bright_red())]
+    #[diagnostic(help(
+        "Literal, ibang `dapat` at mga operator lamang ang maaaring gamitin sa `dapat` sa labas ng paraan"
+    ))]
+    NotConstant {
+        #[label("Malalaman lamang ito habang tumatakbo ang programa")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Masyadong malaki ang literal".bright_red())]
+    LiteralTooLarge {
+        #[label("Hindi ito kasya kahit sa 64 bits")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Hindi mabasa ang float na literal".bright_red())]
+    InvalidFloatLiteral {
+        #[label("Hindi ito wastong float")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Hindi kasya ang literal sa tipo nito".bright_red())]
+    #[diagnostic(help("Ang `{ttype}` ay mula {min} hanggang {max}"))]
+    LiteralOutOfRange {
+        value: i128,
+        ttype: String,
+        min: i128,
+        max: i128,
+
+        #[label("Hindi kasya ang {value} sa `{ttype}`")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Nabigo ang pagkalkula habang nagko-compile".bright_red())]
+    ConstEvalFailed {
+        reason: String,
+
+        #[label("{reason}")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Maling laki ng array".bright_red())]
+    #[diagnostic(help(
+        "Integer na literal o `dapat` na hindi negatibo ang maaaring maging laki ng array"
+    ))]
+    InvalidArraySize {
+        #[label("Hindi ito magagamit bilang laki")]
+        span: SourceSpan,
+    },
+
+    #[error("{} `{name}` na paraan", "Walang".bright_red())]
+    #[diagnostic(help("Dito magsisimula ang programa: `paraan {name}() -> i32:`"))]
+    MissingEntryPoint { name: String },
+
+    #[error("{} `{command}`", "Nabigo ang C compiler na".bright_red())]
+    #[diagnostic(help(
+        "Gamitin ang `tol run --interp` para patakbuhin ang programa nang walang C compiler"
+    ))]
+    CCompilerFailed { command: String },
+
+    #[error("{} ang {what}", "Hindi pa kaya ng bytecode".bright_red())]
+    #[diagnostic(help("Gamitin ang `tol run` o `tol run --interp` para sa programang ito"))]
+    Unsupported { what: String },
+
+    #[error("{}: {reason}", "Sirang bytecode".bright_red())]
+    #[diagnostic(help("Buuin ulit ang file gamit ang `tol emit --stage bytecode -o`"))]
+    InvalidBytecode { reason: String },
+}
+
+/// An error that stops a running program. The messages match the ones printed
+/// by the C runtime helpers so both backends fail the same way.
+#[derive(Error, Debug)]
+pub enum RuntimeError {
+    #[error("paghahati sa zero")]
+    DivisionByZero,
+
+    #[error("lumampas ang resulta ng paghahati")]
+    DivisionOverflow,
+
+    #[error("negatibong exponent sa integer")]
+    NegativeExponent,
+
+    #[error("labis ang dami ng shift")]
+    ShiftOutOfRange,
+
+    #[error("masyadong malalim ang recursion")]
+    StackOverflow,
+
+    #[error("hindi pa suportado ang {0}")]
+    Unsupported(String),
+
+    /// Only a corrupted `.tolc` file gives an instruction a value of the
+    /// wrong kind
+    #[error("sirang bytecode, maling halaga para sa `{0}`")]
+    InvalidBytecode(String),
+}
+
+/// A warning reported by a lint. Denied lints are reported as errors instead
+/// through `CompilerError::DeniedLint`.
+#[derive(Error, Debug)]
+#[error("{kind}")]
+pub struct CompilerWarning {
+    pub lint: Lint,
+    pub denied: bool,
+    pub kind: WarningKind,
+}
+
+impl CompilerWarning {
+    pub fn new(kind: WarningKind) -> Self {
+        Self {
+            lint: kind.lint(),
+            denied: false,
+            kind,
+        }
+    }
+}
+
+impl Diagnostic for CompilerWarning {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.lint))
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        if self.denied {
+            Some(Severity::Error)
+        } else {
+            Some(Severity::Warning)
+        }
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.kind.help()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.kind.labels()
+    }
+}
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum WarningKind {
+    #[error("{}", "Hindi maaabot na code".bright_yellow())]
+    UnreachableCode {
+        #[label("Hindi kailanman mapupuntahan ang pahayag na ito")]
+        span: SourceSpan,
+
+        #[label("Dahil sa pahayag na ito")]
+        cause_span: SourceSpan,
+    },
+
+    #[error("{}", "Tinatakpan ang pangalan sa labas na sakop".bright_yellow())]
+    Shadowing {
+        name: String,
+
+        #[label("Tinatakpan nito ang `{name}`")]
+        span: SourceSpan,
+
+        #[label("Naideklara sa labas dito")]
+        shadowed_span: SourceSpan,
+    },
+
+    #[error("{}", "Hindi kailangan ang `gagawin`".bright_yellow())]
+    #[diagnostic(help("Ang `gagawin` ay para lang sa mga blokeng walang ibang laman"))]
+    NeedlessGagawin {
+        #[label("Maaari itong tanggalin")]
+        span: SourceSpan,
+    },
+
+    #[error("{}", "Hindi nagamit na variable".bright_yellow())]
+    UnusedVariable {
+        name: String,
+
+        #[label("Hindi kailanman nabasa ang `{name}`")]
+        span: SourceSpan,
+
+        #[help]
+        fix: Fix,
+    },
+
+    #[error("{}", "Hindi nagamit na paraan".bright_yellow())]
+    #[diagnostic(help("Kung sinadya, lagyan ng `_` sa unahan ang pangalan"))]
+    UnusedFunction {
+        name: String,
+
+        #[label("Hindi kailanman tinawag ang `{name}`")]
+        span: SourceSpan,
+    },
+}
+
+/// A replacement that can be applied to the source as is to resolve a warning
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub span: Range<usize>,
+    pub replacement: String,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kung sinadya, palitan ng `{}` ang pangalan",
+            self.replacement
+        )
+    }
+}
+
+impl WarningKind {
+    /// Returns the fix that can be applied without review, if there is one
+    pub fn fix(&self) -> Option<&Fix> {
+        match self {
+            WarningKind::UnusedVariable { fix, .. } => Some(fix),
+            _ => None,
+        }
+    }
+
+    pub fn lint(&self) -> Lint {
+        match self {
+            WarningKind::UnreachableCode { .. } => Lint::UnreachableCode,
+            WarningKind::Shadowing { .. } => Lint::Shadowing,
+            WarningKind::NeedlessGagawin { .. } => Lint::NeedlessGagawin,
+            WarningKind::UnusedVariable { .. } => Lint::UnusedVariable,
+            WarningKind::UnusedFunction { .. } => Lint::UnusedFunction,
+        }
+    }
+
+    /// Start of the code the warning points at, used to match `-- tol:allow(...)` comments
+    pub fn offset(&self) -> usize {
+        match self {
+            WarningKind::UnreachableCode { span, .. }
+            | WarningKind::Shadowing { span, .. }
+            | WarningKind::NeedlessGagawin { span }
+            | WarningKind::UnusedVariable { span, .. }
+            | WarningKind::UnusedFunction { span, .. } => span.offset(),
+        }
+    }
 }
@@ -0,0 +1,81 @@
+//! Interned strings.
+//!
+//! Names of variables and `paraan`s are interned once for the whole
+//! process, so they are compared and hashed as numbers and the typed AST
+//! doesn't borrow from the source. The REPL keeps its names between inputs,
+//! which is why the interner isn't tied to one compilation. Interned strings
+//! are never freed, which is fine for names but why literals are kept as
+//! values instead.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{Mutex, OnceLock},
+};
+
+/// A string interned for the whole process. It carries the string along with
+/// its number, so reading it doesn't take the lock.
+#[derive(Clone, Copy)]
+pub struct Name {
+    id: u32,
+    string: &'static str,
+}
+
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<&'static str, Name>,
+}
+
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Name {
+    pub fn new(string: &str) -> Self {
+        let mut interner = interner().lock().unwrap();
+        if let Some(name) = interner.ids.get(string) {
+            return *name;
+        }
+
+        let string: &'static str = Box::leak(string.into());
+        let name = Name {
+            id: interner.ids.len() as u32,
+            string,
+        };
+        interner.ids.insert(string, name);
+
+        name
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.string
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Name {}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
@@ -0,0 +1,421 @@
+//! Runs a program by walking its typed AST, without going through C.
+//!
+//! Integer arithmetic wraps like the generated C compiled with `-fwrapv`, and
+//! `/`, `%`, `**` and shifts stop the program where the C runtime helpers
+//! would.
+
+pub mod value;
+
+use std::collections::HashMap;
+
+use crate::{
+    analyzer::{
+        ENTRY_POINT, SymbolId,
+        symbol::{Symbol, SymbolKind},
+    },
+    ast::{
+        TypedAst,
+        typed_expr::{TypedExpr, TypedExprKind},
+        typed_stmt::{TypedStmt, TypedStmtKind},
+    },
+    error::RuntimeError,
+    lexer::token::TokenKind,
+    toltype::TolType,
+};
+
+use value::{Value, arith, compare, unary};
+
+/// Deepest chain of `paraan` calls before the program is stopped
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Stack size of the thread running the interpreter, enough for
+/// [`MAX_CALL_DEPTH`] calls
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Why evaluation stopped before reaching the end of a statement
+enum Unwind {
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(Value),
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(value: RuntimeError) -> Self {
+        Unwind::Error(value)
+    }
+}
+
+/// What a loop does after its body finished or unwound
+enum LoopStep {
+    Next,
+    Exit,
+}
+
+pub struct Interpreter<'a> {
+    symbols: &'a [Symbol],
+    functions: HashMap<SymbolId, &'a TypedStmt>,
+    globals: HashMap<SymbolId, Value>,
+    /// Locals of each `paraan` being called, innermost last
+    frames: Vec<HashMap<SymbolId, Value>>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(symbols: &'a [Symbol]) -> Self {
+        Self::with_globals(symbols, HashMap::new())
+    }
+
+    /// Continues with the globals an earlier interpreter left behind, see
+    /// [`Interpreter::into_globals`]
+    pub fn with_globals(symbols: &'a [Symbol], globals: HashMap<SymbolId, Value>) -> Self {
+        Self {
+            symbols,
+            functions: HashMap::new(),
+            globals,
+            frames: Vec::new(),
+        }
+    }
+
+    /// The values of the top-level variables, for a later interpreter to continue with
+    pub fn into_globals(self) -> HashMap<SymbolId, Value> {
+        self.globals
+    }
+
+    /// Runs the top-level statements of `ast`, then calls `main`, which the
+    /// caller made sure exists. Returns the exit code of the program, which
+    /// is what `main` returns.
+    pub fn run(&mut self, ast: &'a TypedAst) -> Result<i32, RuntimeError> {
+        for stmt in ast.iter() {
+            self.exec_top_level(stmt)?;
+        }
+
+        let main_id = self
+            .functions
+            .keys()
+            .copied()
+            .find(|id| self.symbols[*id].name() == ENTRY_POINT)
+            .expect("`main` should have been checked before running");
+
+        match self.call(main_id, Vec::new())? {
+            Value::Int(code) => Ok(code as i32),
+            _ => Ok(0),
+        }
+    }
+
+    /// Runs a statement outside of any `paraan`
+    pub fn exec_top_level(&mut self, stmt: &'a TypedStmt) -> Result<(), RuntimeError> {
+        match self.exec_stmt(stmt) {
+            Ok(()) => Ok(()),
+            Err(Unwind::Error(e)) => Err(e),
+            // The analyzer rejects these outside of a `paraan` or a loop
+            Err(Unwind::Return(_) | Unwind::Break(_) | Unwind::Continue(_)) => unreachable!(),
+        }
+    }
+
+    /// Evaluates an expression outside of any `paraan`
+    pub fn eval_top_level(&mut self, expr: &'a TypedExpr) -> Result<Value, RuntimeError> {
+        match self.eval_expr(expr) {
+            Ok(value) => Ok(value),
+            Err(Unwind::Error(e)) => Err(e),
+            Err(Unwind::Return(_) | Unwind::Break(_) | Unwind::Continue(_)) => unreachable!(),
+        }
+    }
+
+    /// Makes a `paraan` declared outside of the statements being run callable
+    pub fn declare_paraan(&mut self, paraan: &'a TypedStmt) {
+        if let TypedStmtKind::Paraan { symbol_id, .. } = &paraan.kind {
+            self.functions.insert(*symbol_id, paraan);
+        }
+    }
+
+    fn call(&mut self, symbol_id: SymbolId, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow);
+        }
+
+        let paraan = self.functions[&symbol_id];
+        let TypedStmtKind::Paraan {
+            param_ids, block, ..
+        } = &paraan.kind
+        else {
+            unreachable!()
+        };
+        let SymbolKind::Func {
+            param_types,
+            return_type,
+        } = self.symbols[symbol_id].kind().clone()
+        else {
+            unreachable!()
+        };
+
+        let frame = param_ids
+            .iter()
+            .zip(&param_types)
+            .zip(args)
+            .map(|((id, ttype), arg)| (*id, arg.convert(ttype)))
+            .collect();
+
+        self.frames.push(frame);
+        let result = self.exec_stmt(block);
+        self.frames.pop();
+
+        match result {
+            Ok(()) => Ok(Value::Void),
+            Err(Unwind::Return(value)) => Ok(value.convert(&return_type)),
+            Err(Unwind::Error(e)) => Err(e),
+            Err(Unwind::Break(_) | Unwind::Continue(_)) => unreachable!(),
+        }
+    }
+
+    fn exec_stmt(&mut self, stmt: &'a TypedStmt) -> Result<(), Unwind> {
+        match &stmt.kind {
+            TypedStmtKind::Paraan { symbol_id, .. } => {
+                self.functions.insert(*symbol_id, stmt);
+            }
+            TypedStmtKind::Ang { symbol_id, rhs } | TypedStmtKind::Dapat { symbol_id, rhs } => {
+                let value = self.eval_expr(rhs)?;
+                self.declare(*symbol_id, value);
+            }
+            TypedStmtKind::Block { stmts } => {
+                for stmt in stmts {
+                    self.exec_stmt(stmt)?;
+                }
+            }
+            TypedStmtKind::Ibalik { rhs } => {
+                let value = match rhs {
+                    Some(rhs) => self.eval_expr(rhs)?,
+                    None => Value::Void,
+                };
+                return Err(Unwind::Return(value));
+            }
+            TypedStmtKind::Bawat {
+                iter,
+                bind_id,
+                block,
+                label,
+                ..
+            } => {
+                let Value::Array(elems) = self.eval_expr(iter)? else {
+                    return Err(
+                        RuntimeError::Unsupported(format!("`bawat` sa `{}`", iter.ttype)).into(),
+                    );
+                };
+
+                for elem in elems {
+                    self.declare(*bind_id, elem);
+                    let result = self.exec_stmt(block);
+                    if let LoopStep::Exit = Self::loop_step(result, label)? {
+                        break;
+                    }
+                }
+            }
+            TypedStmtKind::Habang { cond, block, label } => {
+                while self.eval_expr(cond)?.as_bool() {
+                    let result = self.exec_stmt(block);
+                    if let LoopStep::Exit = Self::loop_step(result, label)? {
+                        break;
+                    }
+                }
+            }
+            TypedStmtKind::Babalik { block, cond, label } => loop {
+                let result = self.exec_stmt(block);
+                if let LoopStep::Exit = Self::loop_step(result, label)? {
+                    break;
+                }
+                if !self.eval_expr(cond)?.as_bool() {
+                    break;
+                }
+            },
+            TypedStmtKind::Tigil { label } => return Err(Unwind::Break(label.clone())),
+            TypedStmtKind::Tuloy { label } => return Err(Unwind::Continue(label.clone())),
+            TypedStmtKind::Expr { expr } => {
+                self.eval_expr(expr)?;
+            }
+            TypedStmtKind::Kung { branches } => {
+                for branch in branches {
+                    let taken = match &branch.cond {
+                        Some(cond) => self.eval_expr(cond)?.as_bool(),
+                        None => true,
+                    };
+                    if taken {
+                        return self.exec_stmt(&branch.block);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decides whether a loop keeps going after its body ran. `tigil` and
+    /// `tuloy` aimed at an outer loop keep unwinding.
+    fn loop_step(result: Result<(), Unwind>, label: &Option<String>) -> Result<LoopStep, Unwind> {
+        match result {
+            Ok(()) => Ok(LoopStep::Next),
+            Err(Unwind::Break(target)) if target.is_none() || target == *label => {
+                Ok(LoopStep::Exit)
+            }
+            Err(Unwind::Continue(target)) if target.is_none() || target == *label => {
+                Ok(LoopStep::Next)
+            }
+            Err(unwind) => Err(unwind),
+        }
+    }
+
+    fn eval_expr(&mut self, expr: &'a TypedExpr) -> Result<Value, Unwind> {
+        let value = match &expr.kind {
+            TypedExprKind::Integer { .. } => Value::Int(expr.int_literal_value().unwrap()),
+            TypedExprKind::Float { .. } => Value::Float(expr.float_literal_value().unwrap()),
+            TypedExprKind::Bool { value } => Value::Bool(*value),
+            TypedExprKind::Identifier { symbol_id, .. } => self.load(*symbol_id),
+            TypedExprKind::Binary { left, right, op } => {
+                self.eval_binary(left, right, op, &expr.ttype)?
+            }
+            TypedExprKind::Unary { right, op } => {
+                let right = self.eval_expr(right)?;
+                unary(op, right)
+            }
+            TypedExprKind::FnCall { callee, args } => {
+                let Value::Paraan(symbol_id) = self.eval_expr(callee)? else {
+                    unreachable!()
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval_expr(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.call(symbol_id, args)?
+            }
+            TypedExprKind::ArrayLiteral { elems } => Value::Array(
+                elems
+                    .iter()
+                    .map(|elem| self.eval_expr(elem))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            TypedExprKind::Block { stmts, tail } => {
+                for stmt in stmts {
+                    self.exec_stmt(stmt)?;
+                }
+                match tail {
+                    Some(tail) => self.eval_expr(tail)?,
+                    None => Value::Void,
+                }
+            }
+            TypedExprKind::Kung { branches } => {
+                let mut value = Value::Void;
+                for arm in branches {
+                    let taken = match &arm.cond {
+                        Some(cond) => self.eval_expr(cond)?.as_bool(),
+                        None => true,
+                    };
+                    if taken {
+                        value = self.eval_expr(&arm.value)?;
+                        break;
+                    }
+                }
+                value
+            }
+        };
+
+        Ok(value.convert(&expr.ttype))
+    }
+
+    fn eval_binary(
+        &mut self,
+        left: &'a TypedExpr,
+        right: &'a TypedExpr,
+        op: &TokenKind,
+        ttype: &TolType,
+    ) -> Result<Value, Unwind> {
+        // Assignments store into the variable instead of reading it first
+        if let Some(arith_op) = Self::assignment_op(op) {
+            let TypedExprKind::Identifier { symbol_id, .. } = &left.kind else {
+                unreachable!()
+            };
+            let rhs = self.eval_expr(right)?;
+            let value = match arith_op {
+                Some(arith_op) => {
+                    let current = self.load(*symbol_id);
+                    arith(current, rhs, &arith_op, &left.ttype)?
+                }
+                None => rhs,
+            }
+            .convert(&left.ttype);
+
+            self.store(*symbol_id, value.clone());
+            return Ok(value);
+        }
+
+        let common = left.ttype.coerce(&right.ttype);
+        let left = self.eval_expr(left)?;
+        match op {
+            // Short-circuits like C
+            TokenKind::AmperAmper if !left.as_bool() => return Ok(Value::Bool(false)),
+            TokenKind::PipePipe if left.as_bool() => return Ok(Value::Bool(true)),
+            TokenKind::AmperAmper | TokenKind::PipePipe => return self.eval_expr(right),
+            _ => {}
+        }
+
+        let right = self.eval_expr(right)?;
+        let value = match op {
+            TokenKind::EqualEqual
+            | TokenKind::BangEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual
+            | TokenKind::Greater
+            | TokenKind::GreaterEqual => {
+                // Converted to a common type like in C, as an unsized literal
+                // may not fit in the type of the other operand
+                match common {
+                    Some(common) => compare(&left.convert(&common), &right.convert(&common), op),
+                    None => compare(&left, &right, op),
+                }
+            }
+            _ => arith(left, right, op, ttype)?,
+        };
+
+        Ok(value)
+    }
+
+    /// The operator applied by an assignment, `Some(None)` for plain `=`
+    fn assignment_op(op: &TokenKind) -> Option<Option<TokenKind>> {
+        match op {
+            TokenKind::Equal => Some(None),
+            TokenKind::PlusEqual => Some(Some(TokenKind::Plus)),
+            TokenKind::MinusEqual => Some(Some(TokenKind::Minus)),
+            TokenKind::StarEqual => Some(Some(TokenKind::Star)),
+            TokenKind::SlashEqual => Some(Some(TokenKind::Slash)),
+            _ => None,
+        }
+    }
+
+    fn declare(&mut self, symbol_id: SymbolId, value: Value) {
+        let value = value.convert(&self.symbols[symbol_id].get_type());
+        match self.frames.last_mut() {
+            Some(frame) => frame.insert(symbol_id, value),
+            None => self.globals.insert(symbol_id, value),
+        };
+    }
+
+    fn store(&mut self, symbol_id: SymbolId, value: Value) {
+        match self.frames.last_mut() {
+            Some(frame) if frame.contains_key(&symbol_id) => frame.insert(symbol_id, value),
+            _ => self.globals.insert(symbol_id, value),
+        };
+    }
+
+    fn load(&self, symbol_id: SymbolId) -> Value {
+        if let SymbolKind::Func { .. } = self.symbols[symbol_id].kind() {
+            return Value::Paraan(symbol_id);
+        }
+
+        self.frames
+            .last()
+            .and_then(|frame| frame.get(&symbol_id))
+            .or_else(|| self.globals.get(&symbol_id))
+            .cloned()
+            .unwrap_or_else(|| {
+                unreachable!("`{}` was never declared", self.symbols[symbol_id].name())
+            })
+    }
+}
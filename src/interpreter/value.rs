@@ -0,0 +1,217 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::{analyzer::SymbolId, error::RuntimeError, lexer::token::TokenKind, toltype::TolType};
+
+/// A value of a running program. Integers of every width are held as `i128`
+/// and wrapped to the width of their type after each operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+    Paraan(SymbolId),
+    Void,
+}
+
+impl Value {
+    pub fn as_int(&self) -> i128 {
+        match self {
+            Value::Int(value) => *value,
+            _ => unreachable!("Expected an integer, found `{self:?}`"),
+        }
+    }
+
+    pub fn as_float(&self) -> f64 {
+        match self {
+            Value::Float(value) => *value,
+            // An integer exponent of a float base
+            Value::Int(value) => *value as f64,
+            _ => unreachable!("Expected a float, found `{self:?}`"),
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Value::Bool(value) => *value,
+            _ => unreachable!("Expected a bool, found `{self:?}`"),
+        }
+    }
+
+    /// Converts the value to `ttype` the way C does when assigning, wrapping
+    /// integers and rounding `f32`s
+    pub fn convert(self, ttype: &TolType) -> Value {
+        match self {
+            Value::Int(value) => Value::Int(wrap_int(value, ttype)),
+            Value::Float(value) if *ttype == TolType::F32 => Value::Float(value as f32 as f64),
+            Value::Array(elems) => match ttype {
+                TolType::Array { inner, .. } => {
+                    Value::Array(elems.into_iter().map(|e| e.convert(inner)).collect())
+                }
+                _ => Value::Array(elems),
+            },
+            value => value,
+        }
+    }
+}
+
+/// Wraps `value` to the range of the integer type `ttype`. Unsized integers
+/// are `i64`s, as in the generated C.
+pub fn wrap_int(value: i128, ttype: &TolType) -> i128 {
+    match ttype {
+        TolType::I8 => value as i8 as i128,
+        TolType::I16 => value as i16 as i128,
+        TolType::I32 => value as i32 as i128,
+        TolType::I64 | TolType::ISize | TolType::UnsizedInteger => value as i64 as i128,
+        TolType::U8 => value as u8 as i128,
+        TolType::U16 => value as u16 as i128,
+        TolType::U32 => value as u32 as i128,
+        TolType::U64 | TolType::USize => value as u64 as i128,
+        _ => value,
+    }
+}
+
+/// Smallest value of a signed integer type, the one that overflows when
+/// divided by -1
+fn signed_min(ttype: &TolType) -> Option<i128> {
+    match ttype {
+        TolType::I8 => Some(i8::MIN as i128),
+        TolType::I16 => Some(i16::MIN as i128),
+        TolType::I32 => Some(i32::MIN as i128),
+        TolType::I64 | TolType::ISize | TolType::UnsizedInteger => Some(i64::MIN as i128),
+        _ => None,
+    }
+}
+
+/// Applies `==`, `!=`, `<`, `<=`, `>` or `>=`
+pub fn compare(left: &Value, right: &Value, op: &TokenKind) -> Value {
+    let ordering = match (left, right) {
+        (Value::Int(l), Value::Int(r)) => l.partial_cmp(r),
+        (Value::Float(_), _) | (_, Value::Float(_)) => {
+            left.as_float().partial_cmp(&right.as_float())
+        }
+        // Only `==` and `!=` apply to the rest
+        _ => (left == right).then_some(Ordering::Equal),
+    };
+
+    Value::Bool(match op {
+        TokenKind::EqualEqual => ordering.is_some_and(Ordering::is_eq),
+        TokenKind::BangEqual => !ordering.is_some_and(Ordering::is_eq),
+        TokenKind::Less => ordering.is_some_and(Ordering::is_lt),
+        TokenKind::LessEqual => ordering.is_some_and(Ordering::is_le),
+        TokenKind::Greater => ordering.is_some_and(Ordering::is_gt),
+        TokenKind::GreaterEqual => ordering.is_some_and(Ordering::is_ge),
+        _ => unreachable!("Comparison `{op:?}`"),
+    })
+}
+
+/// Applies `-`, `!` or `~`
+pub fn unary(op: &TokenKind, right: Value) -> Value {
+    match (op, right) {
+        (TokenKind::Minus, Value::Int(value)) => Value::Int(value.wrapping_neg()),
+        (TokenKind::Minus, Value::Float(value)) => Value::Float(-value),
+        (TokenKind::Bang, Value::Bool(value)) => Value::Bool(!value),
+        (TokenKind::Tilde, Value::Int(value)) => Value::Int(!value),
+        (op, right) => unreachable!("Unary `{op:?}` on `{right:?}`"),
+    }
+}
+
+/// Applies an arithmetic or bitwise operator whose result is a `ttype`
+pub fn arith(
+    left: Value,
+    right: Value,
+    op: &TokenKind,
+    ttype: &TolType,
+) -> Result<Value, RuntimeError> {
+    if ttype.is_float() {
+        let (l, r) = (left.as_float(), right.as_float());
+        let value = match op {
+            TokenKind::Plus => l + r,
+            TokenKind::Minus => l - r,
+            TokenKind::Star => l * r,
+            TokenKind::Slash => l / r,
+            TokenKind::StarStar => l.powf(r),
+            _ => unreachable!("Float operator `{op:?}`"),
+        };
+        return Ok(Value::Float(value));
+    }
+
+    let (l, r) = (left.as_int(), right.as_int());
+    let value = match op {
+        TokenKind::Plus => l.wrapping_add(r),
+        TokenKind::Minus => l.wrapping_sub(r),
+        TokenKind::Star => l.wrapping_mul(r),
+        TokenKind::Slash | TokenKind::Percent => {
+            if r == 0 {
+                return Err(RuntimeError::DivisionByZero);
+            }
+            if r == -1 && signed_min(ttype) == Some(l) {
+                return Err(RuntimeError::DivisionOverflow);
+            }
+
+            if *op == TokenKind::Slash {
+                l / r
+            } else {
+                l % r
+            }
+        }
+        TokenKind::StarStar => int_pow(l, r, ttype)?,
+        TokenKind::Amper => l & r,
+        TokenKind::Pipe => l | r,
+        TokenKind::Caret => l ^ r,
+        TokenKind::LessLess => l.wrapping_shl(shift_amount(r, ttype)?),
+        TokenKind::GreaterGreater => l >> shift_amount(r, ttype)?,
+        _ => unreachable!("Integer operator `{op:?}`"),
+    };
+
+    Ok(Value::Int(wrap_int(value, ttype)))
+}
+
+/// A shift amount must be less than the width of the shifted `ttype`
+fn shift_amount(amount: i128, ttype: &TolType) -> Result<u32, RuntimeError> {
+    u32::try_from(amount)
+        .ok()
+        .filter(|amount| *amount < ttype.bit_width().unwrap_or(64))
+        .ok_or(RuntimeError::ShiftOutOfRange)
+}
+
+/// Exponentiation by squaring, wrapping on overflow
+fn int_pow(base: i128, mut exp: i128, ttype: &TolType) -> Result<i128, RuntimeError> {
+    if exp < 0 {
+        return Err(RuntimeError::NegativeExponent);
+    }
+
+    let mut result: i128 = 1;
+    let mut base = base;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = wrap_int(result.wrapping_mul(base), ttype);
+        }
+        base = wrap_int(base.wrapping_mul(base), ttype);
+        exp >>= 1;
+    }
+
+    Ok(result)
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value:?}"),
+            Value::Bool(true) => write!(f, "tama"),
+            Value::Bool(false) => write!(f, "mali"),
+            Value::Array(elems) => write!(
+                f,
+                "[{}]",
+                elems
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Paraan(_) => write!(f, "<paraan>"),
+            Value::Void => write!(f, "wala"),
+        }
+    }
+}
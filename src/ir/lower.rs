@@ -0,0 +1,850 @@
+//! Lowers the typed AST into a [`Module`].
+//!
+//! Integers and floats without a size are computed as `i64`s and `f64`s, and
+//! every value is converted to the type it is used as with a `Cast`, so the
+//! IR has no implicit conversions. Constants are converted right away.
+//!
+//! The IR is only made for the C backend, so what C can't hold yet, `paraan`
+//! values and arrays without a size, is reported here rather than by the
+//! analyzer, which accepts them for the interpreter and the VM.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+};
+
+use crate::{
+    analyzer::{
+        SymbolId,
+        const_eval::ConstValue,
+        symbol::{Symbol, SymbolKind},
+    },
+    ast::{
+        TypedAst,
+        typed_expr::{TypedExpr, TypedExprKind},
+        typed_stmt::{TypedStmt, TypedStmtKind},
+    },
+    error::CompilerError,
+    ir::{
+        BinOp, Block, BlockId, Function, Global, INIT_NAME, Inst, Local, LocalId, Module, Operand,
+        Place, Rvalue, TempId, Terminator, UnOp,
+    },
+    lexer::token::TokenKind,
+    toltype::TolType,
+};
+
+/// Name of the locals holding the value of `&&`, `||` and `kung` expressions
+const TEMP_LOCAL_NAME: &str = "tmp";
+
+/// Where `tigil` and `tuloy` jump to in a loop
+struct LoopTargets {
+    label: Option<String>,
+    break_block: BlockId,
+    continue_block: BlockId,
+}
+
+pub fn lower(ast: &TypedAst, symbols: &[Symbol]) -> Result<Module, Vec<CompilerError>> {
+    lower_module(ast, symbols, false)
+}
+
+/// Lowers with an `Inst::Location` before the instructions of every
+/// statement, for `#line` directives in the generated C
+pub fn lower_with_locations(
+    ast: &TypedAst,
+    symbols: &[Symbol],
+) -> Result<Module, Vec<CompilerError>> {
+    lower_module(ast, symbols, true)
+}
+
+fn lower_module(
+    ast: &TypedAst,
+    symbols: &[Symbol],
+    locations: bool,
+) -> Result<Module, Vec<CompilerError>> {
+    let mut lowerer = Lowerer::new(symbols, locations);
+
+    for stmt in ast.iter() {
+        if let TypedStmtKind::Paraan { symbol_id, .. } = &stmt.kind {
+            lowerer.name(*symbol_id);
+        }
+    }
+
+    // Everything outside of a `paraan` runs in `init`, in order
+    lowerer.start_function(INIT_NAME, None, TolType::Void);
+    for stmt in ast.iter() {
+        match &stmt.kind {
+            TypedStmtKind::Paraan { .. } => {}
+            TypedStmtKind::Ang { symbol_id, rhs } => {
+                lowerer.locate(stmt);
+                lowerer.lower_global(*symbol_id, rhs, false);
+            }
+            TypedStmtKind::Dapat { symbol_id, rhs } => {
+                lowerer.locate(stmt);
+                lowerer.lower_global(*symbol_id, rhs, true);
+            }
+            _ => lowerer.lower_stmt(stmt),
+        }
+    }
+    let init = lowerer.finish_function();
+
+    let mut functions = Vec::new();
+    for stmt in ast.iter() {
+        if let TypedStmtKind::Paraan {
+            symbol_id,
+            param_ids,
+            block,
+            ..
+        } = &stmt.kind
+        {
+            functions.push(lowerer.lower_function(*symbol_id, param_ids, block));
+        }
+    }
+
+    if !lowerer.errors.is_empty() {
+        return Err(lowerer.errors);
+    }
+
+    Ok(Module {
+        globals: lowerer.globals,
+        functions,
+        init,
+        names: lowerer.names,
+    })
+}
+
+/// The type a value is computed as
+pub fn ir_type(ttype: &TolType) -> TolType {
+    match ttype {
+        TolType::UnsizedInteger => TolType::I64,
+        TolType::UnsizedFloat => TolType::F64,
+        TolType::Array { inner, size } => TolType::Array {
+            inner: Box::new(ir_type(inner)),
+            size: *size,
+        },
+        other => other.clone(),
+    }
+}
+
+struct Lowerer<'a> {
+    symbols: &'a [Symbol],
+    globals: Vec<Global>,
+    names: BTreeMap<SymbolId, String>,
+
+    /// Function being lowered
+    function: Function,
+    /// Block new instructions go to, `None` right after a terminator
+    current: Option<BlockId>,
+    /// Blocks in the order they were started, which becomes their order in
+    /// the function
+    order: Vec<BlockId>,
+    locals: HashMap<SymbolId, LocalId>,
+    loops: Vec<LoopTargets>,
+    /// Whether to mark where statements are with `Inst::Location`
+    locations: bool,
+    /// What C can't hold yet. Lowering goes on to find the rest, but the
+    /// module is thrown away.
+    errors: Vec<CompilerError>,
+}
+
+impl<'a> Lowerer<'a> {
+    fn new(symbols: &'a [Symbol], locations: bool) -> Self {
+        Self {
+            symbols,
+            globals: Vec::new(),
+            names: BTreeMap::new(),
+            function: Self::empty_function(INIT_NAME, None, TolType::Void),
+            current: None,
+            order: Vec::new(),
+            locals: HashMap::new(),
+            loops: Vec::new(),
+            locations,
+            errors: Vec::new(),
+        }
+    }
+
+    fn empty_function(name: &str, symbol_id: Option<SymbolId>, return_type: TolType) -> Function {
+        Function {
+            name: name.to_string(),
+            symbol_id,
+            params: Vec::new(),
+            return_type,
+            locals: Vec::new(),
+            temps: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn start_function(&mut self, name: &str, symbol_id: Option<SymbolId>, return_type: TolType) {
+        self.function = Self::empty_function(name, symbol_id, return_type);
+        self.order.clear();
+        self.locals.clear();
+        self.loops.clear();
+
+        let entry = self.new_block();
+        self.switch_to(entry);
+    }
+
+    fn lower_function(
+        &mut self,
+        symbol_id: SymbolId,
+        param_ids: &[SymbolId],
+        block: &TypedStmt,
+    ) -> Function {
+        let symbol = &self.symbols[symbol_id];
+        let return_type = ir_type(&symbol.get_type());
+        self.check_sized(&return_type, symbol.span());
+        self.start_function(symbol.name(), Some(symbol_id), return_type);
+        for id in param_ids {
+            let local = self.declare_local(*id);
+            self.function.params.push(local);
+        }
+
+        self.lower_stmt(block);
+        self.finish_function()
+    }
+
+    /// Ends the last block and puts the blocks in the order they were started
+    fn finish_function(&mut self) -> Function {
+        if self.current.is_some() {
+            let terminator = match self.function.return_type {
+                TolType::Void => Terminator::Return(None),
+                // The analyzer makes sure every path returns
+                _ => Terminator::Unreachable,
+            };
+            self.terminate(terminator);
+        }
+
+        let mut function = std::mem::replace(
+            &mut self.function,
+            Self::empty_function(INIT_NAME, None, TolType::Void),
+        );
+        function.reorder_blocks(&self.order);
+
+        function
+    }
+
+    /// Declares a top-level variable. Globals initialized with a constant
+    /// hold it from the start instead of having it stored by `init`.
+    fn lower_global(&mut self, symbol_id: SymbolId, rhs: &TypedExpr, is_const: bool) {
+        let symbol = &self.symbols[symbol_id];
+        let ttype = ir_type(&symbol.get_type());
+        self.check_sized(&ttype, symbol.span());
+
+        let blocks = self.function.blocks.len();
+        let insts = self.current_insts();
+        let value = self.lower_value_as(rhs, &ttype);
+        let emitted_code = self.function.blocks.len() != blocks || self.current_insts() != insts;
+
+        let init = match value {
+            Operand::Const(value, _) if !emitted_code => Some(value),
+            value => {
+                self.push(Inst::Store {
+                    place: Place::Global(symbol_id),
+                    value,
+                });
+                None
+            }
+        };
+
+        self.globals.push(Global {
+            symbol_id,
+            name: symbol.name().to_string(),
+            ttype,
+            is_const,
+            init,
+        });
+    }
+
+    fn lower_stmt(&mut self, stmt: &TypedStmt) {
+        if !matches!(stmt.kind, TypedStmtKind::Block { .. }) {
+            self.locate(stmt);
+        }
+
+        match &stmt.kind {
+            TypedStmtKind::Ang { symbol_id, rhs } | TypedStmtKind::Dapat { symbol_id, rhs } => {
+                let ttype = ir_type(&self.symbols[*symbol_id].get_type());
+                let value = self.lower_value_as(rhs, &ttype);
+                let local = self.declare_local(*symbol_id);
+                self.push(Inst::Store {
+                    place: Place::Local(local),
+                    value,
+                });
+            }
+            // Only found at the top level, where they are lowered on their own
+            TypedStmtKind::Paraan { .. } => unreachable!("Nested `paraan`"),
+            TypedStmtKind::Block { stmts } => {
+                for stmt in stmts {
+                    self.lower_stmt(stmt);
+                }
+            }
+            TypedStmtKind::Ibalik { rhs } => {
+                let return_type = self.function.return_type.clone();
+                let value = rhs
+                    .as_ref()
+                    .map(|rhs| self.lower_value_as(rhs, &return_type));
+                self.terminate(Terminator::Return(value));
+            }
+            TypedStmtKind::Bawat { .. } => unreachable!("The parser doesn't accept `bawat` yet"),
+            TypedStmtKind::Habang { cond, block, label } => {
+                let header = self.new_block();
+                self.jump(header);
+                self.switch_to(header);
+                self.locate(stmt);
+
+                let cond = self.lower_value(cond);
+                let body = self.new_block();
+                let exit = self.new_block();
+                self.branch(cond, body, exit);
+
+                self.switch_to(body);
+                self.lower_loop_body(block, label, exit, header);
+                self.jump(header);
+                self.switch_to(exit);
+            }
+            TypedStmtKind::Babalik { block, cond, label } => {
+                let body = self.new_block();
+                self.jump(body);
+                self.switch_to(body);
+
+                let next = self.new_block();
+                let exit = self.new_block();
+                self.lower_loop_body(block, label, exit, next);
+                self.jump(next);
+
+                self.switch_to(next);
+                self.locate(stmt);
+                let cond = self.lower_value(cond);
+                self.branch(cond, body, exit);
+                self.switch_to(exit);
+            }
+            TypedStmtKind::Tigil { label } => {
+                let target = self.target_loop(label).break_block;
+                self.jump(target);
+            }
+            TypedStmtKind::Tuloy { label } => {
+                let target = self.target_loop(label).continue_block;
+                self.jump(target);
+            }
+            TypedStmtKind::Expr { expr } => {
+                self.lower_expr(expr);
+            }
+            TypedStmtKind::Kung { branches } => {
+                let end = self.new_block();
+                for branch in branches {
+                    let Some(cond) = &branch.cond else {
+                        self.lower_stmt(&branch.block);
+                        break;
+                    };
+
+                    let cond = self.lower_value(cond);
+                    let then_block = self.new_block();
+                    let next = self.new_block();
+                    self.branch(cond, then_block, next);
+
+                    self.switch_to(then_block);
+                    self.lower_stmt(&branch.block);
+                    self.jump(end);
+                    self.switch_to(next);
+                }
+
+                self.jump(end);
+                self.switch_to(end);
+            }
+        }
+    }
+
+    fn lower_loop_body(
+        &mut self,
+        block: &TypedStmt,
+        label: &Option<String>,
+        break_block: BlockId,
+        continue_block: BlockId,
+    ) {
+        self.loops.push(LoopTargets {
+            label: label.clone(),
+            break_block,
+            continue_block,
+        });
+        self.lower_stmt(block);
+        self.loops.pop();
+    }
+
+    /// The innermost loop, or the one with the label
+    fn target_loop(&self, label: &Option<String>) -> &LoopTargets {
+        self.loops
+            .iter()
+            .rfind(|lp| label.is_none() || lp.label == *label)
+            .expect("the analyzer rejects `tigil` and `tuloy` outside of a loop")
+    }
+
+    /// Lowers an expression that has a value
+    fn lower_value(&mut self, expr: &TypedExpr) -> Operand {
+        self.lower_expr(expr)
+            .unwrap_or_else(|| unreachable!("Expected a value, found `{}`", expr.ttype))
+    }
+
+    /// Lowers an expression as a value of `ttype`. An array literal is built
+    /// with elements of the type right away instead of being converted
+    /// whole, which C can only do one element at a time.
+    fn lower_value_as(&mut self, expr: &TypedExpr, ttype: &TolType) -> Operand {
+        let value = match &expr.kind {
+            TypedExprKind::ArrayLiteral { elems } if matches!(ttype, TolType::Array { .. }) => {
+                self.lower_array(elems, ttype)
+            }
+            _ => self.lower_value(expr),
+        };
+
+        self.convert(value, ttype)
+    }
+
+    /// Builds an array of the elements, converted to the element type of
+    /// `ttype`
+    fn lower_array(&mut self, elems: &[TypedExpr], ttype: &TolType) -> Operand {
+        let TolType::Array { inner, .. } = ttype else {
+            unreachable!("Array literal of type `{ttype}`")
+        };
+        let elems = elems
+            .iter()
+            .map(|elem| self.lower_value_as(elem, inner))
+            .collect::<Vec<_>>();
+        let ttype = TolType::Array {
+            inner: inner.clone(),
+            size: Some(elems.len()),
+        };
+
+        self.assign(ttype, Rvalue::Array(elems))
+    }
+
+    /// Lowers an expression, returning its value unless it is `void`
+    fn lower_expr(&mut self, expr: &TypedExpr) -> Option<Operand> {
+        let ttype = ir_type(&expr.ttype);
+        let operand = match &expr.kind {
+            TypedExprKind::Integer { .. } => {
+                let value = expr.int_literal_value().unwrap();
+                Operand::Const(ConstValue::Int(value).cast(&ttype), ttype)
+            }
+            TypedExprKind::Float { .. } => {
+                let value = expr.float_literal_value().unwrap();
+                Operand::Const(ConstValue::Float(value).cast(&ttype), ttype)
+            }
+            TypedExprKind::Bool { value } => Operand::Const(ConstValue::Bool(*value), ttype),
+            TypedExprKind::Identifier { symbol_id, .. } => {
+                let place = self.place(*symbol_id, expr.span());
+                self.assign(ttype, Rvalue::Use(place.into()))
+            }
+            TypedExprKind::Binary { left, right, op } => self.lower_binary(left, right, op, ttype),
+            TypedExprKind::Unary { right, op } => {
+                // Negative literals are constants too
+                if *op == TokenKind::Minus
+                    && let Some(Operand::Const(value, _)) = self.literal(right)
+                {
+                    let value = match value {
+                        ConstValue::Int(value) => ConstValue::Int(-value),
+                        ConstValue::Float(value) => ConstValue::Float(-value),
+                        ConstValue::Bool(_) => unreachable!("Negated bool"),
+                    };
+                    return Some(Operand::Const(value.cast(&ttype), ttype));
+                }
+
+                let operand = self.lower_value(right);
+                let operand = self.convert(operand, &ttype);
+                let op = match op {
+                    TokenKind::Minus => UnOp::Neg,
+                    TokenKind::Bang => UnOp::Not,
+                    TokenKind::Tilde => UnOp::BitNot,
+                    _ => unreachable!("Unary `{op:?}`"),
+                };
+                self.assign(ttype, Rvalue::Unary { op, operand })
+            }
+            TypedExprKind::FnCall { callee, args } => {
+                let TypedExprKind::Identifier {
+                    symbol_id: callee, ..
+                } = &callee.kind
+                else {
+                    unreachable!("The analyzer only allows calling a `paraan` by name")
+                };
+                let callee = *callee;
+                let SymbolKind::Func { param_types, .. } = self.symbols[callee].kind() else {
+                    unreachable!("The analyzer only allows calling a `paraan`")
+                };
+
+                let args = args
+                    .iter()
+                    .zip(param_types)
+                    .map(|(arg, param_type)| self.lower_value_as(arg, &ir_type(param_type)))
+                    .collect();
+                self.name(callee);
+
+                if ttype == TolType::Void {
+                    self.push(Inst::Call { callee, args });
+                    return None;
+                }
+                self.assign(ttype, Rvalue::Call { callee, args })
+            }
+            TypedExprKind::ArrayLiteral { elems } => self.lower_array(elems, &ttype),
+            TypedExprKind::Block { stmts, tail } => {
+                for stmt in stmts {
+                    self.lower_stmt(stmt);
+                }
+                let value = self.lower_expr(tail.as_ref()?)?;
+                self.convert(value, &ttype)
+            }
+            TypedExprKind::Kung { branches } => {
+                let result = (ttype != TolType::Void).then(|| self.new_local(ttype.clone()));
+                let end = self.new_block();
+                for arm in branches {
+                    let next = arm.cond.as_ref().map(|cond| {
+                        let cond = self.lower_value(cond);
+                        let then_block = self.new_block();
+                        let next = self.new_block();
+                        self.branch(cond, then_block, next);
+                        self.switch_to(then_block);
+                        next
+                    });
+
+                    let value = self.lower_expr(&arm.value);
+                    if let (Some(result), Some(value)) = (result, value) {
+                        let value = self.convert(value, &ttype);
+                        self.push(Inst::Store {
+                            place: Place::Local(result),
+                            value,
+                        });
+                    }
+                    self.jump(end);
+
+                    match next {
+                        Some(next) => self.switch_to(next),
+                        None => break,
+                    }
+                }
+
+                self.jump(end);
+                self.switch_to(end);
+                self.assign(ttype, Rvalue::Use(Operand::Local(result?)))
+            }
+        };
+
+        Some(operand)
+    }
+
+    fn lower_binary(
+        &mut self,
+        left: &TypedExpr,
+        right: &TypedExpr,
+        op: &TokenKind,
+        ttype: TolType,
+    ) -> Operand {
+        if let Some(arith_op) = Self::assignment_op(op) {
+            let TypedExprKind::Identifier { symbol_id, .. } = &left.kind else {
+                unreachable!("The analyzer only allows assigning to a name")
+            };
+            let place = self.place(*symbol_id, left.span());
+            let place_type = match place {
+                Place::Local(id) => self.function.locals[id].ttype.clone(),
+                // Also covers globals of earlier REPL inputs
+                Place::Global(id) => ir_type(&self.symbols[id].get_type()),
+            };
+
+            let value = match arith_op {
+                Some(arith_op) => {
+                    let current = self.assign(place_type.clone(), Rvalue::Use(place.into()));
+                    let right = self.lower_value(right);
+                    let right = self.convert(right, &place_type);
+                    self.assign(
+                        place_type,
+                        Rvalue::Binary {
+                            op: arith_op,
+                            left: current,
+                            right,
+                        },
+                    )
+                }
+                None => self.lower_value_as(right, &place_type),
+            };
+
+            self.push(Inst::Store {
+                place,
+                value: value.clone(),
+            });
+            return value;
+        }
+
+        if matches!(op, TokenKind::AmperAmper | TokenKind::PipePipe) {
+            return self.lower_short_circuit(left, right, op);
+        }
+
+        let op = Self::bin_op(op);
+        let (left, right) = if op.is_comparison() {
+            let common = left
+                .ttype
+                .coerce(&right.ttype)
+                .unwrap_or_else(|| left.ttype.clone());
+            let common = ir_type(&common);
+            let left = self.lower_value(left);
+            let left = self.convert(left, &common);
+            let right = self.lower_value(right);
+            (left, self.convert(right, &common))
+        } else {
+            let left = self.lower_value(left);
+            let left = self.convert(left, &ttype);
+            let right_value = self.lower_value(right);
+            // Only the value shifted has to have the type of the result
+            let right_type = match op {
+                BinOp::Shl | BinOp::Shr => ir_type(&right.ttype),
+                _ => ttype.clone(),
+            };
+            (left, self.convert(right_value, &right_type))
+        };
+
+        self.assign(ttype, Rvalue::Binary { op, left, right })
+    }
+
+    /// Evaluates the right operand of `&&` and `||` only if the left one
+    /// doesn't decide the result
+    fn lower_short_circuit(
+        &mut self,
+        left: &TypedExpr,
+        right: &TypedExpr,
+        op: &TokenKind,
+    ) -> Operand {
+        let result = self.new_local(TolType::Bool);
+        let left = self.lower_value(left);
+        self.push(Inst::Store {
+            place: Place::Local(result),
+            value: left.clone(),
+        });
+
+        let rhs_block = self.new_block();
+        let end = self.new_block();
+        if *op == TokenKind::AmperAmper {
+            self.branch(left, rhs_block, end);
+        } else {
+            self.branch(left, end, rhs_block);
+        }
+
+        self.switch_to(rhs_block);
+        let right = self.lower_value(right);
+        self.push(Inst::Store {
+            place: Place::Local(result),
+            value: right,
+        });
+        self.jump(end);
+
+        self.switch_to(end);
+        self.assign(TolType::Bool, Rvalue::Use(Operand::Local(result)))
+    }
+
+    /// The operator applied by an assignment, `Some(None)` for plain `=`
+    fn assignment_op(op: &TokenKind) -> Option<Option<BinOp>> {
+        match op {
+            TokenKind::Equal => Some(None),
+            TokenKind::PlusEqual => Some(Some(BinOp::Add)),
+            TokenKind::MinusEqual => Some(Some(BinOp::Sub)),
+            TokenKind::StarEqual => Some(Some(BinOp::Mul)),
+            TokenKind::SlashEqual => Some(Some(BinOp::Div)),
+            _ => None,
+        }
+    }
+
+    fn bin_op(op: &TokenKind) -> BinOp {
+        match op {
+            TokenKind::Plus => BinOp::Add,
+            TokenKind::Minus => BinOp::Sub,
+            TokenKind::Star => BinOp::Mul,
+            TokenKind::Slash => BinOp::Div,
+            TokenKind::Percent => BinOp::Rem,
+            TokenKind::StarStar => BinOp::Pow,
+            TokenKind::Amper => BinOp::BitAnd,
+            TokenKind::Pipe => BinOp::BitOr,
+            TokenKind::Caret => BinOp::BitXor,
+            TokenKind::LessLess => BinOp::Shl,
+            TokenKind::GreaterGreater => BinOp::Shr,
+            TokenKind::EqualEqual => BinOp::Eq,
+            TokenKind::BangEqual => BinOp::Ne,
+            TokenKind::Less => BinOp::Lt,
+            TokenKind::LessEqual => BinOp::Le,
+            TokenKind::Greater => BinOp::Gt,
+            TokenKind::GreaterEqual => BinOp::Ge,
+            _ => unreachable!("Binary operator `{op:?}`"),
+        }
+    }
+
+    /// The constant a literal lowers to, without lowering anything else
+    fn literal(&mut self, expr: &TypedExpr) -> Option<Operand> {
+        match expr.kind {
+            TypedExprKind::Integer { .. } | TypedExprKind::Float { .. } => self.lower_expr(expr),
+            _ => None,
+        }
+    }
+
+    /// Converts a value to `ttype`, casting it unless it already has the type
+    fn convert(&mut self, value: Operand, ttype: &TolType) -> Operand {
+        let from = self.function.operand_type(&value, &self.globals);
+        if same_type(from, ttype) {
+            return value;
+        }
+
+        match value {
+            Operand::Const(value, _) if !matches!(ttype, TolType::Array { .. }) => {
+                Operand::Const(value.cast(ttype), ttype.clone())
+            }
+            value => self.assign(ttype.clone(), Rvalue::Cast(value)),
+        }
+    }
+
+    /// Where a variable lives, locals shadowing globals
+    fn place(&mut self, symbol_id: SymbolId, span: Range<usize>) -> Place {
+        if let Some(local) = self.locals.get(&symbol_id) {
+            return Place::Local(*local);
+        }
+        if matches!(self.symbols[symbol_id].kind(), SymbolKind::Func { .. }) {
+            self.errors.push(CompilerError::UnsupportedInC {
+                what: "`paraan` bilang halaga".to_string(),
+                span: span.into(),
+            });
+        }
+
+        Place::Global(symbol_id)
+    }
+
+    /// Reports an array without a size, which C can't pass around by value
+    fn check_sized(&mut self, ttype: &TolType, span: Range<usize>) {
+        let mut ttype = ttype;
+        while let TolType::Array { inner, size } = ttype {
+            if size.is_none() {
+                self.errors.push(CompilerError::UnsupportedInC {
+                    what: "array na walang haba".to_string(),
+                    span: span.into(),
+                });
+                return;
+            }
+            ttype = inner;
+        }
+    }
+
+    fn declare_local(&mut self, symbol_id: SymbolId) -> LocalId {
+        let symbol = &self.symbols[symbol_id];
+        let ttype = ir_type(&symbol.get_type());
+        self.check_sized(&ttype, symbol.span());
+        self.function.locals.push(Local {
+            name: symbol.name().to_string(),
+            symbol_id: Some(symbol_id),
+            ttype,
+        });
+        let local = self.function.locals.len() - 1;
+        self.locals.insert(symbol_id, local);
+
+        local
+    }
+
+    /// Adds a local that isn't a variable of the program
+    fn new_local(&mut self, ttype: TolType) -> LocalId {
+        self.function.locals.push(Local {
+            name: TEMP_LOCAL_NAME.to_string(),
+            symbol_id: None,
+            ttype,
+        });
+
+        self.function.locals.len() - 1
+    }
+
+    /// Assigns a new temporary, returning it
+    fn assign(&mut self, ttype: TolType, value: Rvalue) -> Operand {
+        self.function.temps.push(ttype);
+        let dest: TempId = self.function.temps.len() - 1;
+        self.push(Inst::Let { dest, value });
+
+        Operand::Temp(dest)
+    }
+
+    fn name(&mut self, symbol_id: SymbolId) {
+        self.names
+            .entry(symbol_id)
+            .or_insert_with(|| self.symbols[symbol_id].name().to_string());
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.function.blocks.push(Block {
+            insts: Vec::new(),
+            terminator: Terminator::Unreachable,
+        });
+
+        self.function.blocks.len() - 1
+    }
+
+    /// Makes new instructions go to a block that was just created
+    fn switch_to(&mut self, block: BlockId) {
+        self.order.push(block);
+        self.current = Some(block);
+    }
+
+    /// The block new instructions go to. Code right after a terminator can't
+    /// be reached, but still goes to a block of its own.
+    fn current_block(&mut self) -> BlockId {
+        match self.current {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.switch_to(block);
+                block
+            }
+        }
+    }
+
+    fn current_insts(&self) -> usize {
+        self.current
+            .map_or(0, |block| self.function.blocks[block].insts.len())
+    }
+
+    /// Marks that the next instructions come from `stmt`. Code that can't be
+    /// reached isn't marked, as that would start a block for it.
+    fn locate(&mut self, stmt: &TypedStmt) {
+        if self.locations && self.current.is_some() {
+            self.push(Inst::Location(stmt.span()));
+        }
+    }
+
+    fn push(&mut self, inst: Inst) {
+        let block = self.current_block();
+        self.function.blocks[block].insts.push(inst);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.current_block();
+        self.function.blocks[block].terminator = terminator;
+        self.current = None;
+    }
+
+    /// Jumps to `target` unless the current block already ended
+    fn jump(&mut self, target: BlockId) {
+        if self.current.is_some() {
+            self.terminate(Terminator::Jump(target));
+        }
+    }
+
+    fn branch(&mut self, cond: Operand, then_block: BlockId, else_block: BlockId) {
+        self.terminate(Terminator::Branch {
+            cond,
+            then_block,
+            else_block,
+        });
+    }
+}
+
+/// Whether values of the types need no conversion. An array without a size
+/// holds any number of elements.
+pub fn same_type(a: &TolType, b: &TolType) -> bool {
+    match (a, b) {
+        (
+            TolType::Array {
+                inner: a,
+                size: a_size,
+            },
+            TolType::Array {
+                inner: b,
+                size: b_size,
+            },
+        ) => same_type(a, b) && (a_size.is_none() || b_size.is_none() || a_size == b_size),
+        (a, b) => a == b,
+    }
+}
@@ -0,0 +1,400 @@
+//! A mid-level representation between the typed AST and C.
+//!
+//! [`lower`] turns every `paraan` into a [`Function`] made of basic blocks.
+//! Each block is a list of [`Inst`]s ending in a single [`Terminator`], so
+//! control flow, including `&&`, `||` and `kung` expressions, is explicit.
+//!
+//! Values are either temporaries or variables. A temporary is assigned once
+//! and only used later in the block that assigns it, which keeps them easy to
+//! move around. Variables, the locals of a function and the globals of the
+//! program, can be stored to any number of times and are what carries a value
+//! from one block to another. Reading a variable copies it into a temporary,
+//! so later stores can't change what was read.
+//!
+//! [`verify`] checks these rules and the types of every instruction. The IR
+//! is printed by `tol emit --stage ir`.
+
+pub mod lower;
+pub mod opt;
+pub mod print;
+#[cfg(test)]
+mod tests;
+pub mod verify;
+
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::{analyzer::SymbolId, analyzer::const_eval::ConstValue, toltype::TolType};
+
+pub type BlockId = usize;
+pub type TempId = usize;
+pub type LocalId = usize;
+
+/// Name of `Module::init` in the printed IR
+pub const INIT_NAME: &str = "<init>";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Const(ConstValue, TolType),
+    Temp(TempId),
+    Local(LocalId),
+    Global(SymbolId),
+}
+
+/// Something that can be stored to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Place {
+    Local(LocalId),
+    Global(SymbolId),
+}
+
+impl From<Place> for Operand {
+    fn from(place: Place) -> Self {
+        match place {
+            Place::Local(id) => Operand::Local(id),
+            Place::Global(id) => Operand::Global(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+/// What a temporary is assigned
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rvalue {
+    Use(Operand),
+    /// Both operands have the type of the result, or the same type for
+    /// comparisons, except for the amount of a shift
+    Binary {
+        op: BinOp,
+        left: Operand,
+        right: Operand,
+    },
+    Unary {
+        op: UnOp,
+        operand: Operand,
+    },
+    /// Converts a number to the type of the temporary, wrapping integers
+    Cast(Operand),
+    Call {
+        callee: SymbolId,
+        args: Vec<Operand>,
+    },
+    Array(Vec<Operand>),
+}
+
+impl Rvalue {
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Rvalue::Use(operand) | Rvalue::Unary { operand, .. } | Rvalue::Cast(operand) => {
+                vec![operand]
+            }
+            Rvalue::Binary { left, right, .. } => vec![left, right],
+            Rvalue::Call { args, .. } | Rvalue::Array(args) => args.iter().collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Rvalue::Use(operand) | Rvalue::Unary { operand, .. } | Rvalue::Cast(operand) => {
+                vec![operand]
+            }
+            Rvalue::Binary { left, right, .. } => vec![left, right],
+            Rvalue::Call { args, .. } | Rvalue::Array(args) => args.iter_mut().collect(),
+        }
+    }
+
+    /// Whether computing the value, of type `ttype`, does more than that: it
+    /// calls a function, or an integer `/`, `%`, `**` or shift that may stop
+    /// the program
+    pub fn has_effects(&self, ttype: &TolType) -> bool {
+        let divisor_ok = |right: &Operand| matches!(right, Operand::Const(ConstValue::Int(value), _) if *value != 0 && *value != -1);
+        let exponent_ok = |right: &Operand| matches!(right, Operand::Const(ConstValue::Int(value), _) if *value >= 0);
+
+        match self {
+            Rvalue::Call { .. } => true,
+            Rvalue::Binary {
+                op: BinOp::Div | BinOp::Rem,
+                right,
+                ..
+            } => ttype.is_integer() && !divisor_ok(right),
+            Rvalue::Binary {
+                op: BinOp::Pow,
+                right,
+                ..
+            } => ttype.is_integer() && !exponent_ok(right),
+            Rvalue::Binary {
+                op: BinOp::Shl | BinOp::Shr,
+                right,
+                ..
+            } => !shift_amount_ok(right, ttype),
+            _ => false,
+        }
+    }
+}
+
+/// Whether `amount` is a constant a `ttype` can be shifted by without
+/// stopping the program
+pub fn shift_amount_ok(amount: &Operand, ttype: &TolType) -> bool {
+    let width = ttype.bit_width().unwrap_or(64);
+    matches!(amount, Operand::Const(ConstValue::Int(value), _) if (0..width as i128).contains(value))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst {
+    /// Assigns a new temporary
+    Let {
+        dest: TempId,
+        value: Rvalue,
+    },
+    Store {
+        place: Place,
+        value: Operand,
+    },
+    /// Calls a function without keeping what it returns
+    Call {
+        callee: SymbolId,
+        args: Vec<Operand>,
+    },
+    /// Marks where in the source the statement the next instructions come
+    /// from is. Only lowered for debug info.
+    Location(Range<usize>),
+}
+
+impl Inst {
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Inst::Let { value, .. } => value.operands(),
+            Inst::Store { value, .. } => vec![value],
+            Inst::Call { args, .. } => args.iter().collect(),
+            Inst::Location(_) => Vec::new(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Inst::Let { value, .. } => value.operands_mut(),
+            Inst::Store { value, .. } => vec![value],
+            Inst::Call { args, .. } => args.iter_mut().collect(),
+            Inst::Location(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        cond: Operand,
+        then_block: BlockId,
+        else_block: BlockId,
+    },
+    Return(Option<Operand>),
+    /// Ends a block that control never reaches the end of, like the last
+    /// block of a `paraan` that returns on every path
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![*then_block, *else_block],
+            Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    pub fn operand(&self) -> Option<&Operand> {
+        match self {
+            Terminator::Branch { cond, .. } => Some(cond),
+            Terminator::Return(value) => value.as_ref(),
+            Terminator::Jump(_) | Terminator::Unreachable => None,
+        }
+    }
+
+    pub fn operand_mut(&mut self) -> Option<&mut Operand> {
+        match self {
+            Terminator::Branch { cond, .. } => Some(cond),
+            Terminator::Return(value) => value.as_mut(),
+            Terminator::Jump(_) | Terminator::Unreachable => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub insts: Vec<Inst>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+    pub name: String,
+    /// `None` for locals the lowering made up
+    pub symbol_id: Option<SymbolId>,
+    pub ttype: TolType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// `None` for `Module::init`
+    pub symbol_id: Option<SymbolId>,
+    /// The first locals, in order
+    pub params: Vec<LocalId>,
+    pub return_type: TolType,
+    pub locals: Vec<Local>,
+    /// Type of every temporary
+    pub temps: Vec<TolType>,
+    /// Control starts at the first block
+    pub blocks: Vec<Block>,
+}
+
+impl Function {
+    pub fn operand_type<'f>(&'f self, operand: &'f Operand, globals: &'f [Global]) -> &'f TolType {
+        match operand {
+            Operand::Const(_, ttype) => ttype,
+            Operand::Temp(id) => &self.temps[*id],
+            Operand::Local(id) => &self.locals[*id].ttype,
+            Operand::Global(id) => global_type(*id, globals),
+        }
+    }
+
+    pub fn place_type<'f>(&'f self, place: &Place, globals: &'f [Global]) -> &'f TolType {
+        match place {
+            Place::Local(id) => &self.locals[*id].ttype,
+            Place::Global(id) => global_type(*id, globals),
+        }
+    }
+
+    /// Keeps only the blocks in `order`, in that order. Every block jumped to
+    /// must be kept.
+    pub fn reorder_blocks(&mut self, order: &[BlockId]) {
+        let mut new_ids = vec![None; self.blocks.len()];
+        for (new_id, old_id) in order.iter().enumerate() {
+            new_ids[*old_id] = Some(new_id);
+        }
+        let new_id = |id: &mut BlockId| {
+            *id = new_ids[*id].unwrap_or_else(|| unreachable!("Jump to removed bb{id}"))
+        };
+
+        let mut blocks = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.blocks = order.iter().map(|id| blocks[*id].take().unwrap()).collect();
+        for block in self.blocks.iter_mut() {
+            match &mut block.terminator {
+                Terminator::Jump(target) => new_id(target),
+                Terminator::Branch {
+                    then_block,
+                    else_block,
+                    ..
+                } => {
+                    new_id(then_block);
+                    new_id(else_block);
+                }
+                Terminator::Return(_) | Terminator::Unreachable => {}
+            }
+        }
+    }
+
+    /// Whether the function has no code but a `ret`
+    pub fn is_empty(&self) -> bool {
+        self.blocks.len() == 1
+            && self.blocks[0]
+                .insts
+                .iter()
+                .all(|inst| matches!(inst, Inst::Location(_)))
+            && self.blocks[0].terminator == Terminator::Return(None)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    pub symbol_id: SymbolId,
+    pub name: String,
+    pub ttype: TolType,
+    pub is_const: bool,
+    /// The value of a global initialized with a constant. Others are stored
+    /// by `Module::init`.
+    pub init: Option<ConstValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub globals: Vec<Global>,
+    pub functions: Vec<Function>,
+    /// Initializes the globals that aren't constants, before `main` runs
+    pub init: Function,
+    /// Names of the functions called, which may not all be in `functions`
+    /// when the program failed to analyze
+    pub names: BTreeMap<SymbolId, String>,
+}
+
+fn global_type(symbol_id: SymbolId, globals: &[Global]) -> &TolType {
+    &globals
+        .iter()
+        .find(|g| g.symbol_id == symbol_id)
+        .unwrap_or_else(|| unreachable!("Unknown global {symbol_id}"))
+        .ttype
+}
+
+impl Module {
+    pub fn global(&self, symbol_id: SymbolId) -> Option<&Global> {
+        self.globals.iter().find(|g| g.symbol_id == symbol_id)
+    }
+
+    /// `init` and then every function
+    pub fn all_functions(&self) -> impl Iterator<Item = &Function> {
+        std::iter::once(&self.init).chain(self.functions.iter())
+    }
+
+    pub fn all_functions_mut(&mut self) -> impl Iterator<Item = &mut Function> {
+        std::iter::once(&mut self.init).chain(self.functions.iter_mut())
+    }
+
+    pub fn function(&self, symbol_id: SymbolId) -> Option<&Function> {
+        self.functions
+            .iter()
+            .find(|f| f.symbol_id == Some(symbol_id))
+    }
+}
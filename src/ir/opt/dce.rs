@@ -0,0 +1,113 @@
+//! Dead code elimination.
+//!
+//! Removes temporaries that are never used, stores to locals that are never
+//! read, and then the locals left with nothing referring to them. A call whose
+//! value is unused is kept as a call, as is an integer division that may stop
+//! the program. Temporaries and locals are renumbered in order afterwards.
+
+use crate::ir::{
+    Function, Inst, Module, Operand, Place, Rvalue,
+    opt::{for_each_operand, rename},
+};
+
+pub fn run(module: &mut Module) {
+    for function in module.all_functions_mut() {
+        while remove_dead_code(function) {}
+        renumber(function);
+    }
+}
+
+/// Removes what is dead now, returning whether anything was. Removing an
+/// instruction can leave what it used dead too.
+fn remove_dead_code(function: &mut Function) -> bool {
+    let mut temp_uses = vec![0; function.temps.len()];
+    let mut local_reads = vec![false; function.locals.len()];
+    for_each_operand(function, |operand| match operand {
+        Operand::Temp(id) => temp_uses[*id] += 1,
+        Operand::Local(id) => local_reads[*id] = true,
+        Operand::Const(..) | Operand::Global(_) => {}
+    });
+
+    let mut changed = false;
+    for block in function.blocks.iter_mut() {
+        let insts = std::mem::take(&mut block.insts);
+        for inst in insts {
+            match inst {
+                Inst::Let {
+                    dest,
+                    value: Rvalue::Call { callee, args },
+                } if temp_uses[dest] == 0 => {
+                    changed = true;
+                    block.insts.push(Inst::Call { callee, args });
+                }
+                Inst::Let { dest, value }
+                    if temp_uses[dest] == 0 && !value.has_effects(&function.temps[dest]) =>
+                {
+                    changed = true;
+                }
+                Inst::Store {
+                    place: Place::Local(id),
+                    ..
+                } if !local_reads[id] => changed = true,
+                inst => block.insts.push(inst),
+            }
+        }
+    }
+
+    changed
+}
+
+/// Drops the locals nothing refers to, other than the parameters, and
+/// numbers the locals and temporaries left from zero
+fn renumber(function: &mut Function) {
+    let mut local_used = vec![false; function.locals.len()];
+    let mut temp_defined = vec![false; function.temps.len()];
+    for id in function.params.iter() {
+        local_used[*id] = true;
+    }
+    for_each_operand(function, |operand| {
+        if let Operand::Local(id) = operand {
+            local_used[*id] = true;
+        }
+    });
+    for inst in function.blocks.iter().flat_map(|block| block.insts.iter()) {
+        match inst {
+            Inst::Let { dest, .. } => temp_defined[*dest] = true,
+            Inst::Store {
+                place: Place::Local(id),
+                ..
+            } => local_used[*id] = true,
+            Inst::Store { .. } | Inst::Call { .. } | Inst::Location(_) => {}
+        }
+    }
+
+    let local_ids = new_ids(&local_used);
+    let temp_ids = new_ids(&temp_defined);
+    rename(function, &|id| local_ids[id].unwrap(), &|id| {
+        temp_ids[id].unwrap()
+    });
+
+    let mut locals = std::mem::take(&mut function.locals).into_iter();
+    function.locals = local_used
+        .iter()
+        .filter_map(|used| locals.next().filter(|_| *used))
+        .collect();
+    let mut temps = std::mem::take(&mut function.temps).into_iter();
+    function.temps = temp_defined
+        .iter()
+        .filter_map(|defined| temps.next().filter(|_| *defined))
+        .collect();
+}
+
+/// The new ids of the items kept, in order
+fn new_ids(kept: &[bool]) -> Vec<Option<usize>> {
+    let mut next = 0;
+    kept.iter()
+        .map(|kept| {
+            kept.then(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
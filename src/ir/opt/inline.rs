@@ -0,0 +1,118 @@
+//! Copies the body of small `paraan`s into their callers.
+//!
+//! Only a `paraan` made of a single block ending in `ibalik`, with at most
+//! [`MAX_INSTS`] instructions, is inlined, so the call is replaced without
+//! splitting the caller's block. Its parameters and locals become locals of
+//! the caller, which the other passes usually remove. Inlined bodies aren't
+//! inlined into again, so recursion stops after one level.
+
+use std::collections::HashMap;
+
+use crate::ir::{
+    Function, Inst, Module, Operand, Place, Rvalue, TempId, Terminator,
+    opt::{rename_inst, rename_operand},
+};
+
+/// Instructions a `paraan` may have to be inlined
+const MAX_INSTS: usize = 8;
+
+pub fn run(module: &mut Module) {
+    let inlinable = module
+        .functions
+        .iter()
+        .filter(|function| is_inlinable(function))
+        .filter_map(|function| Some((function.symbol_id?, function.clone())))
+        .collect::<HashMap<_, _>>();
+
+    for function in module.all_functions_mut() {
+        for block_id in 0..function.blocks.len() {
+            let insts = std::mem::take(&mut function.blocks[block_id].insts);
+            let mut new_insts = Vec::new();
+            for inst in insts {
+                let (callee, args, dest) = match inst {
+                    Inst::Let {
+                        dest,
+                        value: Rvalue::Call { callee, args },
+                    } => (callee, args, Some(dest)),
+                    Inst::Call { callee, args } => (callee, args, None),
+                    inst => {
+                        new_insts.push(inst);
+                        continue;
+                    }
+                };
+
+                match inlinable.get(&callee) {
+                    Some(body) if function.symbol_id != Some(callee) => {
+                        inline(function, body, args, dest, &mut new_insts)
+                    }
+                    _ => new_insts.push(match dest {
+                        Some(dest) => Inst::Let {
+                            dest,
+                            value: Rvalue::Call { callee, args },
+                        },
+                        None => Inst::Call { callee, args },
+                    }),
+                }
+            }
+            function.blocks[block_id].insts = new_insts;
+        }
+    }
+}
+
+fn is_inlinable(function: &Function) -> bool {
+    let [block] = function.blocks.as_slice() else {
+        return false;
+    };
+
+    let insts = block
+        .insts
+        .iter()
+        .filter(|inst| !matches!(inst, Inst::Location(_)))
+        .count();
+    matches!(block.terminator, Terminator::Return(_)) && insts <= MAX_INSTS
+}
+
+/// Pushes the body of `callee` called with `args`, assigning what it returns
+/// to `dest`
+fn inline(
+    function: &mut Function,
+    callee: &Function,
+    args: Vec<Operand>,
+    dest: Option<TempId>,
+    insts: &mut Vec<Inst>,
+) {
+    let local_base = function.locals.len();
+    let temp_base = function.temps.len();
+    function.locals.extend(callee.locals.iter().cloned());
+    function.temps.extend(callee.temps.iter().cloned());
+    let local = |id| id + local_base;
+    let temp = |id| id + temp_base;
+
+    for (param, arg) in callee.params.iter().zip(args) {
+        insts.push(Inst::Store {
+            place: Place::Local(local(*param)),
+            value: arg,
+        });
+    }
+
+    // The inlined code is attributed to the call
+    let block = &callee.blocks[0];
+    let body = block
+        .insts
+        .iter()
+        .filter(|inst| !matches!(inst, Inst::Location(_)));
+    for inst in body {
+        let mut inst = inst.clone();
+        rename_inst(&mut inst, &local, &temp);
+        insts.push(inst);
+    }
+
+    if let (Terminator::Return(Some(value)), Some(dest)) = (&block.terminator, dest) {
+        let mut value = value.clone();
+        rename_operand(&mut value, &local, &temp);
+        insts.push(Inst::Let {
+            dest,
+            value: Rvalue::Use(value),
+        });
+    }
+}
@@ -0,0 +1,161 @@
+//! Optimizations over the IR, run by `-O`.
+//!
+//! Every pass can also be turned on or off on its own, with `-Z pass=NAME`
+//! and `-Z pass=-NAME`. The passes run in the order of [`Pass::ALL`], and each
+//! leaves a module that still verifies.
+
+mod dce;
+mod inline;
+mod propagate;
+mod unreachable;
+
+#[cfg(test)]
+mod tests;
+
+use crate::ir::{Function, Inst, LocalId, Module, Operand, Place, TempId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    /// Copies the body of small `paraan`s into their callers
+    Inline,
+    /// Constant and copy propagation, folding operations on constants
+    Propagate,
+    /// Removes blocks that control never reaches
+    UnreachableBlocks,
+    /// Dead code elimination
+    Dce,
+}
+
+impl Pass {
+    /// Every pass, in the order they run
+    pub const ALL: [Pass; 4] = [
+        Pass::Inline,
+        Pass::Propagate,
+        Pass::UnreachableBlocks,
+        Pass::Dce,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Inline => "inline",
+            Pass::Propagate => "propagate",
+            Pass::UnreachableBlocks => "unreachable_blocks",
+            Pass::Dce => "dce",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+
+    pub fn run(&self, module: &mut Module) {
+        match self {
+            Pass::Inline => inline::run(module),
+            Pass::Propagate => propagate::run(module),
+            Pass::UnreachableBlocks => unreachable::run(module),
+            Pass::Dce => dce::run(module),
+        }
+    }
+}
+
+/// A `-Z pass=NAME` or `-Z pass=-NAME` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassToggle {
+    pub pass: Pass,
+    pub enabled: bool,
+}
+
+impl PassToggle {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let Some(name) = s.strip_prefix("pass=") else {
+            return Err(format!(
+                "hindi kilalang opsyon `{s}`, `pass=PANGALAN` o `pass=-PANGALAN` lamang"
+            ));
+        };
+        let (name, enabled) = match name.strip_prefix('-') {
+            Some(name) => (name, false),
+            None => (name, true),
+        };
+
+        Pass::from_name(name)
+            .map(|pass| PassToggle { pass, enabled })
+            .ok_or_else(|| {
+                let names = Pass::ALL
+                    .iter()
+                    .map(|pass| pass.name())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("hindi kilalang pass `{name}`, pagpipilian: {names}")
+            })
+    }
+}
+
+/// The passes to run, in the order they run. `-O` turns every pass on before
+/// the toggles apply, in order.
+pub fn select_passes(optimize: bool, toggles: &[PassToggle]) -> Vec<Pass> {
+    Pass::ALL
+        .into_iter()
+        .filter(|pass| {
+            toggles
+                .iter()
+                .rfind(|toggle| toggle.pass == *pass)
+                .map_or(optimize, |toggle| toggle.enabled)
+        })
+        .collect()
+}
+
+/// Every operand read by a function
+fn for_each_operand(function: &Function, mut f: impl FnMut(&Operand)) {
+    for block in function.blocks.iter() {
+        for inst in block.insts.iter() {
+            inst.operands().into_iter().for_each(&mut f);
+        }
+        block.terminator.operand().into_iter().for_each(&mut f);
+    }
+}
+
+/// Renames the locals and temporaries of a function
+fn rename(
+    function: &mut Function,
+    local: &impl Fn(LocalId) -> LocalId,
+    temp: &impl Fn(TempId) -> TempId,
+) {
+    for block in function.blocks.iter_mut() {
+        for inst in block.insts.iter_mut() {
+            rename_inst(inst, local, temp);
+        }
+        if let Some(operand) = block.terminator.operand_mut() {
+            rename_operand(operand, local, temp);
+        }
+    }
+}
+
+fn rename_inst(
+    inst: &mut Inst,
+    local: &impl Fn(LocalId) -> LocalId,
+    temp: &impl Fn(TempId) -> TempId,
+) {
+    match inst {
+        Inst::Let { dest, .. } => *dest = temp(*dest),
+        Inst::Store {
+            place: Place::Local(id),
+            ..
+        } => *id = local(*id),
+        Inst::Store { .. } | Inst::Call { .. } | Inst::Location(_) => {}
+    }
+    for operand in inst.operands_mut() {
+        rename_operand(operand, local, temp);
+    }
+}
+
+fn rename_operand(
+    operand: &mut Operand,
+    local: &impl Fn(LocalId) -> LocalId,
+    temp: &impl Fn(TempId) -> TempId,
+) {
+    match operand {
+        Operand::Local(id) => *id = local(*id),
+        Operand::Temp(id) => *id = temp(*id),
+        Operand::Const(..) | Operand::Global(_) => {}
+    }
+}
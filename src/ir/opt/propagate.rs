@@ -0,0 +1,314 @@
+//! Constant and copy propagation.
+//!
+//! Temporaries holding a constant or a copy of another temporary are
+//! replaced by what they hold, operations on constants are folded, and a read
+//! of a variable gets what was last stored to or read from it in the block.
+//! Constants stored to locals are followed from block to block as long as
+//! every way into a block agrees on them, and a branch on a constant becomes
+//! a jump. Folding wraps integers like the running program does, and leaves
+//! alone what would stop it, like dividing by zero.
+
+use std::collections::HashMap;
+
+use crate::{
+    analyzer::{SymbolId, const_eval::ConstValue},
+    ir::{BinOp, Block, Function, Inst, LocalId, Module, Operand, Place, Rvalue, Terminator, UnOp},
+    toltype::TolType,
+};
+
+/// Constants held by locals when a block starts
+type Known = HashMap<LocalId, Operand>;
+
+pub fn run(module: &mut Module) {
+    // `dapat`s are never stored to, so they hold their initial value
+    let consts = module
+        .globals
+        .iter()
+        .filter(|global| global.is_const)
+        .filter_map(|global| {
+            let value = global.init.clone()?;
+            Some((
+                global.symbol_id,
+                Operand::Const(value, global.ttype.clone()),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+
+    for function in module.all_functions_mut() {
+        let entry = entry_states(function, &consts);
+        for (block, known) in function.blocks.iter_mut().zip(entry) {
+            propagate_block(block, &function.temps, known.unwrap_or_default(), &consts);
+        }
+    }
+}
+
+/// What every block starts knowing, `None` for blocks control never reaches
+fn entry_states(function: &Function, consts: &HashMap<SymbolId, Operand>) -> Vec<Option<Known>> {
+    let mut entry: Vec<Option<Known>> = vec![None; function.blocks.len()];
+    entry[0] = Some(Known::new());
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in 0..function.blocks.len() {
+            let Some(known) = entry[id].clone() else {
+                continue;
+            };
+            let mut block = function.blocks[id].clone();
+            let exit = propagate_block(&mut block, &function.temps, known, consts);
+
+            for successor in block.terminator.successors() {
+                let met = match &entry[successor] {
+                    Some(known) => known
+                        .iter()
+                        .filter(|(local, value)| exit.get(local) == Some(value))
+                        .map(|(local, value)| (*local, value.clone()))
+                        .collect(),
+                    None => exit.clone(),
+                };
+                if entry[successor].as_ref() != Some(&met) {
+                    entry[successor] = Some(met);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    entry
+}
+
+/// Propagates through a block starting with `known`, returning the
+/// constants its locals hold at the end
+fn propagate_block(
+    block: &mut Block,
+    temps: &[TolType],
+    known: Known,
+    consts: &HashMap<SymbolId, Operand>,
+) -> Known {
+    // What a temporary was replaced by
+    let mut replaced = HashMap::new();
+    // What a variable holds, a constant or a temporary of this block
+    let mut places = known
+        .into_iter()
+        .map(|(local, value)| (Place::Local(local), value))
+        .collect::<HashMap<_, _>>();
+
+    for inst in block.insts.iter_mut() {
+        for operand in inst.operands_mut() {
+            if let Operand::Temp(id) = operand
+                && let Some(value) = replaced.get(id)
+            {
+                *operand = Operand::clone(value);
+            }
+        }
+
+        match inst {
+            Inst::Let { dest, value } => {
+                let ttype = &temps[*dest];
+                if let Some(simple) = simplify(value, ttype, &places, consts) {
+                    replaced.insert(*dest, simple.clone());
+                    *value = Rvalue::Use(simple);
+                }
+                match value {
+                    // Reading the variable again gives the same value
+                    Rvalue::Use(Operand::Local(id)) => {
+                        places.insert(Place::Local(*id), Operand::Temp(*dest));
+                    }
+                    Rvalue::Use(Operand::Global(id)) => {
+                        places.insert(Place::Global(*id), Operand::Temp(*dest));
+                    }
+                    Rvalue::Call { .. } => forget_globals(&mut places),
+                    _ => {}
+                }
+            }
+            Inst::Store { place, value } => match value {
+                Operand::Const(..) | Operand::Temp(_) => {
+                    places.insert(*place, value.clone());
+                }
+                Operand::Local(_) | Operand::Global(_) => {
+                    places.remove(place);
+                }
+            },
+            Inst::Call { .. } => forget_globals(&mut places),
+            Inst::Location(_) => {}
+        }
+    }
+
+    if let Some(Operand::Temp(id)) = block.terminator.operand_mut()
+        && let Some(value) = replaced.get(id)
+    {
+        *block.terminator.operand_mut().unwrap() = value.clone();
+    }
+    if let Terminator::Branch {
+        cond: Operand::Const(ConstValue::Bool(cond), _),
+        then_block,
+        else_block,
+    } = block.terminator
+    {
+        let target = if cond { then_block } else { else_block };
+        block.terminator = Terminator::Jump(target);
+    }
+
+    places
+        .into_iter()
+        .filter_map(|(place, value)| match (place, value) {
+            (Place::Local(local), value @ Operand::Const(..)) => Some((local, value)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A call may store to any global
+fn forget_globals(places: &mut HashMap<Place, Operand>) {
+    places.retain(|place, _| matches!(place, Place::Local(_)));
+}
+
+/// The operand an rvalue of type `ttype` comes down to, if it is a
+/// constant or a copy
+fn simplify(
+    value: &Rvalue,
+    ttype: &TolType,
+    places: &HashMap<Place, Operand>,
+    consts: &HashMap<SymbolId, Operand>,
+) -> Option<Operand> {
+    let constant = |value: ConstValue| Some(Operand::Const(value, ttype.clone()));
+
+    match value {
+        Rvalue::Use(Operand::Local(id)) => places.get(&Place::Local(*id)).cloned(),
+        Rvalue::Use(Operand::Global(id)) => places
+            .get(&Place::Global(*id))
+            .or_else(|| consts.get(id))
+            .cloned(),
+        Rvalue::Use(operand) => Some(operand.clone()),
+        Rvalue::Cast(Operand::Const(value, _)) => constant(fold_cast(value, ttype)?),
+        Rvalue::Unary {
+            op,
+            operand: Operand::Const(value, _),
+        } => constant(fold_unary(*op, value, ttype)?),
+        Rvalue::Binary {
+            op,
+            left: Operand::Const(left, _),
+            right: Operand::Const(right, _),
+        } => constant(fold_binary(*op, left, right, ttype)?),
+        _ => None,
+    }
+}
+
+fn fold_cast(value: &ConstValue, ttype: &TolType) -> Option<ConstValue> {
+    match (value, ttype) {
+        (_, TolType::Array { .. }) => None,
+        // Converting a float that doesn't fit is undefined in C
+        (ConstValue::Float(float), ttype) if ttype.is_integer() => {
+            let int = float.trunc();
+            (int.is_finite() && ConstValue::Int(int as i128).convert(ttype).is_ok())
+                .then_some(ConstValue::Int(int as i128))
+        }
+        (value, ttype) => Some(value.clone().cast(ttype)),
+    }
+}
+
+fn fold_unary(op: UnOp, value: &ConstValue, ttype: &TolType) -> Option<ConstValue> {
+    let value = match (op, value) {
+        (UnOp::Neg, ConstValue::Int(value)) => ConstValue::Int(-value),
+        (UnOp::Neg, ConstValue::Float(value)) => ConstValue::Float(-value),
+        (UnOp::Not, ConstValue::Bool(value)) => ConstValue::Bool(!value),
+        (UnOp::BitNot, ConstValue::Int(value)) => ConstValue::Int(!value),
+        _ => return None,
+    };
+
+    Some(value.cast(ttype))
+}
+
+fn fold_binary(
+    op: BinOp,
+    left: &ConstValue,
+    right: &ConstValue,
+    ttype: &TolType,
+) -> Option<ConstValue> {
+    use ConstValue::*;
+
+    let value = match (left, right) {
+        (Int(l), Int(r)) => match op {
+            BinOp::Eq => Bool(l == r),
+            BinOp::Ne => Bool(l != r),
+            BinOp::Lt => Bool(l < r),
+            BinOp::Le => Bool(l <= r),
+            BinOp::Gt => Bool(l > r),
+            BinOp::Ge => Bool(l >= r),
+            _ => Int(fold_int(op, *l, *r, ttype)?).cast(ttype),
+        },
+        (Float(l), Float(r)) => match op {
+            BinOp::Eq => Bool(l == r),
+            BinOp::Ne => Bool(l != r),
+            BinOp::Lt => Bool(l < r),
+            BinOp::Le => Bool(l <= r),
+            BinOp::Gt => Bool(l > r),
+            BinOp::Ge => Bool(l >= r),
+            BinOp::Add => Float(l + r).cast(ttype),
+            BinOp::Sub => Float(l - r).cast(ttype),
+            BinOp::Mul => Float(l * r).cast(ttype),
+            BinOp::Div => Float(l / r).cast(ttype),
+            BinOp::Rem => Float(l % r).cast(ttype),
+            // `powf` may round differently than `pow` computed in `f64`
+            BinOp::Pow if *ttype == TolType::F64 => Float(l.powf(*r)),
+            _ => return None,
+        },
+        (Bool(l), Bool(r)) => match op {
+            BinOp::Eq => Bool(l == r),
+            BinOp::Ne => Bool(l != r),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Integer arithmetic, wrapped to `ttype` by the caller. `None` for what
+/// stops the program or is undefined in C.
+fn fold_int(op: BinOp, l: i128, r: i128, ttype: &TolType) -> Option<i128> {
+    let wrap = |value: i128| match ConstValue::Int(value).cast(ttype) {
+        ConstValue::Int(value) => value,
+        _ => unreachable!("Integer cast to a float"),
+    };
+
+    let value = match op {
+        BinOp::Add => l.wrapping_add(r),
+        BinOp::Sub => l.wrapping_sub(r),
+        BinOp::Mul => l.wrapping_mul(r),
+        // Dividing by -1 overflows for the smallest value
+        BinOp::Div | BinOp::Rem if r == 0 || r == -1 => return None,
+        BinOp::Div => l / r,
+        BinOp::Rem => l % r,
+        BinOp::Pow if r < 0 => return None,
+        BinOp::Pow => {
+            let (mut base, mut exp, mut result) = (l, r, 1i128);
+            while exp > 0 {
+                if exp & 1 == 1 {
+                    result = wrap(result.wrapping_mul(base));
+                }
+                base = wrap(base.wrapping_mul(base));
+                exp >>= 1;
+            }
+            result
+        }
+        BinOp::BitAnd => l & r,
+        BinOp::BitOr => l | r,
+        BinOp::BitXor => l ^ r,
+        BinOp::Shl | BinOp::Shr => {
+            let amount = u32::try_from(r)
+                .ok()
+                .filter(|amount| *amount < ttype.bit_width().unwrap_or(64))?;
+            if op == BinOp::Shl {
+                l.wrapping_shl(amount)
+            } else {
+                l >> amount
+            }
+        }
+        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+            unreachable!("Comparison folded as arithmetic")
+        }
+    };
+
+    Some(value)
+}
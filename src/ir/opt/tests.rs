@@ -0,0 +1,328 @@
+use crate::ir::{
+    opt::{Pass, PassToggle, select_passes},
+    tests::{PROGRAM, lower_source},
+    verify::verify,
+};
+
+/// Runs `passes` over the lowered `source`, checking the IR before and after
+fn assert_passes(passes: &[Pass], source: &str, before: &str, after: &str) {
+    let mut module = lower_source(source);
+    assert_eq!(module.to_string(), before);
+
+    for pass in passes {
+        pass.run(&mut module);
+        if let Err(e) = verify(&module) {
+            panic!("`{}` broke the IR: {e}\n{module}", pass.name());
+        }
+    }
+    assert_eq!(module.to_string(), after);
+}
+
+#[test]
+fn inlines_small_paraan() {
+    let source = "\
+paraan doble(n na i32) -> i32:
+    ibalik n * 2
+
+paraan main() -> i32:
+    ibalik doble(21)
+";
+    let doble = "\
+fn @<init>() -> void {
+bb0:
+    ret
+}
+
+fn @doble($n: i32) -> i32 {
+bb0:
+    %0: i32 = copy $n
+    %1: i32 = mul %0, 2
+    ret %1
+}
+";
+
+    assert_passes(
+        &[Pass::Inline],
+        source,
+        &format!(
+            "{doble}
+fn @main() -> i32 {{
+bb0:
+    %0: i32 = call @doble(21)
+    ret %0
+}}
+"
+        ),
+        &format!(
+            "{doble}
+fn @main() -> i32 {{
+    let $n: i32
+bb0:
+    store $n, 21
+    %1: i32 = copy $n
+    %2: i32 = mul %1, 2
+    %0: i32 = copy %2
+    ret %0
+}}
+"
+        ),
+    );
+}
+
+#[test]
+fn propagates_and_folds_constants() {
+    let source = "\
+dapat HANGGAN na u8 = 250
+
+paraan main() -> u8:
+    ang x na u8 = HANGGAN + 10
+    ang wala na u8 = 0
+    kung x < 5:
+        ibalik x / wala
+    ibalik x
+";
+    let header = "\
+const @HANGGAN: u8 = 250
+
+fn @<init>() -> void {
+bb0:
+    ret
+}
+
+fn @main() -> u8 {
+    let $x: u8
+    let $wala: u8
+";
+
+    // The sum wraps, and the division by zero is left for the running
+    // program to stop at
+    assert_passes(
+        &[Pass::Propagate],
+        source,
+        &format!(
+            "{header}\
+bb0:
+    %0: u8 = copy @HANGGAN
+    %1: u8 = add %0, 10
+    store $x, %1
+    store $wala, 0
+    %2: u8 = copy $x
+    %3: bool = lt %2, 5
+    br %3, bb1, bb2
+bb1:
+    %4: u8 = copy $x
+    %5: u8 = copy $wala
+    %6: u8 = div %4, %5
+    ret %6
+bb2:
+    jump bb3
+bb3:
+    %7: u8 = copy $x
+    ret %7
+}}
+"
+        ),
+        &format!(
+            "{header}\
+bb0:
+    %0: u8 = copy 250
+    %1: u8 = copy 4
+    store $x, 4
+    store $wala, 0
+    %2: u8 = copy 4
+    %3: bool = copy true
+    jump bb1
+bb1:
+    %4: u8 = copy 4
+    %5: u8 = copy 0
+    %6: u8 = div 4, 0
+    ret %6
+bb2:
+    jump bb3
+bb3:
+    %7: u8 = copy $x
+    ret %7
+}}
+"
+        ),
+    );
+}
+
+#[test]
+fn removes_unreachable_blocks() {
+    let source = "\
+paraan main() -> i32:
+    ibalik 1
+    ibalik 0
+";
+
+    assert_passes(
+        &[Pass::UnreachableBlocks],
+        source,
+        "\
+fn @<init>() -> void {
+bb0:
+    ret
+}
+
+fn @main() -> i32 {
+bb0:
+    ret 1
+bb1:
+    ret 0
+}
+",
+        "\
+fn @<init>() -> void {
+bb0:
+    ret
+}
+
+fn @main() -> i32 {
+bb0:
+    ret 1
+}
+",
+    );
+}
+
+#[test]
+fn removes_dead_code_but_keeps_calls() {
+    let source = "\
+ang bilang na i32 = 0
+
+paraan tawag() -> i32:
+    bilang += 1
+    ibalik bilang
+
+paraan main() -> i32:
+    ang hindi_gamit na i32 = 5 * 2
+    ang x na i32 = tawag()
+    ibalik 0
+";
+    let tawag = "\
+global @bilang: i32 = 0
+
+fn @<init>() -> void {
+bb0:
+    ret
+}
+
+fn @tawag() -> i32 {
+bb0:
+    %0: i32 = copy @bilang
+    %1: i32 = add %0, 1
+    store @bilang, %1
+    %2: i32 = copy @bilang
+    ret %2
+}
+";
+
+    assert_passes(
+        &[Pass::Dce],
+        source,
+        &format!(
+            "{tawag}
+fn @main() -> i32 {{
+    let $hindi_gamit: i32
+    let $x: i32
+bb0:
+    %0: i64 = mul 5, 2
+    %1: i32 = cast %0
+    store $hindi_gamit, %1
+    %2: i32 = call @tawag()
+    store $x, %2
+    ret 0
+}}
+"
+        ),
+        &format!(
+            "{tawag}
+fn @main() -> i32 {{
+bb0:
+    call @tawag()
+    ret 0
+}}
+"
+        ),
+    );
+}
+
+#[test]
+fn keeps_shifts_that_stop_the_program() {
+    let source = "\
+paraan main() -> i32:
+    ang s na i32 = 33
+    ang isa na i32 = 1
+    ang x na i32 = isa << s
+    ibalik 0
+";
+    let init = "\
+fn @<init>() -> void {
+bb0:
+    ret
+}
+";
+
+    // Shifting an `i32` by 33 stops the program, so it is neither folded nor
+    // removed once its value is unused
+    assert_passes(
+        &[Pass::Propagate, Pass::Dce],
+        source,
+        &format!(
+            "{init}
+fn @main() -> i32 {{
+    let $s: i32
+    let $isa: i32
+    let $x: i32
+bb0:
+    store $s, 33
+    store $isa, 1
+    %0: i32 = copy $isa
+    %1: i32 = copy $s
+    %2: i32 = shl %0, %1
+    store $x, %2
+    ret 0
+}}
+"
+        ),
+        &format!(
+            "{init}
+fn @main() -> i32 {{
+bb0:
+    %0: i32 = shl 1, 33
+    ret 0
+}}
+"
+        ),
+    );
+}
+
+#[test]
+fn keeps_ir_valid_through_every_pass() {
+    let mut module = lower_source(PROGRAM);
+    for pass in Pass::ALL {
+        pass.run(&mut module);
+        if let Err(e) = verify(&module) {
+            panic!("`{}` broke the IR: {e}\n{module}", pass.name());
+        }
+    }
+}
+
+#[test]
+fn selects_passes_with_toggles() {
+    let toggle = |s| PassToggle::parse(s).unwrap();
+
+    assert_eq!(select_passes(true, &[]), Pass::ALL);
+    assert_eq!(select_passes(false, &[]), []);
+    assert_eq!(
+        select_passes(true, &[toggle("pass=-inline"), toggle("pass=-dce")]),
+        [Pass::Propagate, Pass::UnreachableBlocks]
+    );
+    assert_eq!(
+        select_passes(false, &[toggle("pass=-dce"), toggle("pass=dce")]),
+        [Pass::Dce]
+    );
+    assert!(PassToggle::parse("pass=wala").is_err());
+    assert!(PassToggle::parse("inline").is_err());
+}
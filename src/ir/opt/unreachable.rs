@@ -0,0 +1,27 @@
+//! Removes the blocks that control never reaches, like the code after an
+//! `ibalik` or a branch that constant propagation decided.
+
+use crate::ir::{Function, Module};
+
+pub fn run(module: &mut Module) {
+    for function in module.all_functions_mut() {
+        remove_unreachable(function);
+    }
+}
+
+fn remove_unreachable(function: &mut Function) {
+    let mut reachable = vec![false; function.blocks.len()];
+    let mut stack = vec![0];
+    while let Some(id) = stack.pop() {
+        if reachable[id] {
+            continue;
+        }
+        reachable[id] = true;
+        stack.extend(function.blocks[id].terminator.successors());
+    }
+
+    let order = (0..function.blocks.len())
+        .filter(|id| reachable[*id])
+        .collect::<Vec<_>>();
+    function.reorder_blocks(&order);
+}
@@ -0,0 +1,229 @@
+//! Prints a [`Module`] for `tol emit --stage ir`.
+//!
+//! Temporaries are printed as `%0`, locals as `$name` and globals and
+//! functions as `@name`. Locals sharing a name, because of shadowing, get
+//! their index appended, as in `$x.3`.
+
+use std::fmt;
+
+use crate::{
+    analyzer::const_eval::ConstValue,
+    ir::{BinOp, Function, Global, Inst, LocalId, Module, Operand, Rvalue, Terminator, UnOp},
+};
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "mul",
+            BinOp::Div => "div",
+            BinOp::Rem => "rem",
+            BinOp::Pow => "pow",
+            BinOp::BitAnd => "and",
+            BinOp::BitOr => "or",
+            BinOp::BitXor => "xor",
+            BinOp::Shl => "shl",
+            BinOp::Shr => "shr",
+            BinOp::Eq => "eq",
+            BinOp::Ne => "ne",
+            BinOp::Lt => "lt",
+            BinOp::Le => "le",
+            BinOp::Gt => "gt",
+            BinOp::Ge => "ge",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl fmt::Display for UnOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UnOp::Neg => "neg",
+            UnOp::Not => "not",
+            UnOp::BitNot => "bitnot",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Int(value) => write!(f, "{value}"),
+            ConstValue::Float(value) => write!(f, "{value:?}"),
+            ConstValue::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for global in self.globals.iter() {
+            self.fmt_global(f, global)?;
+        }
+        if !self.globals.is_empty() {
+            writeln!(f)?;
+        }
+
+        self.fmt_function(f, &self.init)?;
+        for function in self.functions.iter() {
+            writeln!(f)?;
+            self.fmt_function(f, function)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Module {
+    fn fmt_global(&self, f: &mut fmt::Formatter<'_>, global: &Global) -> fmt::Result {
+        let keyword = if global.is_const { "const" } else { "global" };
+        write!(f, "{keyword} @{}: {}", global.name, global.ttype)?;
+        if let Some(init) = &global.init {
+            write!(f, " = {init}")?;
+        }
+        writeln!(f)
+    }
+
+    fn fmt_function(&self, f: &mut fmt::Formatter<'_>, function: &Function) -> fmt::Result {
+        let params = function
+            .params
+            .iter()
+            .map(|id| format!("{}: {}", local(function, *id), function.locals[*id].ttype))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            f,
+            "fn @{}({params}) -> {} {{",
+            function.name, function.return_type
+        )?;
+        for id in function.params.len()..function.locals.len() {
+            writeln!(
+                f,
+                "    let {}: {}",
+                local(function, id),
+                function.locals[id].ttype
+            )?;
+        }
+
+        for (id, block) in function.blocks.iter().enumerate() {
+            writeln!(f, "bb{id}:")?;
+            for inst in block.insts.iter() {
+                write!(f, "    ")?;
+                self.fmt_inst(f, function, inst)?;
+                writeln!(f)?;
+            }
+            write!(f, "    ")?;
+            self.fmt_terminator(f, function, &block.terminator)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "}}")
+    }
+
+    fn fmt_inst(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        function: &Function,
+        inst: &Inst,
+    ) -> fmt::Result {
+        match inst {
+            Inst::Let { dest, value } => {
+                write!(f, "%{dest}: {} = ", function.temps[*dest])?;
+                self.fmt_rvalue(f, function, value)
+            }
+            Inst::Store { place, value } => write!(
+                f,
+                "store {}, {}",
+                self.operand(function, &(*place).into()),
+                self.operand(function, value)
+            ),
+            Inst::Call { callee, args } => {
+                write!(f, "call {}", self.call(function, *callee, args))
+            }
+            Inst::Location(span) => write!(f, "loc {}..{}", span.start, span.end),
+        }
+    }
+
+    fn fmt_rvalue(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        function: &Function,
+        value: &Rvalue,
+    ) -> fmt::Result {
+        match value {
+            Rvalue::Use(operand) => write!(f, "copy {}", self.operand(function, operand)),
+            Rvalue::Binary { op, left, right } => write!(
+                f,
+                "{op} {}, {}",
+                self.operand(function, left),
+                self.operand(function, right)
+            ),
+            Rvalue::Unary { op, operand } => write!(f, "{op} {}", self.operand(function, operand)),
+            Rvalue::Cast(operand) => write!(f, "cast {}", self.operand(function, operand)),
+            Rvalue::Call { callee, args } => {
+                write!(f, "call {}", self.call(function, *callee, args))
+            }
+            Rvalue::Array(elems) => write!(f, "array [{}]", self.operands(function, elems)),
+        }
+    }
+
+    fn fmt_terminator(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        function: &Function,
+        terminator: &Terminator,
+    ) -> fmt::Result {
+        match terminator {
+            Terminator::Jump(target) => write!(f, "jump bb{target}"),
+            Terminator::Branch {
+                cond,
+                then_block,
+                else_block,
+            } => write!(
+                f,
+                "br {}, bb{then_block}, bb{else_block}",
+                self.operand(function, cond)
+            ),
+            Terminator::Return(Some(value)) => write!(f, "ret {}", self.operand(function, value)),
+            Terminator::Return(None) => write!(f, "ret"),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+
+    fn call(&self, function: &Function, callee: usize, args: &[Operand]) -> String {
+        let name = self.names.get(&callee).map_or("?", String::as_str);
+        format!("@{name}({})", self.operands(function, args))
+    }
+
+    fn operands(&self, function: &Function, operands: &[Operand]) -> String {
+        operands
+            .iter()
+            .map(|operand| self.operand(function, operand))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn operand(&self, function: &Function, operand: &Operand) -> String {
+        match operand {
+            Operand::Const(value, _) => value.to_string(),
+            Operand::Temp(id) => format!("%{id}"),
+            Operand::Local(id) => local(function, *id),
+            Operand::Global(id) => {
+                let name = self.global(*id).map_or("?", |g| g.name.as_str());
+                format!("@{name}")
+            }
+        }
+    }
+}
+
+fn local(function: &Function, id: LocalId) -> String {
+    let name = &function.locals[id].name;
+    let shared = function.locals.iter().filter(|l| l.name == *name).count() > 1;
+    if shared {
+        format!("${name}.{id}")
+    } else {
+        format!("${name}")
+    }
+}
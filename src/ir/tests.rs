@@ -0,0 +1,209 @@
+use crate::{
+    analyzer::{SemanticAnalyzer, const_eval::ConstValue, symbol::Symbol},
+    ast::TypedAst,
+    compiler::CompilerCtx,
+    error::CompilerError,
+    ir::{
+        Block, Inst, Module, Operand, Place, Rvalue, Terminator,
+        lower::{lower, lower_with_locations},
+        opt::Pass,
+        verify::verify,
+    },
+    lexer::Lexer,
+    parser::Parser,
+    toltype::TolType,
+};
+
+type LowerFn = fn(&TypedAst, &[Symbol]) -> Result<Module, Vec<CompilerError>>;
+
+/// Lowers a program that analyzes without errors
+pub(super) fn lower_source(source: &str) -> Module {
+    lower_source_with(source, lower).unwrap_or_else(|errors| {
+        panic!("`{source}` failed to lower: {errors:?}");
+    })
+}
+
+fn lower_source_with(source: &str, lower: LowerFn) -> Result<Module, Vec<CompilerError>> {
+    let mut ctx = CompilerCtx::new();
+    let tokens = Lexer::new(source, "test").lex(&mut ctx);
+    let ast = Parser::new(&tokens).parse(&mut ctx);
+    let typed_ast = SemanticAnalyzer::new(&mut ctx).analyze(ast);
+    assert!(
+        ctx.errors.is_empty(),
+        "`{source}` failed to analyze: {:?}",
+        ctx.errors
+    );
+
+    lower(&typed_ast, &ctx.symbol_table)
+}
+
+pub(super) const PROGRAM: &str = "\
+ang bilang na i64 = 0
+dapat HANGGAN na u8 = 3
+
+paraan dagdag(n na i64) -> i64:
+    bilang += n
+    ibalik bilang
+
+paraan main() -> i32:
+    ang i na u8 = 0
+    labas: habang tama:
+        babalik:
+            i += 1
+            kung i > HANGGAN && dagdag(1) > 10:
+                tigil labas
+        habang i < 2 || mali
+        tuloy
+    ang uri na i32 = kung i == 0:
+        1
+    kungdi:
+        2
+    ibalik uri
+";
+
+#[test]
+fn lowers_programs_that_verify() {
+    let module = lower_source(PROGRAM);
+    if let Err(e) = verify(&module) {
+        panic!("{e}\n{module}");
+    }
+}
+
+#[test]
+fn keeps_constant_globals_out_of_init() {
+    let module = lower_source(PROGRAM);
+
+    assert!(module.init.is_empty(), "{module}");
+    let values = module
+        .globals
+        .iter()
+        .map(|g| g.init.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(values, [Some(ConstValue::Int(0)), Some(ConstValue::Int(3))]);
+}
+
+#[test]
+fn marks_statement_locations_that_passes_keep_valid() {
+    let mut module = lower_source_with(PROGRAM, lower_with_locations).unwrap();
+    let main = module.functions.iter().find(|f| f.name == "main").unwrap();
+    let first = &main.blocks[0].insts[0];
+    let start = PROGRAM.find("ang i na u8").unwrap();
+    assert!(
+        matches!(first, Inst::Location(span) if span.start == start),
+        "{module}"
+    );
+    assert!(module.init.is_empty(), "{module}");
+
+    for pass in Pass::ALL {
+        pass.run(&mut module);
+        if let Err(e) = verify(&module) {
+            panic!("`{}` broke the IR: {e}\n{module}", pass.name());
+        }
+    }
+}
+
+#[test]
+fn builds_array_literals_of_the_type_they_are_used_as() {
+    let module = lower_source(
+        "\
+paraan main() -> i32:
+    ang mga na [[i32; 2]; 1] = [[4, 5]]
+    ibalik 0
+",
+    );
+
+    let insts = &module.functions[0].blocks[0].insts;
+    assert!(
+        !insts.iter().any(|inst| matches!(
+            inst,
+            Inst::Let {
+                value: Rvalue::Cast(_),
+                ..
+            }
+        )),
+        "{module}"
+    );
+}
+
+#[test]
+fn reports_what_c_cannot_hold_yet() {
+    let source = "\
+paraan pito() -> i32:
+    ibalik 7
+
+paraan una(_mga na [i32]) -> i32:
+    ibalik 1
+
+paraan main() -> i32:
+    ang _f = pito
+    ibalik pito()
+";
+    let errors = lower_source_with(source, lower).unwrap_err();
+
+    // Not the call of `pito`, only its use as a value
+    let spans = errors
+        .iter()
+        .map(|e| match e {
+            CompilerError::UnsupportedInC { span, .. } => span.offset(),
+            e => panic!("{e:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        [
+            source.find("_mga").unwrap(),
+            source.find("= pito").unwrap() + 2
+        ]
+    );
+}
+
+#[test]
+fn rejects_temporary_used_in_another_block() {
+    let mut module = lower_source(PROGRAM);
+    let main = module
+        .functions
+        .iter_mut()
+        .find(|f| f.name == "main")
+        .unwrap();
+    main.temps.push(TolType::I32);
+    let temp = main.temps.len() - 1;
+    main.blocks[0].insts.push(Inst::Let {
+        dest: temp,
+        value: Rvalue::Use(Operand::Const(ConstValue::Int(1), TolType::I32)),
+    });
+    main.blocks.push(Block {
+        insts: Vec::new(),
+        terminator: Terminator::Return(Some(Operand::Temp(temp))),
+    });
+
+    let error = verify(&module).unwrap_err();
+    assert_eq!(error.function, "main");
+    assert!(error.message.contains("assigned in bb0"), "{error}");
+}
+
+#[test]
+fn rejects_store_of_wrong_type() {
+    let mut module = lower_source(PROGRAM);
+    let main = module
+        .functions
+        .iter_mut()
+        .find(|f| f.name == "main")
+        .unwrap();
+    main.blocks[0].insts.push(Inst::Store {
+        place: Place::Local(0),
+        value: Operand::Const(ConstValue::Bool(true), TolType::Bool),
+    });
+
+    let error = verify(&module).unwrap_err();
+    assert!(error.message.contains("instead of `u8`"), "{error}");
+}
+
+#[test]
+fn rejects_jump_to_missing_block() {
+    let mut module = lower_source(PROGRAM);
+    let blocks = module.init.blocks.len();
+    module.init.blocks[0].terminator = Terminator::Jump(blocks);
+
+    let error = verify(&module).unwrap_err();
+    assert!(error.message.contains("doesn't exist"), "{error}");
+}
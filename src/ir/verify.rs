@@ -0,0 +1,290 @@
+//! Checks that a [`Module`] follows the rules of the IR.
+//!
+//! The lowering and every pass over the IR are expected to produce modules
+//! that pass, so a failure is a bug in the compiler rather than in the
+//! program. Catching it here is easier to debug than the C it would become.
+
+use std::fmt;
+
+use crate::{
+    ir::{
+        BinOp, Function, Inst, Module, Operand, Place, Rvalue, TempId, Terminator, UnOp,
+        lower::same_type,
+    },
+    toltype::TolType,
+};
+
+#[derive(Debug)]
+pub struct VerifyError {
+    pub function: String,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid IR in `{}`: {}", self.function, self.message)
+    }
+}
+
+pub fn verify(module: &Module) -> Result<(), VerifyError> {
+    for function in module.all_functions() {
+        Verifier { module, function }
+            .verify()
+            .map_err(|message| VerifyError {
+                function: function.name.clone(),
+                message,
+            })?;
+    }
+
+    Ok(())
+}
+
+struct Verifier<'m> {
+    module: &'m Module,
+    function: &'m Function,
+}
+
+impl Verifier<'_> {
+    fn verify(&self) -> Result<(), String> {
+        let function = self.function;
+        if function.blocks.is_empty() {
+            return Err("there are no blocks".to_string());
+        }
+        if function.params.iter().enumerate().any(|(i, id)| i != *id) {
+            return Err("the parameters aren't the first locals".to_string());
+        }
+        if function.params.len() > function.locals.len() {
+            return Err("a parameter isn't a local".to_string());
+        }
+
+        // Where every temporary was assigned
+        let mut assigned: Vec<Option<usize>> = vec![None; function.temps.len()];
+        for (block_id, block) in function.blocks.iter().enumerate() {
+            for inst in block.insts.iter() {
+                for operand in inst.operands() {
+                    self.check_operand(operand, block_id, &assigned)?;
+                }
+                self.check_inst(inst)?;
+
+                if let Inst::Let { dest, .. } = inst {
+                    match assigned.get_mut(*dest) {
+                        Some(Some(_)) => return Err(format!("%{dest} is assigned twice")),
+                        Some(slot) => *slot = Some(block_id),
+                        None => return Err(format!("%{dest} has no type")),
+                    }
+                }
+            }
+
+            self.check_terminator(&block.terminator, block_id, &assigned)
+                .map_err(|message| format!("bb{block_id}: {message}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that an operand refers to something that exists, and that a
+    /// temporary was assigned earlier in the block using it
+    fn check_operand(
+        &self,
+        operand: &Operand,
+        block_id: usize,
+        assigned: &[Option<usize>],
+    ) -> Result<(), String> {
+        match operand {
+            Operand::Const(..) => Ok(()),
+            Operand::Temp(id) => match assigned.get(*id) {
+                Some(Some(block)) if *block == block_id => Ok(()),
+                Some(Some(block)) => Err(format!(
+                    "%{id} is used in bb{block_id} but assigned in bb{block}"
+                )),
+                _ => Err(format!("%{id} is used before it is assigned")),
+            },
+            Operand::Local(id) if *id < self.function.locals.len() => Ok(()),
+            Operand::Local(id) => Err(format!("local {id} doesn't exist")),
+            Operand::Global(id) if self.module.global(*id).is_some() => Ok(()),
+            Operand::Global(id) => Err(format!("global {id} doesn't exist")),
+        }
+    }
+
+    fn check_inst(&self, inst: &Inst) -> Result<(), String> {
+        match inst {
+            Inst::Let { dest, value } => self.check_rvalue(*dest, value),
+            Inst::Store { place, value } => {
+                if let Place::Global(id) = place
+                    && self.module.global(*id).is_none()
+                {
+                    return Err(format!("global {id} doesn't exist"));
+                }
+                if let Place::Local(id) = place
+                    && *id >= self.function.locals.len()
+                {
+                    return Err(format!("local {id} doesn't exist"));
+                }
+
+                let place_type = self.function.place_type(place, &self.module.globals);
+                self.expect_type(value, place_type)
+            }
+            Inst::Call { callee, args } => self.check_call(*callee, args).map(|_| ()),
+            Inst::Location(_) => Ok(()),
+        }
+    }
+
+    fn check_rvalue(&self, dest: TempId, value: &Rvalue) -> Result<(), String> {
+        let Some(ttype) = self.function.temps.get(dest) else {
+            return Err(format!("%{dest} has no type"));
+        };
+
+        match value {
+            Rvalue::Use(operand) => self.expect_type(operand, ttype),
+            Rvalue::Binary { op, left, right } => {
+                let left_type = self.type_of(left);
+                let operand_ok = match op {
+                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem | BinOp::Pow => {
+                        left_type.is_numeric()
+                    }
+                    BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => {
+                        left_type.is_integer()
+                    }
+                    BinOp::Eq | BinOp::Ne => left_type.is_numeric() || *left_type == TolType::Bool,
+                    BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => left_type.is_numeric(),
+                };
+                if !operand_ok {
+                    return Err(format!("`{op}` on `{left_type}`"));
+                }
+
+                if op.is_comparison() {
+                    self.expect_type(right, left_type)?;
+                    return self.expect(ttype, &TolType::Bool, &format!("%{dest}"));
+                }
+                match op {
+                    BinOp::Shl | BinOp::Shr if !self.type_of(right).is_integer() => {
+                        Err(format!("`{op}` by `{}`", self.type_of(right)))
+                    }
+                    BinOp::Shl | BinOp::Shr => self.expect_type(left, ttype),
+                    _ => {
+                        self.expect_type(left, ttype)?;
+                        self.expect_type(right, ttype)
+                    }
+                }
+            }
+            Rvalue::Unary { op, operand } => {
+                let operand_ok = match op {
+                    UnOp::Neg => ttype.is_numeric(),
+                    UnOp::Not => *ttype == TolType::Bool,
+                    UnOp::BitNot => ttype.is_integer(),
+                };
+                if !operand_ok {
+                    return Err(format!("`{op}` on `{ttype}`"));
+                }
+                self.expect_type(operand, ttype)
+            }
+            Rvalue::Cast(operand) => {
+                let from = self.type_of(operand);
+                if !Self::castable(from) || !Self::castable(ttype) {
+                    return Err(format!("cast from `{from}` to `{ttype}`"));
+                }
+                Ok(())
+            }
+            Rvalue::Call { callee, args } => {
+                let return_type = self.check_call(*callee, args)?;
+                if let Some(return_type) = return_type {
+                    self.expect(ttype, return_type, &format!("%{dest}"))?;
+                }
+                Ok(())
+            }
+            Rvalue::Array(elems) => {
+                let TolType::Array { inner, size } = ttype else {
+                    return Err(format!("array assigned to `{ttype}`"));
+                };
+                if size.is_some_and(|size| size != elems.len()) {
+                    return Err(format!("{} elements in `{ttype}`", elems.len()));
+                }
+                elems
+                    .iter()
+                    .try_for_each(|elem| self.expect_type(elem, inner))
+            }
+        }
+    }
+
+    /// Checks the arguments of a call, returning the type the callee returns
+    /// if it is part of the module
+    fn check_call(&self, callee: usize, args: &[Operand]) -> Result<Option<&TolType>, String> {
+        let Some(function) = self.module.function(callee) else {
+            return Err(format!("function {callee} doesn't exist"));
+        };
+        if function.params.len() != args.len() {
+            return Err(format!(
+                "`{}` takes {} arguments, given {}",
+                function.name,
+                function.params.len(),
+                args.len()
+            ));
+        }
+        for (arg, param) in args.iter().zip(function.params.iter()) {
+            self.expect_type(arg, &function.locals[*param].ttype)?;
+        }
+
+        Ok(Some(&function.return_type))
+    }
+
+    fn check_terminator(
+        &self,
+        terminator: &Terminator,
+        block_id: usize,
+        assigned: &[Option<usize>],
+    ) -> Result<(), String> {
+        for target in terminator.successors() {
+            if target >= self.function.blocks.len() {
+                return Err(format!("bb{target} doesn't exist"));
+            }
+        }
+
+        match terminator {
+            Terminator::Branch { cond, .. } => {
+                self.check_operand(cond, block_id, assigned)?;
+                self.expect_type(cond, &TolType::Bool)
+            }
+            Terminator::Return(Some(value)) => {
+                self.check_operand(value, block_id, assigned)?;
+                if self.function.return_type == TolType::Void {
+                    return Err("returns a value from a `void` function".to_string());
+                }
+                self.expect_type(value, &self.function.return_type)
+            }
+            Terminator::Return(None) if self.function.return_type != TolType::Void => Err(format!(
+                "returns nothing instead of `{}`",
+                self.function.return_type
+            )),
+            Terminator::Return(None) | Terminator::Jump(_) | Terminator::Unreachable => Ok(()),
+        }
+    }
+
+    fn castable(ttype: &TolType) -> bool {
+        match ttype {
+            TolType::Array { inner, .. } => Self::castable(inner),
+            ttype => ttype.is_numeric(),
+        }
+    }
+
+    fn type_of<'a>(&'a self, operand: &'a Operand) -> &'a TolType {
+        self.function.operand_type(operand, &self.module.globals)
+    }
+
+    fn expect_type(&self, operand: &Operand, expected: &TolType) -> Result<(), String> {
+        let what = match operand {
+            Operand::Const(value, _) => value.to_string(),
+            Operand::Temp(id) => format!("%{id}"),
+            Operand::Local(id) => format!("${}", self.function.locals[*id].name),
+            Operand::Global(id) => format!("@{}", self.module.global(*id).unwrap().name),
+        };
+        self.expect(self.type_of(operand), expected, &what)
+    }
+
+    fn expect(&self, found: &TolType, expected: &TolType, what: &str) -> Result<(), String> {
+        if same_type(found, expected) {
+            Ok(())
+        } else {
+            Err(format!("{what} is `{found}` instead of `{expected}`"))
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Values of number literals. Every backend reads literals through these, so
+//! they agree on what a lexeme means whatever form it is written in.
+
+/// The value of an integer literal without its suffix, written in decimal or
+/// with a `0x`, `0o` or `0b` prefix, with `_` between digits allowed.
+/// `None` if it doesn't fit in an `i128`.
+pub fn int_value(lexeme: &str) -> Option<i128> {
+    let digits = lexeme.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x" | "0X") => (16, &digits[2..]),
+        Some("0o" | "0O") => (8, &digits[2..]),
+        Some("0b" | "0B") => (2, &digits[2..]),
+        _ => (10, digits.as_str()),
+    };
+
+    i128::from_str_radix(digits, radix).ok()
+}
+
+/// The value of a float literal without its suffix, with `_` between digits
+/// allowed
+pub fn float_value(lexeme: &str) -> Option<f64> {
+    lexeme.replace('_', "").parse().ok()
+}
@@ -4,9 +4,11 @@ use crate::{
     compiler::CompilerCtx,
     error::CompilerError,
     lexer::token::{Token, TokenKind},
+    lint::{Lint, LintSuppression},
     toltype::TolType,
 };
 
+pub mod literal;
 pub mod token;
 
 macro_rules! enter_bracket_and_add {
@@ -31,12 +33,14 @@ struct BracketInfo {
 pub struct Lexer<'a> {
     source_code: &'a str,
     source_iter: Peekable<Chars<'a>>,
-    tokens: Vec<Token>,
+    tokens: Vec<Token<'a>>,
     indent_stack: Vec<usize>,
     bracket_stack: Vec<BracketInfo>,
+    lint_suppressions: Vec<LintSuppression>,
     start: usize,
     current: usize,
     is_at_start: bool,
+    dev_debug: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -49,13 +53,25 @@ impl<'a> Lexer<'a> {
             tokens: Vec::new(),
             indent_stack: vec![0],
             bracket_stack: Vec::new(),
+            lint_suppressions: Vec::new(),
             start: 0,
             current: 0,
             is_at_start: true,
+            dev_debug: false,
         }
     }
 
-    pub fn lex(mut self, ctx: &mut CompilerCtx) -> Vec<Token> {
+    /// Starts lexing at `offset`, a line start, so that the spans of the
+    /// tokens still point into all of `source_code`
+    pub fn starting_at(mut self, offset: usize) -> Self {
+        self.source_iter = self.source_code[offset..].chars().peekable();
+        self.start = offset;
+        self.current = offset;
+        self
+    }
+
+    pub fn lex(mut self, ctx: &mut CompilerCtx) -> Vec<Token<'a>> {
+        self.dev_debug = ctx.dev_debug;
         while self.peek().is_some() {
             self.start = self.current;
             if self.is_at_start {
@@ -81,6 +97,7 @@ impl<'a> Lexer<'a> {
         }
 
         self.add_token(TokenKind::Eof, Some("<PAGTATAPOS_NG_FILE>"));
+        ctx.lint_suppressions.append(&mut self.lint_suppressions);
         self.tokens
     }
 
@@ -104,6 +121,11 @@ impl<'a> Lexer<'a> {
             self.advance();
         }
 
+        // Comment-only lines don't affect indentation
+        if self.source_code[self.current..].starts_with("--") {
+            return Ok(());
+        }
+
         let top_indent = *self.indent_stack.last().unwrap();
         match indent_count.cmp(&top_indent) {
             Ordering::Greater => {
@@ -137,9 +159,30 @@ impl<'a> Lexer<'a> {
             unreachable!()
         };
         match ch {
-            '+' => self.add_token(TokenKind::Plus, None),
-            '*' => self.add_token(TokenKind::Star, None),
-            '/' => self.add_token(TokenKind::Slash, None),
+            '+' => {
+                if self.match_char('=') {
+                    self.add_token(TokenKind::PlusEqual, None);
+                } else {
+                    self.add_token(TokenKind::Plus, None);
+                }
+            }
+            '*' => {
+                if self.match_char('*') {
+                    self.add_token(TokenKind::StarStar, None);
+                } else if self.match_char('=') {
+                    self.add_token(TokenKind::StarEqual, None);
+                } else {
+                    self.add_token(TokenKind::Star, None);
+                }
+            }
+            '%' => self.add_token(TokenKind::Percent, None),
+            '/' => {
+                if self.match_char('=') {
+                    self.add_token(TokenKind::SlashEqual, None);
+                } else {
+                    self.add_token(TokenKind::Slash, None);
+                }
+            }
             '(' => enter_bracket_and_add!(self, '(', TokenKind::LParen),
             ')' => exit_bracket_and_add!(self, ')', TokenKind::RParen),
             '{' => enter_bracket_and_add!(self, '{', TokenKind::LBrace),
@@ -153,13 +196,18 @@ impl<'a> Lexer<'a> {
             '-' => {
                 // Comments
                 if self.match_char('-') {
-                    while let Some(c) = self.advance() {
+                    // Leave the newline for semicolon inference and indentation
+                    while let Some(c) = self.peek() {
                         if c == '\n' {
                             break;
                         }
+                        self.advance();
                     }
+                    self.lex_lint_suppression()?;
                 } else if self.match_char('>') {
                     self.add_token(TokenKind::ThinArrow, None);
+                } else if self.match_char('=') {
+                    self.add_token(TokenKind::MinusEqual, None);
                 } else {
                     self.add_token(TokenKind::Minus, None)
                 }
@@ -194,9 +242,13 @@ impl<'a> Lexer<'a> {
                     self.add_token(TokenKind::Bang, None);
                 }
             }
+            '^' => self.add_token(TokenKind::Caret, None),
+            '~' => self.add_token(TokenKind::Tilde, None),
             '<' => {
                 if self.match_char('=') {
                     self.add_token(TokenKind::LessEqual, None);
+                } else if self.match_char('<') {
+                    self.add_token(TokenKind::LessLess, None);
                 } else {
                     self.add_token(TokenKind::Less, None);
                 }
@@ -204,6 +256,8 @@ impl<'a> Lexer<'a> {
             '>' => {
                 if self.match_char('=') {
                     self.add_token(TokenKind::GreaterEqual, None);
+                } else if self.match_char('>') {
+                    self.add_token(TokenKind::GreaterGreater, None);
                 } else {
                     self.add_token(TokenKind::Greater, None);
                 }
@@ -211,12 +265,11 @@ impl<'a> Lexer<'a> {
             '\r' | '\t' | ' ' => {} // Skip whitespace
             '\n' => {
                 self.is_at_start = true;
-                if !self.is_inside_bracket() {
-                    let last_token = self.tokens.last().unwrap();
-
-                    if last_token.kind.is_semicolon_inferrable() {
-                        self.add_token(TokenKind::Semicolon, Some(";"));
-                    }
+                if !self.is_inside_bracket()
+                    && let Some(last_token) = self.tokens.last()
+                    && last_token.kind.is_semicolon_inferrable()
+                {
+                    self.add_token(TokenKind::Semicolon, Some(";"));
                 }
             }
             _ => {
@@ -248,8 +301,9 @@ impl<'a> Lexer<'a> {
         Ok(())
     }
 
+    /// Lexes a string, checking its escapes. The lexeme is the string as
+    /// written, quotes and escapes included.
     fn lex_string(&mut self) -> Result<(), CompilerError> {
-        let mut strn = String::from("\"");
         while let Some(ch) = self.advance() {
             match ch {
                 '\n' => {
@@ -257,20 +311,11 @@ impl<'a> Lexer<'a> {
                         span: (self.span().start..self.span().start + 1).into(),
                     });
                 }
-                '"' => {
-                    strn.push('"');
-                    break;
-                }
+                '"' => break,
                 '\\' => {
                     let escape_start = self.current - 1;
-                    strn.push(match self.advance() {
-                        Some('n') => '\n',
-                        Some('r') => '\r',
-                        Some('\'') => '\'',
-                        Some('\"') => '\"',
-                        Some('\\') => '\\',
-                        Some('t') => '\t',
-                        Some('0') => '\0',
+                    match self.advance() {
+                        Some('n' | 'r' | '\'' | '"' | '\\' | 't' | '0') => {}
                         None => {
                             return Err(CompilerError::UnterminatedString {
                                 span: (self.span().start..self.span().start + 1).into(),
@@ -283,30 +328,78 @@ impl<'a> Lexer<'a> {
                                 span: (escape_start..escape_end).into(),
                             });
                         }
-                    })
+                    }
                 }
-                _ => strn.push(ch),
+                _ => {}
             }
         }
 
-        self.add_token(TokenKind::String, Some(&strn));
+        self.add_token(TokenKind::String, None);
+        Ok(())
+    }
+
+    /// Records a `-- tol:allow(lint, ...)` comment. A comment on its own line
+    /// applies to the next line, otherwise it applies to its own line.
+    fn lex_lint_suppression(&mut self) -> Result<(), CompilerError> {
+        let comment = &self.source_code[self.start + 2..self.current];
+        let Some(names) = comment
+            .trim()
+            .strip_prefix("tol:allow(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        else {
+            return Ok(());
+        };
+
+        let line_start = self.source_code[..self.start]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let span = if self.source_code[line_start..self.start].trim().is_empty() {
+            let next_line_start = (self.current + 1).min(self.source_code.len());
+            let next_line_end = self.source_code[next_line_start..]
+                .find('\n')
+                .map_or(self.source_code.len(), |i| next_line_start + i);
+            next_line_start..next_line_end
+        } else {
+            line_start..self.start
+        };
+
+        let names_start = self.start + 2 + comment.find('(').unwrap() + 1;
+        let mut offset = names_start;
+        for name in names.split(',') {
+            let trimmed = name.trim();
+            let name_start = offset + name.find(trimmed).unwrap_or(0);
+            offset += name.len() + 1;
+
+            let Some(lint) = Lint::from_name(trimmed) else {
+                return Err(CompilerError::UnknownLint {
+                    name: trimmed.to_string(),
+                    span: (name_start..name_start + trimmed.len()).into(),
+                });
+            };
+
+            self.lint_suppressions.push(LintSuppression {
+                lint,
+                span: span.clone(),
+            });
+        }
+
         Ok(())
     }
 
     fn lex_ident_or_keyword(&mut self) {
         while let Some(ch) = self.peek() {
             if !ch.is_alphanumeric() && ch != '_' {
-                println!("Not alphanumeric or _ = {ch}");
+                if self.dev_debug {
+                    println!("Not alphanumeric or _ = {ch}");
+                }
                 break;
             }
             self.advance();
         }
 
-        let lexed = &self.source_code[self.start..self.current];
-        match TokenKind::from_keyword(lexed) {
-            Some(k) => self.add_token(k, Some(lexed)),
-            None => self.add_token(TokenKind::Identifier, Some(lexed)),
-        }
+        let lexed = &self.source_code[self.span()];
+        let kind = TokenKind::from_keyword(lexed).unwrap_or(TokenKind::Identifier);
+        self.add_token(kind, None);
     }
 
     fn lex_number(&mut self, lexing_mode: NumberLexingMode) -> Result<(), CompilerError> {
@@ -324,11 +417,16 @@ impl<'a> Lexer<'a> {
         let mut is_float = false;
         while let Some(ch) = self.peek() {
             if !ch.is_numeric() && !matches!(ch, '.' | '_') {
-                println!("Not numeric, _, or . = {ch}");
+                if self.dev_debug {
+                    println!("Not numeric, _, or . = {ch}");
+                }
                 break;
             }
 
             if ch == '.' {
+                if is_float {
+                    return Err(self.extra_point());
+                }
                 is_float = true;
             }
 
@@ -351,35 +449,48 @@ impl<'a> Lexer<'a> {
             TolType::from_suffix(&suffix, suffix_start)?;
         }
 
-        let lexed = &self.source_code[self.start..self.current];
-        let number_without_underscores: String = lexed.chars().filter(|&c| c != '_').collect();
-
+        // The `_`s between digits are left for `literal` to skip
         if is_float {
-            self.add_token(TokenKind::Float, Some(&number_without_underscores));
+            self.add_token(TokenKind::Float, None);
         } else {
-            self.add_token(TokenKind::Integer, Some(&number_without_underscores));
+            self.add_token(TokenKind::Integer, None);
         }
 
         Ok(())
     }
 
+    /// Reports a number with more than one `.`, like `1.5.0`, consuming the
+    /// rest of it so lexing continues after the whole literal
+    fn extra_point(&mut self) -> CompilerError {
+        while self
+            .peek()
+            .is_some_and(|ch| ch.is_alphanumeric() || matches!(ch, '.' | '_'))
+        {
+            self.advance();
+        }
+
+        CompilerError::Lexer {
+            message: "Higit sa isang `.` ang bilang".to_string(),
+            span: self.span().into(),
+            help: Some("Isang `.` lamang ang maaari sa isang float".to_string()),
+        }
+    }
+
     fn lex_binary(&mut self) {
         self.advance(); // Consumes 'b'
 
         while let Some(ch) = self.peek() {
             if !matches!(ch, '0' | '1' | '_') {
-                println!("Not 0 or 1 = {ch}");
+                if self.dev_debug {
+                    println!("Not 0 or 1 = {ch}");
+                }
                 break;
             }
 
             self.advance();
         }
 
-        let without_underscores: String = self.source_code[self.start..self.current]
-            .chars()
-            .filter(|&c| c != '_')
-            .collect();
-        self.add_token(TokenKind::BinLiteral, Some(&without_underscores));
+        self.add_token(TokenKind::BinLiteral, None);
     }
 
     fn lex_hex(&mut self) {
@@ -405,20 +516,18 @@ impl<'a> Lexer<'a> {
                     | 'f'
                     | '_'
             ) {
-                println!(
-                    "Not '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f' or '_' = {ch}"
-                );
+                if self.dev_debug {
+                    println!(
+                        "Not '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f' or '_' = {ch}"
+                    );
+                }
                 break;
             }
 
             self.advance();
         }
 
-        let without_underscores: String = self.source_code[self.start..self.current]
-            .chars()
-            .filter(|&c| c != '_')
-            .collect();
-        self.add_token(TokenKind::HexLiteral, Some(&without_underscores));
+        self.add_token(TokenKind::HexLiteral, None);
     }
 
     fn lex_oct(&mut self) {
@@ -429,33 +538,26 @@ impl<'a> Lexer<'a> {
                 ch.to_ascii_lowercase(),
                 '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '_'
             ) {
-                println!("Not '0', '1', '2', '3', '4', '5', '6', '7', or '_' = {ch}");
+                if self.dev_debug {
+                    println!("Not '0', '1', '2', '3', '4', '5', '6', '7', or '_' = {ch}");
+                }
                 break;
             }
 
             self.advance();
         }
 
-        let without_underscores: String = self.source_code[self.start..self.current]
-            .chars()
-            .filter(|&c| c != '_')
-            .collect();
-        self.add_token(TokenKind::OctalLiteral, Some(&without_underscores));
+        self.add_token(TokenKind::OctalLiteral, None);
     }
 
-    fn add_token(&mut self, kind: TokenKind, lexeme: Option<&str>) {
-        match lexeme {
-            Some(s) => self.tokens.push(Token {
-                kind,
-                lexeme: s.to_string(),
-                span: self.span(),
-            }),
-            None => self.tokens.push(Token {
-                kind,
-                lexeme: self.source_code[self.span()].to_string(),
-                span: self.span(),
-            }),
-        }
+    /// Adds a token spanning from `start` to `current`, its lexeme being the
+    /// source it spans unless given
+    fn add_token(&mut self, kind: TokenKind, lexeme: Option<&'a str>) {
+        self.tokens.push(Token {
+            kind,
+            lexeme: lexeme.unwrap_or(&self.source_code[self.span()]),
+            span: self.span(),
+        });
     }
 
     fn enter_bracket(&mut self, bracket: char, span: Range<usize>) {
@@ -2,30 +2,38 @@ use std::ops::Range;
 
 use tokenkind_derive::TolTokenKind;
 
+use crate::intern::Name;
+
+/// A token borrowing its lexeme from the source it was lexed from
 #[derive(Debug, Clone)]
-pub struct Token {
+pub struct Token<'src> {
     pub kind: TokenKind,
-    pub lexeme: String,
+    pub lexeme: &'src str,
     pub span: Range<usize>,
 }
 
-impl Token {
+impl Token<'_> {
     pub fn new_dummy() -> Self {
         Self {
             kind: TokenKind::Dummy,
-            lexeme: "".to_string(),
+            lexeme: "",
             span: 0..0,
         }
     }
 }
 
-impl Token {
+impl<'src> Token<'src> {
     pub fn kind(&self) -> &TokenKind {
         &self.kind
     }
 
-    pub fn lexeme(&self) -> &str {
-        &self.lexeme
+    pub fn lexeme(&self) -> &'src str {
+        self.lexeme
+    }
+
+    /// The lexeme, interned
+    pub fn name(&self) -> Name {
+        Name::new(self.lexeme)
     }
 
     pub fn span(&self) -> Range<usize> {
@@ -73,6 +81,16 @@ pub enum TokenKind {
     #[stmt_starter]
     Kung,
 
+    #[keyword]
+    #[stmt_starter]
+    #[semicolon_inferrable]
+    Tigil,
+
+    #[keyword]
+    #[stmt_starter]
+    #[semicolon_inferrable]
+    Tuloy,
+
     #[keyword]
     Sa,
 
@@ -94,7 +112,9 @@ pub enum TokenKind {
     Plus,
     Minus,
     Star,
+    StarStar,
     Slash,
+    Percent,
     Equal,
     PlusEqual,
     MinusEqual,
@@ -105,12 +125,16 @@ pub enum TokenKind {
     PipePipe,
     Amper,
     AmperAmper,
+    Caret,
+    Tilde,
     Bang,
     BangEqual,
     Less,
     LessEqual,
+    LessLess,
     Greater,
     GreaterEqual,
+    GreaterGreater,
     FatArrow,
     ThinArrow,
 
@@ -179,7 +203,9 @@ impl TokenKind {
                 TokenKind::Plus => "+",
                 TokenKind::Minus => "-",
                 TokenKind::Star => "*",
+                TokenKind::StarStar => "**",
                 TokenKind::Slash => "/",
+                TokenKind::Percent => "%",
                 TokenKind::Equal => "=",
                 TokenKind::PlusEqual => "+=",
                 TokenKind::MinusEqual => "-=",
@@ -190,12 +216,16 @@ impl TokenKind {
                 TokenKind::PipePipe => "||",
                 TokenKind::Amper => "&",
                 TokenKind::AmperAmper => "&&",
+                TokenKind::Caret => "^",
+                TokenKind::Tilde => "~",
                 TokenKind::Bang => "!",
                 TokenKind::BangEqual => "!=",
                 TokenKind::Less => "<",
                 TokenKind::LessEqual => "<=",
+                TokenKind::LessLess => "<<",
                 TokenKind::Greater => ">",
                 TokenKind::GreaterEqual => ">=",
+                TokenKind::GreaterGreater => ">>",
                 _ => return None,
             }
             .to_string(),
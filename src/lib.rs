@@ -5,11 +5,17 @@ pub const ABOUT: &str = "The TOL programming language compiler";
 pub mod compiler;
 pub mod driver;
 pub mod error;
+pub mod lint;
 pub mod module;
 pub mod toltype;
 
 mod analyzer;
 mod ast;
+mod bytecode;
 mod codegen;
+mod intern;
+mod interpreter;
+mod ir;
 mod lexer;
 mod parser;
+mod repl;
@@ -0,0 +1,136 @@
+use std::{collections::HashMap, fmt, ops::Range};
+
+/// Identifiers of every warning the compiler can report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedFunction,
+    Shadowing,
+    /// Reserved for when modules can be imported
+    UnusedImport,
+    NeedlessGagawin,
+    UnreachableCode,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::UnusedVariable,
+        Lint::UnusedFunction,
+        Lint::Shadowing,
+        Lint::UnusedImport,
+        Lint::NeedlessGagawin,
+        Lint::UnreachableCode,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::UnusedFunction => "unused_function",
+            Lint::Shadowing => "shadowing",
+            Lint::UnusedImport => "unused_import",
+            Lint::NeedlessGagawin => "needless_gagawin",
+            Lint::UnreachableCode => "unreachable_code",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+
+    pub fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// What a `--allow`, `--warn` or `--deny` flag applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintSelector {
+    /// `warnings`, every lint at once
+    All,
+    One(Lint),
+}
+
+impl LintSelector {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        if s == "warnings" {
+            return Ok(Self::All);
+        }
+
+        Lint::from_name(s).map(Self::One).ok_or_else(|| {
+            let names = Lint::ALL
+                .iter()
+                .map(|lint| lint.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("hindi kilalang lint `{s}`, pagpipilian: warnings, {names}")
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LintLevels {
+    levels: HashMap<Lint, LintLevel>,
+}
+
+impl LintLevels {
+    /// Builds the levels from command line selectors. `warnings` is applied
+    /// before specific lints so `--deny warnings --allow shadowing` allows
+    /// `shadowing`. For the same selector, `--deny` beats `--warn` beats `--allow`.
+    pub fn from_selectors(
+        allow: &[LintSelector],
+        warn: &[LintSelector],
+        deny: &[LintSelector],
+    ) -> Self {
+        let mut levels = HashMap::new();
+        let ordered = [
+            (allow, LintLevel::Allow),
+            (warn, LintLevel::Warn),
+            (deny, LintLevel::Deny),
+        ];
+
+        for (selectors, level) in ordered.iter() {
+            if selectors.contains(&LintSelector::All) {
+                for lint in Lint::ALL {
+                    levels.insert(lint, *level);
+                }
+            }
+        }
+
+        for (selectors, level) in ordered.iter() {
+            for selector in selectors.iter() {
+                if let LintSelector::One(lint) = selector {
+                    levels.insert(*lint, *level);
+                }
+            }
+        }
+
+        Self { levels }
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+}
+
+/// A `-- tol:allow(...)` comment, silencing `lint` for the code in `span`
+#[derive(Debug, Clone)]
+pub struct LintSuppression {
+    pub lint: Lint,
+    pub span: Range<usize>,
+}
@@ -1,25 +1,43 @@
 use clap::Parser;
 use colored::Colorize;
-use tol2::driver::{CompilerOptions, compile};
+use miette::Report;
+use tol2::driver::{CompilerOptions, compile, print_report, start_repl};
 
 fn main() {
     let opts = CompilerOptions::parse();
-    let compilation_result = compile(opts);
+    if opts.is_repl() {
+        start_repl(opts);
+        return;
+    }
+
+    let diagnostics = compile(opts);
+
+    let source_code = diagnostics.source_code.as_ref();
+    let warning_count = diagnostics.warnings.len();
+    let error_count = diagnostics.errors.len();
+    for w in diagnostics.warnings {
+        print_report(Report::new(w), source_code);
+    }
+
+    for e in diagnostics.errors {
+        print_report(Report::new(e), source_code);
+    }
+
+    if warning_count > 0 || error_count > 0 {
+        eprintln!(
+            "{} {}, {} {}",
+            warning_count.to_string().bright_yellow(),
+            "babala".bright_yellow(),
+            error_count.to_string().bright_red(),
+            "mali".bright_red()
+        );
+    }
 
-    if let Err(ewos) = compilation_result {
-        for e in ewos.errors {
-            match ewos.source_code.as_ref() {
-                Some(src) => {
-                    eprintln!(
-                        "{}",
-                        "========================================================================"
-                            .bright_cyan()
-                    );
-                    eprintln!("{:?}", miette::Report::new(e).with_source_code(src.clone()));
-                }
-                None => eprintln!("{:?}", miette::Report::new(e)),
-            }
-        }
+    if error_count > 0 {
         std::process::exit(1);
     }
+
+    if let Some(code) = diagnostics.exit_code {
+        std::process::exit(code);
+    }
 }
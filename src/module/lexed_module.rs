@@ -1,6 +1,6 @@
-use crate::{error::CompilerError, lexer::token::Token};
+use crate::lexer::token::Token;
 
-pub struct LexedModule {
-    pub tokens: Vec<Token>,
+pub struct LexedModule<'src> {
+    pub tokens: Vec<Token<'src>>,
     pub src_filename: String,
 }
@@ -1,4 +1,2 @@
-pub mod compiled_module;
 pub mod lexed_module;
-pub mod module_registry;
 pub mod parsed_module;
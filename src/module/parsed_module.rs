@@ -1,6 +1,6 @@
 use crate::ast::Ast;
 
-pub struct ParsedModule {
-    pub ast: Ast,
+pub struct ParsedModule<'src> {
+    pub ast: Ast<'src>,
     pub src_filename: String,
 }
@@ -5,8 +5,8 @@ use bitflags::bitflags;
 use crate::{
     ast::{
         Ast,
-        expr::{Expr, ExprKind, StructLiteralField},
-        stmt::{KungBranch, ParamInfo, Stmt, StmtKind},
+        expr::{Expr, ExprId, ExprKind, Exprs, StructLiteralField},
+        stmt::{KungBranch, ParamInfo, Stmt, StmtId, StmtKind, Stmts},
     },
     compiler::CompilerCtx,
     error::CompilerError,
@@ -17,37 +17,49 @@ use crate::{
 
 pub mod operators;
 mod parsing_context;
+#[cfg(test)]
+mod tests;
 
+/// Consumes the `;` ending a statement. A statement ending in an indented
+/// block, like `ang x = kung ...`, is already terminated by its dedent.
 macro_rules! consume_stmt_terminator {
     ($parser:expr) => {
-        $parser.consume(TokenKind::Semicolon, "`;`")?
+        if $parser.previous().kind == TokenKind::Dedent {
+            $parser.previous()
+        } else {
+            $parser.consume(TokenKind::Semicolon, "`;`")?
+        }
     };
 }
 
-pub struct Parser<'a> {
-    tokens: &'a Vec<Token>,
+pub struct Parser<'t, 'src> {
+    tokens: &'t [Token<'src>],
     current: usize,
+    stmts: Stmts<'src>,
+    exprs: Exprs<'src>,
     errors: Vec<CompilerError>,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a Vec<Token>) -> Self {
+impl<'t, 'src> Parser<'t, 'src> {
+    pub fn new(tokens: &'t [Token<'src>]) -> Self {
         Self {
             tokens,
             current: 0,
+            stmts: Stmts::default(),
+            exprs: Exprs::default(),
             errors: Vec::new(),
         }
     }
 
-    pub fn parse(mut self, ctx: &mut CompilerCtx) -> Ast {
-        let mut ast = Vec::new();
+    pub fn parse(mut self, ctx: &mut CompilerCtx) -> Ast<'src> {
+        let mut top_level = Vec::new();
         while !self.is_at_end() {
             if self.peek().kind == TokenKind::Eof {
                 break;
             }
 
             match self.parse_statement() {
-                Ok(s) => ast.push(s),
+                Ok(s) => top_level.push(s),
                 Err(e) => {
                     self.synchronize();
                     self.record(e);
@@ -57,10 +69,14 @@ impl<'a> Parser<'a> {
 
         ctx.extend_errors(self.errors);
 
-        ast
+        Ast {
+            top_level,
+            stmts: self.stmts,
+            exprs: self.exprs,
+        }
     }
 
-    pub fn parse_statement(&mut self) -> Result<Stmt, CompilerError> {
+    pub fn parse_statement(&mut self) -> Result<StmtId, CompilerError> {
         match self.peek().kind() {
             TokenKind::Ang | TokenKind::Dapat => self.parse_angdapat(),
             TokenKind::Paraan => self.parse_paraan(),
@@ -68,15 +84,20 @@ impl<'a> Parser<'a> {
             TokenKind::Habang => self.parse_habang(),
             TokenKind::Kung => self.parse_kung(),
             TokenKind::Ibalik => self.parse_ibalik(),
+            TokenKind::Babalik => self.parse_babalik(),
+            TokenKind::Tigil | TokenKind::Tuloy => self.parse_loop_control(),
+            TokenKind::Identifier if self.peek_next().kind == TokenKind::Colon => {
+                self.parse_labeled_loop()
+            }
             TokenKind::Gagawin => {
                 let start = self.peek().span.start;
                 self.advance();
                 let end = consume_stmt_terminator!(self).span.end;
-                Ok(Stmt::new_gagawin(start..end))
+                Ok(self.stmts.push(Stmt::new_gagawin(start..end)))
             }
             TokenKind::Semicolon => {
                 self.advance();
-                Ok(Stmt::new_null())
+                Ok(self.stmts.push(Stmt::new_null()))
             }
             TokenKind::Indent => {
                 let indent_span = self.advance().span();
@@ -85,6 +106,7 @@ impl<'a> Parser<'a> {
 
                 block
             }
+            _ if Self::starts_an_expression(self.peek().kind()) => self.parse_expr_stmt(),
             _ => {
                 let found = self.peek().lexeme().to_string();
                 let span = self.peek().span();
@@ -96,7 +118,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_angdapat(&mut self) -> Result<Stmt, CompilerError> {
+    fn parse_angdapat(&mut self) -> Result<StmtId, CompilerError> {
         let (start, kind) = {
             let tok = self.consume_many(&[TokenKind::Ang, TokenKind::Dapat], "`ang` o `dapat`")?;
 
@@ -119,17 +141,17 @@ impl<'a> Parser<'a> {
         let rhs = self.parse_expression(0, ExprParseContext::AngDapatStatement)?;
         let end = consume_stmt_terminator!(self).span.end;
 
-        Ok(Stmt {
+        Ok(self.stmts.push(Stmt {
             kind: match kind {
                 TokenKind::Ang => StmtKind::Ang { id, ttype, rhs },
                 TokenKind::Dapat => StmtKind::Dapat { id, ttype, rhs },
                 _ => unreachable!(),
             },
             span: start..end,
-        })
+        }))
     }
 
-    fn parse_paraan(&mut self) -> Result<Stmt, CompilerError> {
+    fn parse_paraan(&mut self) -> Result<StmtId, CompilerError> {
         let start = self.consume(TokenKind::Paraan, "`paraan`")?.span.start;
 
         let id = self
@@ -154,19 +176,19 @@ impl<'a> Parser<'a> {
         let block = self.parse_block(indent_span)?;
         let end = self.consume(TokenKind::Dedent, "dedent")?.span.end;
 
-        Ok(Stmt {
+        Ok(self.stmts.push(Stmt {
             kind: StmtKind::Paraan {
                 id,
                 return_type,
                 params,
-                block: Box::new(block),
+                block,
                 params_span: param_start..param_end,
             },
             span: start..end,
-        })
+        }))
     }
 
-    fn parse_params(&mut self) -> Result<Vec<ParamInfo>, CompilerError> {
+    fn parse_params(&mut self) -> Result<Vec<ParamInfo<'src>>, CompilerError> {
         let mut params = Vec::new();
         while !self.is_at_eof() && self.peek().kind != TokenKind::RParen {
             let param_start = self.peek().span.start;
@@ -199,7 +221,7 @@ impl<'a> Parser<'a> {
     }
 
     #[allow(unreachable_code)]
-    fn parse_bawat(&mut self) -> Result<Stmt, CompilerError> {
+    fn parse_bawat(&mut self) -> Result<StmtId, CompilerError> {
         todo!("Hindi pa sinusuportahan ng linggwahe ang `bawat`");
         let start = self.consume(TokenKind::Bawat, "`bawat`")?.span.start;
 
@@ -214,17 +236,18 @@ impl<'a> Parser<'a> {
         let block = self.parse_block(indent_span)?;
         let end = self.consume(TokenKind::Dedent, "dedent")?.span.end;
 
-        Ok(Stmt {
+        Ok(self.stmts.push(Stmt {
             kind: StmtKind::Bawat {
                 bind,
                 iter: iter_expr,
-                block: Box::new(block),
+                block,
+                label: None,
             },
             span: start..end,
-        })
+        }))
     }
 
-    fn parse_habang(&mut self) -> Result<Stmt, CompilerError> {
+    fn parse_habang(&mut self) -> Result<StmtId, CompilerError> {
         let start = self.consume(TokenKind::Habang, "`habang`")?.span.start;
 
         let cond = self.parse_expression(0, ExprParseContext::HabangStatement)?;
@@ -234,36 +257,140 @@ impl<'a> Parser<'a> {
         let block = self.parse_block(indent_span)?;
         let end = self.consume(TokenKind::Dedent, "dedent")?.span.end;
 
-        Ok(Stmt {
+        Ok(self.stmts.push(Stmt {
             kind: StmtKind::Habang {
                 cond,
-                block: Box::new(block),
+                block,
+                label: None,
             },
             span: start..end,
-        })
+        }))
+    }
+
+    /// Parses `babalik:`, a loop whose condition is checked after its body
+    fn parse_babalik(&mut self) -> Result<StmtId, CompilerError> {
+        let start = self.consume(TokenKind::Babalik, "`babalik`")?.span.start;
+        self.consume(TokenKind::Colon, "`:` pagkatapos ng `babalik`")?;
+
+        let indent_span = self.consume(TokenKind::Indent, "indent")?.span();
+        let block = self.parse_block(indent_span)?;
+        self.consume(TokenKind::Dedent, "dedent")?;
+
+        self.consume(
+            TokenKind::Habang,
+            "`habang` pagkatapos ng katawan ng `babalik`",
+        )?;
+        let cond = self.parse_expression(0, ExprParseContext::BabalikStatement)?;
+        let end = consume_stmt_terminator!(self).span.end;
+
+        Ok(self.stmts.push(Stmt {
+            kind: StmtKind::Babalik {
+                block,
+                cond,
+                label: None,
+            },
+            span: start..end,
+        }))
     }
 
-    fn parse_kung(&mut self) -> Result<Stmt, CompilerError> {
+    /// Parses `pangalan: <loop>`, a loop that `tigil` and `tuloy` can refer to by name
+    fn parse_labeled_loop(&mut self) -> Result<StmtId, CompilerError> {
+        let label_tok = self
+            .consume(TokenKind::Identifier, "pangalan ng loop")?
+            .clone();
+        self.consume(TokenKind::Colon, "`:` pagkatapos ng pangalan")?;
+
+        let id = match self.peek().kind() {
+            TokenKind::Habang => self.parse_habang()?,
+            TokenKind::Bawat => self.parse_bawat()?,
+            TokenKind::Babalik => self.parse_babalik()?,
+            _ => {
+                return Err(CompilerError::UnexpectedToken {
+                    expected: format!(
+                        "Umasa ng loop pagkatapos ng `{}:` pero nakita ay `{}`",
+                        label_tok.lexeme(),
+                        self.peek().lexeme()
+                    ),
+                    span: self.peek().span().into(),
+                    help: Some("Loop lamang ang maaaring pangalanan".to_string()),
+                });
+            }
+        };
+
+        let stmt = &mut self.stmts[id];
+        stmt.span.start = label_tok.span.start;
+        match &mut stmt.kind {
+            StmtKind::Habang { label, .. }
+            | StmtKind::Bawat { label, .. }
+            | StmtKind::Babalik { label, .. } => *label = Some(label_tok),
+            _ => unreachable!(),
+        }
+
+        Ok(id)
+    }
+
+    fn parse_loop_control(&mut self) -> Result<StmtId, CompilerError> {
+        let keyword = self
+            .consume_many(&[TokenKind::Tigil, TokenKind::Tuloy], "`tigil` o `tuloy`")?
+            .clone();
+        let label = if self.peek().kind == TokenKind::Identifier {
+            Some(self.advance().clone())
+        } else {
+            None
+        };
+        let end = consume_stmt_terminator!(self).span.end;
+
+        Ok(self.stmts.push(Stmt {
+            kind: match keyword.kind {
+                TokenKind::Tigil => StmtKind::Tigil { label },
+                TokenKind::Tuloy => StmtKind::Tuloy { label },
+                _ => unreachable!(),
+            },
+            span: keyword.span.start..end,
+        }))
+    }
+
+    fn parse_expr_stmt(&mut self) -> Result<StmtId, CompilerError> {
+        let expr = self.parse_expression(0, ExprParseContext::ExprStatement)?;
+        let start = self.exprs[expr].span.start;
+        let end = consume_stmt_terminator!(self).span.end;
+
+        Ok(self.stmts.push(Stmt {
+            kind: StmtKind::Expr { expr },
+            span: start..end,
+        }))
+    }
+
+    fn parse_kung(&mut self) -> Result<StmtId, CompilerError> {
+        let (branches, span) = self.parse_kung_branches()?;
+
+        Ok(self.stmts.push(Stmt {
+            kind: StmtKind::Kung { branches },
+            span,
+        }))
+    }
+
+    /// Parses a `kung` chain, shared by `kung` statements and expressions
+    fn parse_kung_branches(&mut self) -> Result<(Vec<KungBranch>, Range<usize>), CompilerError> {
         let mut branches = Vec::new();
 
         let start = self.consume(TokenKind::Kung, "`kung`")?.span.start;
 
         // Parse initial `kung` statement
-        let cond = Some(self.parse_expression(0, ExprParseContext::KungStatement)?);
-        let cond_end = cond.as_ref().unwrap().span.end;
+        let cond = self.parse_expression(0, ExprParseContext::KungStatement)?;
+        let cond_end = self.exprs[cond].span.end;
         self.consume(TokenKind::Colon, "`:` pagkatapos ng expresyon")?;
 
         let indent_span = self.consume(TokenKind::Indent, "indent")?.span();
         let block = self.parse_block(indent_span)?;
-        self.consume(TokenKind::Dedent, "dedent")?;
+        let mut end = self.consume(TokenKind::Dedent, "dedent")?.span().end;
         branches.push(KungBranch {
-            cond,
+            cond: Some(cond),
             block,
             span: start..cond_end,
         });
 
         // Parse following `kungdi` brannches
-        let mut end = 0;
         while self.peek().kind == TokenKind::Kungdi {
             let branch_start_span = self.consume(TokenKind::Kungdi, "`kungdi`")?.span();
             let cond = if self.peek().kind != TokenKind::Colon {
@@ -271,8 +398,8 @@ impl<'a> Parser<'a> {
             } else {
                 None
             };
-            let cond_end = match cond.as_ref() {
-                Some(e) => e.span().end,
+            let cond_end = match cond {
+                Some(e) => self.exprs[e].span.end,
                 None => branch_start_span.end,
             };
             self.consume(TokenKind::Colon, "`:` pagkatapos ng expresyon")?;
@@ -288,13 +415,10 @@ impl<'a> Parser<'a> {
             });
         }
 
-        Ok(Stmt {
-            kind: StmtKind::Kung { branches },
-            span: start..end,
-        })
+        Ok((branches, start..end))
     }
 
-    fn parse_ibalik(&mut self) -> Result<Stmt, CompilerError> {
+    fn parse_ibalik(&mut self) -> Result<StmtId, CompilerError> {
         let start = self.consume(TokenKind::Ibalik, "`ibalik`")?.span.start;
         let rhs = if self.peek().kind == TokenKind::Semicolon {
             None
@@ -303,13 +427,13 @@ impl<'a> Parser<'a> {
         };
         let end = consume_stmt_terminator!(self).span.end;
 
-        Ok(Stmt {
+        Ok(self.stmts.push(Stmt {
             kind: StmtKind::Ibalik { rhs },
             span: start..end,
-        })
+        }))
     }
 
-    fn parse_block(&mut self, indent_span: Range<usize>) -> Result<Stmt, CompilerError> {
+    fn parse_block(&mut self, indent_span: Range<usize>) -> Result<StmtId, CompilerError> {
         let mut stmts = Vec::new();
         let start = self.peek().span.start;
 
@@ -328,10 +452,10 @@ impl<'a> Parser<'a> {
             stmts.push(stmt);
         }
 
-        Ok(Stmt {
+        Ok(self.stmts.push(Stmt {
             kind: StmtKind::Block { indent_span, stmts },
             span: start..self.previous().span.end,
-        })
+        }))
     }
 
     fn parse_type(&mut self) -> Result<TolType, CompilerError> {
@@ -397,45 +521,60 @@ impl<'a> Parser<'a> {
                     self.advance();
                     Ok(TolType::Bool)
                 }
-                "[" => {
-                    self.advance();
-                    let inner = self.parse_type()?;
-                    let mut size = None;
-                    if self.peek().kind == TokenKind::Semicolon {
-                        self.advance();
-                        size = Some(
-                            self.consume(TokenKind::Integer, "numero")?
-                                .lexeme()
-                                .parse::<usize>()?,
-                        );
-                    }
-
-                    self.consume(TokenKind::RBracket, "]")?;
-
-                    Ok(TolType::Array {
-                        inner: Box::new(inner),
-                        size,
-                    })
-                }
                 _ => {
-                    let name = self.advance().lexeme.clone();
+                    let name = self.advance().lexeme.to_string();
                     Ok(TolType::UnknownIdentifier(name))
                 }
             },
+            TokenKind::LBracket => {
+                self.advance();
+                let inner = Box::new(self.parse_type()?);
+                if self.peek().kind != TokenKind::Semicolon {
+                    self.consume(TokenKind::RBracket, "]")?;
+                    return Ok(TolType::Array { inner, size: None });
+                }
+
+                self.advance();
+                // The size is a literal or the name of a `dapat`
+                let ttype = if self.peek().kind == TokenKind::Identifier {
+                    let size = self.advance().clone();
+                    TolType::UnresolvedArray {
+                        inner,
+                        size: size.lexeme.to_string(),
+                        size_span: size.span,
+                    }
+                } else {
+                    let size = self.consume(TokenKind::Integer, "numero o pangalan ng `dapat`")?;
+                    TolType::Array {
+                        inner,
+                        size: Some(size.lexeme().replace('_', "").parse::<usize>()?),
+                    }
+                };
+
+                self.consume(TokenKind::RBracket, "]")?;
+                Ok(ttype)
+            }
             _ => Err(CompilerError::UnexpectedType {
-                found: self.peek().lexeme.clone(),
+                found: self.peek().lexeme.to_string(),
                 span: self.peek().span().into(),
                 help: None,
             }),
         }
     }
 
-    fn parse_expression(&mut self, prec: u8, ctx: ExprParseContext) -> Result<Expr, CompilerError> {
+    fn parse_expression(
+        &mut self,
+        prec: u8,
+        ctx: ExprParseContext,
+    ) -> Result<ExprId, CompilerError> {
         let mut left = self.nud()?;
 
         while !self.is_at_eof() {
-            let op = self.peek().clone();
-            if left.is_lvalue() && op.kind == TokenKind::LBrace && ctx.can_have_struct_lit() {
+            let op = self.peek();
+            if self.exprs[left].is_lvalue()
+                && op.kind == TokenKind::LBrace
+                && ctx.can_have_struct_lit()
+            {
                 return self.parse_struct_literal(left);
             }
 
@@ -444,13 +583,13 @@ impl<'a> Parser<'a> {
             }
 
             self.advance();
-            left = self.led(&op, left)?;
+            left = self.led(op, left)?;
         }
 
         Ok(left)
     }
 
-    fn nud(&mut self) -> Result<Expr, CompilerError> {
+    fn nud(&mut self) -> Result<ExprId, CompilerError> {
         let current_tok = self.peek().clone();
         let current_tok_span = current_tok.span();
 
@@ -458,79 +597,86 @@ impl<'a> Parser<'a> {
             TokenKind::Integer => {
                 self.advance();
                 let suffix = self.find_suffix(current_tok.lexeme());
-                Ok(Expr {
+                Ok(self.exprs.push(Expr {
                     kind: ExprKind::Integer {
                         lexeme: current_tok,
                         suffix,
                     },
                     span: current_tok_span,
-                })
+                }))
+            }
+            // Only decimal literals take a suffix, as hex digits could be
+            // read as one
+            TokenKind::HexLiteral | TokenKind::OctalLiteral | TokenKind::BinLiteral => {
+                self.advance();
+                Ok(self.exprs.push(Expr {
+                    kind: ExprKind::Integer {
+                        lexeme: current_tok,
+                        suffix: None,
+                    },
+                    span: current_tok_span,
+                }))
             }
             TokenKind::Float => {
                 self.advance();
                 let suffix = self.find_suffix(current_tok.lexeme());
-                Ok(Expr {
+                Ok(self.exprs.push(Expr {
                     kind: ExprKind::Float {
                         lexeme: current_tok,
                         suffix,
                     },
                     span: current_tok_span,
-                })
+                }))
             }
             TokenKind::Tama | TokenKind::Mali => {
                 self.advance();
-                Ok(Expr {
+                Ok(self.exprs.push(Expr {
                     kind: ExprKind::Boolean {
                         lexeme: current_tok,
                     },
                     span: current_tok_span,
-                })
+                }))
             }
             TokenKind::Identifier => {
                 self.advance();
-                Ok(Expr {
+                Ok(self.exprs.push(Expr {
                     kind: ExprKind::Identifier {
                         lexeme: current_tok,
                     },
                     span: current_tok_span,
-                })
+                }))
             }
             TokenKind::LParen => {
                 self.advance();
                 let expr = self.parse_expression(0, ExprParseContext::InExpression)?;
-                self.consume(TokenKind::RParen, ")")?;
+                let end = self.consume(TokenKind::RParen, ")")?.span.end;
+                self.exprs[expr].span = current_tok_span.start..end;
 
                 Ok(expr)
             }
-            TokenKind::Minus => {
+            TokenKind::Minus | TokenKind::Bang | TokenKind::Tilde => {
                 self.advance();
-                let prec = operators::get_prefix_op(&TokenKind::Minus).precedence();
+                let prec = operators::get_prefix_op(current_tok.kind()).precedence();
                 let rhs = self.parse_expression(prec, ExprParseContext::InExpression)?;
-                let end = rhs.span().end;
+                let end = self.exprs[rhs].span.end;
 
-                Ok(Expr {
+                Ok(self.exprs.push(Expr {
                     kind: ExprKind::Unary {
-                        op: TokenKind::Minus,
-                        right: Box::new(rhs),
+                        op: current_tok.kind.clone(),
+                        right: rhs,
                     },
                     span: current_tok_span.start..end,
-                })
-            }
-            TokenKind::Bang => {
-                self.advance();
-                let prec = operators::get_prefix_op(&TokenKind::Bang).precedence();
-                let rhs = self.parse_expression(prec, ExprParseContext::InExpression)?;
-                let end = rhs.span.end;
-
-                Ok(Expr {
-                    kind: ExprKind::Unary {
-                        op: TokenKind::Bang,
-                        right: Box::new(rhs),
-                    },
-                    span: current_tok_span.start..end,
-                })
+                }))
             }
             TokenKind::LBracket => self.parse_array_literal(),
+            TokenKind::LBrace => self.parse_block_expr(),
+            TokenKind::Kung => {
+                let (branches, span) = self.parse_kung_branches()?;
+                Ok(self.exprs.push(Expr {
+                    kind: ExprKind::Kung { branches },
+                    span,
+                }))
+            }
             _ => Err(CompilerError::UnexpectedToken {
                 expected: format!(
                     "Umasa ng expresyon, pero nakita ay `{}`",
@@ -542,18 +688,22 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn led(&mut self, op: &Token, left: Expr) -> Result<Expr, CompilerError> {
+    fn led(&mut self, op: &Token<'src>, left: ExprId) -> Result<ExprId, CompilerError> {
         let infix = operators::get_infix_op(op.kind());
+        // The right side of a right-associative operator may contain the same operator
         let precedence = match infix.assoc() {
             Associativity::Left => infix.precedence(),
-            Associativity::Right => infix.precedence() + 1,
+            Associativity::Right => infix.precedence() - 1,
         };
 
         match op.kind() {
             TokenKind::Plus
             | TokenKind::Minus
             | TokenKind::Star
+            | TokenKind::StarStar
             | TokenKind::Slash
+            | TokenKind::Percent
+            | TokenKind::Equal
             | TokenKind::PlusEqual
             | TokenKind::MinusEqual
             | TokenKind::StarEqual
@@ -564,29 +714,95 @@ impl<'a> Parser<'a> {
             | TokenKind::PipePipe
             | TokenKind::Amper
             | TokenKind::AmperAmper
+            | TokenKind::Caret
+            | TokenKind::LessLess
+            | TokenKind::GreaterGreater
             | TokenKind::LessEqual
             | TokenKind::GreaterEqual
             | TokenKind::Less
             | TokenKind::Greater => {
                 let right = self.parse_expression(precedence, ExprParseContext::InExpression)?;
-                let span = left.span.start..right.span.end;
-                Ok(Expr {
+                let span = self.exprs[left].span.start..self.exprs[right].span.end;
+                Ok(self.exprs.push(Expr {
                     kind: ExprKind::Binary {
-                        left: Box::new(left),
-                        right: Box::new(right),
+                        left,
+                        right,
                         op: op.kind.clone(),
                     },
                     span,
-                })
+                }))
             }
             TokenKind::LParen => self.parse_fncall(left, op.span.start),
-            _ => unreachable!(),
+            _ => Err(CompilerError::UnexpectedToken {
+                expected: format!("Hindi pa suportado ang operator na `{}`", op.lexeme()),
+                span: op.span().into(),
+                help: None,
+            }),
         }
     }
 
-    fn parse_fncall(&mut self, callee: Expr, args_start: usize) -> Result<Expr, CompilerError> {
+    /// Parses `{ stmt; stmt; tail }`. Newlines inside braces don't end
+    /// statements, so every statement needs an explicit `;`.
+    fn parse_block_expr(&mut self) -> Result<ExprId, CompilerError> {
+        let start = self.consume(TokenKind::LBrace, "`{`")?.span.start;
+        let mut stmts = Vec::new();
+        let mut tail = None;
+
+        while !self.is_at_eof() && self.peek().kind != TokenKind::RBrace {
+            if !Self::starts_an_expression(self.peek().kind()) {
+                stmts.push(self.parse_statement()?);
+                continue;
+            }
+
+            let expr = self.parse_expression(0, ExprParseContext::ExprStatement)?;
+            if self.peek().kind == TokenKind::RBrace {
+                tail = Some(expr);
+                break;
+            }
+
+            let stmt_start = self.exprs[expr].span.start;
+            let end = self
+                .consume(TokenKind::Semicolon, "`;` o `}` pagkatapos ng expresyon")?
+                .span
+                .end;
+            stmts.push(self.stmts.push(Stmt {
+                kind: StmtKind::Expr { expr },
+                span: stmt_start..end,
+            }));
+        }
+
+        let end = self.consume(TokenKind::RBrace, "`}`")?.span.end;
+
+        Ok(self.exprs.push(Expr {
+            kind: ExprKind::Block { stmts, tail },
+            span: start..end,
+        }))
+    }
+
+    fn starts_an_expression(kind: &TokenKind) -> bool {
+        matches!(
+            kind,
+            TokenKind::Integer
+                | TokenKind::HexLiteral
+                | TokenKind::OctalLiteral
+                | TokenKind::BinLiteral
+                | TokenKind::Float
+                | TokenKind::Tama
+                | TokenKind::Mali
+                | TokenKind::Identifier
+                | TokenKind::LParen
+                | TokenKind::LBracket
+                | TokenKind::LBrace
+                | TokenKind::Minus
+                | TokenKind::Bang
+                | TokenKind::Tilde
+                | TokenKind::Kung
+        )
+    }
+
+    fn parse_fncall(&mut self, callee: ExprId, args_start: usize) -> Result<ExprId, CompilerError> {
         let mut args = Vec::new();
-        let start = callee.span.start;
+        let start = self.exprs[callee].span.start;
 
         while !self.is_at_eof() && self.peek().kind != TokenKind::RParen {
             args.push(self.parse_expression(0, ExprParseContext::Argument)?);
@@ -606,25 +822,25 @@ impl<'a> Parser<'a> {
 
         let args_end = self.consume(TokenKind::RParen, "`)`")?.span.end;
 
-        Ok(Expr {
+        Ok(self.exprs.push(Expr {
             kind: ExprKind::FnCall {
-                callee: Box::new(callee),
+                callee,
                 args,
                 args_span: args_start..args_end,
             },
-            span: start..self.peek().span.end,
-        })
+            span: start..args_end,
+        }))
     }
 
-    fn parse_struct_literal(&mut self, left: Expr) -> Result<Expr, CompilerError> {
+    fn parse_struct_literal(&mut self, left: ExprId) -> Result<ExprId, CompilerError> {
         let mut fields = Vec::new();
 
-        let start = left.span.start;
+        let start = self.exprs[left].span.start;
 
         self.consume(TokenKind::LBrace, "`{`")?;
         while !self.is_at_eof_or_delimiter(TokenKind::RBrace) {
             let id = match self.consume(TokenKind::Identifier, "pangalan") {
-                Ok(t) => t.to_owned(),
+                Ok(t) => t,
                 Err(e) => {
                     self.record(e);
                     self.synchronize_until(|tk| matches!(tk, TokenKind::RBrace));
@@ -648,7 +864,7 @@ impl<'a> Parser<'a> {
                 None
             };
 
-            fields.push(StructLiteralField(id.lexeme, ex));
+            fields.push(StructLiteralField(id.lexeme.to_string(), ex));
 
             if self.peek().kind == TokenKind::Comma {
                 self.advance();
@@ -663,16 +879,13 @@ impl<'a> Parser<'a> {
 
         let end = self.consume(TokenKind::RBrace, "`}`")?.span.end;
 
-        Ok(Expr {
-            kind: ExprKind::StructLiteral {
-                left: Box::new(left),
-                fields,
-            },
+        Ok(self.exprs.push(Expr {
+            kind: ExprKind::StructLiteral { left, fields },
             span: start..end,
-        })
+        }))
     }
 
-    fn parse_array_literal(&mut self) -> Result<Expr, CompilerError> {
+    fn parse_array_literal(&mut self) -> Result<ExprId, CompilerError> {
         let start = self.advance().span.start;
 
         let mut elems = Vec::new();
@@ -692,10 +905,10 @@ impl<'a> Parser<'a> {
 
         let end = self.consume(TokenKind::RBracket, "`]`")?.span.end;
 
-        Ok(Expr {
+        Ok(self.exprs.push(Expr {
             kind: ExprKind::ArrayLiteral { elems },
-            span: start..end.into(),
-        })
+            span: start..end,
+        }))
     }
 
     fn record(&mut self, err: CompilerError) {
@@ -723,21 +936,15 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn find_suffix(&self, lexeme: &str) -> Option<String> {
+    fn find_suffix(&self, lexeme: &str) -> Option<&'static str> {
         let valid_suffixes = [
             "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize", "f32", "f64",
         ];
 
-        for suf in valid_suffixes {
-            if lexeme.contains(suf) {
-                return Some(suf.to_string());
-            }
-        }
-
-        None
+        valid_suffixes.into_iter().find(|suf| lexeme.contains(suf))
     }
 
-    fn advance(&mut self) -> &Token {
+    fn advance(&mut self) -> &'t Token<'src> {
         if self.is_at_end() {
             panic!("Compiler bug: unexpected end of input")
         }
@@ -746,7 +953,7 @@ impl<'a> Parser<'a> {
         &self.tokens[self.current - 1]
     }
 
-    fn peek(&self) -> &Token {
+    fn peek(&self) -> &'t Token<'src> {
         if self.is_at_end() {
             panic!("Compiler bug: unexpected end of input")
         }
@@ -754,7 +961,15 @@ impl<'a> Parser<'a> {
         &self.tokens[self.current]
     }
 
-    fn previous(&self) -> &Token {
+    fn peek_next(&self) -> &'t Token<'src> {
+        if self.current + 1 >= self.tokens.len() {
+            return self.peek();
+        }
+
+        &self.tokens[self.current + 1]
+    }
+
+    fn previous(&self) -> &'t Token<'src> {
         if self.current > self.tokens.len() {
             panic!("Compiler bug: tried to get previous but previous not a token")
         }
@@ -766,7 +981,7 @@ impl<'a> Parser<'a> {
         &mut self,
         expected: TokenKind,
         expected_str: &str,
-    ) -> Result<&Token, CompilerError> {
+    ) -> Result<&'t Token<'src>, CompilerError> {
         if self.is_at_end() {
             panic!("Compiler bug: unexpected end of input")
         }
@@ -790,7 +1005,7 @@ impl<'a> Parser<'a> {
         &mut self,
         kinds: &[TokenKind],
         expected_str: &str,
-    ) -> Result<&Token, CompilerError> {
+    ) -> Result<&'t Token<'src>, CompilerError> {
         if self.is_at_end() {
             panic!("Compiler bug: unexpected end of input")
         }
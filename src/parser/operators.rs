@@ -25,35 +25,71 @@ impl TolOp {
     }
 }
 
+/// Binding power of every operator, from loosest to tightest. Prefix
+/// operators bind tighter than every binary operator except `**`, so `-a * b`
+/// is `(-a) * b` but `-a ** b` is `-(a ** b)`.
+pub mod prec {
+    /// Not an operator, ends the expression
+    pub const NONE: u8 = 0;
+    /// `=`, `+=`, `-=`, `*=`, `/=`
+    pub const ASSIGNMENT: u8 = 1;
+    /// `||`
+    pub const OR: u8 = 2;
+    /// `&&`
+    pub const AND: u8 = 3;
+    /// `==`, `!=`
+    pub const EQUALITY: u8 = 4;
+    /// `<`, `<=`, `>`, `>=`
+    pub const COMPARISON: u8 = 5;
+    /// `|`
+    pub const BIT_OR: u8 = 6;
+    /// `^`
+    pub const BIT_XOR: u8 = 7;
+    /// `&`
+    pub const BIT_AND: u8 = 8;
+    /// `<<`, `>>`
+    pub const SHIFT: u8 = 9;
+    /// `+`, `-`
+    pub const TERM: u8 = 10;
+    /// `*`, `/`, `%`
+    pub const FACTOR: u8 = 11;
+    /// Prefix `-`, `!`, `~`
+    pub const PREFIX: u8 = 12;
+    /// `**`
+    pub const POWER: u8 = 13;
+    /// Calls, and indexing and field access once they exist
+    pub const POSTFIX: u8 = 14;
+}
+
 use Associativity::*;
 pub fn get_infix_op(kind: &TokenKind) -> TolOp {
     match kind {
-        TokenKind::PlusEqual
+        TokenKind::Equal
+        | TokenKind::PlusEqual
         | TokenKind::MinusEqual
         | TokenKind::StarEqual
-        | TokenKind::SlashEqual
-        | TokenKind::Equal => TolOp::new(Left, 1),
-        TokenKind::PipePipe => TolOp::new(Left, 2),
-        TokenKind::AmperAmper => TolOp::new(Left, 3),
-        TokenKind::BangEqual => TolOp::new(Left, 4),
-        TokenKind::EqualEqual => TolOp::new(Left, 4),
-        TokenKind::LessEqual => TolOp::new(Left, 5),
-        TokenKind::GreaterEqual => TolOp::new(Left, 5),
-        TokenKind::Less => TolOp::new(Left, 5),
-        TokenKind::Greater => TolOp::new(Left, 5),
-        TokenKind::Plus => TolOp::new(Left, 6),
-        TokenKind::Minus => TolOp::new(Left, 6),
-        TokenKind::Star => TolOp::new(Left, 7),
-        TokenKind::Slash => TolOp::new(Left, 7),
-        TokenKind::LParen => TolOp::new(Left, 8),
-        _ => TolOp::new(Left, 0),
+        | TokenKind::SlashEqual => TolOp::new(Right, prec::ASSIGNMENT),
+        TokenKind::PipePipe => TolOp::new(Left, prec::OR),
+        TokenKind::AmperAmper => TolOp::new(Left, prec::AND),
+        TokenKind::EqualEqual | TokenKind::BangEqual => TolOp::new(Left, prec::EQUALITY),
+        TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => {
+            TolOp::new(Left, prec::COMPARISON)
+        }
+        TokenKind::Pipe => TolOp::new(Left, prec::BIT_OR),
+        TokenKind::Caret => TolOp::new(Left, prec::BIT_XOR),
+        TokenKind::Amper => TolOp::new(Left, prec::BIT_AND),
+        TokenKind::LessLess | TokenKind::GreaterGreater => TolOp::new(Left, prec::SHIFT),
+        TokenKind::Plus | TokenKind::Minus => TolOp::new(Left, prec::TERM),
+        TokenKind::Star | TokenKind::Slash | TokenKind::Percent => TolOp::new(Left, prec::FACTOR),
+        TokenKind::StarStar => TolOp::new(Right, prec::POWER),
+        TokenKind::LParen => TolOp::new(Left, prec::POSTFIX),
+        _ => TolOp::new(Left, prec::NONE),
     }
 }
 
 pub fn get_prefix_op(kind: &TokenKind) -> TolOp {
     match kind {
-        TokenKind::Minus => TolOp::new(Right, 3),
-        TokenKind::Bang => TolOp::new(Right, 10),
-        _ => TolOp::new(Right, 0),
+        TokenKind::Minus | TokenKind::Bang | TokenKind::Tilde => TolOp::new(Right, prec::PREFIX),
+        _ => TolOp::new(Right, prec::NONE),
     }
 }
@@ -3,8 +3,10 @@ pub enum ExprParseContext {
     AngDapatStatement,
     KungStatement,
     HabangStatement,
+    BabalikStatement,
     BawatStatement,
     IbalikStatement,
+    ExprStatement,
     InExpression,
     Argument,
     StructLiteralField,
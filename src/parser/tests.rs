@@ -0,0 +1,240 @@
+use crate::{
+    ast::{
+        expr::{ExprId, ExprKind, Exprs},
+        stmt::StmtKind,
+    },
+    compiler::CompilerCtx,
+    lexer::{Lexer, token::TokenKind},
+    parser::Parser,
+};
+
+/// Every binary operator, loosest first. Operators in the same group share a
+/// precedence. Kept separate from `operators.rs` so the table is checked
+/// against an independent description of the language.
+const BINARY_GROUPS: &[(&[&str], Assoc)] = &[
+    (&["=", "+=", "-=", "*=", "/="], Assoc::Right),
+    (&["||"], Assoc::Left),
+    (&["&&"], Assoc::Left),
+    (&["==", "!="], Assoc::Left),
+    (&["<", "<=", ">", ">="], Assoc::Left),
+    (&["|"], Assoc::Left),
+    (&["^"], Assoc::Left),
+    (&["&"], Assoc::Left),
+    (&["<<", ">>"], Assoc::Left),
+    (&["+", "-"], Assoc::Left),
+    (&["*", "/", "%"], Assoc::Left),
+    (&["**"], Assoc::Right),
+];
+
+/// Prefix operators bind tighter than every binary operator but `**`
+const PREFIX_OPS: &[&str] = &["-", "!", "~"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Assoc {
+    Left,
+    Right,
+}
+
+fn binary_ops() -> impl Iterator<Item = (&'static str, usize, Assoc)> {
+    BINARY_GROUPS
+        .iter()
+        .enumerate()
+        .flat_map(|(level, (ops, assoc))| ops.iter().map(move |op| (*op, level, *assoc)))
+}
+
+fn level_of(op: &str) -> usize {
+    binary_ops().find(|(o, ..)| *o == op).unwrap().1
+}
+
+/// Parses `source` as a single expression statement and prints it with every
+/// operation parenthesized
+fn parse_parenthesized(source: &str) -> String {
+    let mut ctx = CompilerCtx::new();
+    let source = format!("{source}\n");
+    let tokens = Lexer::new(&source, "test").lex(&mut ctx);
+    let ast = Parser::new(&tokens).parse(&mut ctx);
+    assert!(
+        ctx.errors.is_empty(),
+        "`{source}` failed to parse: {:?}",
+        ctx.errors
+    );
+    assert_eq!(
+        ast.top_level.len(),
+        1,
+        "`{source}` parsed to more than one statement"
+    );
+
+    match &ast.stmts[ast.top_level[0]].kind {
+        StmtKind::Expr { expr } => parenthesize(&ast.exprs, *expr),
+        other => panic!("`{source}` is not an expression statement: {other:?}"),
+    }
+}
+
+fn parenthesize(exprs: &Exprs, expr: ExprId) -> String {
+    let parenthesize = |expr: &ExprId| parenthesize(exprs, *expr);
+    match &exprs[expr].kind {
+        ExprKind::Integer { lexeme, .. }
+        | ExprKind::Float { lexeme, .. }
+        | ExprKind::Boolean { lexeme }
+        | ExprKind::Identifier { lexeme } => lexeme.lexeme().to_string(),
+        ExprKind::Binary { left, right, op } => format!(
+            "({} {} {})",
+            parenthesize(left),
+            op_str(op),
+            parenthesize(right)
+        ),
+        ExprKind::Unary { op, right } => format!("({}{})", op_str(op), parenthesize(right)),
+        ExprKind::FnCall { callee, args, .. } => format!(
+            "{}({})",
+            parenthesize(callee),
+            args.iter().map(parenthesize).collect::<Vec<_>>().join(", ")
+        ),
+        other => panic!("cannot print {other:?}"),
+    }
+}
+
+fn op_str(op: &TokenKind) -> String {
+    op.op_to_string().unwrap()
+}
+
+fn check(cases: &[(String, String)]) {
+    let failures = cases
+        .iter()
+        .filter_map(|(source, expected)| {
+            let found = parse_parenthesized(source);
+            (&found != expected).then(|| format!("`{source}`: expected {expected}, found {found}"))
+        })
+        .collect::<Vec<_>>();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn binary_operator_pairs() {
+    let mut cases = Vec::new();
+    for (first, first_level, _) in binary_ops() {
+        for (second, second_level, second_assoc) in binary_ops() {
+            let source = format!("a {first} b {second} c");
+            let groups_left = first_level > second_level
+                || (first_level == second_level && second_assoc == Assoc::Left);
+            let expected = if groups_left {
+                format!("((a {first} b) {second} c)")
+            } else {
+                format!("(a {first} (b {second} c))")
+            };
+            cases.push((source, expected));
+        }
+    }
+
+    check(&cases);
+}
+
+#[test]
+fn prefix_operators_against_binary_operators() {
+    let mut cases = Vec::new();
+    for prefix in PREFIX_OPS {
+        for (op, ..) in binary_ops() {
+            let expected = if op == "**" {
+                format!("({prefix}(a ** b))")
+            } else {
+                format!("(({prefix}a) {op} b)")
+            };
+            cases.push((format!("{prefix}a {op} b"), expected));
+            cases.push((format!("a {op} {prefix}b"), format!("(a {op} ({prefix}b))")));
+        }
+    }
+
+    check(&cases);
+}
+
+#[test]
+fn same_operator_chains() {
+    let mut cases = Vec::new();
+    for (op, _, assoc) in binary_ops() {
+        let expected = match assoc {
+            Assoc::Left => format!("(((a {op} b) {op} c) {op} d)"),
+            Assoc::Right => format!("(a {op} (b {op} (c {op} d)))"),
+        };
+        cases.push((format!("a {op} b {op} c {op} d"), expected));
+    }
+
+    check(&cases);
+}
+
+#[test]
+fn parentheses_override_precedence() {
+    let mut cases = Vec::new();
+    for (first, ..) in binary_ops() {
+        for (second, ..) in binary_ops() {
+            // `a op (b op c)` and `(a op b) op c` must keep the written grouping
+            cases.push((
+                format!("(a {first} b) {second} c"),
+                format!("((a {first} b) {second} c)"),
+            ));
+            if level_of(first) > level_of(second) {
+                cases.push((
+                    format!("a {first} (b {second} c)"),
+                    format!("(a {first} (b {second} c))"),
+                ));
+            }
+        }
+    }
+
+    check(&cases);
+}
+
+#[test]
+fn calls_and_prefix_operators() {
+    let cases = [
+        ("-a", "(-a)"),
+        ("- -a", "(-(-a))"),
+        ("!!a", "(!(!a))"),
+        ("~-a", "(~(-a))"),
+        ("-f(a)", "(-f(a))"),
+        ("!f(a, b)", "(!f(a, b))"),
+        ("~f()", "(~f())"),
+        ("f(a + b, -c)", "f((a + b), (-c))"),
+        ("f(a)(b)", "f(a)(b)"),
+        ("f(a) ** 2", "(f(a) ** 2)"),
+        ("2 ** f(a)", "(2 ** f(a))"),
+        ("-2 ** 2", "(-(2 ** 2))"),
+        ("2 ** -2", "(2 ** (-2))"),
+        ("2 ** -2 ** 3", "(2 ** (-(2 ** 3)))"),
+        ("-a * b", "((-a) * b)"),
+        ("-a + b", "((-a) + b)"),
+        ("!a == b", "((!a) == b)"),
+        ("!a && b", "((!a) && b)"),
+        ("~a & b", "((~a) & b)"),
+        ("a = -b", "(a = (-b))"),
+        ("a = b = c", "(a = (b = c))"),
+        ("a += b * c", "(a += (b * c))"),
+        ("a = b || c && d", "(a = (b || (c && d)))"),
+        (
+            "a || b && c == d < e | f ^ g & h << i + j * k ** l",
+            "(a || (b && (c == (d < (e | (f ^ (g & (h << (i + (j * (k ** l)))))))))))",
+        ),
+        (
+            "a ** b * c + d << e & f ^ g | h < i == j && k || l",
+            "(((((((((((a ** b) * c) + d) << e) & f) ^ g) | h) < i) == j) && k) || l)",
+        ),
+        ("1 + 2 * 3", "(1 + (2 * 3))"),
+        ("1.5 * 2.0 - 3.25", "((1.5 * 2.0) - 3.25)"),
+        ("tama && mali || tama", "((tama && mali) || tama)"),
+        ("a % b * c / d", "(((a % b) * c) / d)"),
+        ("a << b >> c", "((a << b) >> c)"),
+        ("a < b == c > d", "((a < b) == (c > d))"),
+    ];
+
+    check(
+        &cases
+            .iter()
+            .map(|(source, expected)| (source.to_string(), expected.to_string()))
+            .collect::<Vec<_>>(),
+    );
+}
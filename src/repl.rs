@@ -0,0 +1,311 @@
+//! `tol repl`: runs code as it is typed.
+//!
+//! Every input is analyzed on top of the names declared by the inputs before
+//! it, then run by the interpreter. Input that fails to analyze or run leaves
+//! no declarations behind.
+//!
+//! An expression prints its value. A `kung` on its own is a statement, whose
+//! branches may end in values of different types or in none at all, so it
+//! prints nothing; `ang x = kung ...` keeps its value.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    mem,
+    sync::Arc,
+};
+
+use colored::Colorize;
+use miette::{NamedSource, Report};
+
+use crate::{
+    VERSION,
+    analyzer::{SemanticAnalyzer, SymbolId, TopLevelScope},
+    ast::{
+        Ast, TypedAst,
+        typed_stmt::{TypedStmt, TypedStmtKind},
+    },
+    codegen::Codegen,
+    compiler::CompilerCtx,
+    driver::{CompilerOptions, print_report},
+    error::CompilerError,
+    interpreter::{Interpreter, value::Value},
+    ir,
+    lexer::{Lexer, token::TokenKind},
+    parser::Parser,
+};
+
+/// Name of the input in diagnostics
+const SOURCE_NAME: &str = "repl";
+
+const PROMPT: &str = "tol> ";
+const CONTINUATION_PROMPT: &str = "...  ";
+
+const HELP: &str = "\
+:type <expr>  Ipakita ang tipo at typed AST ng expresyon
+:ast <code>   Ipakita ang AST ng code
+:c <code>     Ipakita ang C na mabubuo mula sa code
+:help         Ipakita ang mga utos na ito
+:quit         Umalis";
+
+pub struct Repl {
+    ctx: CompilerCtx,
+    scope: TopLevelScope,
+    /// Every `paraan` declared so far, declared again for each input
+    paraans: Vec<TypedStmt>,
+    /// Values of the top-level variables declared so far
+    globals: HashMap<SymbolId, Value>,
+    /// Every input that was run, each one lexed as the continuation of the
+    /// ones before it, so diagnostics can point at names declared by them
+    history: String,
+}
+
+impl Repl {
+    pub fn new(opts: &CompilerOptions) -> Self {
+        let mut ctx = CompilerCtx::new();
+        ctx.lint_levels = opts.lint_levels();
+        ctx.dev_debug = opts.dev_debug();
+
+        Self {
+            ctx,
+            scope: TopLevelScope::new(),
+            paraans: Vec::new(),
+            globals: HashMap::new(),
+            history: String::new(),
+        }
+    }
+
+    /// Reads and runs input from stdin until it ends or `:quit` is entered
+    pub fn start(&mut self) {
+        println!(
+            "TOL {VERSION}, i-type ang `{}` para sa mga utos",
+            ":help".bright_cyan()
+        );
+
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        while let Some(input) = Self::read_input(&mut lines) {
+            let trimmed = input.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(command) = trimmed.strip_prefix(':') {
+                if !self.run_command(command) {
+                    break;
+                }
+            } else {
+                self.eval(&input);
+            }
+        }
+    }
+
+    /// Reads lines until they form a complete input. Returns `None` once stdin
+    /// ends with nothing left to run.
+    fn read_input(lines: &mut impl Iterator<Item = io::Result<String>>) -> Option<String> {
+        let mut input = String::new();
+        loop {
+            let prompt = if input.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            print!("{prompt}");
+            io::stdout().flush().ok();
+
+            match lines.next() {
+                Some(Ok(line)) => {
+                    input.push_str(&line);
+                    input.push('\n');
+                }
+                _ if input.is_empty() => return None,
+                _ => return Some(input),
+            }
+
+            if !Self::is_incomplete(&input) {
+                return Some(input);
+            }
+        }
+    }
+
+    /// Whether the input opens a block that may still continue, or leaves a
+    /// bracket open. A block ends with an empty line.
+    fn is_incomplete(input: &str) -> bool {
+        let mut ctx = CompilerCtx::new();
+        let tokens = Lexer::new(input, SOURCE_NAME).lex(&mut ctx);
+
+        let has_open_bracket = ctx
+            .errors
+            .iter()
+            .any(|e| matches!(e, CompilerError::UnmatchedBracket { .. }));
+        let ends_with_colon = tokens
+            .iter()
+            .rev()
+            .find(|tok| {
+                !matches!(
+                    tok.kind(),
+                    TokenKind::Semicolon | TokenKind::Dedent | TokenKind::Eof
+                )
+            })
+            .is_some_and(|tok| *tok.kind() == TokenKind::Colon);
+        let has_block = tokens.iter().any(|tok| *tok.kind() == TokenKind::Indent);
+
+        has_open_bracket || ends_with_colon || (has_block && !input.ends_with("\n\n"))
+    }
+
+    /// Runs a `:command`. Returns `false` when the REPL should exit.
+    fn run_command(&mut self, command: &str) -> bool {
+        let (name, code) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        // `;` is only inferred at the end of a line
+        let code = &format!("{}{code}\n", self.history);
+
+        match name {
+            "type" => self.show_type(code),
+            "ast" => {
+                if let Some(ast) = self.parse(code) {
+                    println!("{ast:#?}");
+                }
+            }
+            "c" => {
+                if let Some((typed_ast, _)) = self.analyze(code) {
+                    match ir::lower::lower(&typed_ast, &self.ctx.symbol_table) {
+                        Ok(module) => {
+                            let codegen = Codegen::new(&module, &self.ctx.symbol_table);
+                            print!("{}", codegen.generate_fragment());
+                        }
+                        Err(errors) => {
+                            self.ctx.errors.extend(errors);
+                            self.report(code);
+                        }
+                    }
+                }
+            }
+            "help" => println!("{HELP}"),
+            "quit" | "q" => return false,
+            _ => eprintln!(
+                "{} `:{name}`, subukan ang `:help`",
+                "Hindi kilalang utos".bright_red()
+            ),
+        }
+
+        true
+    }
+
+    fn show_type(&mut self, code: &str) {
+        let Some((typed_ast, _)) = self.analyze(code) else {
+            return;
+        };
+
+        match typed_ast.as_slice() {
+            [
+                TypedStmt {
+                    kind: TypedStmtKind::Expr { expr },
+                    ..
+                },
+            ] => {
+                println!("{}", expr.ttype.to_string().bright_green());
+                println!("{expr:#?}");
+            }
+            _ => eprintln!(
+                "{} `:type <expr>`",
+                "Isang expresyon lamang ang tinatanggap ng".bright_red()
+            ),
+        }
+    }
+
+    fn eval(&mut self, input: &str) {
+        let source = format!("{}{input}", self.history);
+        let Some((typed_ast, scope)) = self.analyze(&source) else {
+            return;
+        };
+
+        let mut interpreter =
+            Interpreter::with_globals(&self.ctx.symbol_table, mem::take(&mut self.globals));
+        for paraan in &self.paraans {
+            interpreter.declare_paraan(paraan);
+        }
+
+        let result = match typed_ast.as_slice() {
+            [
+                TypedStmt {
+                    kind: TypedStmtKind::Expr { expr },
+                    ..
+                },
+            ] => interpreter.eval_top_level(expr).map(|value| {
+                if value != Value::Void {
+                    println!("{value}: {}", expr.ttype.to_string().bright_green());
+                }
+            }),
+            stmts => stmts
+                .iter()
+                .try_for_each(|stmt| interpreter.exec_top_level(stmt)),
+        };
+        self.globals = interpreter.into_globals();
+
+        match result {
+            Ok(()) => {
+                self.scope = scope;
+                self.history = source;
+                // A `paraan` can be called by any later input
+                self.paraans.extend(
+                    typed_ast
+                        .into_iter()
+                        .filter(|stmt| matches!(stmt.kind, TypedStmtKind::Paraan { .. })),
+                );
+            }
+            Err(e) => eprintln!("tol: {e}"),
+        }
+    }
+
+    /// Parses the input at the end of `source`, following the history
+    fn parse<'src>(&mut self, source: &'src str) -> Option<Ast<'src>> {
+        self.ctx.continue_compiling = true;
+        self.ctx.lint_suppressions.clear();
+
+        let tokens = Lexer::new(source, SOURCE_NAME)
+            .starting_at(self.history.len())
+            .lex(&mut self.ctx);
+        if !self.ctx.continue_compiling {
+            self.report(source);
+            return None;
+        }
+
+        let ast = Parser::new(&tokens).parse(&mut self.ctx);
+        if self.report(source) {
+            return None;
+        }
+
+        Some(ast)
+    }
+
+    /// Analyzes the input without keeping what it declares. Returns the typed
+    /// input and the top-level names it leaves behind.
+    fn analyze(&mut self, source: &str) -> Option<(TypedAst, TopLevelScope)> {
+        let ast = self.parse(source)?;
+        let analyzed =
+            SemanticAnalyzer::with_scope(&mut self.ctx, self.scope.clone()).analyze_input(ast);
+        if self.report(source) {
+            return None;
+        }
+
+        Some(analyzed)
+    }
+
+    /// Prints and clears the diagnostics of the input. Returns whether there
+    /// were errors.
+    fn report(&mut self, source: &str) -> bool {
+        let source_code = NamedSource::new(SOURCE_NAME, Arc::from(source));
+        for w in self.ctx.warnings.drain(..) {
+            print_report(Report::new(w), Some(&source_code));
+        }
+
+        let has_errors = !self.ctx.errors.is_empty();
+        for e in self.ctx.errors.drain(..) {
+            print_report(Report::new(e), Some(&source_code));
+        }
+
+        has_errors
+    }
+}
@@ -34,6 +34,12 @@ pub enum TolType {
         inner: Box<TolType>,
         size: Option<usize>,
     },
+    /// An array whose size names a `dapat`, until the analyzer evaluates it
+    UnresolvedArray {
+        inner: Box<TolType>,
+        size: String,
+        size_span: Range<usize>,
+    },
 
     // Special
     Void,
@@ -169,27 +175,39 @@ impl TolType {
                 | TolType::UnsizedFloat
         )
     }
-    //
-    // pub fn is_integer(&self) -> bool {
-    //     matches!(
-    //         self,
-    //         TolType::U8
-    //             | TolType::U16
-    //             | TolType::U32
-    //             | TolType::U64
-    //             | TolType::USize
-    //             | TolType::I8
-    //             | TolType::I16
-    //             | TolType::I32
-    //             | TolType::I64
-    //             | TolType::ISize
-    //             | TolType::UnsizedInteger
-    //     )
-    // }
-    //
-    // pub fn is_float(&self) -> bool {
-    //     matches!(self, TolType::F32 | TolType::F64 | TolType::UnsizedFloat)
-    // }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            TolType::U8
+                | TolType::U16
+                | TolType::U32
+                | TolType::U64
+                | TolType::USize
+                | TolType::I8
+                | TolType::I16
+                | TolType::I32
+                | TolType::I64
+                | TolType::ISize
+                | TolType::UnsizedInteger
+        )
+    }
+
+    /// Returns the size in bits of a sized integer type. `usize` and `isize`
+    /// are assumed to be 64 bits wide.
+    pub fn bit_width(&self) -> Option<u32> {
+        match self {
+            TolType::U8 | TolType::I8 => Some(8),
+            TolType::U16 | TolType::I16 => Some(16),
+            TolType::U32 | TolType::I32 => Some(32),
+            TolType::U64 | TolType::I64 | TolType::USize | TolType::ISize => Some(64),
+            _ => None,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, TolType::F32 | TolType::F64 | TolType::UnsizedFloat)
+    }
     //
     // pub fn is_numeric_conflict(&self, other: &TolType) -> bool {
     //     (self.is_integer() && other.is_float()) || (self.is_float() && other.is_integer())
@@ -245,6 +263,7 @@ impl fmt::Display for TolType {
                 inner,
                 size.map_or("".to_string(), |s| format!("; {}", s))
             ),
+            TolType::UnresolvedArray { inner, size, .. } => write!(f, "[{inner}; {size}]"),
         }
     }
 }
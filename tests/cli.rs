@@ -1,4 +1,14 @@
 use assert_cmd::cargo::*;
+use predicates::prelude::*;
+
+/// Whether there is a C compiler to run the C backend with, tests of it
+/// being skipped without one
+fn has_cc() -> bool {
+    std::process::Command::new(std::env::var("CC").unwrap_or("cc".to_string()))
+        .arg("--version")
+        .output()
+        .is_ok()
+}
 
 #[test]
 fn shows_version() {
@@ -19,3 +29,577 @@ fn shows_help() {
         .success()
         .stdout(predicates::str::contains(about));
 }
+
+#[test]
+fn calls_paraan_before_definition() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/forward_call.tol");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("bool is_odd(int32_t n);"));
+}
+
+#[test]
+fn reports_missing_return() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/missing_return.tol");
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "Hindi lahat ng daan ay nagbabalik ng halaga",
+    ));
+}
+
+#[test]
+fn reports_lints_as_warnings_by_default() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/lints.tol");
+    cmd.assert()
+        .success()
+        .stderr(predicates::str::contains("needless_gagawin"))
+        .stderr(predicates::str::contains("Tinatakpan").not())
+        .stderr(predicates::str::contains("1 babala, 0 mali"));
+}
+
+#[test]
+fn denied_lints_fail_the_build() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["examples/lints.tol", "--deny", "warnings"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("0 babala, 1 mali"));
+
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args([
+        "examples/lints.tol",
+        "--deny",
+        "warnings",
+        "--allow",
+        "needless_gagawin",
+    ]);
+    cmd.assert().success();
+}
+
+#[test]
+fn warns_about_unused_symbols() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/unused.tol");
+    cmd.assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Hindi kailanman tinawag ang `helper`",
+        ))
+        .stderr(predicates::str::contains("palitan ng `_b`"))
+        .stderr(predicates::str::contains("`fact`").not())
+        .stderr(predicates::str::contains("2 babala, 0 mali"));
+}
+
+#[test]
+fn jumps_out_of_labeled_loops() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/loops.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("bb1: ;")
+            .and(predicates::str::contains("goto bb1;"))
+            .and(predicates::str::contains("goto bb12;")),
+    );
+}
+
+#[test]
+fn checks_babalik_condition_after_body() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/babalik.tol");
+    cmd.assert().success().stdout(predicates::str::contains(
+        "    bb5: ;\n    if (limit > 5) {\n        goto bb1;\n    }",
+    ));
+}
+
+#[test]
+fn uses_blocks_and_kung_as_expressions() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/kung_expr.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("int32_t tmp;")
+            .and(predicates::str::contains("int64_t").not())
+            .and(predicates::str::contains("tmp = -1;"))
+            .and(predicates::str::contains("x = sign(-5);"))
+            .and(predicates::str::contains("layo = x * x;"))
+            .and(predicates::str::contains("uri = tmp;")),
+    );
+}
+
+#[test]
+fn says_the_generated_c_needs_fwrapv() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/arith.tol");
+    cmd.assert().success().stdout(predicates::str::starts_with(
+        "// Binuo ng tol. I-compile gamit ang -fwrapv:",
+    ));
+}
+
+#[test]
+fn generates_bitwise_operators() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/bitwise.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("mask = (uint8_t)~flags & 15;").and(predicates::str::contains(
+            "halo = (INT64_C(1) << INT64_C(4)) | (INT64_C(3) ^ INT64_C(1));",
+        )),
+    );
+}
+
+#[test]
+fn writes_literals_as_valid_c() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/literals.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("PINAKAMALAKI = UINT64_C(18446744073709551615);")
+            .and(predicates::str::contains("PINAKAMALIIT = INT64_MIN;"))
+            .and(predicates::str::contains("hex = 65535;"))
+            .and(predicates::str::contains("oct = 15;"))
+            .and(predicates::str::contains("bin = 10;"))
+            .and(predicates::str::contains("malaki = UINT32_C(4000000000);"))
+            .and(predicates::str::contains(
+                "negatibo = -INT64_C(5000000000);",
+            ))
+            .and(predicates::str::contains("kalahati = 1000.5f;"))
+            .and(predicates::str::contains("bukas = false;")),
+    );
+}
+
+#[test]
+fn escapes_names_c_cannot_use() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/c_names.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("int32_t tol_int_2 = 3;")
+            .and(predicates::str::contains(
+                "int32_t tol_printf_0(int32_t tol_abort_3) {",
+            ))
+            .and(predicates::str::contains("tol_exit_4 = tol_abort_3 + 1;"))
+            .and(predicates::str::contains("tol_exit_5 = 2;"))
+            .and(predicates::str::contains("int32_t main() {"))
+            .and(predicates::str::contains(
+                "tol_t0_6 = tol_printf_0(tol_int_2);",
+            )),
+    );
+}
+
+#[test]
+fn maps_c_names_to_source() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["emit", "--stage", "names", "examples/c_names.tol"]);
+    cmd.assert().success().stdout(
+        predicates::str::contains("tol_int_2 <- `int` c_names.tol:1:5\n")
+            .and(predicates::str::contains(
+                "tol_printf_0::tol_exit_5 <- `exit` c_names.tol:6:13\n",
+            ))
+            .and(predicates::str::contains(
+                "main <- `main` c_names.tol:10:8\n",
+            )),
+    );
+}
+
+#[test]
+fn points_c_back_at_source_with_line_directives() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["-g", "examples/arith.tol"]);
+    cmd.assert().success().stdout(
+        predicates::str::contains("#line 1 \"examples/arith.tol\"\nint32_t hati(")
+            .and(predicates::str::contains(
+                "#line 5 \"examples/arith.tol\"\n    tira = INT64_C(17) % INT64_C(5);",
+            ))
+            .and(predicates::str::contains(
+                "#line 9 \"examples/arith.tol\"\n    if (!(ugat > 1.0)) {",
+            )),
+    );
+
+    // Every line of a statement spanning more than one C line points at it
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["-g", "examples/recursion.tol"]);
+    cmd.assert().success().stdout(
+        predicates::str::contains("#line 2 \"examples/recursion.tol\"\n        goto bb2;").and(
+            predicates::str::contains(
+                "#line 4 \"examples/recursion.tol\"\n    return t5 + fib(n - 2);",
+            ),
+        ),
+    );
+
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/arith.tol");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("#line").not());
+}
+
+#[test]
+fn keeps_the_executable_built_with_debug_info() {
+    if !has_cc() {
+        return;
+    }
+
+    let name = format!("tol-test-{}-debug", std::process::id());
+    let source = std::env::temp_dir().join(format!("{name}.tol"));
+    std::fs::write(&source, "paraan main() -> i32:\n    ibalik 7\n").unwrap();
+    let exe = std::env::temp_dir().join("tol-debug").join(&name);
+
+    cargo_bin_cmd!()
+        .args(["run", "-g"])
+        .arg(&source)
+        .assert()
+        .code(7)
+        .stdout(predicates::str::is_empty())
+        .stderr(predicates::str::contains(exe.display().to_string()));
+    assert!(exe.exists());
+
+    std::fs::remove_file(&source).unwrap();
+    std::fs::remove_file(&exe).unwrap();
+    std::fs::remove_file(exe.with_extension("c")).unwrap();
+}
+
+#[test]
+fn checks_integer_division_at_runtime() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/arith.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("return tol_div_i32(a, b);")
+            .and(predicates::str::contains(
+                "kubo = tol_pow_i64(INT64_C(2), tol_pow_i64(INT64_C(3), INT64_C(2)));",
+            ))
+            .and(predicates::str::contains("kalahati = kubo / 2;")),
+    );
+}
+
+#[test]
+fn reports_division_by_zero_constant() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/division_by_zero.tol");
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("Paghahati sa zero"));
+}
+
+#[test]
+fn parses_assignment_with_prefix_minus() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/assignment.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("i = i + 8;").and(predicates::str::contains("i = -i + 30;")),
+    );
+}
+
+#[test]
+fn rejects_assignment_to_constant() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/invalid_assignment.tol");
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "Hindi maaaring palitan ang halaga",
+    ));
+}
+
+#[test]
+fn folds_dapat_initializers() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/dapat.tol");
+    cmd.assert().success().stdout(
+        predicates::str::contains("const uint8_t MASK = 143;")
+            .and(predicates::str::contains("const bool BUKAS = true;"))
+            .and(predicates::str::contains("doble = 16;")),
+    );
+}
+
+#[test]
+fn sizes_arrays_with_dapat() {
+    for backend in ["--interp", "--vm"] {
+        cargo_bin_cmd!()
+            .args(["run", backend, "examples/const_array.tol"])
+            .assert()
+            .code(42);
+    }
+
+    cargo_bin_cmd!()
+        .arg("examples/const_array.tol")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "int32_t gitna(tol_arr3_i32 _mga)",
+        ));
+}
+
+#[test]
+fn reports_dapat_that_cannot_be_folded() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("examples/invalid_dapat.tol");
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("Lumampas sa saklaw ng `u8`").and(
+            predicates::str::contains("Hindi alam ang halaga habang nagko-compile"),
+        ));
+}
+
+#[test]
+fn interprets_programs() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["run", "--interp", "examples/recursion.tol"]);
+    cmd.assert().code(60).stdout(predicates::str::is_empty());
+}
+
+#[test]
+fn stops_interpreted_program_on_runtime_error() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["run", "--interp", "examples/runtime_error.tol"]);
+    cmd.assert()
+        .code(101)
+        .stderr(predicates::str::contains("tol: paghahati sa zero"));
+}
+
+#[test]
+fn requires_main_to_run() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["run", "--interp", "examples/forward_call.tol"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("Walang `main` na paraan"));
+}
+
+/// Runs every example through every backend and checks that they agree on the
+/// exit code and on what the program printed. The C backend is skipped when
+/// there is no C compiler.
+#[test]
+fn interpreter_matches_c_backend() {
+    let examples = [
+        ("arith", 28),
+        ("arrays", 4),
+        ("assignment", 0),
+        ("babalik", 3),
+        ("bitwise", 9),
+        ("c_names", 2),
+        ("const_array", 42),
+        ("dapat", 19),
+        ("kung_diverge", 3),
+        ("kung_expr", 2),
+        ("labeled_loops", 1),
+        ("literals", 24),
+        ("optimize", 6),
+        ("recursion", 60),
+        ("runtime_error", 101),
+        ("shift_error", 101),
+        ("shifts", 165),
+        ("wide_literals", 7),
+    ];
+
+    for (example, code) in examples {
+        let path = format!("examples/{example}.tol");
+        let output = cargo_bin_cmd!()
+            .args(["run", "--interp", &path])
+            .assert()
+            .code(code)
+            .get_output()
+            .stdout
+            .clone();
+
+        let mut backends = vec![vec!["run", "--vm"]];
+        if has_cc() {
+            backends.extend([vec!["run"], vec!["run", "-O"], vec!["run", "-O", "-g"]]);
+        }
+        for args in backends {
+            cargo_bin_cmd!()
+                .args(args)
+                .arg(&path)
+                .assert()
+                .code(code)
+                .stdout(output.clone());
+        }
+    }
+}
+
+#[test]
+fn emits_ir() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["emit", "--stage", "ir", "examples/recursion.tol"]);
+    cmd.assert().success().stdout(
+        predicates::str::contains("fn @fib($n: i32) -> i32 {")
+            .and(predicates::str::contains("%5: i32 = call @fib(%4)"))
+            .and(predicates::str::contains("br %1, bb1, bb2"))
+            .and(predicates::str::contains("%2: i64 = cast %1")),
+    );
+}
+
+#[test]
+fn optimizes_ir() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["emit", "--stage", "ir", "-O", "examples/optimize.tol"]);
+    cmd.assert().success().stdout(
+        predicates::str::contains("fn @main() -> i32 {\nbb0:\n    jump bb1\nbb1:\n    ret 6\n}")
+            .and(predicates::str::contains("%1: i32 = mul %0, %0")),
+    );
+}
+
+#[test]
+fn turns_off_single_pass() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args([
+        "emit",
+        "--stage",
+        "ir",
+        "-O",
+        "-Z",
+        "pass=-inline",
+        "examples/optimize.tol",
+    ]);
+    cmd.assert().success().stdout(
+        predicates::str::contains("    call @parisukat(3)\n")
+            .and(predicates::str::contains("%0: i32 = call @parisukat(4)"))
+            .and(predicates::str::contains("%1: bool = gt %0, 10")),
+    );
+}
+
+#[test]
+fn rejects_unknown_pass() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["-Z", "pass=wala", "examples/optimize.tol"]);
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "hindi kilalang pass `wala`, pagpipilian: inline, propagate, unreachable_blocks, dce",
+    ));
+}
+
+#[test]
+fn disassembles_bytecode() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.args(["emit", "--stage", "bytecode", "examples/recursion.tol"]);
+    cmd.assert().success().stdout(
+        predicates::str::contains("fib(n@")
+            .and(predicates::str::contains("call fib 1"))
+            .and(predicates::str::contains("jump.false"))
+            .and(predicates::str::contains("TOLC").not()),
+    );
+}
+
+#[test]
+fn runs_bytecode_files() {
+    let path = std::env::temp_dir().join(format!("tol-test-{}.tolc", std::process::id()));
+    cargo_bin_cmd!()
+        .args(["emit", "--stage", "bytecode", "-o"])
+        .arg(&path)
+        .arg("examples/recursion.tol")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_empty());
+
+    cargo_bin_cmd!().arg("run").arg(&path).assert().code(60);
+
+    // Cut off in the middle of the code
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+    cargo_bin_cmd!()
+        .arg("run")
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Sirang bytecode"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn rejects_bytecode_that_underflows_the_stack() {
+    let path = std::env::temp_dir().join(format!("tol-test-{}-pop.tolc", std::process::id()));
+    cargo_bin_cmd!()
+        .args(["emit", "--stage", "bytecode", "-o"])
+        .arg(&path)
+        .arg("examples/recursion.tol")
+        .assert()
+        .success();
+
+    // The first instruction of `<init>` comes after its name, its parameter
+    // count and its instruction count. Turn its `push.void` into a `pop`.
+    let mut bytes = std::fs::read(&path).unwrap();
+    let name = bytes.windows(6).position(|w| w == b"<init>").unwrap();
+    let first_op = name + 6 + 4 + 4;
+    assert_eq!(bytes[first_op], 1);
+    bytes[first_op] = 2;
+    std::fs::write(&path, &bytes).unwrap();
+
+    cargo_bin_cmd!()
+        .arg("run")
+        .arg(&path)
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("Sirang bytecode").and(predicates::str::contains("Pop")));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn keeps_declarations_between_repl_inputs() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("repl").write_stdin(
+        "ang x na i32 = 40\n\
+         paraan doble(n na i32) -> i32:\n    ibalik n * 2\n\n\
+         x += 1\n\
+         doble(x) - 40\n",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("41: i32").and(predicates::str::contains("42: i32")));
+}
+
+#[test]
+fn forgets_repl_input_that_fails() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("repl").write_stdin(
+        "paraan hati(a na i32, b na i32) -> i32:\n    ibalik a / b\n\n\
+         ang y na i32 = hati(1, 0)\n\
+         y\n",
+    );
+    cmd.assert().success().stderr(
+        predicates::str::contains("tol: paghahati sa zero")
+            .and(predicates::str::contains("Hindi naideklarang pangalan")),
+    );
+}
+
+#[test]
+fn points_repl_redeclarations_at_the_earlier_input() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("repl")
+        .write_stdin("ang x na i32 = 1\n1 + 1\nang x na i32 = 2\n");
+    cmd.assert().success().stderr(
+        predicates::str::contains("1 │ ang x na i32 = 1")
+            .and(predicates::str::contains("Naideklara na dito"))
+            .and(predicates::str::contains("3 │ ang x na i32 = 2")),
+    );
+}
+
+#[test]
+fn shows_type_ast_and_c_in_repl() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("repl")
+        .write_stdin(":type 1 < 2\n:ast -a\n:c ang x na i32 = 7 / 2\n:quit\n1 + 1\n");
+    cmd.assert().success().stdout(
+        predicates::str::contains("bool\n")
+            .and(predicates::str::contains("Unary {"))
+            .and(predicates::str::contains(
+                "int32_t x;\nx = INT64_C(7) / INT64_C(2);",
+            ))
+            .and(predicates::str::contains("2: ").not()),
+    );
+}
+
+#[test]
+fn shows_c_of_expressions_in_repl() {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("repl").write_stdin(
+        "ang x na i32 = 40
+:c x + 1
+:c x
+",
+    );
+    cmd.assert().success().stdout(
+        predicates::str::contains(
+            "tol> x + 1;
+",
+        )
+        .and(predicates::str::contains(
+            "tol> x;
+",
+        )),
+    );
+}
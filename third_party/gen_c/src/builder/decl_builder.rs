@@ -1,51 +1,63 @@
-use crate::{ctype::CType, product::statement::CStatement};
+use crate::{
+    ctype::CType,
+    product::{
+        expr::CExpr,
+        statement::{CStatement, StorageClass},
+    },
+};
+
+pub use crate::product::statement::ConstKind;
 
 pub struct DeclBuilder {
-    modifier: Option<String>,
+    storage: Option<StorageClass>,
+    constness: Option<ConstKind>,
     ttype: CType,
     name: String,
-    rhs: Option<String>,
+    rhs: Option<CExpr>,
 }
 
 impl DeclBuilder {
     pub fn new(ttype: CType, name: &str) -> Self {
         Self {
-            modifier: None,
+            storage: None,
+            constness: None,
             ttype,
             name: name.to_string(),
             rhs: None,
         }
     }
 
-    pub fn with_rhs(mut self, rhs: String) -> Self {
+    pub fn with_rhs(mut self, rhs: CExpr) -> Self {
         self.rhs = Some(rhs);
 
         self
     }
 
     pub fn as_const(mut self, kind: ConstKind) -> Self {
-        let modifier = match kind {
-            ConstKind::Const => "const".to_string(),
-            ConstKind::ConstPtr => "*const".to_string(),
-            ConstKind::ConstConstPtr => "const * const".to_string(),
-        };
-        self.modifier = Some(modifier);
+        self.constness = Some(kind);
+
+        self
+    }
+
+    pub fn as_static(mut self) -> Self {
+        self.storage = Some(StorageClass::Static);
+
+        self
+    }
+
+    pub fn as_extern(mut self) -> Self {
+        self.storage = Some(StorageClass::Extern);
 
         self
     }
 
     pub fn build(self) -> CStatement {
         CStatement::Declaration {
-            modifier: self.modifier,
+            storage: self.storage,
+            constness: self.constness,
             ttype: self.ttype,
             name: self.name,
             rhs: self.rhs,
         }
     }
 }
-
-pub enum ConstKind {
-    Const,
-    ConstPtr,
-    ConstConstPtr,
-}
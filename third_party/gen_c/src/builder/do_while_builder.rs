@@ -0,0 +1,19 @@
+use crate::product::{expr::CExpr, statement::CStatement};
+
+pub struct DoWhileBuilder {
+    body: CStatement,
+    cond: CExpr,
+}
+
+impl DoWhileBuilder {
+    pub fn new(body: CStatement, cond: CExpr) -> Self {
+        Self { body, cond }
+    }
+
+    pub fn build(self) -> CStatement {
+        CStatement::DoWhile {
+            body: Box::new(self.body),
+            cond: self.cond,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::product::{expr::CExpr, typedef::CTypeDef};
+
+pub struct EnumBuilder {
+    name: String,
+    variants: Vec<(String, Option<CExpr>)>,
+}
+
+impl EnumBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            variants: Vec::new(),
+        }
+    }
+
+    pub fn add_variant(mut self, name: &str) -> Self {
+        self.variants.push((name.to_string(), None));
+
+        self
+    }
+
+    pub fn add_variant_with_value(mut self, name: &str, value: CExpr) -> Self {
+        self.variants.push((name.to_string(), Some(value)));
+
+        self
+    }
+
+    pub fn build(self) -> CTypeDef {
+        CTypeDef::Enum {
+            name: self.name,
+            variants: self.variants,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::product::{expr::CExpr, statement::CStatement};
+
+pub struct ForBuilder {
+    init: Option<Box<CStatement>>,
+    cond: Option<CExpr>,
+    step: Option<CExpr>,
+    body: CStatement,
+}
+
+impl ForBuilder {
+    pub fn new(body: CStatement) -> Self {
+        Self {
+            init: None,
+            cond: None,
+            step: None,
+            body,
+        }
+    }
+
+    /// Runs `init` before the loop, a declaration or an expression statement
+    pub fn with_init(mut self, init: CStatement) -> Self {
+        assert!(
+            matches!(
+                init,
+                CStatement::Declaration { .. } | CStatement::Expression { .. }
+            ),
+            "the init of a `for` must be a declaration or an expression"
+        );
+        self.init = Some(Box::new(init));
+
+        self
+    }
+
+    pub fn with_cond(mut self, cond: CExpr) -> Self {
+        self.cond = Some(cond);
+
+        self
+    }
+
+    pub fn with_step(mut self, step: CExpr) -> Self {
+        self.step = Some(step);
+
+        self
+    }
+
+    pub fn build(self) -> CStatement {
+        CStatement::For {
+            init: self.init,
+            cond: self.cond,
+            step: self.step,
+            body: Box::new(self.body),
+        }
+    }
+}
@@ -22,7 +22,7 @@ impl FunctionBuilder {
     }
 
     pub fn add_param(mut self, ttype: CType, name: &str) -> Self {
-        self.params.push(format!("{} {}", ttype, name));
+        self.params.push(ttype.declare(name));
 
         self
     }
@@ -51,4 +51,14 @@ impl FunctionBuilder {
             body: self.body,
         }
     }
+
+    /// Builds only the declaration of the function, dropping its body
+    pub fn build_prototype(self) -> CStatement {
+        CStatement::Prototype {
+            modifiers: self.modifiers,
+            return_type: self.return_type,
+            name: self.name,
+            params: self.params,
+        }
+    }
 }
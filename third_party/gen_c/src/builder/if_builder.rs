@@ -1,4 +1,7 @@
-use crate::product::statement::{CStatement, IfBranch};
+use crate::product::{
+    expr::CExpr,
+    statement::{CStatement, IfBranch},
+};
 
 pub struct IfBuilder {
     initial_branch: IfBranch,
@@ -6,14 +9,14 @@ pub struct IfBuilder {
 }
 
 impl IfBuilder {
-    pub fn new(initial_cond: String, initial_body: CStatement) -> Self {
+    pub fn new(initial_cond: CExpr, initial_body: CStatement) -> Self {
         Self {
             initial_branch: IfBranch::new(Some(initial_cond), initial_body),
             branches: Vec::new(),
         }
     }
 
-    pub fn add_elseif_branch(mut self, cond: String, body: CStatement) -> Self {
+    pub fn add_elseif_branch(mut self, cond: CExpr, body: CStatement) -> Self {
         self.branches.push(IfBranch::new(Some(cond), body));
 
         self
@@ -1,6 +1,11 @@
 pub mod block_builder;
 pub mod decl_builder;
+pub mod do_while_builder;
+pub mod enum_builder;
+pub mod for_builder;
 pub mod function_builder;
 pub mod if_builder;
 pub mod return_builder;
+pub mod struct_builder;
+pub mod switch_builder;
 pub mod while_builder;
@@ -1,7 +1,8 @@
-use crate::product::statement::CStatement;
+use crate::product::{expr::CExpr, statement::CStatement};
 
+#[derive(Default)]
 pub struct ReturnBuilder {
-    rhs: Option<String>,
+    rhs: Option<CExpr>,
 }
 
 impl ReturnBuilder {
@@ -9,7 +10,7 @@ impl ReturnBuilder {
         Self { rhs: None }
     }
 
-    pub fn with_rhs(mut self, rhs: String) -> Self {
+    pub fn with_rhs(mut self, rhs: CExpr) -> Self {
         self.rhs = Some(rhs);
 
         self
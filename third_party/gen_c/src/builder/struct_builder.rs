@@ -0,0 +1,51 @@
+use crate::{
+    ctype::CType,
+    product::typedef::{CField, CTypeDef},
+};
+
+/// Builds a struct, or a union with [`StructBuilder::union`]
+pub struct StructBuilder {
+    name: String,
+    fields: Vec<CField>,
+    is_union: bool,
+}
+
+impl StructBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: Vec::new(),
+            is_union: false,
+        }
+    }
+
+    pub fn union(name: &str) -> Self {
+        Self {
+            is_union: true,
+            ..Self::new(name)
+        }
+    }
+
+    pub fn add_field(mut self, ttype: CType, name: &str) -> Self {
+        self.fields.push(CField {
+            ttype,
+            name: name.to_string(),
+        });
+
+        self
+    }
+
+    pub fn build(self) -> CTypeDef {
+        if self.is_union {
+            CTypeDef::Union {
+                name: self.name,
+                fields: self.fields,
+            }
+        } else {
+            CTypeDef::Struct {
+                name: self.name,
+                fields: self.fields,
+            }
+        }
+    }
+}
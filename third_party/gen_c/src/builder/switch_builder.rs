@@ -0,0 +1,37 @@
+use crate::product::{
+    expr::CExpr,
+    statement::{CStatement, SwitchCase},
+};
+
+pub struct SwitchBuilder {
+    cond: CExpr,
+    cases: Vec<SwitchCase>,
+}
+
+impl SwitchBuilder {
+    pub fn new(cond: CExpr) -> Self {
+        Self {
+            cond,
+            cases: Vec::new(),
+        }
+    }
+
+    pub fn add_case(mut self, value: CExpr, statements: Vec<CStatement>) -> Self {
+        self.cases.push(SwitchCase::new(Some(value), statements));
+
+        self
+    }
+
+    pub fn with_default(mut self, statements: Vec<CStatement>) -> Self {
+        self.cases.push(SwitchCase::new(None, statements));
+
+        self
+    }
+
+    pub fn build(self) -> CStatement {
+        CStatement::Switch {
+            cond: self.cond,
+            cases: self.cases,
+        }
+    }
+}
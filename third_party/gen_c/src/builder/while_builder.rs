@@ -1,12 +1,12 @@
-use crate::product::statement::CStatement;
+use crate::product::{expr::CExpr, statement::CStatement};
 
 pub struct WhileBuilder {
-    cond: String,
+    cond: CExpr,
     body: CStatement,
 }
 
 impl WhileBuilder {
-    pub fn new(cond: String, body: CStatement) -> Self {
+    pub fn new(cond: CExpr, body: CStatement) -> Self {
         Self { cond, body }
     }
 
@@ -1,5 +1,6 @@
 use std::fmt;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CType {
     I8,
     I16,
@@ -16,9 +17,28 @@ pub enum CType {
     Bool,
     Char,
     Void,
+    Pointer(Box<CType>),
+    /// An array of a fixed number of elements, like `int32_t x[3]`
+    Array(Box<CType>, usize),
+    /// A type defined elsewhere, like a `typedef`, by its name
     Custom(String),
 }
 
+impl CType {
+    /// Declares `name` with this type, like `int32_t x`, `char *p` or
+    /// `int32_t (*p)[3]`
+    pub fn declare(&self, name: &str) -> String {
+        match self {
+            CType::Pointer(inner) if matches!(**inner, CType::Array(..)) => {
+                inner.declare(&format!("(*{name})"))
+            }
+            CType::Pointer(inner) => inner.declare(&format!("*{name}")),
+            CType::Array(inner, size) => inner.declare(&format!("{name}[{size}]")),
+            ttype => format!("{ttype} {name}"),
+        }
+    }
+}
+
 impl fmt::Display for CType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -37,6 +57,12 @@ impl fmt::Display for CType {
             CType::Bool => write!(f, "bool"),
             CType::Char => write!(f, "char"),
             CType::Void => write!(f, "void"),
+            CType::Pointer(inner) if matches!(**inner, CType::Pointer(_)) => write!(f, "{inner}*"),
+            CType::Pointer(inner) if matches!(**inner, CType::Array(..)) => {
+                write!(f, "{}", self.declare(""))
+            }
+            CType::Pointer(inner) => write!(f, "{inner} *"),
+            CType::Array(..) => write!(f, "{}", self.declare("")),
             CType::Custom(s) => write!(f, "{s}"),
         }
     }
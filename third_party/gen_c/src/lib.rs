@@ -1,24 +1,93 @@
-use crate::product::statement::CStatement;
+use std::collections::HashMap;
+
+use crate::{
+    ctype::CType,
+    product::{include::CInclude, statement::CStatement, typedef::CTypeDef},
+    style::CStyle,
+};
 
 pub mod builder;
 pub mod ctype;
 pub mod product;
+pub mod style;
 
+/// A translation unit. Whatever is added is printed in sections, in the order
+/// C needs: a header comment, includes, types, prototypes, globals and then
+/// the definitions of functions. Types are reordered so that each comes after the types it
+/// contains. Statements are laid out in its [`CStyle`].
 pub struct CCodeGen {
+    header: Vec<CStatement>,
+    includes: Vec<CInclude>,
+    types: Vec<CTypeDef>,
+    prototypes: Vec<CStatement>,
+    globals: Vec<CStatement>,
     statements: Vec<CStatement>,
-    indent: usize,
-    // includes: Vec<CInclude>,
+    style: CStyle,
 }
 
 #[allow(clippy::new_without_default)]
 impl CCodeGen {
     pub fn new() -> Self {
         Self {
+            header: Vec::new(),
+            includes: Vec::new(),
+            types: Vec::new(),
+            prototypes: Vec::new(),
+            globals: Vec::new(),
             statements: Vec::new(),
-            indent: 0,
+            style: CStyle::default(),
         }
     }
 
+    pub fn with_style(mut self, style: CStyle) -> Self {
+        self.style = style;
+
+        self
+    }
+
+    /// Adds a comment to the top of the file, like a note on how to compile it
+    pub fn add_header_comment(mut self, text: &str) -> Self {
+        self.header.push(CStatement::Comment {
+            text: text.to_string(),
+        });
+
+        self
+    }
+
+    pub fn add_include(mut self, include: CInclude) -> Self {
+        self.includes.push(include);
+
+        self
+    }
+
+    pub fn add_type(mut self, typedef: CTypeDef) -> Self {
+        self.types.push(typedef);
+
+        self
+    }
+
+    pub fn add_prototype(mut self, prototype: CStatement) -> Self {
+        assert!(
+            matches!(prototype, CStatement::Prototype { .. }),
+            "only prototypes go in the prototype section"
+        );
+        self.prototypes.push(prototype);
+
+        self
+    }
+
+    pub fn add_global(mut self, global: CStatement) -> Self {
+        assert!(
+            matches!(global, CStatement::Declaration { .. }),
+            "only declarations go in the global section"
+        );
+        self.globals.push(global);
+
+        self
+    }
+
+    /// Adds a statement after everything else, usually the definition of a
+    /// function
     pub fn add_statement(mut self, statement: CStatement) -> Self {
         self.statements.push(statement);
 
@@ -26,12 +95,122 @@ impl CCodeGen {
     }
 
     pub fn produce_c(self) -> String {
-        let mut out = String::new();
-        for statement in self.statements {
-            out.push_str(&statement.produce_c(self.indent));
-            out.push('\n');
+        let mut sections = Vec::new();
+        sections.push(
+            self.header
+                .iter()
+                .map(|comment| comment.produce_c(&self.style, 0))
+                .collect::<Vec<_>>(),
+        );
+        sections.push(
+            self.includes
+                .iter()
+                .map(|include| include.to_string())
+                .collect::<Vec<_>>(),
+        );
+
+        let types = order_types(self.types);
+        sections.push(
+            types
+                .iter()
+                .filter_map(|typedef| typedef.forward_declaration())
+                .collect(),
+        );
+        // Definitions span lines, so they are kept apart
+        let definitions = types
+            .iter()
+            .map(|typedef| typedef.produce_c(&self.style))
+            .collect::<Vec<_>>();
+        if !definitions.is_empty() {
+            sections.push(vec![definitions.join("\n\n")]);
+        }
+
+        for section in [self.prototypes, self.globals, self.statements] {
+            sections.push(
+                section
+                    .iter()
+                    .map(|statement| statement.produce_c(&self.style, 0))
+                    .collect(),
+            );
         }
 
+        let mut out = sections
+            .into_iter()
+            .filter(|section| !section.is_empty())
+            .map(|section| section.join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        out.push('\n');
+
         out
     }
 }
+
+/// Orders types so that each comes after the ones it needs defined first,
+/// keeping the order they were added in otherwise
+///
+/// # Panics
+///
+/// If a struct or union contains itself, which C can't define.
+fn order_types(types: Vec<CTypeDef>) -> Vec<CTypeDef> {
+    let index = types
+        .iter()
+        .enumerate()
+        .map(|(i, typedef)| (typedef.name().to_string(), i))
+        .collect::<HashMap<_, _>>();
+
+    // A struct or union is declared ahead of every definition, so one only
+    // pointed to doesn't need to be defined first
+    fn needed(ttype: &CType, index: &HashMap<String, usize>, types: &[CTypeDef]) -> Option<usize> {
+        match ttype {
+            CType::Custom(name) => index.get(name).copied(),
+            CType::Pointer(inner) => {
+                needed(inner, index, types).filter(|i| types[*i].tag().is_none())
+            }
+            CType::Array(inner, _) => needed(inner, index, types),
+            _ => None,
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Unvisited,
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        i: usize,
+        types: &[CTypeDef],
+        index: &HashMap<String, usize>,
+        state: &mut [State],
+        order: &mut Vec<usize>,
+    ) {
+        match state[i] {
+            State::Done => return,
+            State::Visiting => panic!("`{}` contains itself", types[i].name()),
+            State::Unvisited => {}
+        }
+
+        state[i] = State::Visiting;
+        for ttype in types[i].used_types() {
+            if let Some(dependency) = needed(ttype, index, types) {
+                visit(dependency, types, index, state, order);
+            }
+        }
+        state[i] = State::Done;
+        order.push(i);
+    }
+
+    let mut state = vec![State::Unvisited; types.len()];
+    let mut order = Vec::new();
+    for i in 0..types.len() {
+        visit(i, &types, &index, &mut state, &mut order);
+    }
+
+    let mut types = types.into_iter().map(Some).collect::<Vec<_>>();
+    order
+        .into_iter()
+        .map(|i| types[i].take().unwrap())
+        .collect()
+}
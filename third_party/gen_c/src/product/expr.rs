@@ -0,0 +1,438 @@
+use crate::ctype::CType;
+use std::fmt;
+
+/// A C expression. Printing it only adds the parentheses that C's precedence
+/// rules need, plus a few around bitwise operators that compilers warn about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CExpr {
+    /// A number, `true`, `false` or a character literal, spelled as it is
+    /// printed
+    Literal(String),
+    /// A string literal, escaped when printed
+    Str(String),
+    Ident(String),
+    Unary {
+        op: CUnaryOp,
+        operand: Box<CExpr>,
+    },
+    Binary {
+        op: CBinaryOp,
+        left: Box<CExpr>,
+        right: Box<CExpr>,
+    },
+    Call {
+        callee: Box<CExpr>,
+        args: Vec<CExpr>,
+    },
+    Cast {
+        ttype: CType,
+        expr: Box<CExpr>,
+    },
+    /// `expr.member`, or `expr->member` through a pointer
+    Member {
+        expr: Box<CExpr>,
+        member: String,
+        arrow: bool,
+    },
+    Index {
+        expr: Box<CExpr>,
+        index: Box<CExpr>,
+    },
+    /// `(ttype){elems}`
+    CompoundLiteral {
+        ttype: CType,
+        elems: Vec<CExpr>,
+    },
+    /// `{elems}`, only valid as the initializer of a declaration
+    InitList(Vec<CExpr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CUnaryOp {
+    Neg,
+    Not,
+    BitNot,
+    Deref,
+    AddrOf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CBinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+    Assign,
+    /// A compound assignment like `+=`, holding the operator it applies
+    AssignOp(CAssignOp),
+}
+
+/// Operators that have a compound assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CAssignOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    BitAnd,
+    BitXor,
+    BitOr,
+}
+
+/// Binding strength of C's operators, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    Assign,
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Equality,
+    Relational,
+    Shift,
+    Additive,
+    Multiplicative,
+    /// Unary operators and casts
+    Unary,
+    /// Calls, indexing and member access
+    Postfix,
+    Primary,
+}
+
+impl CExpr {
+    pub fn literal(value: impl ToString) -> Self {
+        CExpr::Literal(value.to_string())
+    }
+
+    pub fn ident(name: &str) -> Self {
+        CExpr::Ident(name.to_string())
+    }
+
+    pub fn unary(op: CUnaryOp, operand: CExpr) -> Self {
+        CExpr::Unary {
+            op,
+            operand: Box::new(operand),
+        }
+    }
+
+    pub fn binary(op: CBinaryOp, left: CExpr, right: CExpr) -> Self {
+        CExpr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn assign(place: CExpr, value: CExpr) -> Self {
+        CExpr::binary(CBinaryOp::Assign, place, value)
+    }
+
+    /// Calls the function named `name`
+    pub fn call(name: &str, args: Vec<CExpr>) -> Self {
+        CExpr::Call {
+            callee: Box::new(CExpr::ident(name)),
+            args,
+        }
+    }
+
+    pub fn cast(ttype: CType, expr: CExpr) -> Self {
+        CExpr::Cast {
+            ttype,
+            expr: Box::new(expr),
+        }
+    }
+
+    pub fn member(expr: CExpr, member: &str) -> Self {
+        CExpr::Member {
+            expr: Box::new(expr),
+            member: member.to_string(),
+            arrow: false,
+        }
+    }
+
+    pub fn arrow(expr: CExpr, member: &str) -> Self {
+        CExpr::Member {
+            expr: Box::new(expr),
+            member: member.to_string(),
+            arrow: true,
+        }
+    }
+
+    pub fn index(expr: CExpr, index: CExpr) -> Self {
+        CExpr::Index {
+            expr: Box::new(expr),
+            index: Box::new(index),
+        }
+    }
+
+    fn prec(&self) -> Prec {
+        match self {
+            // A negative number binds like the `-` it starts with
+            CExpr::Literal(value) if value.starts_with('-') => Prec::Unary,
+            CExpr::Literal(_)
+            | CExpr::Str(_)
+            | CExpr::Ident(_)
+            | CExpr::InitList(_)
+            | CExpr::CompoundLiteral { .. } => Prec::Primary,
+            CExpr::Unary { .. } | CExpr::Cast { .. } => Prec::Unary,
+            CExpr::Binary { op, .. } => op.prec(),
+            CExpr::Call { .. } | CExpr::Member { .. } | CExpr::Index { .. } => Prec::Postfix,
+        }
+    }
+
+    /// Writes the expression, in parentheses if it binds weaker than `min`
+    fn fmt_prec(&self, f: &mut fmt::Formatter<'_>, min: Prec) -> fmt::Result {
+        if self.prec() < min {
+            write!(f, "(")?;
+            self.fmt_prec(f, Prec::Assign)?;
+            return write!(f, ")");
+        }
+
+        match self {
+            CExpr::Literal(value) | CExpr::Ident(value) => write!(f, "{value}"),
+            CExpr::Str(value) => write!(f, "\"{}\"", escape(value)),
+            CExpr::Unary { op, operand } => {
+                write!(f, "{op}")?;
+                // `- -x` and `-(-1)` would lex as `--`
+                let doubled = match (op, operand.as_ref()) {
+                    (CUnaryOp::Neg, CExpr::Literal(value)) => value.starts_with('-'),
+                    (
+                        CUnaryOp::Neg,
+                        CExpr::Unary {
+                            op: CUnaryOp::Neg, ..
+                        },
+                    ) => true,
+                    _ => false,
+                };
+                operand.fmt_prec(f, if doubled { Prec::Primary } else { Prec::Unary })
+            }
+            CExpr::Binary { op, left, right } => {
+                let prec = op.prec();
+                let (left_min, right_min) = if prec == Prec::Assign {
+                    (Prec::Unary, Prec::Assign)
+                } else {
+                    (prec, Self::above(prec))
+                };
+                left.fmt_operand(f, *op, left_min)?;
+                write!(f, " {op} ")?;
+                right.fmt_operand(f, *op, right_min)
+            }
+            CExpr::Call { callee, args } => {
+                callee.fmt_prec(f, Prec::Postfix)?;
+                write!(f, "(")?;
+                Self::fmt_list(f, args)?;
+                write!(f, ")")
+            }
+            CExpr::Cast { ttype, expr } => {
+                write!(f, "({ttype})")?;
+                expr.fmt_prec(f, Prec::Unary)
+            }
+            CExpr::Member {
+                expr,
+                member,
+                arrow,
+            } => {
+                expr.fmt_prec(f, Prec::Postfix)?;
+                write!(f, "{}{member}", if *arrow { "->" } else { "." })
+            }
+            CExpr::Index { expr, index } => {
+                expr.fmt_prec(f, Prec::Postfix)?;
+                write!(f, "[")?;
+                index.fmt_prec(f, Prec::Assign)?;
+                write!(f, "]")
+            }
+            CExpr::CompoundLiteral { ttype, elems } => {
+                write!(f, "({ttype}){{")?;
+                Self::fmt_list(f, elems)?;
+                write!(f, "}}")
+            }
+            CExpr::InitList(elems) => {
+                write!(f, "{{")?;
+                Self::fmt_list(f, elems)?;
+                write!(f, "}}")
+            }
+        }
+    }
+
+    /// Writes an operand of `parent`. Mixing `&&` with `||`, or a bitwise
+    /// operator or shift with another binary operator, is parenthesized even
+    /// when precedence doesn't need it, as it is easily misread.
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parent: CBinaryOp, min: Prec) -> fmt::Result {
+        let unclear = match self {
+            CExpr::Binary { op, .. } if *op != parent && parent.prec() != Prec::Assign => {
+                let mixes_logical = parent == CBinaryOp::Or && *op == CBinaryOp::And;
+                mixes_logical || parent.is_bitwise() || op.is_bitwise()
+            }
+            _ => false,
+        };
+
+        if unclear && self.prec() > Prec::Assign {
+            self.fmt_prec(f, Prec::Primary)
+        } else {
+            self.fmt_prec(f, min)
+        }
+    }
+
+    fn fmt_list(f: &mut fmt::Formatter<'_>, exprs: &[CExpr]) -> fmt::Result {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            expr.fmt_prec(f, Prec::Assign)?;
+        }
+
+        Ok(())
+    }
+
+    /// The precedence right after `prec`, which the right operand of a
+    /// left-associative operator needs to not be regrouped
+    fn above(prec: Prec) -> Prec {
+        match prec {
+            Prec::Assign => Prec::Or,
+            Prec::Or => Prec::And,
+            Prec::And => Prec::BitOr,
+            Prec::BitOr => Prec::BitXor,
+            Prec::BitXor => Prec::BitAnd,
+            Prec::BitAnd => Prec::Equality,
+            Prec::Equality => Prec::Relational,
+            Prec::Relational => Prec::Shift,
+            Prec::Shift => Prec::Additive,
+            Prec::Additive => Prec::Multiplicative,
+            Prec::Multiplicative => Prec::Unary,
+            Prec::Unary => Prec::Postfix,
+            Prec::Postfix | Prec::Primary => Prec::Primary,
+        }
+    }
+}
+
+/// Escapes a string for a C string literal. Characters outside of ASCII are
+/// kept as they are, C compilers reading the source as UTF-8.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\{:03o}", c as u8)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+impl fmt::Display for CExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_prec(f, Prec::Assign)
+    }
+}
+
+impl CBinaryOp {
+    fn prec(&self) -> Prec {
+        match self {
+            CBinaryOp::Mul | CBinaryOp::Div | CBinaryOp::Rem => Prec::Multiplicative,
+            CBinaryOp::Add | CBinaryOp::Sub => Prec::Additive,
+            CBinaryOp::Shl | CBinaryOp::Shr => Prec::Shift,
+            CBinaryOp::Lt | CBinaryOp::Le | CBinaryOp::Gt | CBinaryOp::Ge => Prec::Relational,
+            CBinaryOp::Eq | CBinaryOp::Ne => Prec::Equality,
+            CBinaryOp::BitAnd => Prec::BitAnd,
+            CBinaryOp::BitXor => Prec::BitXor,
+            CBinaryOp::BitOr => Prec::BitOr,
+            CBinaryOp::And => Prec::And,
+            CBinaryOp::Or => Prec::Or,
+            CBinaryOp::Assign | CBinaryOp::AssignOp(_) => Prec::Assign,
+        }
+    }
+
+    fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            CBinaryOp::BitAnd
+                | CBinaryOp::BitXor
+                | CBinaryOp::BitOr
+                | CBinaryOp::Shl
+                | CBinaryOp::Shr
+        )
+    }
+}
+
+impl fmt::Display for CUnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            CUnaryOp::Neg => "-",
+            CUnaryOp::Not => "!",
+            CUnaryOp::BitNot => "~",
+            CUnaryOp::Deref => "*",
+            CUnaryOp::AddrOf => "&",
+        };
+
+        write!(f, "{op}")
+    }
+}
+
+impl fmt::Display for CBinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CBinaryOp::Mul => write!(f, "*"),
+            CBinaryOp::Div => write!(f, "/"),
+            CBinaryOp::Rem => write!(f, "%"),
+            CBinaryOp::Add => write!(f, "+"),
+            CBinaryOp::Sub => write!(f, "-"),
+            CBinaryOp::Shl => write!(f, "<<"),
+            CBinaryOp::Shr => write!(f, ">>"),
+            CBinaryOp::Lt => write!(f, "<"),
+            CBinaryOp::Le => write!(f, "<="),
+            CBinaryOp::Gt => write!(f, ">"),
+            CBinaryOp::Ge => write!(f, ">="),
+            CBinaryOp::Eq => write!(f, "=="),
+            CBinaryOp::Ne => write!(f, "!="),
+            CBinaryOp::BitAnd => write!(f, "&"),
+            CBinaryOp::BitXor => write!(f, "^"),
+            CBinaryOp::BitOr => write!(f, "|"),
+            CBinaryOp::And => write!(f, "&&"),
+            CBinaryOp::Or => write!(f, "||"),
+            CBinaryOp::Assign => write!(f, "="),
+            CBinaryOp::AssignOp(op) => write!(f, "{op}="),
+        }
+    }
+}
+
+impl fmt::Display for CAssignOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            CAssignOp::Mul => "*",
+            CAssignOp::Div => "/",
+            CAssignOp::Rem => "%",
+            CAssignOp::Add => "+",
+            CAssignOp::Sub => "-",
+            CAssignOp::Shl => "<<",
+            CAssignOp::Shr => ">>",
+            CAssignOp::BitAnd => "&",
+            CAssignOp::BitXor => "^",
+            CAssignOp::BitOr => "|",
+        };
+
+        write!(f, "{op}")
+    }
+}
@@ -0,0 +1,17 @@
+use std::fmt;
+
+pub enum CInclude {
+    /// `#include <name>`
+    System(String),
+    /// `#include "name"`
+    Local(String),
+}
+
+impl fmt::Display for CInclude {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CInclude::System(name) => write!(f, "#include <{name}>"),
+            CInclude::Local(name) => write!(f, "#include \"{name}\""),
+        }
+    }
+}
@@ -1 +1,7 @@
+pub mod expr;
+pub mod include;
 pub mod statement;
+pub mod typedef;
+
+#[cfg(test)]
+mod tests;
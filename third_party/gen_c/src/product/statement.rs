@@ -1,12 +1,17 @@
-use crate::ctype::CType;
-use std::fmt::Write;
+use crate::{
+    ctype::CType,
+    product::expr::CExpr,
+    style::{BraceStyle, CStyle},
+};
+use std::fmt::{self, Write};
 
 pub enum CStatement {
     Declaration {
-        modifier: Option<String>,
+        storage: Option<StorageClass>,
+        constness: Option<ConstKind>,
         ttype: CType,
         name: String,
-        rhs: Option<String>,
+        rhs: Option<CExpr>,
     },
     Function {
         modifiers: Vec<String>,
@@ -15,39 +20,96 @@ pub enum CStatement {
         params: Vec<String>,
         body: Box<CStatement>,
     },
+    Prototype {
+        modifiers: Vec<String>,
+        return_type: CType,
+        name: String,
+        params: Vec<String>,
+    },
     Block {
         statements: Vec<CStatement>,
     },
     Return {
-        rhs: Option<String>,
+        rhs: Option<CExpr>,
     },
     While {
-        cond: String,
+        cond: CExpr,
+        body: Box<CStatement>,
+    },
+    DoWhile {
+        body: Box<CStatement>,
+        cond: CExpr,
+    },
+    For {
+        /// A declaration or an expression statement
+        init: Option<Box<CStatement>>,
+        cond: Option<CExpr>,
+        step: Option<CExpr>,
         body: Box<CStatement>,
     },
+    Switch {
+        cond: CExpr,
+        cases: Vec<SwitchCase>,
+    },
     If {
-        initial_cond: String,
+        initial_cond: CExpr,
         initial_block: Box<CStatement>,
         branches: Vec<IfBranch>,
     },
+    Expression {
+        expr: CExpr,
+    },
+    Break,
+    Continue,
+    Goto {
+        label: String,
+    },
+    /// A label followed by an empty statement, so it can end a block
+    Label {
+        name: String,
+    },
+    /// A `//` comment, one per line of the text
+    Comment {
+        text: String,
+    },
+    /// A `#line` directive, making the compiler and debuggers take the next
+    /// line for line `line` of `file`. Printed at the start of its line
+    /// whatever the depth.
+    Line {
+        line: usize,
+        file: Option<String>,
+    },
 }
 
 impl CStatement {
-    pub fn produce_c(self, indent: usize) -> String {
+    /// Prints the statement nested `depth` levels deep
+    pub fn produce_c(&self, style: &CStyle, depth: usize) -> String {
+        let indent = style.indent(depth);
         match self {
             Self::Declaration {
-                modifier,
+                storage,
+                constness,
                 ttype,
                 name,
                 rhs,
             } => {
+                let declarator = match constness {
+                    None => ttype.declare(name),
+                    // A `const` in front of a pointer type would apply to
+                    // what it points to
+                    Some(ConstKind::Const) if matches!(ttype, CType::Pointer(_)) => {
+                        ttype.declare(&format!("const {name}"))
+                    }
+                    Some(ConstKind::Const) => format!("const {}", ttype.declare(name)),
+                    Some(ConstKind::ConstPtr) => ttype.declare(&format!("*const {name}")),
+                    Some(ConstKind::ConstConstPtr) => {
+                        format!("const {}", ttype.declare(&format!("*const {name}")))
+                    }
+                };
                 format!(
-                    "{}{} {}{}{}",
-                    " ".repeat(indent),
-                    ttype,
-                    modifier.map_or("".to_string(), |s| s.to_string() + " "),
-                    name,
-                    rhs.map_or(";".to_string(), |e| format!(" = {};", e))
+                    "{indent}{}{declarator}{};",
+                    storage.map_or("".to_string(), |s| format!("{s} ")),
+                    rhs.as_ref().map_or("".to_string(), |e| format!(" = {e}"))
                 )
             }
             Self::Function {
@@ -56,116 +118,186 @@ impl CStatement {
                 name,
                 params,
                 body,
-            } => {
-                let body = if let CStatement::Block { statements } = *body {
-                    if statements.is_empty() {
-                        ";".to_string()
-                    } else {
-                        format!(
-                            " {{
-{}
-}}",
-                            statements
-                                .into_iter()
-                                .map(|s| s.produce_c(indent + 4))
-                                .collect::<Vec<_>>()
-                                .join("\n")
-                        )
-                    }
+            } => format!(
+                "{indent}{}{}",
+                signature(modifiers, return_type, name, params),
+                body_c(body, style, depth)
+            ),
+            Self::Prototype {
+                modifiers,
+                return_type,
+                name,
+                params,
+            } => format!(
+                "{indent}{};",
+                signature(modifiers, return_type, name, params)
+            ),
+            Self::Block { statements } => {
+                format!("{indent}{}", braced(statements, style, depth))
+            }
+            Self::Return { rhs } => match rhs {
+                Some(rhs) => format!("{indent}return {rhs};"),
+                None => format!("{indent}return;"),
+            },
+            Self::While { cond, body } => {
+                format!("{indent}while ({cond}){}", body_c(body, style, depth))
+            }
+            Self::DoWhile { body, cond } => {
+                // The `while` stays after the closing brace in every style
+                let gap = if matches!(**body, Self::Block { .. }) {
+                    " ".to_string()
                 } else {
-                    unreachable!()
+                    format!("\n{indent}")
                 };
                 format!(
-                    "{}{} {}({}){}",
-                    if modifiers.is_empty() {
-                        "".to_string()
-                    } else {
-                        modifiers.join(" ")
-                    },
-                    return_type,
-                    name,
-                    params.join(", "),
-                    body,
+                    "{indent}do{}{gap}while ({cond});",
+                    body_c(body, style, depth)
                 )
             }
-            Self::Block { statements } => format!(
-                "{}{{
-{}
-{}}}",
-                " ".repeat(indent),
-                statements
-                    .into_iter()
-                    .map(|cs| cs.produce_c(indent + 4))
-                    .collect::<Vec<_>>()
-                    .join("\n"),
-                " ".repeat(indent)
-            ),
-            Self::Return { rhs } => format!(
-                "{}return {};",
-                " ".repeat(indent),
-                rhs.unwrap_or("".to_string())
-            ),
-
-            Self::While { cond, body } => {
+            Self::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                // The statement brings its own `;`
+                let init = init
+                    .as_ref()
+                    .map_or(";".to_string(), |init| init.produce_c(style, 0));
+                let cond = cond
+                    .as_ref()
+                    .map_or("".to_string(), |cond| format!(" {cond}"));
+                let step = step
+                    .as_ref()
+                    .map_or("".to_string(), |step| format!(" {step}"));
                 format!(
-                    "{}while ({})
-{}",
-                    " ".repeat(indent),
-                    cond,
-                    body.produce_c(indent)
+                    "{indent}for ({init}{cond};{step}){}",
+                    body_c(body, style, depth)
                 )
             }
+            Self::Switch { cond, cases } => {
+                let mut out = format!("{indent}switch ({cond}){}{{", style.brace_gap(depth));
+                let case_indent = style.indent(depth + 1);
+                for case in cases {
+                    let _ = match &case.value {
+                        Some(value) => write!(out, "\n{case_indent}case {value}:"),
+                        None => write!(out, "\n{case_indent}default:"),
+                    };
+                    for statement in &case.statements {
+                        out.push('\n');
+                        out.push_str(&statement.produce_c(style, depth + 2));
+                    }
+                }
+                let _ = write!(out, "\n{indent}}}");
+
+                out
+            }
             Self::If {
                 initial_cond,
                 initial_block,
                 branches,
             } => {
-                let mut initial_branch = format!(
-                    "{}if ({})
-{}",
-                    " ".repeat(indent),
-                    initial_cond,
-                    initial_block.produce_c(indent)
+                let mut out = format!(
+                    "{indent}if ({initial_cond}){}",
+                    body_c(initial_block, style, depth)
                 );
 
+                let mut previous = initial_block;
                 for branch in branches {
-                    let _ = match branch.cond {
-                        Some(s) => write!(
-                            initial_branch,
-                            " else if ({})
-{}",
-                            s,
-                            branch.body.produce_c(indent)
-                        ),
-                        None => write!(
-                            initial_branch,
-                            " else
-{}",
-                            branch.body.produce_c(indent)
-                        ),
+                    // `else` follows a closing brace on its line, unless
+                    // braces go on lines of their own
+                    let gap = match **previous {
+                        Self::Block { .. } if style.brace_style == BraceStyle::Attached => {
+                            " ".to_string()
+                        }
+                        _ => format!("\n{indent}"),
                     };
+                    let _ = match &branch.cond {
+                        Some(cond) => write!(out, "{gap}else if ({cond})"),
+                        None => write!(out, "{gap}else"),
+                    };
+                    out.push_str(&body_c(&branch.body, style, depth));
+                    previous = &branch.body;
                 }
 
-                initial_branch
+                out
             }
+            Self::Expression { expr } => format!("{indent}{expr};"),
+            Self::Break => format!("{indent}break;"),
+            Self::Continue => format!("{indent}continue;"),
+            Self::Goto { label } => format!("{indent}goto {label};"),
+            Self::Label { name } => format!("{indent}{name}: ;"),
+            Self::Comment { text } => text
+                .lines()
+                .map(|line| format!("{indent}// {line}").trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::Line { line, file } => match file {
+                Some(file) => format!("#line {line} \"{}\"", escape_file(file)),
+                None => format!("#line {line}"),
+            },
+        }
+    }
+}
+
+/// Escapes a file name for the string literal of a `#line`
+fn escape_file(file: &str) -> String {
+    file.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn signature(modifiers: &[String], return_type: &CType, name: &str, params: &[String]) -> String {
+    format!(
+        "{}{}",
+        modifiers
+            .iter()
+            .map(|m| format!("{m} "))
+            .collect::<String>(),
+        return_type.declare(&format!("{name}({})", params.join(", ")))
+    )
+}
+
+/// The statements between braces, the closing one indented `depth` levels
+fn braced(statements: &[CStatement], style: &CStyle, depth: usize) -> String {
+    let mut out = "{".to_string();
+    for statement in statements {
+        out.push('\n');
+        out.push_str(&statement.produce_c(style, depth + 1));
+    }
+    let _ = write!(out, "\n{}}}", style.indent(depth));
+
+    out
+}
+
+/// The body of a function or a control statement, printed after the line
+/// that opens it. A body that isn't a block goes on the next line, one
+/// level deeper.
+fn body_c(body: &CStatement, style: &CStyle, depth: usize) -> String {
+    match body {
+        CStatement::Block { statements } => {
+            format!(
+                "{}{}",
+                style.brace_gap(depth),
+                braced(statements, style, depth)
+            )
         }
+        body => format!("\n{}", body.produce_c(style, depth + 1)),
     }
 }
 
 pub struct IfBranch {
-    pub(crate) cond: Option<String>,
+    pub(crate) cond: Option<CExpr>,
     pub(crate) body: Box<CStatement>,
 }
 
 impl IfBranch {
-    pub fn new(cond: Option<String>, body: CStatement) -> Self {
+    pub fn new(cond: Option<CExpr>, body: CStatement) -> Self {
         Self {
             cond,
             body: Box::new(body),
         }
     }
 
-    pub fn cond(&self) -> Option<&String> {
+    pub fn cond(&self) -> Option<&CExpr> {
         self.cond.as_ref()
     }
 
@@ -173,3 +305,53 @@ impl IfBranch {
         &self.body
     }
 }
+
+/// A `case` of a `switch`, or its `default` when it has no value. Its
+/// statements fall through to the next case unless they end with a `break`.
+pub struct SwitchCase {
+    pub(crate) value: Option<CExpr>,
+    pub(crate) statements: Vec<CStatement>,
+}
+
+impl SwitchCase {
+    pub fn new(value: Option<CExpr>, statements: Vec<CStatement>) -> Self {
+        Self { value, statements }
+    }
+
+    pub fn value(&self) -> Option<&CExpr> {
+        self.value.as_ref()
+    }
+
+    pub fn statements(&self) -> &[CStatement] {
+        &self.statements
+    }
+}
+
+/// Where a declaration outside of a function is visible from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageClass {
+    /// Only in its own translation unit
+    Static,
+    /// Defined in another translation unit
+    Extern,
+}
+
+impl fmt::Display for StorageClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageClass::Static => write!(f, "static"),
+            StorageClass::Extern => write!(f, "extern"),
+        }
+    }
+}
+
+/// What a declaration can't change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstKind {
+    /// The value declared, `const T name`
+    Const,
+    /// A pointer to a `T` that can't point elsewhere, `T *const name`
+    ConstPtr,
+    /// Both the pointer and what it points to, `const T *const name`
+    ConstConstPtr,
+}
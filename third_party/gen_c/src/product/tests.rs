@@ -0,0 +1,294 @@
+use crate::{
+    builder::{
+        block_builder::BlockBuilder,
+        decl_builder::{ConstKind, DeclBuilder},
+        function_builder::FunctionBuilder,
+        return_builder::ReturnBuilder,
+        while_builder::WhileBuilder,
+    },
+    ctype::CType,
+    product::{
+        expr::{CAssignOp, CBinaryOp, CExpr, CUnaryOp},
+        statement::CStatement,
+    },
+    style::CStyle,
+};
+
+fn binary(op: CBinaryOp, left: CExpr, right: CExpr) -> CExpr {
+    CExpr::binary(op, left, right)
+}
+
+fn x() -> CExpr {
+    CExpr::ident("x")
+}
+
+fn y() -> CExpr {
+    CExpr::ident("y")
+}
+
+fn one() -> CExpr {
+    CExpr::literal(1)
+}
+
+#[test]
+fn parenthesizes_only_by_precedence() {
+    let sum = binary(CBinaryOp::Add, x(), y());
+    let product = binary(CBinaryOp::Mul, x(), y());
+
+    assert_eq!(
+        binary(CBinaryOp::Add, product.clone(), one()).to_string(),
+        "x * y + 1"
+    );
+    assert_eq!(
+        binary(CBinaryOp::Mul, sum.clone(), one()).to_string(),
+        "(x + y) * 1"
+    );
+    assert_eq!(
+        binary(CBinaryOp::Lt, sum.clone(), product).to_string(),
+        "x + y < x * y"
+    );
+    assert_eq!(
+        CExpr::assign(x(), binary(CBinaryOp::Add, sum.clone(), one())).to_string(),
+        "x = x + y + 1"
+    );
+}
+
+#[test]
+fn keeps_grouping_of_right_operand() {
+    let difference = binary(CBinaryOp::Sub, x(), y());
+
+    assert_eq!(
+        binary(CBinaryOp::Sub, one(), difference.clone()).to_string(),
+        "1 - (x - y)"
+    );
+    assert_eq!(
+        binary(CBinaryOp::Sub, difference, one()).to_string(),
+        "x - y - 1"
+    );
+    // Assignment groups from the right instead
+    assert_eq!(
+        CExpr::assign(x(), CExpr::assign(y(), one())).to_string(),
+        "x = y = 1"
+    );
+}
+
+#[test]
+fn parenthesizes_mixed_bitwise_and_logical_operators() {
+    let and = binary(CBinaryOp::BitAnd, x(), one());
+    let shift = binary(CBinaryOp::Shl, one(), CExpr::literal(4));
+
+    assert_eq!(
+        binary(CBinaryOp::BitOr, shift, and.clone()).to_string(),
+        "(1 << 4) | (x & 1)"
+    );
+    assert_eq!(
+        binary(CBinaryOp::Shl, x(), binary(CBinaryOp::Add, y(), one())).to_string(),
+        "x << (y + 1)"
+    );
+    assert_eq!(
+        binary(CBinaryOp::Eq, and.clone(), one()).to_string(),
+        "(x & 1) == 1"
+    );
+    assert_eq!(binary(CBinaryOp::BitAnd, and, y()).to_string(), "x & 1 & y");
+    assert_eq!(
+        binary(
+            CBinaryOp::Or,
+            binary(CBinaryOp::And, x(), y()),
+            CExpr::unary(CUnaryOp::Not, x())
+        )
+        .to_string(),
+        "(x && y) || !x"
+    );
+    assert_eq!(
+        CExpr::assign(x(), binary(CBinaryOp::BitXor, x(), y())).to_string(),
+        "x = x ^ y"
+    );
+}
+
+#[test]
+fn prints_unary_operators_and_casts() {
+    let sum = binary(CBinaryOp::Add, x(), y());
+    let negated = CExpr::unary(CUnaryOp::Neg, CExpr::literal(5));
+
+    assert_eq!(
+        CExpr::unary(CUnaryOp::Not, sum.clone()).to_string(),
+        "!(x + y)"
+    );
+    assert_eq!(CExpr::cast(CType::U8, sum).to_string(), "(uint8_t)(x + y)");
+    assert_eq!(
+        CExpr::cast(CType::U8, CExpr::unary(CUnaryOp::BitNot, x())).to_string(),
+        "(uint8_t)~x"
+    );
+    assert_eq!(
+        binary(CBinaryOp::Mul, negated.clone(), x()).to_string(),
+        "-5 * x"
+    );
+    // Two minus signs in a row would be a decrement
+    assert_eq!(CExpr::unary(CUnaryOp::Neg, negated).to_string(), "-(-5)");
+    assert_eq!(
+        CExpr::unary(CUnaryOp::Neg, CExpr::literal(-5)).to_string(),
+        "-(-5)"
+    );
+    assert_eq!(
+        CExpr::unary(CUnaryOp::Deref, CExpr::ident("p")).to_string(),
+        "*p"
+    );
+}
+
+#[test]
+fn prints_postfix_expressions() {
+    let p = CExpr::ident("p");
+    let deref = CExpr::unary(CUnaryOp::Deref, p.clone());
+
+    assert_eq!(
+        CExpr::call(
+            "f",
+            vec![binary(CBinaryOp::Add, x(), y()), CExpr::assign(x(), one())]
+        )
+        .to_string(),
+        "f(x + y, x = 1)"
+    );
+    assert_eq!(CExpr::member(deref.clone(), "len").to_string(), "(*p).len");
+    assert_eq!(CExpr::arrow(p.clone(), "len").to_string(), "p->len");
+    assert_eq!(
+        CExpr::index(
+            CExpr::member(p, "items"),
+            binary(CBinaryOp::Sub, x(), one())
+        )
+        .to_string(),
+        "p.items[x - 1]"
+    );
+    assert_eq!(
+        CExpr::unary(CUnaryOp::AddrOf, CExpr::index(x(), one())).to_string(),
+        "&x[1]"
+    );
+}
+
+#[test]
+fn prints_literals_and_initializers() {
+    assert_eq!(
+        CExpr::Str("sabi \"niya\"\n\\".to_string()).to_string(),
+        "\"sabi \\\"niya\\\"\\n\\\\\""
+    );
+    assert_eq!(
+        CExpr::CompoundLiteral {
+            ttype: CType::Custom("struct punto".to_string()),
+            elems: vec![one(), CExpr::literal(2)],
+        }
+        .to_string(),
+        "(struct punto){1, 2}"
+    );
+    assert_eq!(
+        CExpr::InitList(vec![one(), CExpr::literal(2)]).to_string(),
+        "{1, 2}"
+    );
+    assert_eq!(
+        binary(
+            CBinaryOp::AssignOp(CAssignOp::Shr),
+            x(),
+            binary(CBinaryOp::Add, y(), one())
+        )
+        .to_string(),
+        "x >>= y + 1"
+    );
+}
+
+fn produce(statement: CStatement, depth: usize) -> String {
+    statement.produce_c(&CStyle::default(), depth)
+}
+
+#[test]
+fn prints_empty_function_with_body() {
+    assert_eq!(
+        produce(FunctionBuilder::new(CType::Void, "wala").build(), 0),
+        "void wala() {\n}"
+    );
+    assert_eq!(
+        produce(
+            FunctionBuilder::new(CType::Void, "wala").build_prototype(),
+            0
+        ),
+        "void wala();"
+    );
+}
+
+#[test]
+fn separates_modifiers_from_return_type() {
+    let function = || FunctionBuilder::new(CType::I32, "f").as_static();
+
+    assert_eq!(produce(function().build(), 0), "static int32_t f() {\n}");
+    assert_eq!(
+        produce(function().build_prototype(), 0),
+        "static int32_t f();"
+    );
+}
+
+#[test]
+fn indents_nested_bodies_to_their_depth() {
+    let function = FunctionBuilder::new(CType::I32, "f")
+        .add_statement(
+            WhileBuilder::new(
+                x(),
+                BlockBuilder::new()
+                    .add_statement(ReturnBuilder::new().with_rhs(one()).build())
+                    .build(),
+            )
+            .build(),
+        )
+        .build();
+
+    assert_eq!(
+        produce(function, 1),
+        "    int32_t f() {\n        while (x) {\n            return 1;\n        }\n    }"
+    );
+}
+
+#[test]
+fn puts_const_where_it_applies() {
+    let declare =
+        |ttype: CType, kind| produce(DeclBuilder::new(ttype, "p").as_const(kind).build(), 0);
+    let pointer = || CType::Pointer(Box::new(CType::Char));
+
+    assert_eq!(declare(CType::I32, ConstKind::Const), "const int32_t p;");
+    assert_eq!(declare(pointer(), ConstKind::Const), "char *const p;");
+    assert_eq!(declare(CType::Char, ConstKind::ConstPtr), "char *const p;");
+    assert_eq!(
+        declare(CType::Char, ConstKind::ConstConstPtr),
+        "const char *const p;"
+    );
+    assert_eq!(
+        produce(
+            DeclBuilder::new(CType::Pointer(Box::new(pointer())), "argv").build(),
+            0
+        ),
+        "char **argv;"
+    );
+}
+
+#[test]
+fn declares_arrays_inside_out() {
+    let array = || CType::Array(Box::new(CType::I32), 3);
+
+    assert_eq!(
+        produce(DeclBuilder::new(array(), "x").build(), 0),
+        "int32_t x[3];"
+    );
+    assert_eq!(
+        produce(
+            DeclBuilder::new(CType::Array(Box::new(array()), 2), "x").build(),
+            0
+        ),
+        "int32_t x[2][3];"
+    );
+    assert_eq!(
+        produce(
+            DeclBuilder::new(CType::Pointer(Box::new(array())), "p").build(),
+            0
+        ),
+        "int32_t (*p)[3];"
+    );
+    assert_eq!(
+        CType::Pointer(Box::new(array())).to_string(),
+        "int32_t (*)[3]"
+    );
+}
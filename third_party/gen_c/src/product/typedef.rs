@@ -0,0 +1,96 @@
+use crate::{ctype::CType, product::expr::CExpr, style::CStyle};
+use std::fmt::Write;
+
+/// A type defined at the top of a translation unit. Structs, unions and enums
+/// are given a `typedef` of the same name, so they are referred to by
+/// `CType::Custom(name)` alone.
+pub enum CTypeDef {
+    Struct {
+        name: String,
+        fields: Vec<CField>,
+    },
+    Union {
+        name: String,
+        fields: Vec<CField>,
+    },
+    Enum {
+        name: String,
+        variants: Vec<(String, Option<CExpr>)>,
+    },
+    /// `typedef ttype name;`
+    Alias {
+        name: String,
+        ttype: CType,
+    },
+}
+
+pub struct CField {
+    pub ttype: CType,
+    pub name: String,
+}
+
+impl CTypeDef {
+    pub fn name(&self) -> &str {
+        match self {
+            CTypeDef::Struct { name, .. }
+            | CTypeDef::Union { name, .. }
+            | CTypeDef::Enum { name, .. }
+            | CTypeDef::Alias { name, .. } => name,
+        }
+    }
+
+    /// The keyword of a type that can be declared before it is defined
+    pub(crate) fn tag(&self) -> Option<&'static str> {
+        match self {
+            CTypeDef::Struct { .. } => Some("struct"),
+            CTypeDef::Union { .. } => Some("union"),
+            CTypeDef::Enum { .. } | CTypeDef::Alias { .. } => None,
+        }
+    }
+
+    /// The types this one is made of
+    pub(crate) fn used_types(&self) -> Vec<&CType> {
+        match self {
+            CTypeDef::Struct { fields, .. } | CTypeDef::Union { fields, .. } => {
+                fields.iter().map(|field| &field.ttype).collect()
+            }
+            CTypeDef::Enum { .. } => Vec::new(),
+            CTypeDef::Alias { ttype, .. } => vec![ttype],
+        }
+    }
+
+    /// The `typedef` that lets a struct or union be referred to by its name
+    /// before its definition
+    pub(crate) fn forward_declaration(&self) -> Option<String> {
+        self.tag()
+            .map(|tag| format!("typedef {tag} {0} {0};", self.name()))
+    }
+
+    /// Prints the definition, which for structs and unions expects the
+    /// `typedef` of [`CTypeDef::forward_declaration`] to come first
+    pub fn produce_c(&self, style: &CStyle) -> String {
+        let indent = style.indent(1);
+        match self {
+            CTypeDef::Struct { name, fields } | CTypeDef::Union { name, fields } => {
+                let mut out = format!("{} {name}{}{{", self.tag().unwrap(), style.brace_gap(0));
+                for field in fields {
+                    let _ = write!(out, "\n{indent}{};", field.ttype.declare(&field.name));
+                }
+                out.push_str("\n};");
+                out
+            }
+            CTypeDef::Enum { name, variants } => {
+                let mut out = format!("typedef enum {name}{}{{", style.brace_gap(0));
+                for (variant, value) in variants {
+                    let _ = match value {
+                        Some(value) => write!(out, "\n{indent}{variant} = {value},"),
+                        None => write!(out, "\n{indent}{variant},"),
+                    };
+                }
+                let _ = write!(out, "\n}} {name};");
+                out
+            }
+            CTypeDef::Alias { name, ttype } => format!("typedef {};", ttype.declare(name)),
+        }
+    }
+}
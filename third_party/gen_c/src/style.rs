@@ -0,0 +1,41 @@
+/// Where the brace opening a block goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BraceStyle {
+    /// At the end of the line that opens the block, as in `if (x) {`
+    #[default]
+    Attached,
+    /// On a line of its own, lined up with the line that opens the block
+    Allman,
+}
+
+/// How printed C is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CStyle {
+    /// Spaces for each level of nesting
+    pub indent_width: usize,
+    pub brace_style: BraceStyle,
+}
+
+impl Default for CStyle {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            brace_style: BraceStyle::Attached,
+        }
+    }
+}
+
+impl CStyle {
+    pub(crate) fn indent(&self, depth: usize) -> String {
+        " ".repeat(self.indent_width * depth)
+    }
+
+    /// What goes between the line opening a block, like `if (x)`, and its
+    /// opening brace
+    pub(crate) fn brace_gap(&self, depth: usize) -> String {
+        match self.brace_style {
+            BraceStyle::Attached => " ".to_string(),
+            BraceStyle::Allman => format!("\n{}", self.indent(depth)),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Helpers shared by the golden tests
+
+// Every test binary includes this module but only uses some of it
+#![allow(dead_code)]
+
+use gen_c::{
+    builder::block_builder::BlockBuilder,
+    product::{
+        expr::{CBinaryOp, CExpr},
+        statement::CStatement,
+    },
+};
+use std::{
+    env, fs,
+    io::{ErrorKind, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Compares `c` against `tests/golden/{name}.c`, or rewrites the file
+/// instead when `UPDATE_GOLDEN` is set
+pub fn assert_golden(name: &str, c: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.c"));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, c).unwrap();
+        return;
+    }
+    let expected =
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("can't read {}: {e}", path.display()));
+    assert_eq!(c, expected, "{name}.c differs");
+}
+
+/// Checks a whole translation unit against its golden file and `cc`
+pub fn assert_unit(name: &str, c: &str) {
+    assert_golden(name, c);
+    assert_compiles(name, c);
+}
+
+pub fn ident(name: &str) -> CExpr {
+    CExpr::ident(name)
+}
+
+pub fn int(value: i32) -> CExpr {
+    CExpr::literal(value)
+}
+
+pub fn binary(op: CBinaryOp, left: CExpr, right: CExpr) -> CExpr {
+    CExpr::binary(op, left, right)
+}
+
+pub fn block(statements: Vec<CStatement>) -> CStatement {
+    statements
+        .into_iter()
+        .fold(BlockBuilder::new(), |block, statement| {
+            block.add_statement(statement)
+        })
+        .build()
+}
+
+/// Checks that `c` is a valid translation unit with `cc -fsyntax-only`,
+/// skipped where there is no `cc`
+pub fn assert_compiles(name: &str, c: &str) {
+    let child = Command::new("cc")
+        .args([
+            "-std=c11",
+            "-Wall",
+            "-Werror",
+            "-fsyntax-only",
+            "-x",
+            "c",
+            "-",
+        ])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("no cc to check {name}.c with");
+            return;
+        }
+        Err(e) => panic!("can't run cc: {e}"),
+    };
+
+    child.stdin.take().unwrap().write_all(c.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{name}.c doesn't compile:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,39 @@
+#include <stdint.h>
+
+typedef struct pair pair;
+
+struct pair {
+    int32_t first;
+    int32_t second;
+};
+
+typedef enum side {
+    LEFT,
+} side;
+
+static int32_t walk(int32_t n) {
+    if (n < 0) {
+        return 0;
+    } else if (n > 100) {
+        n -= 1;
+    } else {
+        n += 1;
+    }
+    while (n > 10)
+        n >>= 1;
+    do {
+        n -= 1;
+    } while (n > 5);
+    for (int32_t i = 0; i < 3; i += 1) {
+        n += 1;
+    }
+    switch (n) {
+        case 0:
+            return 1;
+        default:
+            break;
+    }
+    return n;
+}
+void nothing() {
+}
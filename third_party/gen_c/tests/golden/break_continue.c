@@ -0,0 +1,11 @@
+int32_t odd_until_seven() {
+    for (int32_t i = 0; i < 10; i += 1) {
+        if (i % 2 == 0) {
+            continue;
+        } else if (i > 7) {
+            break;
+        }
+        use(i);
+    }
+    return 0;
+}
@@ -0,0 +1,6 @@
+int32_t answer() {
+    // The answer to everything.
+    //
+    // Computed at great length.
+    return 42;
+}
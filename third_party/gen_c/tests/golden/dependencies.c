@@ -0,0 +1,23 @@
+#include <stdint.h>
+
+typedef struct point point;
+typedef struct line line;
+typedef union value value;
+
+typedef int32_t coord;
+
+struct point {
+    coord x;
+    coord y;
+    value *tag;
+};
+
+struct line {
+    point start;
+    point end;
+};
+
+union value {
+    int64_t integer;
+    line line;
+};
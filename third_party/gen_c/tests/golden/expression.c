@@ -0,0 +1,6 @@
+int32_t effects(int32_t x) {
+    puts("hello");
+    x = x * 2;
+    (void)x;
+    return x;
+}
@@ -0,0 +1,10 @@
+int32_t sum_to(int32_t n) {
+    int32_t sum = 0;
+    for (int32_t i = 0; i < n; i += 1) {
+        sum += i;
+    }
+    for (;;) {
+        break;
+    }
+    return sum;
+}
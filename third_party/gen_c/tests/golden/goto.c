@@ -0,0 +1,10 @@
+int32_t countdown(int32_t n) {
+    top: ;
+    if (n <= 0) {
+        goto end;
+    }
+    n -= 1;
+    goto top;
+    end: ;
+    return n;
+}
@@ -0,0 +1,5 @@
+int32_t answer() {
+#line 3 "dir\\answer \"v2\".tol"
+    return 42;
+#line 10
+}
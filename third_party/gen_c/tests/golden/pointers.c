@@ -0,0 +1,14 @@
+#include <stdint.h>
+
+typedef struct node node;
+typedef struct list list;
+
+struct node {
+    int32_t value;
+    node *next;
+    list *owner;
+};
+
+struct list {
+    node *head;
+};
@@ -0,0 +1,17 @@
+int32_t classify(int32_t n) {
+    int32_t result;
+    switch (n) {
+        case 0:
+            result = 0;
+            break;
+        case 1:
+        case 2:
+            {
+                result = 1;
+            }
+            break;
+        default:
+            result = -1;
+    }
+    return result;
+}
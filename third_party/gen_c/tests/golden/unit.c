@@ -0,0 +1,29 @@
+// Generated, do not edit
+
+#include <stdint.h>
+#include <stddef.h>
+
+typedef struct rect rect;
+
+typedef enum color {
+    RED,
+    BLUE = 4,
+} color;
+
+struct rect {
+    int32_t w;
+    int32_t h;
+    color fill;
+};
+
+typedef size_t size;
+
+int32_t area(rect r);
+
+static int32_t count = 0;
+extern int32_t shared;
+const int32_t LIMIT = 10;
+
+int32_t area(rect r) {
+    return r.w * r.h;
+}
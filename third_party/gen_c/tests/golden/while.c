@@ -0,0 +1,9 @@
+int32_t halve(int32_t n) {
+    while (n > 100) {
+        n >>= 1;
+    }
+    do {
+        n >>= 1;
+    } while (n > 10);
+    return n;
+}
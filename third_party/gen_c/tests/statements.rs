@@ -0,0 +1,258 @@
+//! Golden tests of how every kind of statement is printed
+
+mod common;
+
+use common::{binary, block, ident, int};
+
+use gen_c::{
+    builder::{
+        decl_builder::DeclBuilder, do_while_builder::DoWhileBuilder, for_builder::ForBuilder,
+        function_builder::FunctionBuilder, if_builder::IfBuilder, return_builder::ReturnBuilder,
+        switch_builder::SwitchBuilder, while_builder::WhileBuilder,
+    },
+    ctype::CType,
+    product::{
+        expr::{CAssignOp, CBinaryOp, CExpr},
+        statement::CStatement,
+    },
+    style::CStyle,
+};
+
+/// What the functions printed by the tests use
+const PRELUDE: &str = "\
+#include <stdint.h>
+#include <stdio.h>
+
+void use(int32_t value);
+
+";
+
+fn assert_golden(name: &str, statement: CStatement) {
+    let c = statement.produce_c(&CStyle::default(), 0) + "\n";
+    common::assert_golden(name, &c);
+    common::assert_compiles(name, &(PRELUDE.to_string() + &c));
+}
+
+fn expr(expr: CExpr) -> CStatement {
+    CStatement::Expression { expr }
+}
+
+/// A function returning `int32_t` with the statements as its body
+fn function(name: &str, params: &[&str], statements: Vec<CStatement>) -> CStatement {
+    let builder = params
+        .iter()
+        .fold(FunctionBuilder::new(CType::I32, name), |builder, param| {
+            builder.add_param(CType::I32, param)
+        });
+
+    statements
+        .into_iter()
+        .fold(builder, |builder, statement| {
+            builder.add_statement(statement)
+        })
+        .build()
+}
+
+#[test]
+fn prints_for() {
+    let sum = || ident("sum");
+    let body = block(vec![expr(binary(
+        CBinaryOp::AssignOp(CAssignOp::Add),
+        sum(),
+        ident("i"),
+    ))]);
+
+    assert_golden(
+        "for",
+        function(
+            "sum_to",
+            &["n"],
+            vec![
+                DeclBuilder::new(CType::I32, "sum").with_rhs(int(0)).build(),
+                ForBuilder::new(body)
+                    .with_init(DeclBuilder::new(CType::I32, "i").with_rhs(int(0)).build())
+                    .with_cond(binary(CBinaryOp::Lt, ident("i"), ident("n")))
+                    .with_step(binary(
+                        CBinaryOp::AssignOp(CAssignOp::Add),
+                        ident("i"),
+                        int(1),
+                    ))
+                    .build(),
+                ForBuilder::new(block(vec![CStatement::Break])).build(),
+                ReturnBuilder::new().with_rhs(sum()).build(),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn prints_while_and_do_while() {
+    let n = || ident("n");
+    let halve = || expr(binary(CBinaryOp::AssignOp(CAssignOp::Shr), n(), int(1)));
+
+    assert_golden(
+        "while",
+        function(
+            "halve",
+            &["n"],
+            vec![
+                WhileBuilder::new(binary(CBinaryOp::Gt, n(), int(100)), block(vec![halve()]))
+                    .build(),
+                DoWhileBuilder::new(block(vec![halve()]), binary(CBinaryOp::Gt, n(), int(10)))
+                    .build(),
+                ReturnBuilder::new().with_rhs(n()).build(),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn prints_switch() {
+    let result = || ident("result");
+    let set = |value| expr(CExpr::assign(result(), int(value)));
+
+    assert_golden(
+        "switch",
+        function(
+            "classify",
+            &["n"],
+            vec![
+                DeclBuilder::new(CType::I32, "result").build(),
+                SwitchBuilder::new(ident("n"))
+                    .add_case(int(0), vec![set(0), CStatement::Break])
+                    // Falls through to the next case
+                    .add_case(int(1), Vec::new())
+                    .add_case(int(2), vec![block(vec![set(1)]), CStatement::Break])
+                    .with_default(vec![set(-1)])
+                    .build(),
+                ReturnBuilder::new().with_rhs(result()).build(),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn prints_break_and_continue() {
+    let i = || ident("i");
+    let body = block(vec![
+        IfBuilder::new(
+            binary(CBinaryOp::Eq, binary(CBinaryOp::Rem, i(), int(2)), int(0)),
+            block(vec![CStatement::Continue]),
+        )
+        .add_elseif_branch(
+            binary(CBinaryOp::Gt, i(), int(7)),
+            block(vec![CStatement::Break]),
+        )
+        .build(),
+        expr(CExpr::call("use", vec![i()])),
+    ]);
+
+    assert_golden(
+        "break_continue",
+        function(
+            "odd_until_seven",
+            &[],
+            vec![
+                ForBuilder::new(body)
+                    .with_init(DeclBuilder::new(CType::I32, "i").with_rhs(int(0)).build())
+                    .with_cond(binary(CBinaryOp::Lt, i(), int(10)))
+                    .with_step(binary(CBinaryOp::AssignOp(CAssignOp::Add), i(), int(1)))
+                    .build(),
+                ReturnBuilder::new().with_rhs(int(0)).build(),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn prints_labels_and_goto() {
+    let n = || ident("n");
+
+    assert_golden(
+        "goto",
+        function(
+            "countdown",
+            &["n"],
+            vec![
+                CStatement::Label {
+                    name: "top".to_string(),
+                },
+                IfBuilder::new(
+                    binary(CBinaryOp::Le, n(), int(0)),
+                    block(vec![CStatement::Goto {
+                        label: "end".to_string(),
+                    }]),
+                )
+                .build(),
+                expr(binary(CBinaryOp::AssignOp(CAssignOp::Sub), n(), int(1))),
+                CStatement::Goto {
+                    label: "top".to_string(),
+                },
+                CStatement::Label {
+                    name: "end".to_string(),
+                },
+                ReturnBuilder::new().with_rhs(n()).build(),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn prints_expression_statements() {
+    assert_golden(
+        "expression",
+        function(
+            "effects",
+            &["x"],
+            vec![
+                expr(CExpr::call("puts", vec![CExpr::Str("hello".to_string())])),
+                expr(CExpr::assign(
+                    ident("x"),
+                    binary(CBinaryOp::Mul, ident("x"), int(2)),
+                )),
+                // Evaluated only for what it does
+                expr(CExpr::cast(CType::Void, ident("x"))),
+                ReturnBuilder::new().with_rhs(ident("x")).build(),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn prints_comments() {
+    assert_golden(
+        "comment",
+        function(
+            "answer",
+            &[],
+            vec![
+                CStatement::Comment {
+                    text: "The answer to everything.\n\nComputed at great length.".to_string(),
+                },
+                ReturnBuilder::new().with_rhs(int(42)).build(),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn prints_line_directives() {
+    assert_golden(
+        "line",
+        function(
+            "answer",
+            &[],
+            vec![
+                CStatement::Line {
+                    line: 3,
+                    file: Some("dir\\answer \"v2\".tol".to_string()),
+                },
+                ReturnBuilder::new().with_rhs(int(42)).build(),
+                CStatement::Line {
+                    line: 10,
+                    file: None,
+                },
+            ],
+        ),
+    );
+}
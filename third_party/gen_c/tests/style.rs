@@ -0,0 +1,113 @@
+//! Golden tests of how the style changes the layout
+
+mod common;
+
+use common::{assert_unit, block, int};
+
+use gen_c::{
+    CCodeGen,
+    builder::{
+        decl_builder::DeclBuilder, do_while_builder::DoWhileBuilder, enum_builder::EnumBuilder,
+        for_builder::ForBuilder, function_builder::FunctionBuilder, if_builder::IfBuilder,
+        return_builder::ReturnBuilder, struct_builder::StructBuilder,
+        switch_builder::SwitchBuilder, while_builder::WhileBuilder,
+    },
+    ctype::CType,
+    product::{
+        expr::{CAssignOp, CBinaryOp, CExpr},
+        include::CInclude,
+        statement::CStatement,
+    },
+    style::{BraceStyle, CStyle},
+};
+
+fn n() -> CExpr {
+    CExpr::ident("n")
+}
+
+fn step(op: CAssignOp) -> CStatement {
+    CStatement::Expression {
+        expr: CExpr::binary(CBinaryOp::AssignOp(op), n(), int(1)),
+    }
+}
+
+/// A unit with every kind of block, printed in `style`
+fn unit(style: CStyle) -> String {
+    let walk = FunctionBuilder::new(CType::I32, "walk")
+        .as_static()
+        .add_param(CType::I32, "n")
+        .add_statement(
+            IfBuilder::new(
+                CExpr::binary(CBinaryOp::Lt, n(), int(0)),
+                block(vec![ReturnBuilder::new().with_rhs(int(0)).build()]),
+            )
+            .add_elseif_branch(
+                CExpr::binary(CBinaryOp::Gt, n(), int(100)),
+                block(vec![step(CAssignOp::Sub)]),
+            )
+            .with_else_branch(block(vec![step(CAssignOp::Add)]))
+            .build(),
+        )
+        // A body that isn't a block
+        .add_statement(
+            WhileBuilder::new(
+                CExpr::binary(CBinaryOp::Gt, n(), int(10)),
+                step(CAssignOp::Shr),
+            )
+            .build(),
+        )
+        .add_statement(
+            DoWhileBuilder::new(
+                block(vec![step(CAssignOp::Sub)]),
+                CExpr::binary(CBinaryOp::Gt, n(), int(5)),
+            )
+            .build(),
+        )
+        .add_statement(
+            ForBuilder::new(block(vec![step(CAssignOp::Add)]))
+                .with_init(DeclBuilder::new(CType::I32, "i").with_rhs(int(0)).build())
+                .with_cond(CExpr::binary(CBinaryOp::Lt, CExpr::ident("i"), int(3)))
+                .with_step(CExpr::binary(
+                    CBinaryOp::AssignOp(CAssignOp::Add),
+                    CExpr::ident("i"),
+                    int(1),
+                ))
+                .build(),
+        )
+        .add_statement(
+            SwitchBuilder::new(n())
+                .add_case(int(0), vec![ReturnBuilder::new().with_rhs(int(1)).build()])
+                .with_default(vec![CStatement::Break])
+                .build(),
+        )
+        .add_statement(ReturnBuilder::new().with_rhs(n()).build());
+
+    CCodeGen::new()
+        .with_style(style)
+        .add_include(CInclude::System("stdint.h".to_string()))
+        .add_type(
+            StructBuilder::new("pair")
+                .add_field(CType::I32, "first")
+                .add_field(CType::I32, "second")
+                .build(),
+        )
+        .add_type(EnumBuilder::new("side").add_variant("LEFT").build())
+        .add_statement(walk.build())
+        .add_statement(FunctionBuilder::new(CType::Void, "nothing").build())
+        .produce_c()
+}
+
+#[test]
+fn attaches_braces_by_default() {
+    assert_unit("attached", &unit(CStyle::default()));
+}
+
+#[test]
+fn puts_braces_on_their_own_lines() {
+    let style = CStyle {
+        indent_width: 2,
+        brace_style: BraceStyle::Allman,
+    };
+
+    assert_unit("allman", &unit(style));
+}
@@ -0,0 +1,143 @@
+//! Golden tests of whole translation units
+
+mod common;
+
+use common::assert_unit;
+
+use gen_c::{
+    CCodeGen,
+    builder::{
+        decl_builder::{ConstKind, DeclBuilder},
+        enum_builder::EnumBuilder,
+        function_builder::FunctionBuilder,
+        return_builder::ReturnBuilder,
+        struct_builder::StructBuilder,
+    },
+    ctype::CType,
+    product::{expr::CExpr, include::CInclude, typedef::CTypeDef},
+};
+
+fn custom(name: &str) -> CType {
+    CType::Custom(name.to_string())
+}
+
+#[test]
+fn prints_sections_in_order() {
+    let area = || {
+        FunctionBuilder::new(CType::I32, "area")
+            .add_param(custom("rect"), "r")
+            .add_statement(
+                ReturnBuilder::new()
+                    .with_rhs(CExpr::binary(
+                        gen_c::product::expr::CBinaryOp::Mul,
+                        CExpr::member(CExpr::ident("r"), "w"),
+                        CExpr::member(CExpr::ident("r"), "h"),
+                    ))
+                    .build(),
+            )
+    };
+
+    // Added out of order, with the definition first and the header last
+    let c = CCodeGen::new()
+        .add_statement(area().build())
+        .add_global(
+            DeclBuilder::new(CType::I32, "count")
+                .with_rhs(CExpr::literal(0))
+                .as_static()
+                .build(),
+        )
+        .add_global(DeclBuilder::new(CType::I32, "shared").as_extern().build())
+        .add_global(
+            DeclBuilder::new(CType::I32, "LIMIT")
+                .with_rhs(CExpr::literal(10))
+                .as_const(ConstKind::Const)
+                .build(),
+        )
+        .add_prototype(area().build_prototype())
+        .add_type(
+            StructBuilder::new("rect")
+                .add_field(CType::I32, "w")
+                .add_field(CType::I32, "h")
+                .add_field(custom("color"), "fill")
+                .build(),
+        )
+        .add_type(
+            EnumBuilder::new("color")
+                .add_variant("RED")
+                .add_variant_with_value("BLUE", CExpr::literal(4))
+                .build(),
+        )
+        .add_type(CTypeDef::Alias {
+            name: "size".to_string(),
+            ttype: CType::Size,
+        })
+        .add_include(CInclude::System("stdint.h".to_string()))
+        .add_include(CInclude::System("stddef.h".to_string()))
+        .add_header_comment("Generated, do not edit")
+        .produce_c();
+
+    assert_unit("unit", &c);
+}
+
+#[test]
+fn defines_contained_types_first() {
+    let c = CCodeGen::new()
+        .add_type(
+            StructBuilder::new("line")
+                .add_field(custom("point"), "start")
+                .add_field(custom("point"), "end")
+                .build(),
+        )
+        .add_type(
+            StructBuilder::union("value")
+                .add_field(CType::I64, "integer")
+                .add_field(custom("line"), "line")
+                .build(),
+        )
+        .add_type(
+            StructBuilder::new("point")
+                .add_field(custom("coord"), "x")
+                .add_field(custom("coord"), "y")
+                // Only pointed to, so it doesn't need to come first
+                .add_field(CType::Pointer(Box::new(custom("value"))), "tag")
+                .build(),
+        )
+        .add_type(CTypeDef::Alias {
+            name: "coord".to_string(),
+            ttype: CType::I32,
+        })
+        .add_include(CInclude::System("stdint.h".to_string()))
+        .produce_c();
+
+    assert_unit("dependencies", &c);
+}
+
+#[test]
+fn allows_types_pointing_to_each_other() {
+    let c = CCodeGen::new()
+        .add_type(
+            StructBuilder::new("node")
+                .add_field(CType::I32, "value")
+                .add_field(CType::Pointer(Box::new(custom("node"))), "next")
+                .add_field(CType::Pointer(Box::new(custom("list"))), "owner")
+                .build(),
+        )
+        .add_type(
+            StructBuilder::new("list")
+                .add_field(CType::Pointer(Box::new(custom("node"))), "head")
+                .build(),
+        )
+        .add_include(CInclude::System("stdint.h".to_string()))
+        .produce_c();
+
+    assert_unit("pointers", &c);
+}
+
+#[test]
+#[should_panic(expected = "contains itself")]
+fn rejects_type_containing_itself() {
+    CCodeGen::new()
+        .add_type(StructBuilder::new("a").add_field(custom("b"), "b").build())
+        .add_type(StructBuilder::new("b").add_field(custom("a"), "a").build())
+        .produce_c();
+}